@@ -0,0 +1,399 @@
+//! Automatic tuning
+//!
+//! `nvoc autotune` bisects the graphics clock offset between stock and the
+//! top of the driver's range. Each step sets an offset, runs the workload
+//! for a short while and judges the run as `nvoc stress` does, then moves
+//! up after a stable step and down after an unstable one. The highest
+//! stable offset is written into a profile, and the GPU is put back to the
+//! offset it had before.
+//!
+//! With `--target efficiency` it instead benchmarks combinations of power
+//! limits, graphics clock caps and an offset on top of the capped clocks,
+//! and saves the one with the best performance per watt that keeps enough
+//! of stock performance.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::bench::Bench;
+use crate::params::{AutotuneParams, AutotuneTarget, EfficiencyParams};
+use crate::constants::{autotune, clocks};
+use crate::gpu::overclock;
+use crate::gpu::state::DeviceState;
+use crate::nvml::{self, Device};
+use crate::profile::stability::{self, Soak};
+use crate::profile::{self, Settings};
+use crate::ratelimit::ModifyPermit;
+use crate::units::MegaHertz;
+use crate::{journal, output, signal, stress, AppError};
+
+/// Bisection between the highest offset found stable and the lowest found
+/// unstable, to `resolution` MHz
+#[derive(Debug)]
+struct Search {
+    stable: i32,
+    unstable: i32,
+    resolution: i32,
+}
+
+impl Search {
+    /// Search up to and including `max`, with stock assumed stable
+    fn new(max: i32, resolution: i32) -> Self {
+        Self { stable: 0, unstable: max + 1, resolution: resolution.max(1) }
+    }
+
+    /// Next offset to try, on a multiple of the resolution, or `None` once
+    /// the bounds are within one step of each other
+    fn next(&self) -> Option<i32> {
+        let mid = self.stable + (self.unstable - self.stable) / 2;
+        let mid = (mid - mid.rem_euclid(self.resolution)).max(self.stable + self.resolution);
+        (mid < self.unstable).then_some(mid)
+    }
+
+    fn record(&mut self, offset: i32, stable: bool) {
+        match stable {
+            true => self.stable = offset,
+            false => self.unstable = offset,
+        }
+    }
+}
+
+/// Set `offset` and run the workload against it, returning the soak if
+/// the step was stable
+fn try_offset(
+    device: &Device,
+    device_index: u32,
+    offset: i32,
+    command: &str,
+    step: Duration,
+) -> Result<Option<Soak>, AppError> {
+    let offset = MegaHertz(offset);
+    overclock::set_graphics_offset(device, offset).map_err(|e| AppError::new("gpu offset", e))?;
+    output::status!("autotune: trying {offset:+} for {}s", step.as_secs());
+    let (soak, workload) = stress::probe(device, device_index, command, step)?;
+    match stress::judge(soak.score(), soak.xid, soak.ecc_errors, workload) {
+        None => {
+            println!("autotune: {offset:+} stable, {}", soak.summary());
+            Ok(Some(soak))
+        }
+        Some(reason) => {
+            println!("autotune: {offset:+} unstable ({reason})");
+            Ok(None)
+        }
+    }
+}
+
+/// Highest stable offset up to `max` and the soak that confirmed it
+fn search(
+    device: &Device,
+    device_index: u32,
+    command: &str,
+    step: Duration,
+    max: i32,
+    resolution: i32,
+) -> Result<(i32, Soak), AppError> {
+    let Some(baseline) = try_offset(device, device_index, 0, command, step)? else {
+        return Err(AppError::msg("autotune", "unstable without an offset, check the workload and cooling".to_string()));
+    };
+    let mut best = (0, baseline);
+    let mut search = Search::new(max, resolution);
+    while let Some(offset) = search.next() {
+        if signal::stop_requested() {
+            break;
+        }
+        let result = try_offset(device, device_index, offset, command, step)?;
+        // A step cut short by Ctrl-C proves nothing either way
+        if signal::stop_requested() {
+            break;
+        }
+        search.record(offset, result.is_some());
+        if let Some(soak) = result {
+            best = (offset, soak);
+        }
+    }
+    Ok(best)
+}
+
+/// Tune `device` for `params.target` and save the result into profile
+/// `params.name`. `permit` is held until the first change is recorded.
+pub fn run(device: &Device, device_index: u32, params: &AutotuneParams, permit: ModifyPermit) -> Result<(), AppError> {
+    match params.target {
+        AutotuneTarget::Offset { max, resolution } => tune_offset(device, device_index, params, (max, resolution), permit),
+        AutotuneTarget::Efficiency(ref efficiency) => tune_efficiency(device, device_index, params, efficiency, permit),
+    }
+}
+
+/// Find the highest stable graphics offset up to `max` (the top of the
+/// driver's range by default), narrowed to `resolution` MHz
+fn tune_offset(
+    device: &Device,
+    device_index: u32,
+    params: &AutotuneParams,
+    (max, resolution): (Option<i32>, i32),
+    permit: ModifyPermit,
+) -> Result<(), AppError> {
+    let AutotuneParams { ref name, ref command, step, .. } = *params;
+    let range = overclock::graphics_offset_range(device).ok();
+    let max = match (max, range) {
+        (Some(max), Some(range)) => {
+            range.check("gpu offset", MegaHertz(max))?;
+            max
+        }
+        (Some(max), None) => max,
+        (None, Some(range)) => range.max.0,
+        (None, None) => {
+            return Err(AppError::msg("autotune", "the driver reports no offset range, pass --max".to_string()));
+        }
+    };
+
+    signal::install();
+    // Kept open during the scan, so a crash puts the prior offset back. The
+    // scan writes offsets up to `max`.
+    let change = journal::Change {
+        operation: "autotune".to_string(),
+        target: DeviceState { graphics_offset: Some(MegaHertz(max)), ..Default::default() },
+        ..Default::default()
+    };
+    let transaction = journal::begin(device, device_index, change)?;
+    drop(permit);
+    let result = search(device, device_index, command, step, max, resolution);
+
+    let prior = transaction.prior.graphics_offset.unwrap_or(clocks::DEFAULT_GRAPHICS_OFFSET);
+    overclock::set_graphics_offset(device, prior).map_err(|e| AppError::new("gpu offset", e))?;
+    output::status!("autotune: gpu offset back to {prior:+}");
+    transaction.commit()?;
+
+    let (best, soak) = result?;
+    if signal::stop_requested() {
+        eprintln!("warning[autotune]: interrupted, saving the best offset found so far");
+    }
+    let uuid = nvml::device_get_uuid(device).map_err(|e| AppError::new("profile", e))?;
+    let mut saved = profile::load_or_default(name)?;
+    saved.gpu.entry(uuid).or_insert_with(Settings::default).graphics_offset = Some(best);
+    let path = profile::save(name, &saved)?;
+    println!("autotune: best stable gpu offset {best:+}MHz, saved to {}", path.display());
+    let record = stability::record(name, &soak)?;
+    println!("profile: {name} {record}");
+    Ok(())
+}
+
+/// One combination tried by the efficiency search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Candidate {
+    /// Percent of the default power limit
+    power_limit: u32,
+    /// Highest graphics clock allowed, in MHz
+    clock_cap: Option<u32>,
+    offset: i32,
+}
+
+impl Candidate {
+    const STOCK: Candidate = Candidate { power_limit: 100, clock_cap: None, offset: 0 };
+
+    /// Settings for the candidate, locking clocks between `lock_min` and the cap
+    fn settings(&self, lock_min: u32) -> Settings {
+        Settings {
+            clocks: self.clock_cap.map(|cap| (lock_min, cap)),
+            graphics_offset: Some(self.offset),
+            power_limit: Some(self.power_limit),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "power {}%", self.power_limit)?;
+        if let Some(cap) = self.clock_cap {
+            write!(f, ", clocks up to {cap}MHz")?;
+        }
+        if self.offset != 0 {
+            write!(f, ", gpu offset {:+}MHz", self.offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Stock first, as the reference, then every power limit from the highest
+/// alone, under each clock cap, and under each cap with `offset` added
+fn candidates(power: &[u32], clock_caps: &[u32], offset: i32) -> Vec<Candidate> {
+    let mut power = power.to_vec();
+    power.sort_unstable_by(|a, b| b.cmp(a));
+    power.dedup();
+    let mut candidates = vec![Candidate::STOCK];
+    for power_limit in power {
+        let uncapped = Candidate { power_limit, ..Candidate::STOCK };
+        let capped = clock_caps.iter().flat_map(|&cap| {
+            let capped = Candidate { clock_cap: Some(cap), ..uncapped };
+            let offsets = if offset != 0 { vec![0, offset] } else { vec![0] };
+            offsets.into_iter().map(move |offset| Candidate { offset, ..capped })
+        });
+        candidates.extend(std::iter::once(uncapped).chain(capped).filter(|c| *c != Candidate::STOCK));
+    }
+    candidates
+}
+
+/// Caps at fixed fractions of the highest supported clock
+fn default_clock_caps(supported_max: u32) -> Vec<u32> {
+    autotune::CLOCK_CAP_PERCENTS
+        .iter()
+        .map(|percent| supported_max * percent / 100 / autotune::CLOCK_STEP_MHZ * autotune::CLOCK_STEP_MHZ)
+        .collect()
+}
+
+/// A stable candidate and what it achieved
+struct Outcome {
+    candidate: Candidate,
+    performance: f64,
+    per_watt: f64,
+    watts: f64,
+    soak: Soak,
+}
+
+/// Index of the outcome with the best performance per watt among those
+/// keeping `min_performance` percent of the first (stock) outcome
+fn recommend(outcomes: &[(f64, f64)], min_performance: u32) -> Option<usize> {
+    let (stock, _) = *outcomes.first()?;
+    let floor = stock * f64::from(min_performance) / 100.0;
+    outcomes
+        .iter()
+        .enumerate()
+        .filter(|(_, (performance, _))| *performance >= floor)
+        .max_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b))
+        .map(|(i, _)| i)
+}
+
+/// Benchmark every candidate and save the most efficient into the profile
+fn tune_efficiency(
+    device: &Device,
+    device_index: u32,
+    params: &AutotuneParams,
+    efficiency: &EfficiencyParams,
+    permit: ModifyPermit,
+) -> Result<(), AppError> {
+    let AutotuneParams { ref name, ref command, step, .. } = *params;
+    let supported = overclock::supported_clock_range(device).ok();
+    let clock_caps = match (efficiency.clock_caps.is_empty(), supported) {
+        (false, _) => efficiency.clock_caps.clone(),
+        (true, Some((_, max))) => default_clock_caps(max),
+        (true, None) => {
+            eprintln!("warning[autotune]: the supported clocks are unknown, trying power limits only; pass --clock-caps");
+            Vec::new()
+        }
+    };
+    if efficiency.offset != 0 && !clock_caps.is_empty() {
+        if let Ok(range) = overclock::graphics_offset_range(device) {
+            range.check("gpu offset", MegaHertz(efficiency.offset))?;
+        }
+    }
+    let lock_min = supported.map_or(clocks::BLACKWELL_IDLE_MIN, |(min, _)| min);
+    let candidates = candidates(&efficiency.power, &clock_caps, efficiency.offset);
+
+    signal::install();
+    output::status!("autotune: {} combinations, up to {}s each", candidates.len(), step.as_secs());
+    let mut permit = Some(permit);
+    let mut bench = Bench::new(command, step);
+    let mut outcomes: Vec<Outcome> = Vec::new();
+    for candidate in candidates {
+        if signal::stop_requested() {
+            break;
+        }
+        let params = candidate.settings(lock_min).to_params(false);
+        let measurement = bench.measure_with(device, device_index, &params, permit.take())?;
+        // A run cut short by Ctrl-C is not comparable
+        if signal::stop_requested() {
+            break;
+        }
+        let reading = (measurement.performance(), measurement.per_watt(), measurement.soak.mean_power_w());
+        match (measurement.failure(), reading) {
+            (Some(reason), _) => println!("autotune: {candidate}: unstable ({reason})"),
+            (None, (Some(performance), Some(per_watt), Some(watts))) => {
+                println!(
+                    "autotune: {candidate}: {:.1} at {watts:.0}W, {:.3}/W",
+                    output::Decimal(performance),
+                    output::Decimal(per_watt)
+                );
+                outcomes.push(Outcome { candidate, performance, per_watt, watts, soak: measurement.soak });
+            }
+            (None, _) => println!("autotune: {candidate}: no power or performance readings, skipped"),
+        }
+        if outcomes.is_empty() {
+            return Err(AppError::msg("autotune", "stock settings did not benchmark, check the benchmark and cooling".to_string()));
+        }
+    }
+
+    let scores: Vec<(f64, f64)> = outcomes.iter().map(|o| (o.performance, o.per_watt)).collect();
+    let Some(best) = recommend(&scores, efficiency.min_performance).map(|i| &outcomes[i]) else {
+        return Err(AppError::msg("autotune", "interrupted before stock settings were measured".to_string()));
+    };
+    if signal::stop_requested() {
+        eprintln!("warning[autotune]: interrupted, recommending from the combinations measured so far");
+    }
+    let stock = &outcomes[0];
+    println!(
+        "autotune: most efficient: {}, {:.0}% of stock performance at {:.0}% of stock power",
+        best.candidate,
+        best.performance / stock.performance * 100.0,
+        best.watts / stock.watts * 100.0
+    );
+
+    let uuid = nvml::device_get_uuid(device).map_err(|e| AppError::new("profile", e))?;
+    let mut saved = profile::load_or_default(name)?;
+    let settings = best.candidate.settings(lock_min);
+    let entry = saved.gpu.entry(uuid).or_insert_with(Settings::default);
+    entry.clocks = settings.clocks;
+    entry.graphics_offset = settings.graphics_offset;
+    entry.power_limit = settings.power_limit;
+    let path = profile::save(name, &saved)?;
+    println!("autotune: saved to {}", path.display());
+    let record = stability::record(name, &best.soak)?;
+    println!("profile: {name} {record}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Offsets tried when everything up to `limit` is stable
+    fn scan(max: i32, resolution: i32, limit: i32) -> (Vec<i32>, i32) {
+        let mut search = Search::new(max, resolution);
+        let mut tried = Vec::new();
+        while let Some(offset) = search.next() {
+            tried.push(offset);
+            search.record(offset, offset <= limit);
+        }
+        (tried, search.stable)
+    }
+
+    #[test]
+    fn test_search_converges_on_resolution() {
+        let (tried, best) = scan(1000, 15, 620);
+        assert_eq!(&tried[..3], &[495, 735, 615]);
+        assert_eq!(best, 615);
+        assert!(tried.iter().all(|o| o % 15 == 0));
+
+        assert_eq!(scan(1000, 15, 2000).1, 990);
+        assert_eq!(scan(1000, 15, -1).1, 0);
+    }
+
+    #[test]
+    fn test_efficiency_candidates() {
+        let all = candidates(&[80, 100, 90], &[2400], 100);
+        assert_eq!(all.len(), 1 + 2 + 3 + 3);
+        assert_eq!(all[0], Candidate::STOCK);
+        assert_eq!(all[1], Candidate { clock_cap: Some(2400), ..Candidate::STOCK });
+        assert_eq!(all[3], Candidate { power_limit: 90, ..Candidate::STOCK });
+        assert_eq!(candidates(&[70], &[], 100).len(), 2);
+        assert_eq!(default_clock_caps(3090), vec![2460, 2775]);
+    }
+
+    #[test]
+    fn test_recommend_keeps_enough_performance() {
+        let outcomes = [(100.0, 0.20), (97.0, 0.25), (80.0, 0.30)];
+        assert_eq!(recommend(&outcomes, 90), Some(1));
+        assert_eq!(recommend(&outcomes, 75), Some(2));
+        assert_eq!(recommend(&outcomes[..1], 90), Some(0));
+        assert_eq!(recommend(&[], 90), None);
+    }
+}
@@ -0,0 +1,108 @@
+//! Benchmark measurements for comparing settings
+//!
+//! Efficiency tuning and power sweeps run the same benchmark under different
+//! settings and compare performance per watt. The performance of a run is
+//! the last number the benchmark printed on stdout, such as a frame rate or
+//! a throughput, or the average graphics clock when it printed none.
+
+use std::time::Duration;
+
+use crate::params::OverclockParams;
+use crate::gpu::overclock::{self, Trial};
+use crate::nvml::Device;
+use crate::profile::stability::Soak;
+use crate::ratelimit::ModifyPermit;
+use crate::stress::{self, Workload};
+use crate::AppError;
+
+/// Outcome of one benchmark run
+#[derive(Debug)]
+pub struct Measurement {
+    pub soak: Soak,
+    pub workload: Workload,
+    /// Last number the benchmark printed
+    pub score: Option<f64>,
+}
+
+impl Measurement {
+    /// The printed score, or the average graphics clock without one
+    pub fn performance(&self) -> Option<f64> {
+        self.score.or(self.soak.mean_clock_mhz())
+    }
+
+    pub fn per_watt(&self) -> Option<f64> {
+        let watts = self.soak.mean_power_w().filter(|&w| w > 0.0)?;
+        Some(self.performance()? / watts)
+    }
+
+    /// Why the run cannot be compared with others, if it cannot
+    pub fn failure(&self) -> Option<String> {
+        stress::judge(self.soak.score(), self.soak.xid, self.soak.ecc_errors, self.workload)
+    }
+}
+
+/// Last number on the last line that has one, e.g. `142.7` from
+/// `avg fps: 142.7` or `score=8812`
+fn parse_score(stdout: &str) -> Option<f64> {
+    stdout.lines().rev().find_map(|line| {
+        line.split(|c: char| c.is_whitespace() || matches!(c, '=' | ':' | ','))
+            .rev()
+            .find_map(|token| token.parse::<f64>().ok().filter(|v| v.is_finite()))
+    })
+}
+
+/// A benchmark command run for at most `duration` per measurement
+pub struct Bench<'a> {
+    command: &'a str,
+    duration: Duration,
+    /// Whether the fallback to clocks was already reported
+    warned: bool,
+}
+
+impl<'a> Bench<'a> {
+    pub fn new(command: &'a str, duration: Duration) -> Self {
+        Self { command, duration, warned: false }
+    }
+
+    /// Run the benchmark against the device's current settings
+    fn measure(&mut self, device: &Device, device_index: u32) -> Result<Measurement, AppError> {
+        let (soak, workload, stdout) = stress::probe_captured(device, device_index, self.command, self.duration)?;
+        let score = parse_score(&stdout);
+        if score.is_none() && !self.warned {
+            eprintln!("warning[bench]: `{}` printed no score, comparing average graphics clocks", self.command);
+            self.warned = true;
+        }
+        Ok(Measurement { soak, workload, score })
+    }
+
+    /// Apply `params`, run the benchmark against them and put the prior
+    /// settings back. `permit`, if any, is held while applying.
+    pub fn measure_with(
+        &mut self,
+        device: &Device,
+        device_index: u32,
+        params: &OverclockParams,
+        permit: Option<ModifyPermit>,
+    ) -> Result<Measurement, AppError> {
+        overclock::cool_down(device, device_index);
+        let trial = Trial::start(device, device_index, params)?;
+        drop(permit);
+        let measurement = self.measure(device, device_index);
+        trial.revert()?;
+        measurement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_score() {
+        assert_eq!(parse_score("warming up\navg fps: 142.7\n"), Some(142.7));
+        assert_eq!(parse_score("score=8812\n\ndone\n"), Some(8812.0));
+        assert_eq!(parse_score("run 3 of 3, 97 GFLOPS\n"), Some(97.0));
+        assert_eq!(parse_score("no numbers here\n"), None);
+        assert_eq!(parse_score(""), None);
+    }
+}
@@ -0,0 +1,339 @@
+//! Configuration file loaded from TOML
+//!
+//! ```toml
+//! [daemon]
+//! interval = "2s"
+//! socket = "/run/nvoc.sock"
+//! socket_mode = 0o660
+//! admin_group = "nvoc"
+//! max_memory_temperature = 100
+//!
+//! [limits]
+//! min_modify_interval = "250ms"
+//! min_power_limit = 80
+//! max_apply_temperature = 80
+//! allow_power_raise_on_battery = false
+//!
+//! [boot]
+//! fallback = "safe"
+//!
+//! [defaults]
+//! device = 1
+//! interval = "500ms"
+//!
+//! [paths]
+//! library = "/usr/lib/x86_64-linux-gnu/libnvidia-ml.so.1"
+//! profiles = "/etc/nvoc/profiles"
+//!
+//! [history]
+//! path = "/var/lib/nvoc/history.db"
+//! resolution = "10s"
+//! retention = "7d"
+//!
+//! [architecture]
+//! unknown = "warn"
+//!
+//! [budget]
+//! total_watts = 900
+//! devices = [0, 1]
+//!
+//! [[link]]
+//! source = 0
+//! target = 1
+//! profile = "capped"
+//! temperature_above = 83
+//!
+//! [fan]
+//! hysteresis = 3
+//! curve = [[40, 30], [60, 50], [75, 80], [85, 100]]
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::constants::{daemon, history, limits};
+use crate::daemon::curve::FanCurve;
+use crate::gpu::validation::UnknownArchitecture;
+use crate::history::Retention;
+use crate::AppError;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub daemon: DaemonSection,
+    pub fan: Option<FanSection>,
+    pub budget: Option<BudgetSection>,
+    pub history: Option<HistorySection>,
+    #[serde(default, rename = "link")]
+    pub links: Vec<LinkSection>,
+    #[serde(default)]
+    pub limits: LimitsSection,
+    #[serde(default)]
+    pub boot: BootSection,
+    #[serde(default)]
+    pub architecture: ArchitectureSection,
+    #[serde(default)]
+    pub defaults: DefaultsSection,
+    #[serde(default)]
+    pub paths: PathsSection,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DaemonSection {
+    /// Interval between temperature samples
+    #[serde(default = "default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+    /// Control socket; not served when omitted
+    pub socket: Option<PathBuf>,
+    /// Permissions of the control socket. Only the owner and group may
+    /// connect by default; with a wider mode access is decided per
+    /// connection.
+    #[serde(default = "default_socket_mode")]
+    pub socket_mode: u32,
+    /// Group whose members may modify settings over the socket. Everyone
+    /// else, except root, gets read-only access.
+    pub admin_group: Option<String>,
+    /// Memory junction temperature in °C above which the memory offset is
+    /// reset to zero
+    pub max_memory_temperature: Option<u32>,
+}
+
+impl Default for DaemonSection {
+    fn default() -> Self {
+        Self {
+            interval: default_interval(),
+            socket: None,
+            socket_mode: default_socket_mode(),
+            admin_group: None,
+            max_memory_temperature: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FanSection {
+    /// `[temp °C, speed %]` points
+    pub curve: Vec<(u32, u32)>,
+    /// Degrees the temperature must fall before fans slow down
+    #[serde(default)]
+    pub hysteresis: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BudgetSection {
+    /// Combined power limit for the group
+    pub total_watts: u32,
+    /// GPU indexes in the group; every GPU when omitted
+    #[serde(default)]
+    pub devices: Vec<u32>,
+}
+
+/// Profile applied to `target` while `source` overheats, throttles or fails
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LinkSection {
+    pub source: u32,
+    pub target: u32,
+    pub profile: String,
+    /// Trigger at or above this temperature
+    pub temperature_above: Option<u32>,
+    /// Trigger while the source is held back by a thermal or hardware slowdown
+    #[serde(default)]
+    pub throttled: bool,
+    /// Trigger when the source stops answering
+    #[serde(default)]
+    pub failed: bool,
+}
+
+impl LinkSection {
+    pub fn validate(&self) -> Result<(), AppError> {
+        let rule = format!("link {} -> {}", self.source, self.target);
+        if self.source == self.target {
+            return Err(AppError::msg("config", format!("{rule}: source and target must differ")));
+        }
+        if self.temperature_above.is_none() && !self.throttled && !self.failed {
+            return Err(AppError::msg(
+                "config",
+                format!("{rule}: set temperature_above, throttled or failed"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HistorySection {
+    /// SQLite database the daemon records telemetry into
+    #[serde(default = "default_history_path")]
+    pub path: PathBuf,
+    /// Time between recorded samples
+    #[serde(default = "default_resolution", deserialize_with = "deserialize_duration")]
+    pub resolution: Duration,
+    /// Drop samples older than this
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub retention: Option<Duration>,
+    /// Drop the oldest samples beyond this many rows
+    pub max_rows: Option<u64>,
+}
+
+impl HistorySection {
+    pub fn retention(&self) -> Retention {
+        Retention { max_age: self.retention, max_rows: self.max_rows }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsSection {
+    /// Minimum time between modifying operations on the same device
+    #[serde(default = "default_min_modify_interval", deserialize_with = "deserialize_duration")]
+    pub min_modify_interval: Duration,
+    /// Power limit, as a percentage of the default, that nothing nvoc does
+    /// may go below
+    #[serde(default, deserialize_with = "deserialize_power_floor")]
+    pub min_power_limit: Option<u32>,
+    /// Temperature in °C above which raising the power limit or an offset
+    /// is refused
+    pub max_apply_temperature: Option<u32>,
+    /// Let a laptop GPU running on battery have its power limit raised,
+    /// with a warning instead of an error
+    #[serde(default)]
+    pub allow_power_raise_on_battery: bool,
+}
+
+impl Default for LimitsSection {
+    fn default() -> Self {
+        Self {
+            min_modify_interval: default_min_modify_interval(),
+            min_power_limit: None,
+            max_apply_temperature: None,
+            allow_power_raise_on_battery: false,
+        }
+    }
+}
+
+/// Defaults for command-line options that were not given
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DefaultsSection {
+    /// GPU index used without `-d`
+    pub device: Option<u32>,
+    /// Interval of `monitor`, `watch` and `log` without `--interval`
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub interval: Option<Duration>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathsSection {
+    /// NVML library to load instead of searching the usual locations
+    pub library: Option<PathBuf>,
+    /// Directory holding named profiles
+    pub profiles: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BootSection {
+    /// Profile applied by `profile apply --boot` instead of the requested
+    /// one when the previous boot ended uncleanly
+    pub fallback: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArchitectureSection {
+    /// GPUs nvoc does not recognize are rejected, warned about, or treated
+    /// as a chosen architecture
+    #[serde(default, deserialize_with = "deserialize_policy")]
+    pub unknown: UnknownArchitecture,
+}
+
+impl FanSection {
+    pub fn fan_curve(&self) -> Result<FanCurve, AppError> {
+        FanCurve::new(self.curve.clone()).map_err(|e| AppError::msg("config", e))
+    }
+}
+
+fn default_interval() -> Duration {
+    Duration::from_millis(daemon::DEFAULT_INTERVAL_MS)
+}
+
+fn default_socket_mode() -> u32 {
+    daemon::DEFAULT_SOCKET_MODE
+}
+
+fn default_min_modify_interval() -> Duration {
+    Duration::from_millis(limits::DEFAULT_MIN_MODIFY_INTERVAL_MS)
+}
+
+fn default_history_path() -> PathBuf {
+    PathBuf::from(history::DEFAULT_PATH)
+}
+
+fn default_resolution() -> Duration {
+    Duration::from_millis(history::DEFAULT_RESOLUTION_MS)
+}
+
+/// Parse a duration such as `500ms`, `2s`, `10m`, `24h` or `7d`. A bare number is
+/// taken as seconds.
+pub fn parse_duration(s: &str) -> std::result::Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{s}'"))?;
+
+    match unit {
+        "ms" => Ok(Duration::from_millis(value)),
+        "" | "s" => Ok(Duration::from_secs(value)),
+        "m" => Ok(Duration::from_secs(value * 60)),
+        "h" => Ok(Duration::from_secs(value * 3600)),
+        "d" => Ok(Duration::from_secs(value * 86_400)),
+        _ => Err(format!("invalid duration unit '{unit}', use ms, s, m, h or d")),
+    }
+}
+
+fn deserialize_optional_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    deserialize_duration(deserializer).map(Some)
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    parse_duration(&s).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_power_floor<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    let percent = u32::deserialize(deserializer)?;
+    if !(1..=100).contains(&percent) {
+        return Err(serde::de::Error::custom(format!("min_power_limit {percent}% must be between 1 and 100")));
+    }
+    Ok(Some(percent))
+}
+
+fn deserialize_policy<'de, D: Deserializer<'de>>(deserializer: D) -> Result<UnknownArchitecture, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(serde::de::Error::custom)
+}
+
+/// Read and parse a config file
+pub fn load(path: &Path) -> Result<ConfigFile, AppError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| AppError::msg("config", format!("{}: {e}", path.display())))?;
+    toml::from_str(&text).map_err(|e| AppError::msg("config", format!("{}: {}", path.display(), e.message())))
+}
+
+/// Read a config file if present, falling back to defaults when it does not exist
+pub fn load_or_default(path: &Path) -> Result<ConfigFile, AppError> {
+    if !path.exists() {
+        return Ok(ConfigFile::default());
+    }
+    load(path)
+}
@@ -0,0 +1,275 @@
+//! Application constants and configuration values
+
+/// Application metadata
+pub mod app {
+    pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+    pub const NAME: &str = "nvoc";
+    pub const AUTHOR: &str = "NVOC Contributors";
+    pub const DESCRIPTION: &str = "NVIDIA GPU overclocking utility for Blackwell (RTX 50-series)";
+}
+
+/// Driver and hardware constraints
+pub mod hardware {
+    /// Minimum supported NVIDIA driver version
+    pub const MIN_DRIVER_VERSION: u32 = 550;
+
+    /// First driver with `nvmlDeviceGetClockOffsets`/`nvmlDeviceSetClockOffsets`
+    pub const CLOCK_OFFSETS_DRIVER_VERSION: u32 = 555;
+
+    /// GPU temperature sensor index for NVML calls
+    pub const GPU_TEMP_SENSOR: u32 = 0;
+
+    /// Power conversion factor (milliwatts to watts)
+    pub const MILLIWATTS_TO_WATTS: u32 = 1000;
+}
+
+/// Clock management and validation
+pub mod clocks {
+    use crate::units::MegaHertz;
+
+    /// Blackwell idle clock range for safe reset (min, max in MHz)
+    pub const BLACKWELL_IDLE_MIN: u32 = 200;
+    pub const BLACKWELL_IDLE_MAX: u32 = 250;
+
+    /// Default graphics offset for reset operations
+    pub const DEFAULT_GRAPHICS_OFFSET: MegaHertz = MegaHertz(0);
+
+    /// Default memory offset for reset operations
+    pub const DEFAULT_MEMORY_OFFSET: MegaHertz = MegaHertz(0);
+}
+
+/// Buffer sizes for NVML operations
+pub mod buffers {
+    /// Driver version string buffer size
+    pub const DRIVER_VERSION_BUFFER_SIZE: usize = 80;
+
+    /// Device name buffer size
+    pub const DEVICE_NAME_BUFFER_SIZE: usize = 64;
+
+    /// Device UUID buffer size (NVML_DEVICE_UUID_V2_BUFFER_SIZE)
+    pub const DEVICE_UUID_BUFFER_SIZE: usize = 96;
+
+    /// VBIOS version buffer size
+    pub const VBIOS_VERSION_BUFFER_SIZE: usize = 32;
+
+    /// InfoROM version buffer size
+    pub const INFOROM_VERSION_BUFFER_SIZE: usize = 16;
+
+    /// Board serial number buffer size
+    pub const SERIAL_BUFFER_SIZE: usize = 30;
+
+    /// Board part number buffer size
+    pub const PART_NUMBER_BUFFER_SIZE: usize = 80;
+
+    /// Entries read from the supported clock lists
+    pub const SUPPORTED_CLOCKS_BUFFER_SIZE: usize = 512;
+}
+
+/// Filesystem locations for configuration and persistent state
+pub mod paths {
+    use std::path::PathBuf;
+
+    /// Where `path` lives on this machine. Simulated GPUs keeping their
+    /// state in `NVOC_MOCK_STATE` stand in for a machine of their own,
+    /// rooted at that file's directory, so their config, profiles, journal
+    /// and runtime files are kept there instead of under `/`.
+    pub fn system(path: &str) -> PathBuf {
+        match crate::nvml::mock::root() {
+            Some(root) => root.join(path.trim_start_matches('/')),
+            None => PathBuf::from(path),
+        }
+    }
+
+    /// Whether system paths belong to a simulated machine, which needs no
+    /// root to change
+    pub fn simulated() -> bool {
+        crate::nvml::mock::root().is_some()
+    }
+
+    /// Configuration file
+    pub const CONFIG_FILE: &str = "/etc/nvoc/config.toml";
+
+    /// Directory holding named profiles
+    pub const PROFILES_DIR: &str = "/etc/nvoc/profiles";
+
+    /// Journal of in-flight GPU changes, one file named after the GPU's UUID
+    /// that is present only while a change to that GPU is being applied, and
+    /// the `<uuid>.lock` file the applying process holds meanwhile. The
+    /// daemon holds `<uuid>.budget` and `<uuid>.fan` while it runs.
+    pub const JOURNAL_DIR: &str = "/var/lib/nvoc/journal.d";
+
+    /// Profile currently applied, one `gpu<index>` file per GPU, removed on
+    /// clean shutdown
+    pub const ACTIVE_PROFILE_DIR: &str = "/var/lib/nvoc/active.d";
+
+    /// Overclocks reverted after an XID error, one line per failure
+    pub const XID_LOG_FILE: &str = "/var/lib/nvoc/xid.log";
+
+    /// Stability scores from soak runs, keyed by profile name
+    pub const STABILITY_FILE: &str = "/var/lib/nvoc/stability.toml";
+
+    /// Directory for per-boot runtime state such as rate limit stamps
+    pub const RUNTIME_DIR: &str = "/run/nvoc";
+}
+
+/// Daemon polling and control behavior
+pub mod daemon {
+    /// Default interval between temperature samples (milliseconds)
+    pub const DEFAULT_INTERVAL_MS: u64 = 2000;
+
+    /// Upper bound for fan duty cycle percentages
+    pub const MAX_FAN_SPEED: u32 = 100;
+
+    /// Default control socket permissions: owner and group (the admin group
+    /// when one is set) may connect
+    pub const DEFAULT_SOCKET_MODE: u32 = 0o660;
+
+    /// Interval between checks of live settings against the applied profile (milliseconds)
+    pub const DRIFT_CHECK_INTERVAL_MS: u64 = 30_000;
+
+    /// A linked rule triggered by temperature is released this many degrees
+    /// below its threshold
+    pub const LINK_HYSTERESIS_C: u32 = 5;
+
+    /// Wait after a display hotplug before checking offsets, so the memory
+    /// clock has finished changing state (milliseconds)
+    pub const DISPLAY_SETTLE_MS: u64 = 3_000;
+
+    /// Delay before restarting a GPU's control loop after it stopped; doubles
+    /// with each consecutive failure up to the maximum (milliseconds)
+    pub const WORKER_RESTART_MIN_MS: u64 = 1_000;
+    pub const WORKER_RESTART_MAX_MS: u64 = 60_000;
+
+    /// A control loop that ran this long before failing starts over with the
+    /// minimum restart delay (milliseconds)
+    pub const WORKER_STABLE_MS: u64 = 60_000;
+
+    /// Interval at which the supervisor checks on the control loops (milliseconds)
+    pub const SUPERVISOR_POLL_MS: u64 = 250;
+}
+
+/// Safety limits for modifying operations
+pub mod limits {
+    /// Default minimum interval between modifying operations per device (milliseconds)
+    pub const DEFAULT_MIN_MODIFY_INTERVAL_MS: u64 = 250;
+}
+
+/// Prometheus exporter defaults
+pub mod exporter {
+    /// Default TCP listen address, localhost only
+    pub const DEFAULT_LISTEN: &str = "127.0.0.1:9835";
+
+    /// Default permissions for the Unix socket (owner and group read/write)
+    pub const DEFAULT_SOCKET_MODE: &str = "660";
+
+    /// Default time readings are served from cache before a scrape resamples
+    pub const DEFAULT_CACHE_TTL: &str = "2s";
+
+    /// Longest a scrape waits for fresh readings before serving stale ones
+    pub const SAMPLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+}
+
+/// Live terminal dashboard
+pub mod monitor {
+    /// Default interval between redraws
+    pub const DEFAULT_INTERVAL: &str = "1s";
+
+    /// Readings kept per sparkline, enough to fill a wide terminal
+    pub const HISTORY_LEN: usize = 512;
+}
+
+/// Plain-text status lines
+pub mod watch {
+    /// Default interval between status lines
+    pub const DEFAULT_INTERVAL: &str = "2s";
+}
+
+/// Locating the NVML library
+pub mod library {
+    /// Longest a candidate path may take to read before it is skipped, in
+    /// milliseconds, so a hung network mount does not hang nvoc
+    pub const PROBE_TIMEOUT_MS: u64 = 2000;
+}
+
+/// NVML event streaming
+pub mod events {
+    /// Longest a wait for events blocks before checking for Ctrl-C, in milliseconds
+    pub const WAIT_TIMEOUT_MS: u32 = 500;
+}
+
+/// CSV telemetry logging
+pub mod csv_log {
+    /// Default interval between samples
+    pub const DEFAULT_INTERVAL: &str = "1s";
+}
+
+/// Telemetry history kept by the daemon
+pub mod history {
+    /// Database shared by the daemons of all GPUs
+    pub const DEFAULT_PATH: &str = "/var/lib/nvoc/history.db";
+
+    /// Default time between recorded samples, in milliseconds
+    pub const DEFAULT_RESOLUTION_MS: u64 = 10_000;
+
+    /// Default number of rows printed by `nvoc history`
+    pub const DEFAULT_BUCKETS: &str = "24";
+
+    /// How long a writer waits for another daemon's transaction, in milliseconds
+    pub const BUSY_TIMEOUT_MS: u64 = 2_000;
+
+    /// Power draw, as a fraction of the limit, counted as sitting at the cap
+    pub const POWER_CAP_RATIO: f64 = 0.97;
+
+    /// Utilization from which a sample counts as busy for the headroom estimate
+    pub const BUSY_UTILIZATION: u32 = 90;
+
+    /// Power limit step the headroom estimate is given for, in percent
+    pub const HEADROOM_STEP_PERCENT: f64 = 5.0;
+
+    /// Busy samples needed before estimating headroom
+    pub const MIN_HEADROOM_SAMPLES: usize = 30;
+
+    /// Share of samples at the cap below which more power is not suggested
+    pub const MIN_CAPPED_FRACTION: f64 = 0.1;
+}
+
+/// Power budget redistribution
+pub mod budget {
+    /// Weight added to every device's utilization so idle GPUs keep a share
+    pub const IDLE_WEIGHT: u32 = 10;
+
+    /// Limit changes smaller than this are not written (milliwatts)
+    pub const DEADBAND_MW: u32 = 5000;
+}
+
+/// `nvoc stress`
+pub mod stress {
+    /// Lowest soak score that counts as stable, for `stress` and `setup`
+    pub const MIN_STABLE_SCORE: u32 = 90;
+
+    /// Time the workload gets to exit after SIGTERM before it is killed
+    pub const KILL_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+}
+
+/// `nvoc autotune`
+pub mod autotune {
+    /// Power limits tried by `--target efficiency`, in percent
+    pub const POWER_STEPS: &str = "70..100:10";
+
+    /// Default graphics clock caps of `--target efficiency`, in percent of
+    /// the supported maximum
+    pub const CLOCK_CAP_PERCENTS: [u32; 2] = [80, 90];
+
+    /// Clock caps are rounded down to this step (MHz)
+    pub const CLOCK_STEP_MHZ: u32 = 15;
+}
+
+/// `nvoc setup`
+pub mod setup {
+    /// Graphics offset proposed for a first profile, well inside what
+    /// current cards reach (MHz)
+    pub const GRAPHICS_OFFSET_MHZ: i32 = 100;
+
+    /// Memory offset proposed for a first profile (MHz)
+    pub const MEMORY_OFFSET_MHZ: i32 = 500;
+}
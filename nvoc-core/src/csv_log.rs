@@ -0,0 +1,84 @@
+//! Telemetry logging to CSV
+//!
+//! `nvoc log` appends a row per GPU every interval with a UTC timestamp, so
+//! a stability test can be lined up against clocks, temperature and power
+//! afterwards in a spreadsheet or plotting tool.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::gpu::telemetry::{self, Sample};
+use crate::{signal, AppError};
+
+const HEADER: &str = "timestamp,gpu,temperature_c,graphics_clock_mhz,memory_clock_mhz,graphics_offset_mhz,\
+memory_offset_mhz,power_watts,power_limit_watts,fan_percent,utilization_percent";
+
+/// Missing readings are left empty
+fn cell<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn row(timestamp: &str, sample: &Sample) -> String {
+    [
+        timestamp.to_string(),
+        sample.index.to_string(),
+        cell(sample.temperature_c),
+        cell(sample.graphics_clock_mhz),
+        cell(sample.memory_clock_mhz),
+        cell(sample.graphics_offset_mhz),
+        cell(sample.memory_offset_mhz),
+        cell(sample.power_watts),
+        cell(sample.power_limit_watts),
+        cell(sample.fan_percent),
+        cell(sample.utilization_percent),
+    ]
+    .join(",")
+}
+
+pub fn run(indices: &[u32], out: &Path, interval: Duration) -> Result<(), AppError> {
+    let err = |e: std::io::Error| AppError::msg("log", format!("{}: {e}", out.display()));
+    let mut writer = BufWriter::new(File::create(out).map_err(err)?);
+    writeln!(writer, "{HEADER}").map_err(err)?;
+    println!("log: writing to {} every {}ms, Ctrl-C to stop", out.display(), interval.as_millis());
+
+    signal::install();
+    let mut rows = 0u64;
+    loop {
+        let timestamp = telemetry::utc_timestamp(SystemTime::now());
+        for &index in indices {
+            // A GPU that cannot be read still gets a row, so gaps are visible
+            let sample = telemetry::sample(index).unwrap_or(Sample { index, ..Default::default() });
+            writeln!(writer, "{}", row(&timestamp, &sample)).map_err(err)?;
+            rows += 1;
+        }
+        // Flushed every interval so the file is usable if the machine crashes
+        writer.flush().map_err(err)?;
+        if !signal::sleep(interval) {
+            break;
+        }
+    }
+    println!("log: {rows} rows written");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_matches_header() {
+        let sample = Sample {
+            index: 0,
+            temperature_c: Some(71),
+            graphics_clock_mhz: Some(2805),
+            graphics_offset_mhz: Some(-50),
+            power_watts: Some(540),
+            ..Default::default()
+        };
+        let line = row("2025-03-01T18:04:05.250Z", &sample);
+        assert_eq!(line, "2025-03-01T18:04:05.250Z,0,71,2805,,-50,,540,,,");
+        assert_eq!(line.split(',').count(), HEADER.split(',').count());
+    }
+}
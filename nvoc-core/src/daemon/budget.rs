@@ -0,0 +1,235 @@
+//! Power budget shared across a group of GPUs
+//!
+//! Splits a total wattage between devices in proportion to their current
+//! utilization, respecting each device's hardware power limit range. The
+//! limits in effect when the daemon started are restored on exit, and are
+//! journaled before the first change so a killed daemon's are restored on
+//! the next start.
+
+use crate::constants::budget;
+use crate::gpu::state::DeviceState;
+use crate::gpu::{self, domain};
+use crate::nvml::{self, Device};
+use crate::units::{Milliwatts, Watts};
+use crate::{journal, output, ratelimit, AppError};
+
+/// A device's claim on the budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Share {
+    pub weight: u32,
+    pub min_mw: u32,
+    pub max_mw: u32,
+}
+
+/// Distribute `total_mw` across shares proportionally to weight. Shares
+/// whose proportional part falls outside their range are pinned to the
+/// nearest bound and the remainder is redistributed among the others.
+pub fn allocate(total_mw: u32, shares: &[Share]) -> Vec<u32> {
+    let mut alloc = vec![0u32; shares.len()];
+    let mut fixed = vec![false; shares.len()];
+    let mut remaining = u64::from(total_mw);
+
+    loop {
+        let weight_sum: u64 = (0..shares.len())
+            .filter(|&i| !fixed[i])
+            .map(|i| u64::from(shares[i].weight))
+            .sum();
+        if weight_sum == 0 {
+            break;
+        }
+
+        let targets: Vec<(usize, u64)> = (0..shares.len())
+            .filter(|&i| !fixed[i])
+            .map(|i| (i, remaining * u64::from(shares[i].weight) / weight_sum))
+            .collect();
+        let violators: Vec<(usize, u64)> = targets
+            .iter()
+            .copied()
+            .filter(|&(i, t)| t < u64::from(shares[i].min_mw) || t > u64::from(shares[i].max_mw))
+            .collect();
+
+        if violators.is_empty() {
+            for (i, t) in targets {
+                alloc[i] = t as u32;
+            }
+            break;
+        }
+        for (i, t) in violators {
+            alloc[i] = (t as u32).clamp(shares[i].min_mw, shares[i].max_mw);
+            fixed[i] = true;
+            remaining = remaining.saturating_sub(u64::from(alloc[i]));
+        }
+    }
+
+    alloc
+}
+
+struct Member {
+    index: u32,
+    device: Device,
+    min_mw: u32,
+    max_mw: u32,
+    prior_mw: u32,
+    current_mw: u32,
+    /// Open from the first change until `prior_mw` is restored
+    transaction: Option<journal::Transaction>,
+}
+
+impl Member {
+    /// Journal the limit to restore before the budget first changes it
+    fn journal(&mut self, target: Milliwatts) -> Result<(), AppError> {
+        if self.transaction.is_none() {
+            let prior = DeviceState { power_limit_mw: Some(Milliwatts(self.prior_mw)), ..DeviceState::default() };
+            let change = journal::Change {
+                operation: "budget".to_string(),
+                target: DeviceState { power_limit_mw: Some(target), ..DeviceState::default() },
+                ..journal::Change::default()
+            };
+            self.transaction = Some(journal::hold(&self.device, self.index, prior, change)?);
+        }
+        Ok(())
+    }
+}
+
+pub struct PowerBudget {
+    total_mw: u32,
+    members: Vec<Member>,
+}
+
+impl PowerBudget {
+    /// Set up a budget over `indices` (every GPU when empty)
+    pub fn new(total_watts: u32, indices: &[u32]) -> Result<Self, AppError> {
+        let indices: Vec<u32> = match indices.is_empty() {
+            true => (0..gpu::device_count().map_err(|e| AppError::new("budget", e))?).collect(),
+            false => indices.to_vec(),
+        };
+
+        let mut members = Vec::with_capacity(indices.len());
+        for index in indices {
+            let device = &gpu::get_device(index).map_err(|e| AppError::new("budget", e))?;
+            let (min_mw, max_mw) = nvml::device_get_power_limit_constraints(device)
+                .map_err(|e| AppError::new("budget", e))?;
+            // Never split below the configured floor
+            let floor = domain::power_floor_mw(device).map_err(|e| AppError::new("budget", e))?;
+            let min_mw = min_mw.max(floor.0).min(max_mw);
+            let prior_mw = nvml::device_get_power_limit(device).map_err(|e| AppError::new("budget", e))?;
+            members.push(Member {
+                index,
+                device: device.clone(),
+                min_mw,
+                max_mw,
+                prior_mw,
+                current_mw: prior_mw,
+                transaction: None,
+            });
+        }
+
+        let total_mw = Milliwatts::from(Watts(total_watts)).0;
+        let floor_mw: u32 = members.iter().map(|m| m.min_mw).sum();
+        if floor_mw > total_mw {
+            eprintln!(
+                "warning[budget]: {total_watts}W is below the combined minimum of {}, devices will sit at their minimum",
+                Watts::from(Milliwatts(floor_mw))
+            );
+        }
+
+        Ok(Self { total_mw, members })
+    }
+
+    /// Switch to a new total and device set. GPUs staying in the budget keep
+    /// their original limit for restoring on exit; GPUs leaving it get their
+    /// original limit back now. On error the budget is left unchanged.
+    pub fn reconfigure(&mut self, total_watts: u32, indices: &[u32]) -> Result<(), AppError> {
+        let mut next = PowerBudget::new(total_watts, indices)?;
+        for member in &mut next.members {
+            if let Some(old) = self.members.iter_mut().find(|m| m.index == member.index) {
+                member.prior_mw = old.prior_mw;
+                member.current_mw = old.current_mw;
+                member.transaction = old.transaction.take();
+            }
+        }
+        self.members.retain(|old| !next.members.iter().any(|m| m.index == old.index));
+        // Dropping the old budget restores the devices that left
+        drop(std::mem::replace(self, next));
+        Ok(())
+    }
+
+    /// Re-read the live limits, which someone else may have changed
+    pub fn resync(&mut self) {
+        for member in &mut self.members {
+            // An unreadable limit is rewritten on the next rebalance
+            member.current_mw = nvml::device_get_power_limit(&member.device).unwrap_or(0);
+        }
+    }
+
+    /// Recompute the split from current utilization and apply any limit
+    /// that moved by more than the deadband
+    pub fn rebalance(&mut self, min_modify_interval: std::time::Duration) -> Result<(), AppError> {
+        let shares: Vec<Share> = self
+            .members
+            .iter()
+            .map(|m| Share {
+                weight: nvml::device_get_utilization_rates(&m.device).map(|u| u.gpu).unwrap_or(0)
+                    + budget::IDLE_WEIGHT,
+                min_mw: m.min_mw,
+                max_mw: m.max_mw,
+            })
+            .collect();
+
+        for (member, target_mw) in self.members.iter_mut().zip(allocate(self.total_mw, &shares)) {
+            if member.current_mw.abs_diff(target_mw) < budget::DEADBAND_MW {
+                continue;
+            }
+            let _permit = ratelimit::acquire(member.index, min_modify_interval)?;
+            member.journal(Milliwatts(target_mw))?;
+            let target = domain::set_power_limit(&member.device, Milliwatts(target_mw))
+                .map_err(|e| AppError::new("budget", e))?;
+            member.current_mw = target.0;
+            output::status!("budget: gpu {} {}", member.index, Watts::from(target));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PowerBudget {
+    fn drop(&mut self) {
+        for member in &mut self.members {
+            match domain::set_power_limit(&member.device, Milliwatts(member.prior_mw)) {
+                Ok(limit) => output::status!("budget: gpu {} restored {}", member.index, Watts::from(limit)),
+                // The entry stays for the next start to retry
+                Err(e) => {
+                    eprintln!("error[budget]: gpu {}: {}", member.index, e.user_message());
+                    continue;
+                }
+            }
+            if let Some(Err(e)) = member.transaction.take().map(journal::Transaction::commit) {
+                eprintln!("{e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn share(weight: u32) -> Share {
+        Share { weight, min_mw: 100_000, max_mw: 400_000 }
+    }
+
+    #[test]
+    fn test_proportional_split() {
+        assert_eq!(allocate(600_000, &[share(1), share(1)]), vec![300_000, 300_000]);
+    }
+
+    #[test]
+    fn test_capped_share_redistributed() {
+        // 500W proportional share exceeds the 400W cap, the rest goes to the other GPU
+        assert_eq!(allocate(600_000, &[share(100), share(20)]), vec![400_000, 200_000]);
+    }
+
+    #[test]
+    fn test_budget_below_minimums() {
+        assert_eq!(allocate(150_000, &[share(1), share(1)]), vec![100_000, 100_000]);
+    }
+}
@@ -0,0 +1,356 @@
+//! Daemon control socket
+//!
+//! Clients send one command per connection as a line of text and get one
+//! line back:
+//!
+//! - `status`: JSON with the driver version, applied profile and current
+//!   GPU readings
+//! - `apply <profile>`: apply a saved profile, replying `ok` or `error: ...`
+//!
+//! A line starting with `{` is read as a JSON-RPC 2.0 request instead, for
+//! scripts wanting structured errors. Its methods are `status`, `apply`
+//! (`{"profile": name}`) and `set`, which takes the same fields as a profile
+//! (`clocks`, `mem_clocks`, `graphics_offset`, `memory_offset`, `power_limit`).
+//!
+//! Access is decided from the peer's credentials. Root and members of the
+//! configured admin group may modify settings; anyone else who can open the
+//! socket is limited to `status`, so dashboards can poll without being able
+//! to change clocks.
+//!
+//! `nvoc info` queries the socket when a daemon is running, which spares
+//! scripts polling every second from loading and initializing NVML each time.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::rpc::{self, RpcError};
+use crate::gpu::{self, info::GpuInfo};
+use crate::profile::{self, Settings};
+use crate::socket::{self, PeerCred};
+use crate::{journal, output, ratelimit, AppError};
+
+/// Time a connected client has to send its whole command, and to take the reply
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest command accepted, in bytes
+const MAX_REQUEST: u64 = 64 * 1024;
+
+/// Connections served at once; more are closed straight away
+const MAX_CONNECTIONS: usize = 16;
+
+/// How long a client waits for the daemon before falling back to NVML
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Reply to `status`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Status {
+    pub driver_version: String,
+    pub profile: Option<String>,
+    pub gpu: GpuInfo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    ReadOnly,
+    Modify,
+}
+
+fn access(cred: &PeerCred, admin_gid: Option<u32>) -> Access {
+    match cred.uid == 0 || admin_gid.is_some_and(|gid| cred.in_group(gid)) {
+        true => Access::Modify,
+        false => Access::ReadOnly,
+    }
+}
+
+/// Socket path for a device, substituting `{gpu}` with its index so one
+/// config serves a daemon per GPU
+pub fn socket_path(template: &Path, device_index: u32) -> PathBuf {
+    PathBuf::from(template.to_string_lossy().replace("{gpu}", &device_index.to_string()))
+}
+
+struct Server {
+    device_index: u32,
+    admin_gid: Option<u32>,
+    min_modify_interval: Duration,
+}
+
+/// Read one command line from `stream`, giving up once `REQUEST_TIMEOUT`
+/// has passed in total however slowly the client sends, or the line grows
+/// past `MAX_REQUEST`
+fn read_request(stream: &UnixStream) -> io::Result<String> {
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST));
+    let mut line = Vec::new();
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        stream.set_read_timeout(Some(remaining))?;
+        let buf = reader.fill_buf()?;
+        if let Some(end) = buf.iter().position(|&b| b == b'\n') {
+            line.extend_from_slice(&buf[..=end]);
+            break;
+        }
+        if buf.is_empty() {
+            // The client closed without a newline, or sent too much
+            if line.len() as u64 >= MAX_REQUEST {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "request too long"));
+            }
+            break;
+        }
+        let read = buf.len();
+        line.extend_from_slice(buf);
+        reader.consume(read);
+    }
+    String::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Params of the JSON-RPC `apply` method
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApplyParams {
+    profile: String,
+}
+
+impl Server {
+    fn status(&self) -> Result<Status, AppError> {
+        let device = &gpu::get_device(self.device_index).map_err(|e| AppError::new("device", e))?;
+        Ok(Status {
+            driver_version: gpu::driver_version().map_err(|e| AppError::new("driver", e))?,
+            profile: profile::applied(self.device_index),
+            gpu: gpu::info::collect(device, self.device_index).map_err(|e| AppError::new("info", e))?,
+        })
+    }
+
+    fn apply(&self, name: &str) -> Result<(), AppError> {
+        let device = &gpu::get_device(self.device_index).map_err(|e| AppError::new("device", e))?;
+        let _permit = ratelimit::acquire(self.device_index, self.min_modify_interval)?;
+        profile::apply(device, self.device_index, name, false)?;
+        journal::mark_active(self.device_index, name, false)
+    }
+
+    /// Apply individual settings, as `nvoc` with overclock flags would
+    fn set(&self, settings: &Settings) -> Result<(), AppError> {
+        let device = &gpu::get_device(self.device_index).map_err(|e| AppError::new("device", e))?;
+        let _permit = ratelimit::acquire(self.device_index, self.min_modify_interval)?;
+        profile::apply_settings(device, self.device_index, settings, false)?;
+        profile::record_applied(self.device_index, None)?;
+        journal::clear_active(self.device_index)
+    }
+
+    fn text_command(&self, line: &str, access: Access) -> String {
+        let mut words = line.split_whitespace();
+        let reply = match (words.next(), words.next(), access) {
+            (Some("status"), None, _) => self
+                .status()
+                .and_then(|status| serde_json::to_string(&status).map_err(|e| AppError::msg("control", e.to_string()))),
+            (Some("apply"), Some(_), Access::ReadOnly) => Err(AppError::msg("control", "permission denied".to_string())),
+            (Some("apply"), Some(name), Access::Modify) => self.apply(name).map(|()| "ok".to_string()),
+            _ => Err(AppError::msg("control", format!("unknown command '{}'", line.trim()))),
+        };
+        reply.unwrap_or_else(|e| e.to_string())
+    }
+
+    fn rpc_call(&self, method: &str, params: &Value, access: Access) -> Result<Value, RpcError> {
+        let server_error = |e: AppError| RpcError::server(e.to_string());
+        match (method, access) {
+            ("status", _) => {
+                let status = self.status().map_err(server_error)?;
+                serde_json::to_value(status).map_err(|e| RpcError::server(e.to_string()))
+            }
+            ("apply" | "set", Access::ReadOnly) => Err(RpcError::permission_denied()),
+            ("apply", Access::Modify) => {
+                let ApplyParams { profile } = rpc::params(params)?;
+                self.apply(&profile).map_err(server_error)?;
+                Ok(Value::Null)
+            }
+            ("set", Access::Modify) => {
+                let settings: Settings = rpc::params(params)?;
+                if settings == Settings::default() {
+                    return Err(RpcError::invalid_params("no settings given".to_string()));
+                }
+                // A curve needs an applied profile for the daemon to follow
+                if settings.fan_curve.is_some() {
+                    return Err(RpcError::invalid_params("fan_curve can only be set through a profile".to_string()));
+                }
+                self.set(&settings).map_err(server_error)?;
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::method_not_found(method)),
+        }
+    }
+
+    fn rpc_command(&self, line: &str, access: Access) -> String {
+        let response = match rpc::parse(line) {
+            Ok(request) => rpc::response(request.id, self.rpc_call(&request.method, &request.params, access)),
+            Err((id, e)) => rpc::response(id, Err(e)),
+        };
+        response.to_string()
+    }
+
+    fn handle(&self, stream: UnixStream) {
+        let access = match socket::peer_cred(&stream) {
+            Ok(cred) => access(&cred, self.admin_gid),
+            Err(_) => return,
+        };
+        let Ok(line) = read_request(&stream) else {
+            return;
+        };
+
+        let reply = match line.trim_start().starts_with('{') {
+            true => self.rpc_command(&line, access),
+            false => self.text_command(&line, access),
+        };
+        let _ = stream.set_write_timeout(Some(REQUEST_TIMEOUT));
+        let _ = writeln!(&stream, "{reply}");
+    }
+}
+
+/// One of the `MAX_CONNECTIONS` connections being served, given back on drop
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    /// Take a slot, or `None` when every one is in use
+    fn take(active: &Arc<AtomicUsize>) -> Option<Self> {
+        active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| (n < MAX_CONNECTIONS).then_some(n + 1))
+            .ok()
+            .map(|_| Slot(Arc::clone(active)))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Serve the control socket on a background thread. Returns the bound
+/// path so the caller can remove it on exit.
+pub fn spawn(
+    template: &Path,
+    mode: u32,
+    admin_group: Option<&str>,
+    device_index: u32,
+    min_modify_interval: Duration,
+) -> Result<PathBuf, AppError> {
+    let admin_gid = match admin_group {
+        Some(name) => Some(
+            socket::group_id(name).ok_or_else(|| AppError::msg("config", format!("unknown group '{name}'")))?,
+        ),
+        None => None,
+    };
+    let path = socket_path(template, device_index);
+    let listener = socket::bind_unix(&path, mode, "control")?;
+    // Lets a group-only mode such as 0o660 admit the admin group
+    if let Some(gid) = admin_gid {
+        std::os::unix::fs::chown(&path, None, Some(gid))
+            .map_err(|e| AppError::msg("control", format!("{}: {e}", path.display())))?;
+    }
+    let server = Arc::new(Server { device_index, admin_gid, min_modify_interval });
+    let active = Arc::new(AtomicUsize::new(0));
+    // A slow client only holds up its own connection, and only so many can be held up at once
+    let accept = move || {
+        for stream in listener.incoming().flatten() {
+            let Some(slot) = Slot::take(&active) else {
+                // Never waits, as the reply fits in the empty send buffer
+                let _ = stream.set_nonblocking(true);
+                let _ = writeln!(&stream, "error: too many connections, try again");
+                continue;
+            };
+            let server = Arc::clone(&server);
+            let handler = thread::Builder::new().name("control".to_string()).spawn(move || {
+                let _slot = slot;
+                server.handle(stream)
+            });
+            if let Err(e) = handler {
+                eprintln!("warning[control]: cannot serve connection: {e}");
+            }
+        }
+    };
+    thread::Builder::new()
+        .name("control-accept".to_string())
+        .spawn(accept)
+        .map_err(|e| AppError::msg("control", format!("cannot start listener: {e}")))?;
+    output::status!("control: unix:{}", path.display());
+    Ok(path)
+}
+
+/// Send one command to a daemon's control socket and return its reply line
+fn request(path: &Path, command: &str) -> io::Result<String> {
+    let stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    writeln!(&stream, "{command}")?;
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    Ok(reply)
+}
+
+/// Status from the daemon serving `device_index`, if one is listening and
+/// answers in time
+pub fn query_status(template: &Path, device_index: u32) -> Option<Status> {
+    let reply = request(&socket_path(template, device_index), "status").ok()?;
+    serde_json::from_str(&reply).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access_levels() {
+        let root = PeerCred::default();
+        let member = PeerCred { uid: 1000, gid: 1000, groups: vec![985], ..PeerCred::default() };
+        let other = PeerCred { uid: 472, gid: 472, groups: vec![472], ..PeerCred::default() };
+        assert_eq!(access(&root, None), Access::Modify);
+        assert_eq!(access(&member, Some(985)), Access::Modify);
+        assert_eq!(access(&other, Some(985)), Access::ReadOnly);
+        assert_eq!(access(&member, None), Access::ReadOnly);
+        assert_eq!(access(&PeerCred { groups: Vec::new(), ..member }, Some(985)), Access::ReadOnly);
+    }
+
+    #[test]
+    fn test_read_request_limits() {
+        let (client, server) = UnixStream::pair().unwrap();
+        writeln!(&client, "status").unwrap();
+        assert_eq!(read_request(&server).unwrap(), "status\n");
+
+        let (client, server) = UnixStream::pair().unwrap();
+        let sender = std::thread::spawn(move || {
+            let _ = (&client).write_all(&vec![b'x'; MAX_REQUEST as usize + 1]);
+        });
+        assert_eq!(read_request(&server).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        drop(server);
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_connection_limit() {
+        let active = Arc::new(AtomicUsize::new(0));
+        let mut slots: Vec<Slot> = (0..MAX_CONNECTIONS).map_while(|_| Slot::take(&active)).collect();
+        assert_eq!(slots.len(), MAX_CONNECTIONS);
+        assert!(Slot::take(&active).is_none());
+        slots.pop();
+        assert!(Slot::take(&active).is_some());
+        drop(slots);
+        assert_eq!(active.load(Ordering::Acquire), 0);
+    }
+
+    #[test]
+    fn test_socket_path_template() {
+        assert_eq!(
+            socket_path(Path::new("/run/nvoc/daemon-{gpu}.sock"), 1),
+            PathBuf::from("/run/nvoc/daemon-1.sock")
+        );
+    }
+}
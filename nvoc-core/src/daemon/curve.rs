@@ -0,0 +1,121 @@
+//! Temperature to fan speed curve with hysteresis
+
+use crate::constants::daemon::MAX_FAN_SPEED;
+
+/// Piecewise-linear mapping of temperature (°C) to fan speed (%)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FanCurve {
+    points: Vec<(u32, u32)>,
+}
+
+impl FanCurve {
+    /// Build a curve from `(temp, speed)` points. Temperatures must be
+    /// strictly increasing and speeds must not exceed 100%.
+    pub fn new(points: Vec<(u32, u32)>) -> Result<Self, String> {
+        if points.is_empty() {
+            return Err("fan curve needs at least one point".to_string());
+        }
+        if let Some(w) = points.windows(2).find(|w| w[0].0 >= w[1].0) {
+            return Err(format!("fan curve temperatures must increase ({}°C then {}°C)", w[0].0, w[1].0));
+        }
+        if let Some(&(temp, speed)) = points.iter().find(|p| p.1 > MAX_FAN_SPEED) {
+            return Err(format!("fan speed {speed}% at {temp}°C exceeds {MAX_FAN_SPEED}%"));
+        }
+        Ok(Self { points })
+    }
+
+    /// Fan speed for a temperature, interpolating between points and
+    /// clamping outside the defined range
+    pub fn speed_at(&self, temp: u32) -> u32 {
+        let first = self.points[0];
+        let last = self.points[self.points.len() - 1];
+        if temp <= first.0 {
+            return first.1;
+        }
+        if temp >= last.0 {
+            return last.1;
+        }
+
+        let upper = self.points.iter().position(|p| p.0 >= temp).unwrap_or(self.points.len() - 1);
+        let (t0, s0) = self.points[upper - 1];
+        let (t1, s1) = self.points[upper];
+        let span = (t1 - t0) as f32;
+        let progress = (temp - t0) as f32 / span;
+        (s0 as f32 + (s1 as f32 - s0 as f32) * progress).round() as u32
+    }
+}
+
+/// Tracks the last applied speed so fans ramp up immediately but only slow
+/// down once the temperature has dropped `hysteresis` degrees below the
+/// point where the current speed was chosen.
+#[derive(Debug)]
+pub struct FanController {
+    curve: FanCurve,
+    hysteresis: u32,
+    last: Option<(u32, u32)>,
+}
+
+impl FanController {
+    pub fn new(curve: FanCurve, hysteresis: u32) -> Self {
+        Self { curve, hysteresis, last: None }
+    }
+
+    /// Forget the last speed set, so the next sample sets one again
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+
+    /// Feed a temperature sample; returns the new speed if it should change
+    pub fn update(&mut self, temp: u32) -> Option<u32> {
+        let target = self.curve.speed_at(temp);
+        let change = match self.last {
+            None => true,
+            Some((_, speed)) if target > speed => true,
+            Some((set_temp, speed)) if target < speed => temp + self.hysteresis <= set_temp,
+            Some(_) => false,
+        };
+
+        if change {
+            self.last = Some((temp, target));
+            Some(target)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve() -> FanCurve {
+        FanCurve::new(vec![(40, 30), (60, 50), (80, 100)]).unwrap()
+    }
+
+    #[test]
+    fn test_curve_interpolation() {
+        let curve = curve();
+        assert_eq!(curve.speed_at(20), 30);
+        assert_eq!(curve.speed_at(50), 40);
+        assert_eq!(curve.speed_at(70), 75);
+        assert_eq!(curve.speed_at(95), 100);
+    }
+
+    #[test]
+    fn test_curve_validation() {
+        assert!(FanCurve::new(vec![]).is_err());
+        assert!(FanCurve::new(vec![(60, 50), (40, 30)]).is_err());
+        assert!(FanCurve::new(vec![(60, 120)]).is_err());
+    }
+
+    #[test]
+    fn test_hysteresis() {
+        let mut controller = FanController::new(curve(), 5);
+        assert_eq!(controller.update(60), Some(50));
+        assert_eq!(controller.update(70), Some(75));
+        // Small drop within hysteresis band keeps the current speed
+        assert_eq!(controller.update(67), None);
+        assert_eq!(controller.update(65), Some(63));
+        assert_eq!(controller.update(65), None);
+    }
+}
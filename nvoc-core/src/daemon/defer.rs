@@ -0,0 +1,170 @@
+//! Stepping aside for external GPU managers
+//!
+//! Vendor tools and test harnesses drive the same knobs as the daemon, and
+//! two programs correcting each other's settings never settle. With
+//! `--defer-to`, the daemon watches for such a manager, named by a pidfile
+//! or a well-known name on the system D-Bus, and suspends everything that
+//! writes to the GPU while one is running. Fans go back to automatic control
+//! for that time; enforcement resumes once the manager is gone.
+
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::output;
+
+/// An external manager recognized by `--defer-to`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Manager {
+    /// Running while the file holds the PID of a live process
+    Pidfile(PathBuf),
+    /// Running while the name has an owner on the system bus
+    BusName(String),
+}
+
+impl fmt::Display for Manager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Manager::Pidfile(path) => write!(f, "{}", path.display()),
+            Manager::BusName(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// Whether `name` is a well-known D-Bus name such as `com.example.Tuner`
+fn is_bus_name(name: &str) -> bool {
+    let elements: Vec<&str> = name.split('.').collect();
+    name.len() <= 255
+        && elements.len() >= 2
+        && elements.iter().all(|e| {
+            e.chars().next().is_some_and(|c| !c.is_ascii_digit())
+                && e.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
+}
+
+impl Manager {
+    /// Paths name a pidfile, anything else must be a D-Bus name
+    pub fn parse(s: &str) -> Result<Self, String> {
+        if s.contains('/') {
+            return Ok(Manager::Pidfile(PathBuf::from(s)));
+        }
+        match is_bus_name(s) {
+            true => Ok(Manager::BusName(s.to_string())),
+            false => Err(format!("'{s}' is neither a pidfile path nor a D-Bus name like com.example.Tuner")),
+        }
+    }
+
+    fn is_running(&self) -> Result<bool, String> {
+        match self {
+            Manager::Pidfile(path) => Ok(fs::read_to_string(path)
+                .ok()
+                .and_then(|text| text.trim().parse::<libc::pid_t>().ok())
+                .is_some_and(pid_alive)),
+            Manager::BusName(name) => name_has_owner(name),
+        }
+    }
+}
+
+fn pid_alive(pid: libc::pid_t) -> bool {
+    if pid <= 0 {
+        return false;
+    }
+    // Signal 0 only checks; EPERM means the process exists under another user
+    unsafe { libc::kill(pid, 0) == 0 || *libc::__errno_location() == libc::EPERM }
+}
+
+fn name_has_owner(name: &str) -> Result<bool, String> {
+    let output = Command::new("busctl")
+        .args(["--system", "call", "org.freedesktop.DBus", "/org/freedesktop/DBus"])
+        .args(["org.freedesktop.DBus", "NameHasOwner", "s", name])
+        .output()
+        .map_err(|e| format!("busctl: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("busctl: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim() == "b true")
+}
+
+/// Tracks whether the daemon of one GPU is standing aside
+pub struct Deferral {
+    device_index: u32,
+    managers: Vec<Manager>,
+    /// Manager the daemon is currently deferring to
+    active: Option<Manager>,
+    /// Managers whose state could not be checked, warned about once
+    unreadable: Vec<Manager>,
+}
+
+/// Change reported by [`Deferral::poll`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Suspend,
+    Resume,
+}
+
+impl Deferral {
+    pub fn new(device_index: u32, managers: Vec<Manager>) -> Self {
+        Self { device_index, managers, active: None, unreadable: Vec::new() }
+    }
+
+    /// Whether enforcement is currently suspended
+    pub fn is_deferring(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Look for a running manager, reporting when enforcement should be
+    /// suspended or resumed. A manager that cannot be checked counts as not
+    /// running, so the GPU is not left unmanaged.
+    pub fn poll(&mut self) -> Option<Transition> {
+        let mut running = None;
+        for manager in &self.managers {
+            match manager.is_running() {
+                Ok(true) => {
+                    running = Some(manager.clone());
+                    break;
+                }
+                Ok(false) => {}
+                Err(e) if !self.unreadable.contains(manager) => {
+                    eprintln!("warning[daemon]: cannot check for {manager}, not deferring to it: {e}");
+                    self.unreadable.push(manager.clone());
+                }
+                Err(_) => {}
+            }
+        }
+
+        match (&self.active, running) {
+            (None, Some(manager)) => {
+                output::status!("daemon: gpu {} deferring to {manager}", self.device_index);
+                self.active = Some(manager);
+                Some(Transition::Suspend)
+            }
+            (Some(manager), None) => {
+                output::status!("daemon: gpu {} {manager} stopped, resuming", self.device_index);
+                self.active = None;
+                Some(Transition::Resume)
+            }
+            (Some(_), Some(manager)) => {
+                self.active = Some(manager);
+                None
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manager() {
+        assert_eq!(Manager::parse("/run/tuner.pid"), Ok(Manager::Pidfile(PathBuf::from("/run/tuner.pid"))));
+        assert_eq!(Manager::parse("com.example.Tuner"), Ok(Manager::BusName("com.example.Tuner".to_string())));
+        assert!(Manager::parse("tuner").is_err());
+        assert!(Manager::parse("com.9example").is_err());
+        assert!(Manager::parse("com..Tuner").is_err());
+        assert!(!pid_alive(0));
+        assert!(pid_alive(std::process::id() as libc::pid_t));
+    }
+}
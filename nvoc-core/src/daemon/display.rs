@@ -0,0 +1,141 @@
+//! Re-applying offsets after display changes
+//!
+//! Connecting, disconnecting or reconfiguring a monitor forces the memory
+//! clock through a performance state transition, and some drivers drop the
+//! applied clock offsets on the way. The daemon listens for the kernel's DRM
+//! hotplug uevents and, once the change has settled, restores the offsets
+//! of the applied profile if they no longer match.
+
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::{Duration, Instant};
+
+use crate::constants::daemon;
+use crate::gpu::{overclock, state};
+use crate::nvml::{self, Device};
+use crate::profile::{self, Settings};
+use crate::units::MegaHertz;
+use crate::{output, ratelimit, AppError};
+
+/// Kernel uevent multicast group
+const UEVENT_GROUP: u32 = 1;
+
+/// Whether a kernel uevent reports a DRM hotplug or connector change
+fn is_drm_hotplug(message: &[u8]) -> bool {
+    let has = |field: &[u8]| message.split(|b| *b == 0).any(|f| f == field);
+    has(b"SUBSYSTEM=drm") && has(b"HOTPLUG=1")
+}
+
+/// Non-blocking socket receiving kernel uevents
+fn open_uevent_socket() -> std::io::Result<OwnedFd> {
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+            libc::NETLINK_KOBJECT_UEVENT,
+        )
+    };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    addr.nl_groups = UEVENT_GROUP;
+    let result = unsafe {
+        libc::bind(
+            fd.as_raw_fd(),
+            &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(fd)
+}
+
+pub struct DisplayWatch {
+    device: Device,
+    device_index: u32,
+    /// `None` when uevents are unavailable, e.g. in a network namespace
+    socket: Option<OwnedFd>,
+    /// When the last hotplug was seen, until it has been handled
+    pending: Option<Instant>,
+}
+
+impl DisplayWatch {
+    pub fn new(device: &Device, device_index: u32) -> Self {
+        let socket = match open_uevent_socket() {
+            Ok(socket) => Some(socket),
+            Err(e) => {
+                eprintln!("warning[display]: not watching for display changes: {e}");
+                None
+            }
+        };
+        Self { device: device.clone(), device_index, socket, pending: None }
+    }
+
+    /// Drain queued uevents, returning whether any was a DRM hotplug
+    fn hotplugged(&self) -> bool {
+        let Some(socket) = &self.socket else {
+            return false;
+        };
+        let mut buf = [0u8; 8192];
+        let mut seen = false;
+        loop {
+            let n = unsafe { libc::recv(socket.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+            if n <= 0 {
+                return seen;
+            }
+            seen |= is_drm_hotplug(&buf[..n as usize]);
+        }
+    }
+
+    /// Note display changes, and restore the applied profile's offsets once
+    /// a change has settled
+    pub fn poll(&mut self, min_modify_interval: Duration) -> Result<(), AppError> {
+        if self.hotplugged() {
+            self.pending = Some(Instant::now());
+        }
+        let settle = Duration::from_millis(daemon::DISPLAY_SETTLE_MS);
+        if self.pending.is_none_or(|at| at.elapsed() < settle) {
+            return Ok(());
+        }
+        self.pending = None;
+
+        let Some(name) = profile::applied(self.device_index) else {
+            return Ok(());
+        };
+        let uuid = nvml::device_get_uuid(&self.device).map_err(|e| AppError::new("display", e))?;
+        let settings = profile::load(&name)?.settings_for(&uuid);
+        let live = state::capture(&self.device);
+        let offsets = Settings {
+            graphics_offset: settings.graphics_offset.filter(|&o| live.graphics_offset != Some(MegaHertz(o))),
+            memory_offset: settings.memory_offset.filter(|&o| live.memory_offset != Some(MegaHertz(o))),
+            ..Default::default()
+        };
+        if offsets == Settings::default() {
+            return Ok(());
+        }
+
+        let _permit = ratelimit::acquire(self.device_index, min_modify_interval)?;
+        output::status!("display: configuration changed, restoring offsets of '{name}'");
+        overclock::apply(&self.device, self.device_index, &offsets.to_params(false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_drm_hotplug() {
+        let hotplug = b"change@/devices/pci0000:00/0000:00:01.0/0000:01:00.0/drm/card1\0ACTION=change\0\
+            DEVPATH=/devices/pci0000:00/0000:00:01.0/0000:01:00.0/drm/card1\0SUBSYSTEM=drm\0HOTPLUG=1\0SEQNUM=4711\0";
+        assert!(is_drm_hotplug(hotplug));
+        let usb = b"add@/devices/usb1/1-1\0ACTION=add\0SUBSYSTEM=usb\0SEQNUM=4712\0";
+        assert!(!is_drm_hotplug(usb));
+        let drm_add = b"add@/devices/virtual/drm/ttm\0ACTION=add\0SUBSYSTEM=drm\0SEQNUM=4713\0";
+        assert!(!is_drm_hotplug(drm_add));
+    }
+}
@@ -0,0 +1,169 @@
+//! Detection of settings changed behind nvoc's back
+//!
+//! Other tools, or a driver reset, can silently revert an applied profile.
+//! The daemon periodically compares the live offsets and power limit with
+//! the profile recorded by `profile apply`, warns when they differ, and
+//! with `--enforce` applies the profile again. Locked clocks cannot be read
+//! back and are not compared.
+
+use std::time::{Duration, Instant};
+
+use crate::constants::daemon;
+use crate::gpu::domain::get_power_info;
+use crate::gpu::{overclock, state};
+use crate::nvml::{self, Device};
+use crate::profile::{self, Settings};
+use crate::units::{MegaHertz, Watts};
+use crate::{output, ratelimit, AppError};
+
+/// A setting whose live value differs from the profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drift {
+    pub setting: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Live values comparable against a profile
+#[derive(Debug, Default)]
+struct Observed {
+    graphics_offset: Option<MegaHertz>,
+    memory_offset: Option<MegaHertz>,
+    power_limit_watts: Option<Watts>,
+}
+
+/// Compare observed values with what the profile sets. `expected_watts` is
+/// the profile's power limit converted for this device; values that could
+/// not be read are skipped.
+fn compare(expected: &Settings, expected_watts: Option<Watts>, observed: &Observed) -> Vec<Drift> {
+    let mut drifts = Vec::new();
+    let mut check = |setting, expected: Option<String>, actual: Option<String>| {
+        if let (Some(expected), Some(actual)) = (expected, actual) {
+            if expected != actual {
+                drifts.push(Drift { setting, expected, actual });
+            }
+        }
+    };
+    let offset = |o: Option<MegaHertz>| o.map(|o| format!("{o:+}"));
+    let watts = |w: Option<Watts>| w.map(|w| w.to_string());
+
+    check("gpu offset", offset(expected.graphics_offset.map(MegaHertz)), offset(observed.graphics_offset));
+    check("mem offset", offset(expected.memory_offset.map(MegaHertz)), offset(observed.memory_offset));
+    check("power limit", watts(expected_watts), watts(observed.power_limit_watts));
+    drifts
+}
+
+pub struct DriftWatch {
+    device: Device,
+    device_index: u32,
+    enforce: bool,
+    /// Power limits are managed by the budget and not compared
+    ignore_power: bool,
+    last_check: Option<Instant>,
+    /// Last drift reported, so a persisting drift is only reported once
+    reported: Vec<Drift>,
+}
+
+impl DriftWatch {
+    pub fn new(device: &Device, device_index: u32, enforce: bool, ignore_power: bool) -> Self {
+        Self { device: device.clone(), device_index, enforce, ignore_power, last_check: None, reported: Vec::new() }
+    }
+
+    pub fn set_ignore_power(&mut self, ignore_power: bool) {
+        self.ignore_power = ignore_power;
+    }
+
+    /// Check the device on the next poll, reporting any drift afresh
+    pub fn recheck(&mut self) {
+        self.last_check = None;
+        self.reported.clear();
+    }
+
+    /// Check the device if the check interval has elapsed
+    pub fn poll(&mut self, min_modify_interval: Duration) -> Result<(), AppError> {
+        let interval = Duration::from_millis(daemon::DRIFT_CHECK_INTERVAL_MS);
+        if self.last_check.is_some_and(|t| t.elapsed() < interval) {
+            return Ok(());
+        }
+        self.last_check = Some(Instant::now());
+
+        let Some(name) = profile::applied(self.device_index) else {
+            self.reported.clear();
+            return Ok(());
+        };
+        let uuid = nvml::device_get_uuid(&self.device).map_err(|e| AppError::new("drift", e))?;
+        let mut expected = match profile::load(&name) {
+            Ok(loaded) => loaded.settings_for(&uuid),
+            Err(e) => {
+                eprintln!("warning[drift]: {e}");
+                return Ok(());
+            }
+        };
+        if self.ignore_power {
+            expected.power_limit = None;
+        }
+
+        let power = get_power_info(&self.device).ok();
+        let live = state::capture(&self.device);
+        let observed = Observed {
+            graphics_offset: live.graphics_offset,
+            memory_offset: live.memory_offset,
+            power_limit_watts: power.as_ref().map(|p| p.limit_watts),
+        };
+        let expected_watts = expected
+            .power_limit
+            .zip(power.as_ref())
+            .map(|(percentage, p)| p.effective_watts_from_percentage(percentage));
+
+        let drifts = compare(&expected, expected_watts, &observed);
+        if drifts.is_empty() {
+            self.reported.clear();
+            return Ok(());
+        }
+        if drifts != self.reported || self.enforce {
+            for drift in &drifts {
+                eprintln!(
+                    "warning[drift]: gpu {} {} is {}, profile '{name}' sets {}",
+                    self.device_index, drift.setting, drift.actual, drift.expected
+                );
+            }
+        }
+        self.reported = drifts;
+
+        if self.enforce {
+            let _permit = ratelimit::acquire(self.device_index, min_modify_interval)?;
+            output::status!("drift: re-applying profile '{name}'");
+            overclock::apply(&self.device, self.device_index, &expected.to_params(false))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_reports_changed_settings_only() {
+        let expected = Settings {
+            clocks: Some((200, 2820)),
+            graphics_offset: Some(856),
+            memory_offset: Some(2000),
+            power_limit: Some(105),
+            ..Default::default()
+        };
+        let observed = Observed {
+            graphics_offset: Some(MegaHertz(0)),
+            memory_offset: Some(MegaHertz(2000)),
+            power_limit_watts: None,
+        };
+        assert_eq!(
+            compare(&expected, Some(Watts(604)), &observed),
+            vec![Drift {
+                setting: "gpu offset",
+                expected: "+856MHz".to_string(),
+                actual: "+0MHz".to_string(),
+            }]
+        );
+    }
+}
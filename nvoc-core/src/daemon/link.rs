@@ -0,0 +1,146 @@
+//! Linked policies between GPUs
+//!
+//! A `[[link]]` rule watches one GPU and applies a profile to another while
+//! the watched card overheats, throttles or stops responding, for example
+//! capping a compute card when the display card next to it runs hot. The
+//! rule is evaluated by the daemon of the watched GPU. The other card's
+//! previous settings and profile are restored once the trigger clears, or
+//! when the daemon stops while the watched card is still answering.
+
+use std::time::Duration;
+
+use crate::config::LinkSection;
+use crate::constants::daemon;
+use crate::gpu::{self, state};
+use crate::nvml::{self, Device};
+use crate::profile::{self, stability::SLOWDOWN_MASK};
+use crate::{output, ratelimit, AppError};
+
+/// What the watched GPU reported this tick, `None` when it did not answer
+#[derive(Debug, Clone, Copy, Default)]
+struct Reading {
+    temperature: Option<u32>,
+    reasons: Option<u64>,
+}
+
+fn read(device: &Device) -> Option<Reading> {
+    let temperature = nvml::device_get_temperature(device).ok()?;
+    Some(Reading { temperature: Some(temperature), reasons: nvml::device_get_current_clocks_event_reasons(device).ok() })
+}
+
+/// Why `rule` fires for `reading`, if it does. While `engaged`, the
+/// temperature trigger only clears below the threshold minus the hysteresis.
+fn trigger(rule: &LinkSection, reading: Option<Reading>, engaged: bool) -> Option<String> {
+    let Some(reading) = reading else {
+        return rule.failed.then(|| "not responding".to_string());
+    };
+    if let (Some(threshold), Some(temperature)) = (rule.temperature_above, reading.temperature) {
+        let release = match engaged {
+            true => threshold.saturating_sub(daemon::LINK_HYSTERESIS_C),
+            false => threshold,
+        };
+        if temperature >= release {
+            return Some(format!("{temperature}°C"));
+        }
+    }
+    if rule.throttled && reading.reasons.is_some_and(|r| r & SLOWDOWN_MASK != 0) {
+        return Some("throttling".to_string());
+    }
+    None
+}
+
+/// Target state saved when a rule engaged
+struct Engaged {
+    target: Device,
+    saved: state::DeviceState,
+    profile: Option<String>,
+    /// Engaged because the source stopped answering
+    source_failed: bool,
+}
+
+pub struct LinkWatch {
+    rule: LinkSection,
+    engaged: Option<Engaged>,
+}
+
+impl LinkWatch {
+    pub fn new(rule: LinkSection) -> Self {
+        Self { rule, engaged: None }
+    }
+
+    /// Evaluate the rule against the watched device and engage or release
+    pub fn poll(&mut self, device: &Device, min_modify_interval: Duration) -> Result<(), AppError> {
+        let reading = read(device);
+        let cause = trigger(&self.rule, reading, self.engaged.is_some());
+        match (cause, self.engaged.is_some()) {
+            (Some(cause), false) => self.engage(&cause, reading.is_none(), min_modify_interval),
+            (None, true) => {
+                let _permit = ratelimit::acquire(self.rule.target, min_modify_interval)?;
+                self.release()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn engage(&mut self, cause: &str, source_failed: bool, min_modify_interval: Duration) -> Result<(), AppError> {
+        let (source, target) = (self.rule.source, self.rule.target);
+        let device = &gpu::get_device(target).map_err(|e| AppError::new("link", e))?;
+        let saved = state::capture(device);
+        let previous = profile::applied(target);
+
+        let _permit = ratelimit::acquire(target, min_modify_interval)?;
+        output::status!("link: gpu {source} {cause}, applying '{}' to gpu {target}", self.rule.profile);
+        profile::apply(device, target, &self.rule.profile, false)?;
+        self.engaged = Some(Engaged { target: device.clone(), saved, profile: previous, source_failed });
+        Ok(())
+    }
+
+    fn release(&mut self) -> Result<(), AppError> {
+        let Some(engaged) = self.engaged.take() else {
+            return Ok(());
+        };
+        let (source, target) = (self.rule.source, self.rule.target);
+        output::status!("link: gpu {source} recovered, restoring gpu {target}");
+        state::restore(&engaged.target, &engaged.saved).map_err(|e| AppError::new("link", e))?;
+        profile::record_applied(target, engaged.profile.as_deref())
+    }
+}
+
+impl Drop for LinkWatch {
+    fn drop(&mut self) {
+        // A failed source usually takes the daemon down with it; the target
+        // keeps the failover profile rather than being restored on the way out
+        if self.engaged.as_ref().is_some_and(|e| e.source_failed) {
+            let (source, target) = (self.rule.source, self.rule.target);
+            eprintln!("warning[link]: gpu {source} not responding, leaving gpu {target} at '{}'", self.rule.profile);
+            return;
+        }
+        if let Err(e) = self.release() {
+            eprintln!("{e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule() -> LinkSection {
+        toml::from_str("source = 0\ntarget = 1\nprofile = \"capped\"\ntemperature_above = 83\nthrottled = true\n").unwrap()
+    }
+
+    #[test]
+    fn test_trigger() {
+        let hot = |t| Some(Reading { temperature: Some(t), reasons: Some(0) });
+        assert_eq!(trigger(&rule(), hot(84), false).as_deref(), Some("84°C"));
+        assert_eq!(trigger(&rule(), hot(80), false), None);
+        // Engaged rules hold until the temperature drops past the hysteresis
+        assert_eq!(trigger(&rule(), hot(80), true).as_deref(), Some("80°C"));
+        assert_eq!(trigger(&rule(), hot(77), true), None);
+
+        let throttled = Some(Reading { temperature: Some(60), reasons: Some(SLOWDOWN_MASK) });
+        assert_eq!(trigger(&rule(), throttled, false).as_deref(), Some("throttling"));
+        // Failure only counts when the rule asks for it
+        assert_eq!(trigger(&rule(), None, false), None);
+    }
+}
@@ -0,0 +1,88 @@
+//! Memory junction temperature cutoff
+//!
+//! GDDR memory overheats long before the GPU core reports anything
+//! unusual, and a memory offset makes it worse. With
+//! `daemon.max_memory_temperature` set, the memory offsets go back to zero
+//! once the junction temperature passes the limit, and the applied profile
+//! is forgotten so `--enforce` does not raise them again.
+
+use std::time::Duration;
+
+use crate::constants::clocks;
+use crate::gpu::{field, overclock};
+use crate::nvml::{Device, NvmlClockType};
+use crate::{profile, ratelimit, AppError};
+
+/// Whether the memory offset has to come down: the junction is above
+/// `limit` and the memory is overclocked. A negative offset already helps.
+fn should_cut(temp: u32, limit: u32, memory_offset: i32) -> bool {
+    temp > limit && memory_offset > 0
+}
+
+pub struct MemoryTempWatch {
+    device: Device,
+    device_index: u32,
+    /// Whether the GPU reports a junction temperature; warned about once
+    supported: bool,
+}
+
+impl MemoryTempWatch {
+    pub fn new(device: &Device, device_index: u32) -> Self {
+        Self { device: device.clone(), device_index, supported: true }
+    }
+
+    /// Reset the memory offsets if the junction is above `limit`
+    pub fn poll(&mut self, limit: Option<u32>, min_modify_interval: Duration) -> Result<(), AppError> {
+        let Some(limit) = limit.filter(|_| self.supported) else {
+            return Ok(());
+        };
+        let temp = match field::memory_temperature(&self.device) {
+            Ok(temp) => temp,
+            Err(e) => {
+                eprintln!(
+                    "warning[memtemp]: gpu {}: memory temperature unavailable ({}), max_memory_temperature is ignored",
+                    self.device_index,
+                    e.user_message()
+                );
+                self.supported = false;
+                return Ok(());
+            }
+        };
+        let offset = overclock::memory_offset(&self.device).unwrap_or_default();
+        if !should_cut(temp, limit, offset.0) {
+            return Ok(());
+        }
+
+        let _permit = ratelimit::acquire(self.device_index, min_modify_interval)?;
+        eprintln!(
+            "warning[memtemp]: gpu {} memory at {temp}°C is above {limit}°C, resetting the memory offset",
+            self.device_index
+        );
+        let mut result = overclock::set_memory_offset(&self.device, clocks::DEFAULT_MEMORY_OFFSET)
+            .map_err(|e| AppError::new("memtemp", e));
+        for (pstate, _) in overclock::pstate_offsets(&self.device, NvmlClockType::Memory) {
+            result = result.and(overclock::set_pstate_offset(
+                &self.device,
+                NvmlClockType::Memory,
+                pstate,
+                clocks::DEFAULT_MEMORY_OFFSET,
+            ));
+        }
+        // Forget the profile so `--enforce` does not apply it again
+        profile::record_applied(self.device_index, None)?;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_cut() {
+        assert!(should_cut(106, 100, 2000));
+        assert!(!should_cut(100, 100, 2000));
+        assert!(!should_cut(106, 100, 0));
+        assert!(!should_cut(106, 100, -500));
+    }
+}
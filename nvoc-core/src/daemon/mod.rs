@@ -0,0 +1,416 @@
+//! Long-running daemon mode
+//!
+//! Polls GPU temperature and drives the fans along a user-defined curve,
+//! either from the config or from the fan curve of the applied profile,
+//! and/or keeps a group of GPUs under a shared power budget. Fans and power
+//! limits are restored when the daemon exits, whether through a signal, an
+//! error, or a panic. Settings of the applied profile are watched for drift
+//! throughout and its offsets restored after display hotplugs, telemetry is
+//! optionally recorded for `nvoc history`, and `[[link]]` rules watching
+//! this GPU adjust other GPUs. An XID error reverts the GPU's overclock,
+//! and a memory junction above `max_memory_temperature` its memory offset.
+//! SIGHUP reloads the configuration without a restart. Each GPU is driven
+//! by its own control loop, restarted by the supervisor when it fails.
+//! While an external manager given with `--defer-to` runs, the loop stops
+//! writing to the GPU.
+
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+use crate::config::ConfigFile;
+use crate::gpu::domain;
+use crate::gpu::fan::{self, AutoFanGuard};
+use crate::gpu::virtualization::{self, Knob};
+use crate::history::Recorder;
+use crate::xid::XidGuard;
+use crate::nvml::{self, device_get_temperature, Device};
+use crate::{config, gpu, output, profile, ratelimit, signal, AppError};
+
+pub mod budget;
+pub mod control;
+pub mod curve;
+pub mod defer;
+pub mod display;
+pub mod drift;
+pub mod link;
+pub mod memtemp;
+pub mod reload;
+pub mod rpc;
+pub mod supervisor;
+
+use budget::PowerBudget;
+use curve::{FanController, FanCurve};
+use defer::{Deferral, Manager, Transition};
+use display::DisplayWatch;
+use drift::DriftWatch;
+use link::LinkWatch;
+use memtemp::MemoryTempWatch;
+
+fn check_configured(config: &ConfigFile, config_path: &Path, enforce: bool, profile_curve: bool) -> Result<(), AppError> {
+    let idle = config.fan.is_none()
+        && config.budget.is_none()
+        && config.history.is_none()
+        && config.links.is_empty()
+        && config.daemon.max_memory_temperature.is_none();
+    if idle && !enforce && !profile_curve {
+        return Err(AppError::msg(
+            "config",
+            format!(
+                "{}: no [fan] curve, [budget], [history], [[link]] or max_memory_temperature configured, and the applied profile has no fan curve",
+                config_path.display()
+            ),
+        ));
+    }
+    // Profiles are loaded up front so a typo surfaces at start, not on the first trigger
+    config.links.iter().try_for_each(|link| link.validate().and_then(|()| profile::load(&link.profile).map(drop)))
+}
+
+/// Fan curve of the profile applied to the device, if it has one
+fn profile_fan_curve(device: &Device, device_index: u32) -> Result<Option<FanCurve>, AppError> {
+    let Some(name) = profile::applied(device_index) else {
+        return Ok(None);
+    };
+    let uuid = nvml::device_get_uuid(device).map_err(|e| AppError::new("profile", e))?;
+    profile::load(&name)?.settings_for(&uuid).curve()
+}
+
+/// The `[fan]` curve from the config, which takes precedence, or else the
+/// applied profile's curve
+fn fan_controller(device: &Device, device_index: u32, config: &ConfigFile) -> Result<Option<FanController>, AppError> {
+    let controller = match &config.fan {
+        Some(fan_config) => Some(FanController::new(fan_config.fan_curve()?, fan_config.hysteresis)),
+        None => profile_fan_curve(device, device_index)?.map(|curve| FanController::new(curve, 0)),
+    };
+    if controller.is_some() {
+        virtualization::check_knobs(device, &[Knob::Fans])?;
+    }
+    Ok(controller)
+}
+
+fn recorder(config: &ConfigFile) -> Result<Option<Recorder>, AppError> {
+    match &config.history {
+        Some(h) => Ok(Some(Recorder::open(&h.path, h.retention(), h.resolution)?)),
+        None => Ok(None),
+    }
+}
+
+/// Watches for the rules whose source is this GPU
+fn link_watches(config: &ConfigFile, device_index: u32) -> Vec<LinkWatch> {
+    config.links.iter().filter(|l| l.source == device_index).cloned().map(LinkWatch::new).collect()
+}
+
+fn is_budgeted(config: &ConfigFile, device_index: u32) -> bool {
+    config
+        .budget
+        .as_ref()
+        .is_some_and(|b| b.devices.is_empty() || b.devices.contains(&device_index))
+}
+
+/// Everything the control loop drives, rebuilt from the config on reload
+struct Daemon {
+    device: Device,
+    device_index: u32,
+    config: ConfigFile,
+    controller: Option<FanController>,
+    fan_guard: Option<AutoFanGuard>,
+    /// Applied profile the fan curve was last taken from, when the config
+    /// has no `[fan]` section
+    fan_profile: Option<String>,
+    /// Whether this loop drives the `[budget]`, which spans several GPUs
+    owns_budget: bool,
+    budget: Option<PowerBudget>,
+    drift: DriftWatch,
+    display: DisplayWatch,
+    recorder: Option<Recorder>,
+    links: Vec<LinkWatch>,
+    xid: XidGuard,
+    memory_temp: MemoryTempWatch,
+    defer: Deferral,
+}
+
+impl Daemon {
+    fn new(
+        device: &Device,
+        device_index: u32,
+        config: ConfigFile,
+        enforce: bool,
+        owns_budget: bool,
+        defer_to: Vec<Manager>,
+    ) -> Result<Self, AppError> {
+        let controller = fan_controller(device, device_index, &config)?;
+        let fan_guard = controller.as_ref().map(|_| AutoFanGuard::new(device));
+        let fan_profile = profile::applied(device_index);
+        let budget = match &config.budget {
+            Some(b) if owns_budget => Some(PowerBudget::new(b.total_watts, &b.devices)?),
+            _ => None,
+        };
+        let drift = DriftWatch::new(device, device_index, enforce, is_budgeted(&config, device_index));
+        let display = DisplayWatch::new(device, device_index);
+        let recorder = recorder(&config)?;
+        let links = link_watches(&config, device_index);
+        let xid = XidGuard::new(device, device_index);
+        let memory_temp = MemoryTempWatch::new(device, device_index);
+        let defer = Deferral::new(device_index, defer_to);
+        Ok(Self {
+            device: device.clone(),
+            device_index,
+            config,
+            controller,
+            fan_guard,
+            fan_profile,
+            owns_budget,
+            budget,
+            drift,
+            display,
+            recorder,
+            links,
+            xid,
+            memory_temp,
+            defer,
+        })
+    }
+
+    /// Swap the fan controller, returning fans to automatic control when
+    /// there is no longer a curve to follow
+    fn set_controller(&mut self, controller: Option<FanController>) {
+        self.fan_guard = match controller {
+            Some(_) => self.fan_guard.take().or_else(|| Some(AutoFanGuard::new(&self.device))),
+            None => None,
+        };
+        self.controller = controller;
+    }
+
+    /// Pick up the fan curve of a newly applied profile
+    fn follow_profile(&mut self) -> Result<(), AppError> {
+        let applied = profile::applied(self.device_index);
+        if self.config.fan.is_some() || applied == self.fan_profile {
+            return Ok(());
+        }
+        let controller = match fan_controller(&self.device, self.device_index, &self.config) {
+            Ok(controller) => controller,
+            Err(e) => {
+                eprintln!("warning[fan]: keeping current fan control: {e}");
+                self.fan_profile = applied;
+                return Ok(());
+            }
+        };
+        match &applied {
+            Some(name) if controller.is_some() => output::status!("fan: following the curve of '{name}'"),
+            _ if self.controller.is_some() => output::status!("fan: profile curve removed"),
+            _ => {}
+        }
+        self.fan_profile = applied;
+        self.set_controller(controller);
+        Ok(())
+    }
+
+    /// Hand the GPU to an external manager: fans go back to automatic
+    /// control and nothing is written until it stops
+    fn suspend(&mut self) {
+        self.fan_guard = None;
+    }
+
+    /// Take the GPU back, forgetting what was last written since the
+    /// manager may have changed it
+    fn resume(&mut self) {
+        if let Some(controller) = self.controller.as_mut() {
+            controller.reset();
+            self.fan_guard.get_or_insert_with(|| AutoFanGuard::new(&self.device));
+        }
+        if let Some(budget) = self.budget.as_mut() {
+            budget.resync();
+        }
+        self.drift.recheck();
+    }
+
+    fn tick(&mut self) -> Result<(), AppError> {
+        let min_modify_interval = self.config.limits.min_modify_interval;
+        match self.defer.poll() {
+            Some(Transition::Suspend) => self.suspend(),
+            Some(Transition::Resume) => self.resume(),
+            None => {}
+        }
+        if self.defer.is_deferring() {
+            // Faults under the manager's settings are the manager's to handle
+            self.xid.drain()?;
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.record(self.device_index)?;
+            }
+            return Ok(());
+        }
+        // An unstable overclock is reverted before anything else touches the GPU
+        self.xid.check()?;
+        self.memory_temp.poll(self.config.daemon.max_memory_temperature, min_modify_interval)?;
+        // Links first, so a source that stops answering is acted on before
+        // the fan controller fails on it
+        for link in &mut self.links {
+            link.poll(&self.device, min_modify_interval)?;
+        }
+        self.follow_profile()?;
+        if let Some(controller) = self.controller.as_mut() {
+            let temp = device_get_temperature(&self.device).map_err(|e| AppError::new("temp", e))?;
+            if let Some(speed) = controller.update(temp) {
+                let speed = fan::clamp_to_range(&self.device, speed);
+                let _permit = ratelimit::acquire(self.device_index, min_modify_interval)?;
+                self.fan_guard.get_or_insert_with(|| AutoFanGuard::new(&self.device)).journal()?;
+                fan::set_speed(&self.device, speed).map_err(|e| AppError::new("fan", e))?;
+                output::status!("fan: {speed}% at {temp}°C");
+            }
+        }
+        if let Some(budget) = self.budget.as_mut() {
+            budget.rebalance(min_modify_interval)?;
+        }
+        self.display.poll(min_modify_interval)?;
+        self.drift.poll(min_modify_interval)?;
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(self.device_index)?;
+        }
+        Ok(())
+    }
+
+    /// Switch to `next`. Everything that can fail is done before the
+    /// running state is touched, so a bad config leaves the daemon as it was.
+    fn reload(&mut self, next: ConfigFile) -> Result<(), AppError> {
+        let changes = reload::diff(&self.config, &next);
+        if changes.is_empty() {
+            output::status!("reload: no changes");
+            return Ok(());
+        }
+
+        let fan_changed = changes.iter().any(|c| c.starts_with("fan."));
+        let controller = match fan_changed {
+            true => fan_controller(&self.device, self.device_index, &next)?,
+            false => None,
+        };
+        let history_changed = changes.iter().any(|c| c.starts_with("history."));
+        let next_recorder = match history_changed {
+            true => recorder(&next)?,
+            false => None,
+        };
+        let budget_changed = self.owns_budget && changes.iter().any(|c| c.starts_with("budget."));
+        if budget_changed {
+            match (self.budget.as_mut(), &next.budget) {
+                (Some(budget), Some(b)) => budget.reconfigure(b.total_watts, &b.devices)?,
+                (None, Some(b)) => self.budget = Some(PowerBudget::new(b.total_watts, &b.devices)?),
+                (_, None) => self.budget = None,
+            }
+        }
+
+        for change in &changes {
+            output::status!("reload: {change}");
+        }
+        if changes.iter().any(|c| c.starts_with("daemon.socket") || c.starts_with("daemon.admin_group")) {
+            eprintln!("warning[reload]: control socket changes take effect on restart");
+        }
+        if fan_changed {
+            self.fan_profile = profile::applied(self.device_index);
+            self.set_controller(controller);
+        }
+        if history_changed {
+            self.recorder = next_recorder;
+        }
+        if changes.iter().any(|c| c.starts_with("link")) {
+            // Dropping the old watches restores any GPU they had changed
+            self.links.clear();
+            self.links = link_watches(&next, self.device_index);
+        }
+        self.drift.set_ignore_power(is_budgeted(&next, self.device_index));
+        domain::set_power_floor(next.limits.min_power_limit);
+        domain::set_power_raise_on_battery(next.limits.allow_power_raise_on_battery);
+        self.config = next;
+        Ok(())
+    }
+}
+
+/// Set up and poll one GPU until a termination signal. Errors and panics
+/// end the loop and leave restarting it to the supervisor.
+fn control_loop(
+    device_index: u32,
+    config_path: &Path,
+    enforce: bool,
+    owns_budget: bool,
+    defer_to: Vec<Manager>,
+    flags: &supervisor::Flags,
+) -> Result<(), AppError> {
+    let device = &gpu::get_device(device_index).map_err(|e| AppError::new("device", e))?;
+    let config = config::load(config_path)?;
+    domain::set_power_floor(config.limits.min_power_limit);
+    domain::set_power_raise_on_battery(config.limits.allow_power_raise_on_battery);
+    let mut daemon = Daemon::new(device, device_index, config, enforce, owns_budget, defer_to)?;
+    flags.running.store(true, Ordering::SeqCst);
+
+    while !signal::stop_requested() {
+        if flags.reload.swap(false, Ordering::SeqCst) {
+            let result = config::load(config_path)
+                .and_then(|next| {
+                    let profile_curve = daemon.controller.is_some() && daemon.config.fan.is_none();
+                    check_configured(&next, config_path, enforce, profile_curve).map(|()| next)
+                })
+                .and_then(|next| daemon.reload(next));
+            if let Err(e) = result {
+                eprintln!("warning[reload]: keeping current config: {e}");
+            }
+        }
+        daemon.tick()?;
+        signal::sleep(daemon.config.daemon.interval);
+    }
+    Ok(())
+}
+
+/// Run the daemon for the GPUs in `indices`, each in its own control loop,
+/// standing aside while one of `defer_to` runs
+pub fn run(indices: &[u32], config_path: &Path, enforce: bool, defer_to: &[Manager]) -> Result<(), AppError> {
+    // The budget spans GPUs, so only the first loop drives it
+    let Some(&budget_owner) = indices.first() else {
+        return Err(AppError::msg("device", "no GPUs found".to_string()));
+    };
+    let config = config::load(config_path)?;
+    let mut profile_curve = false;
+    for &device_index in indices {
+        let device = &gpu::get_device(device_index).map_err(|e| AppError::new("device", e))?;
+        profile_curve |= profile_fan_curve(device, device_index)?.is_some();
+    }
+    check_configured(&config, config_path, enforce, profile_curve)?;
+    if let Some(template) = config.daemon.socket.as_ref().filter(|t| !t.to_string_lossy().contains("{gpu}")) {
+        if indices.len() > 1 {
+            return Err(AppError::msg(
+                "config",
+                format!("daemon.socket {}: needs a {{gpu}} placeholder to serve several GPUs", template.display()),
+            ));
+        }
+    }
+
+    signal::install();
+    signal::install_reload();
+    let mut control_sockets = Vec::new();
+    if let Some(template) = &config.daemon.socket {
+        for &device_index in indices {
+            control_sockets.push(control::spawn(
+                template,
+                config.daemon.socket_mode,
+                config.daemon.admin_group.as_deref(),
+                device_index,
+                config.limits.min_modify_interval,
+            )?);
+        }
+    }
+    for &device_index in indices {
+        output::status!("daemon: gpu {device_index}, polling every {}ms", config.daemon.interval.as_millis());
+    }
+
+    let result = supervisor::run(indices, |device_index, flags| {
+        let config_path = config_path.to_path_buf();
+        let defer_to = defer_to.to_vec();
+        std::thread::Builder::new()
+            .name(format!("gpu-{device_index}"))
+            .spawn(move || {
+                control_loop(device_index, &config_path, enforce, device_index == budget_owner, defer_to, &flags)
+            })
+            .expect("spawning a control loop thread")
+    });
+
+    for path in control_sockets {
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
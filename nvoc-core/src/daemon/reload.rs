@@ -0,0 +1,100 @@
+//! Differences between two configurations, logged on reload
+
+use std::fmt::Debug;
+
+use crate::config::ConfigFile;
+
+fn show<T: Debug>(value: Option<T>) -> String {
+    match value {
+        Some(value) => format!("{value:?}"),
+        None => "unset".to_string(),
+    }
+}
+
+/// One `key: old -> new` line per changed setting
+pub fn diff(old: &ConfigFile, new: &ConfigFile) -> Vec<String> {
+    let mut changes = Vec::new();
+    let mut compare = |key: &str, before: String, after: String| {
+        if before != after {
+            changes.push(format!("{key}: {before} -> {after}"));
+        }
+    };
+
+    compare("daemon.interval", show(Some(old.daemon.interval)), show(Some(new.daemon.interval)));
+    compare("daemon.socket", show(old.daemon.socket.as_ref()), show(new.daemon.socket.as_ref()));
+    compare(
+        "daemon.socket_mode",
+        format!("{:o}", old.daemon.socket_mode),
+        format!("{:o}", new.daemon.socket_mode),
+    );
+    compare("daemon.admin_group", show(old.daemon.admin_group.as_ref()), show(new.daemon.admin_group.as_ref()));
+    compare(
+        "daemon.max_memory_temperature",
+        show(old.daemon.max_memory_temperature),
+        show(new.daemon.max_memory_temperature),
+    );
+    compare("fan.curve", show(old.fan.as_ref().map(|f| &f.curve)), show(new.fan.as_ref().map(|f| &f.curve)));
+    compare(
+        "fan.hysteresis",
+        show(old.fan.as_ref().map(|f| f.hysteresis)),
+        show(new.fan.as_ref().map(|f| f.hysteresis)),
+    );
+    compare(
+        "budget.total_watts",
+        show(old.budget.as_ref().map(|b| b.total_watts)),
+        show(new.budget.as_ref().map(|b| b.total_watts)),
+    );
+    compare(
+        "budget.devices",
+        show(old.budget.as_ref().map(|b| &b.devices)),
+        show(new.budget.as_ref().map(|b| &b.devices)),
+    );
+    compare(
+        "limits.min_modify_interval",
+        show(Some(old.limits.min_modify_interval)),
+        show(Some(new.limits.min_modify_interval)),
+    );
+    compare("limits.min_power_limit", show(old.limits.min_power_limit), show(new.limits.min_power_limit));
+    compare(
+        "limits.allow_power_raise_on_battery",
+        show(Some(old.limits.allow_power_raise_on_battery)),
+        show(Some(new.limits.allow_power_raise_on_battery)),
+    );
+    compare("history.path", show(old.history.as_ref().map(|h| &h.path)), show(new.history.as_ref().map(|h| &h.path)));
+    compare(
+        "history.resolution",
+        show(old.history.as_ref().map(|h| h.resolution)),
+        show(new.history.as_ref().map(|h| h.resolution)),
+    );
+    compare(
+        "history.retention",
+        show(old.history.as_ref().and_then(|h| h.retention)),
+        show(new.history.as_ref().and_then(|h| h.retention)),
+    );
+    compare(
+        "history.max_rows",
+        show(old.history.as_ref().and_then(|h| h.max_rows)),
+        show(new.history.as_ref().and_then(|h| h.max_rows)),
+    );
+    compare("link", format!("{:?}", old.links), format!("{:?}", new.links));
+    compare("boot.fallback", show(old.boot.fallback.as_ref()), show(new.boot.fallback.as_ref()));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lists_changed_settings() {
+        let old: ConfigFile = toml::from_str("[fan]\ncurve = [[40, 30], [80, 100]]\n").unwrap();
+        let new: ConfigFile =
+            toml::from_str("[daemon]\ninterval = \"1s\"\n\n[fan]\ncurve = [[40, 30], [80, 100]]\nhysteresis = 3\n")
+                .unwrap();
+        assert_eq!(
+            diff(&old, &new),
+            vec!["daemon.interval: 2s -> 1s".to_string(), "fan.hysteresis: 0 -> 3".to_string()]
+        );
+        assert!(diff(&new, &new).is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! JSON-RPC 2.0 framing for the control socket
+//!
+//! A request is one JSON object on a single line. Every request gets a
+//! response line, including notifications without an `id`, since the
+//! connection carries nothing else.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Implementation-defined server errors
+const SERVER_ERROR: i64 = -32000;
+const PERMISSION_DENIED: i64 = -32001;
+
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug)]
+pub struct Request {
+    pub id: Value,
+    pub method: String,
+    pub params: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    pub fn method_not_found(method: &str) -> Self {
+        Self { code: METHOD_NOT_FOUND, message: format!("unknown method '{method}'") }
+    }
+
+    pub fn invalid_params(message: String) -> Self {
+        Self { code: INVALID_PARAMS, message }
+    }
+
+    pub fn permission_denied() -> Self {
+        Self { code: PERMISSION_DENIED, message: "permission denied".to_string() }
+    }
+
+    pub fn server(message: String) -> Self {
+        Self { code: SERVER_ERROR, message }
+    }
+}
+
+/// Parse a request line. On failure the error is paired with whatever id
+/// could be recovered, for the response.
+pub fn parse(line: &str) -> Result<Request, (Value, RpcError)> {
+    let value: Value = serde_json::from_str(line)
+        .map_err(|e| (Value::Null, RpcError { code: PARSE_ERROR, message: e.to_string() }))?;
+    let id = value.get("id").cloned().unwrap_or(Value::Null);
+    let invalid = |message: String| (id.clone(), RpcError { code: INVALID_REQUEST, message });
+
+    let envelope: Envelope = serde_json::from_value(value).map_err(|e| invalid(e.to_string()))?;
+    if envelope.jsonrpc != "2.0" {
+        return Err(invalid(format!("unsupported jsonrpc version '{}'", envelope.jsonrpc)));
+    }
+    Ok(Request { id: envelope.id, method: envelope.method, params: envelope.params })
+}
+
+/// Deserialize a request's params into `T`
+pub fn params<T: for<'de> Deserialize<'de>>(params: &Value) -> Result<T, RpcError> {
+    serde_json::from_value(params.clone()).map_err(|e| RpcError::invalid_params(e.to_string()))
+}
+
+pub fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": e.code, "message": e.message } }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_requests() {
+        let request = parse(r#"{"jsonrpc": "2.0", "id": 7, "method": "apply", "params": {"profile": "daily"}}"#).unwrap();
+        assert_eq!((request.id, request.method.as_str()), (json!(7), "apply"));
+        assert_eq!(request.params, json!({"profile": "daily"}));
+
+        let (id, error) = parse(r#"{"jsonrpc": "1.0", "id": "a", "method": "status"}"#).unwrap_err();
+        assert_eq!((id, error.code), (json!("a"), INVALID_REQUEST));
+        assert_eq!(parse("status").unwrap_err().1.code, PARSE_ERROR);
+    }
+
+    #[test]
+    fn test_error_response() {
+        assert_eq!(
+            response(json!(1), Err(RpcError::permission_denied())),
+            json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32001, "message": "permission denied"}})
+        );
+    }
+}
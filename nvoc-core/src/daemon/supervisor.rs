@@ -0,0 +1,148 @@
+//! Supervision of the per-GPU control loops
+//!
+//! Each GPU's control loop runs in a thread of its own, so a device that
+//! stalls in the driver or keeps failing does not hold up the others. A
+//! loop that returns an error or panics has already restored its fans and
+//! power limits while unwinding, and is started again after a delay that
+//! doubles with every consecutive failure. A loop that fails before it
+//! ever got running stops the daemon instead, so configuration errors
+//! still surface at start.
+
+use std::any::Any;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::constants::daemon;
+use crate::{signal, AppError};
+
+/// Flags shared between the supervisor and one control loop
+#[derive(Default)]
+pub struct Flags {
+    /// Set by the supervisor on SIGHUP, taken by the loop
+    pub reload: AtomicBool,
+    /// Set by the loop once it is set up and polling
+    pub running: AtomicBool,
+}
+
+pub type Handle = JoinHandle<Result<(), AppError>>;
+
+/// Delay before the `failures`th consecutive restart
+fn restart_delay(failures: u32) -> Duration {
+    let min = Duration::from_millis(daemon::WORKER_RESTART_MIN_MS);
+    let max = Duration::from_millis(daemon::WORKER_RESTART_MAX_MS);
+    min.saturating_mul(1 << failures.saturating_sub(1).min(16)).min(max)
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => panic.downcast_ref::<String>().map_or("unknown cause", String::as_str),
+    }
+}
+
+struct Worker {
+    device_index: u32,
+    flags: Arc<Flags>,
+    handle: Option<Handle>,
+    started: Instant,
+    /// Whether any run of the loop got as far as polling
+    ever_ran: bool,
+    failures: u32,
+    restart_at: Option<Instant>,
+}
+
+impl Worker {
+    fn start(device_index: u32, launch: &impl Fn(u32, Arc<Flags>) -> Handle) -> Self {
+        let flags = Arc::new(Flags::default());
+        Self {
+            device_index,
+            handle: Some(launch(device_index, Arc::clone(&flags))),
+            flags,
+            started: Instant::now(),
+            ever_ran: false,
+            failures: 0,
+            restart_at: None,
+        }
+    }
+
+    /// Restart the loop when it is due, and schedule a restart when it has
+    /// stopped. Fails when the loop never got running.
+    fn check(&mut self, launch: &impl Fn(u32, Arc<Flags>) -> Handle) -> Result<(), AppError> {
+        if let Some(at) = self.restart_at {
+            if Instant::now() >= at {
+                self.restart_at = None;
+                self.flags.running.store(false, Ordering::SeqCst);
+                self.started = Instant::now();
+                self.handle = Some(launch(self.device_index, Arc::clone(&self.flags)));
+            }
+            return Ok(());
+        }
+        let Some(handle) = self.handle.take_if(|h| h.is_finished()) else {
+            return Ok(());
+        };
+        self.ever_ran |= self.flags.running.load(Ordering::SeqCst);
+        let error = match handle.join() {
+            Ok(Ok(())) => return Ok(()),
+            Ok(Err(e)) => e,
+            Err(panic) => AppError::msg(
+                "daemon",
+                format!("gpu {} control loop panicked: {}", self.device_index, panic_message(&*panic)),
+            ),
+        };
+        if !self.ever_ran {
+            return Err(error);
+        }
+        if !error.printed {
+            eprintln!("{error}");
+        }
+
+        if self.started.elapsed() >= Duration::from_millis(daemon::WORKER_STABLE_MS) {
+            self.failures = 0;
+        }
+        self.failures += 1;
+        let delay = restart_delay(self.failures);
+        eprintln!("warning[daemon]: restarting gpu {} control loop in {}s", self.device_index, delay.as_secs());
+        self.restart_at = Some(Instant::now() + delay);
+        Ok(())
+    }
+}
+
+/// Run a control loop per device from `launch` until a termination signal,
+/// forwarding reload requests and restarting loops that stop
+pub fn run(indices: &[u32], launch: impl Fn(u32, Arc<Flags>) -> Handle) -> Result<(), AppError> {
+    let mut workers: Vec<Worker> = indices.iter().map(|&i| Worker::start(i, &launch)).collect();
+    let mut result = Ok(());
+    while result.is_ok() && !signal::stop_requested() {
+        if signal::take_reload() {
+            for worker in &workers {
+                worker.flags.reload.store(true, Ordering::SeqCst);
+            }
+        }
+        result = workers.iter_mut().try_for_each(|worker| worker.check(&launch));
+        signal::sleep(Duration::from_millis(daemon::SUPERVISOR_POLL_MS));
+    }
+
+    // Stop the remaining loops when one failed to start
+    signal::request_stop();
+    for handle in workers.into_iter().filter_map(|w| w.handle) {
+        match handle.join() {
+            Ok(Err(e)) if !e.printed => eprintln!("{e}"),
+            _ => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restart_delay() {
+        assert_eq!(restart_delay(1), Duration::from_millis(daemon::WORKER_RESTART_MIN_MS));
+        assert_eq!(restart_delay(2), Duration::from_millis(2 * daemon::WORKER_RESTART_MIN_MS));
+        assert_eq!(restart_delay(100), Duration::from_millis(daemon::WORKER_RESTART_MAX_MS));
+    }
+}
@@ -0,0 +1,78 @@
+//! Diagnostics for driver installations nvoc cannot use
+//!
+//! `nvoc doctor` reports which library was loaded, any candidate path
+//! skipped because it did not respond, whether NVML initializes, and the
+//! VBIOS and InfoROM version of each GPU, as offsets can behave differently
+//! between VBIOS revisions. With `--symbols` it also resolves every NVML
+//! function nvoc binds, so a "required function not available" error can be
+//! traced to the exact symbol an old or unusual driver build lacks. Symbols
+//! found only in an older version show the name that was used instead.
+
+use crate::gpu;
+use crate::nvml::{self, loader, NvmlInforomObject};
+use crate::AppError;
+
+fn report_symbols() -> Result<(), AppError> {
+    let api = loader::api().map_err(|e| AppError::new("doctor", e))?;
+    let mut missing = 0;
+    for &symbol in loader::SYMBOLS {
+        match api.resolved_name(symbol) {
+            Some(name) if name == symbol => println!("{symbol}: present"),
+            Some(name) => println!("{symbol}: present as {name}"),
+            None => {
+                missing += 1;
+                println!("{symbol}: missing");
+            }
+        }
+    }
+    println!("symbols: {} of {} present", loader::SYMBOLS.len() - missing, loader::SYMBOLS.len());
+    if missing > 0 {
+        eprintln!("warning[doctor]: operations using missing symbols fail with 'required function not available'");
+    }
+    Ok(())
+}
+
+/// Name and firmware versions of a GPU, which decide how offsets behave
+fn report_device(index: u32) {
+    let Ok(device) = &gpu::get_device(index) else {
+        println!("gpu {index}: cannot be opened");
+        return;
+    };
+    let version = |value: nvml::Result<String>| value.unwrap_or_else(|_| "n/a".to_string());
+    println!(
+        "gpu {index}: {}, vbios {}, inforom {}",
+        version(nvml::device_get_name(device)),
+        version(nvml::device_get_vbios_version(device)),
+        version(nvml::device_get_inforom_version(device, NvmlInforomObject::Oem))
+    );
+}
+
+pub fn run(symbols: bool) -> Result<(), AppError> {
+    let library = loader::library_name();
+    for path in loader::timed_out_paths() {
+        println!("skipped: {} (timed out)", path.display());
+    }
+    let library = library.map_err(|e| AppError::new("library", e))?;
+    match loader::library_path().filter(|path| path.as_os_str() != library) {
+        Some(path) => println!("library: {library} ({})", path.display()),
+        None => println!("library: {library}"),
+    }
+
+    match gpu::init_with_cleanup() {
+        Ok(_cleanup) => {
+            let version = gpu::driver_version().map_err(|e| AppError::new("driver", e))?;
+            println!("driver: {version}");
+            let count = gpu::device_count().map_err(|e| AppError::new("device", e))?;
+            println!("gpus: {count}");
+            for index in 0..count {
+                report_device(index);
+            }
+        }
+        Err(e) => println!("init: {e}"),
+    }
+
+    if symbols {
+        report_symbols()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! Grafana dashboard generation
+//!
+//! Builds a dashboard with one time series panel per exported metric, so the
+//! panels always match the metric names and labels the exporter serves.
+
+use serde_json::{json, Value};
+
+use crate::constants::app;
+use crate::exporter::metrics::METRICS;
+
+/// Panel grid geometry (Grafana uses a 24 column grid)
+const PANEL_WIDTH: usize = 12;
+const PANEL_HEIGHT: usize = 8;
+
+fn panel(id: usize, title: &str, metric: &str, unit: &str) -> Value {
+    json!({
+        "id": id,
+        "type": "timeseries",
+        "title": title,
+        "datasource": { "type": "prometheus", "uid": "${datasource}" },
+        "gridPos": {
+            "x": (id % 2) * PANEL_WIDTH,
+            "y": (id / 2) * PANEL_HEIGHT,
+            "w": PANEL_WIDTH,
+            "h": PANEL_HEIGHT,
+        },
+        "fieldConfig": { "defaults": { "unit": unit }, "overrides": [] },
+        "targets": [{
+            "refId": "A",
+            "expr": format!("{metric}{{gpu=~\"$gpu\"}}"),
+            "legendFormat": "gpu {{gpu}} {{name}}",
+        }],
+    })
+}
+
+/// Build the dashboard document
+pub fn dashboard() -> Value {
+    let panels: Vec<Value> = METRICS
+        .iter()
+        .enumerate()
+        .map(|(id, metric)| panel(id, metric.help, metric.name, metric.unit))
+        .collect();
+
+    json!({
+        "title": "nvoc GPU",
+        "uid": "nvoc-gpu",
+        "tags": [app::NAME],
+        "schemaVersion": 39,
+        "refresh": "10s",
+        "time": { "from": "now-1h", "to": "now" },
+        "templating": {
+            "list": [
+                {
+                    "name": "datasource",
+                    "type": "datasource",
+                    "query": "prometheus",
+                },
+                {
+                    "name": "gpu",
+                    "type": "query",
+                    "datasource": { "type": "prometheus", "uid": "${datasource}" },
+                    "query": format!("label_values({}, gpu)", METRICS[0].name),
+                    "includeAll": true,
+                    "multi": true,
+                    "current": { "text": "All", "value": "$__all" },
+                },
+            ],
+        },
+        "panels": panels,
+    })
+}
@@ -0,0 +1,174 @@
+//! Prometheus metric definitions and text exposition
+
+use std::time::Duration;
+
+use crate::gpu;
+use crate::nvml::{
+    device_get_clock_info, device_get_name, device_get_power_limit,
+    device_get_power_usage, device_get_temperature, NvmlClockType,
+};
+
+/// A gauge exported for every GPU, labelled with `gpu` and `name`
+pub struct Metric {
+    pub name: &'static str,
+    pub help: &'static str,
+    /// Grafana display unit
+    pub unit: &'static str,
+    value: fn(&DeviceSample) -> Option<f64>,
+}
+
+pub const METRICS: &[Metric] = &[
+    Metric {
+        name: "nvoc_gpu_temperature_celsius",
+        help: "GPU core temperature",
+        unit: "celsius",
+        value: |s| s.temperature.map(f64::from),
+    },
+    Metric {
+        name: "nvoc_gpu_graphics_clock_mhz",
+        help: "Current graphics clock",
+        unit: "suffix:MHz",
+        value: |s| s.graphics_clock.map(f64::from),
+    },
+    Metric {
+        name: "nvoc_gpu_memory_clock_mhz",
+        help: "Current memory clock",
+        unit: "suffix:MHz",
+        value: |s| s.memory_clock.map(f64::from),
+    },
+    Metric {
+        name: "nvoc_gpu_graphics_offset_mhz",
+        help: "Applied graphics clock offset",
+        unit: "suffix:MHz",
+        value: |s| s.graphics_offset.map(f64::from),
+    },
+    Metric {
+        name: "nvoc_gpu_memory_offset_mhz",
+        help: "Applied memory clock offset",
+        unit: "suffix:MHz",
+        value: |s| s.memory_offset.map(f64::from),
+    },
+    Metric {
+        name: "nvoc_gpu_power_watts",
+        help: "Current power draw",
+        unit: "watt",
+        value: |s| s.power_mw.map(|mw| f64::from(mw) / 1000.0),
+    },
+    Metric {
+        name: "nvoc_gpu_power_limit_watts",
+        help: "Current power limit",
+        unit: "watt",
+        value: |s| s.power_limit_mw.map(|mw| f64::from(mw) / 1000.0),
+    },
+];
+
+/// One reading of every exported value for a GPU
+#[derive(Debug, Clone)]
+pub struct DeviceSample {
+    pub index: u32,
+    pub name: String,
+    pub temperature: Option<u32>,
+    pub graphics_clock: Option<u32>,
+    pub memory_clock: Option<u32>,
+    pub graphics_offset: Option<i32>,
+    pub memory_offset: Option<i32>,
+    pub power_mw: Option<u32>,
+    pub power_limit_mw: Option<u32>,
+}
+
+/// Read every exported value from the GPU at `index`. Values that fail to
+/// read are omitted from the exposition rather than failing the scrape.
+pub fn sample(index: u32) -> Option<DeviceSample> {
+    let device = &gpu::get_device(index).ok()?;
+    Some(DeviceSample {
+        index,
+        name: device_get_name(device).unwrap_or_default(),
+        temperature: device_get_temperature(device).ok(),
+        graphics_clock: device_get_clock_info(device, NvmlClockType::Graphics).ok(),
+        memory_clock: device_get_clock_info(device, NvmlClockType::Memory).ok(),
+        graphics_offset: gpu::overclock::graphics_offset(device).ok().map(|o| o.0),
+        memory_offset: gpu::overclock::memory_offset(device).ok().map(|o| o.0),
+        power_mw: device_get_power_usage(device).ok(),
+        power_limit_mw: device_get_power_limit(device).ok(),
+    })
+}
+
+/// Sample every GPU in the system
+pub fn sample_all() -> Vec<DeviceSample> {
+    let count = gpu::device_count().unwrap_or(0);
+    (0..count).filter_map(sample).collect()
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Render samples in the Prometheus text exposition format
+pub fn render(samples: &[DeviceSample]) -> String {
+    let mut out = String::new();
+    for metric in METRICS {
+        out.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", metric.name, metric.help, metric.name));
+        for sample in samples {
+            if let Some(value) = (metric.value)(sample) {
+                out.push_str(&format!(
+                    "{}{{gpu=\"{}\",name=\"{}\"}} {}\n",
+                    metric.name,
+                    sample.index,
+                    escape_label(&sample.name),
+                    value
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// Freshness of the served readings, rendered per scrape rather than cached
+pub fn render_freshness(age: Option<Duration>, stale: bool) -> String {
+    let mut out = String::new();
+    out.push_str("# HELP nvoc_exporter_sample_age_seconds Age of the GPU readings in this response\n");
+    out.push_str("# TYPE nvoc_exporter_sample_age_seconds gauge\n");
+    if let Some(age) = age {
+        out.push_str(&format!("nvoc_exporter_sample_age_seconds {:.3}\n", age.as_secs_f64()));
+    }
+    out.push_str("# HELP nvoc_exporter_sample_stale Whether sampling NVML failed to finish in time\n");
+    out.push_str("# TYPE nvoc_exporter_sample_stale gauge\n");
+    out.push_str(&format!("nvoc_exporter_sample_stale {}\n", u8::from(stale)));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_skips_missing_values() {
+        let sample = DeviceSample {
+            index: 1,
+            name: "RTX \"5090\"".to_string(),
+            temperature: Some(45),
+            graphics_clock: None,
+            memory_clock: None,
+            graphics_offset: Some(-100),
+            memory_offset: None,
+            power_mw: Some(14500),
+            power_limit_mw: None,
+        };
+        let text = render(&[sample]);
+        assert!(text.contains("nvoc_gpu_temperature_celsius{gpu=\"1\",name=\"RTX \\\"5090\\\"\"} 45\n"));
+        assert!(text.contains("nvoc_gpu_graphics_offset_mhz{gpu=\"1\",name=\"RTX \\\"5090\\\"\"} -100\n"));
+        assert!(text.contains("nvoc_gpu_power_watts{gpu=\"1\",name=\"RTX \\\"5090\\\"\"} 14.5\n"));
+        assert!(!text.contains("nvoc_gpu_graphics_clock_mhz{"));
+    }
+
+    #[test]
+    fn test_render_freshness() {
+        let text = render_freshness(Some(Duration::from_millis(1500)), false);
+        assert!(text.contains("nvoc_exporter_sample_age_seconds 1.500\n"));
+        assert!(text.contains("nvoc_exporter_sample_stale 0\n"));
+        // No readings yet: no age, and the response is stale
+        let text = render_freshness(None, true);
+        assert!(!text.contains("\nnvoc_exporter_sample_age_seconds "));
+        assert!(text.contains("nvoc_exporter_sample_stale 1\n"));
+    }
+}
@@ -0,0 +1,143 @@
+//! Prometheus metrics exporter
+//!
+//! Samples every GPU when scraped and serves the readings at `/metrics`
+//! over TCP and/or a Unix socket. TCP binds to localhost unless told
+//! otherwise, and the Unix socket lets a reverse proxy expose metrics
+//! without opening a network port at all.
+//!
+//! Readings are cached for a configurable TTL so frequent scrapers do not
+//! hammer NVML. Sampling runs off the request thread: if NVML hangs, the
+//! scrape is answered with the last readings after a bounded wait, and the
+//! freshness metrics show how old they are.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::params::ExporterParams;
+use crate::constants::exporter;
+use crate::{signal, socket, AppError};
+
+pub mod dashboard;
+pub mod metrics;
+
+/// Timeout for reading a request from a connected client
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Rendered readings and when they were taken
+#[derive(Default)]
+struct Sampled {
+    text: String,
+    at: Option<Instant>,
+    /// A sampling thread is running
+    sampling: bool,
+}
+
+struct Cache {
+    ttl: Duration,
+    state: Mutex<Sampled>,
+    done: Condvar,
+}
+
+impl Cache {
+    fn new(ttl: Duration) -> Self {
+        Self { ttl, state: Mutex::new(Sampled::default()), done: Condvar::new() }
+    }
+
+    /// Readings no older than the TTL, sampling if needed. A sample that
+    /// does not finish within the timeout leaves the previous readings in
+    /// place, marked stale.
+    fn get(self: &Arc<Self>) -> String {
+        let Ok(mut state) = self.state.lock() else {
+            return String::new();
+        };
+        let fresh = state.at.is_some_and(|at| at.elapsed() < self.ttl);
+        if !fresh && !state.sampling {
+            state.sampling = true;
+            let cache = Arc::clone(self);
+            std::thread::spawn(move || cache.refresh());
+        }
+        if !fresh {
+            state = match self.done.wait_timeout_while(state, exporter::SAMPLE_TIMEOUT, |s| s.sampling) {
+                Ok((state, _)) => state,
+                Err(_) => return String::new(),
+            };
+        }
+
+        let age = state.at.map(|at| at.elapsed());
+        let stale = age.is_none_or(|age| age >= self.ttl);
+        format!("{}{}", state.text, metrics::render_freshness(age, stale))
+    }
+
+    fn refresh(&self) {
+        let text = metrics::render(&metrics::sample_all());
+        if let Ok(mut state) = self.state.lock() {
+            *state = Sampled { text, at: Some(Instant::now()), sampling: false };
+        }
+        self.done.notify_all();
+    }
+}
+
+fn respond<S: Read + Write>(mut stream: S, cache: &Arc<Cache>) {
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).unwrap_or(0);
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+
+    let (status, content_type, body) = match path {
+        "/metrics" => ("200 OK", "text/plain; version=0.0.4", cache.get()),
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+}
+
+/// Print a Grafana dashboard matching the exported metrics
+pub fn emit_dashboard() -> Result<(), AppError> {
+    let text = serde_json::to_string_pretty(&dashboard::dashboard())
+        .map_err(|e| AppError::msg("exporter", e.to_string()))?;
+    println!("{text}");
+    Ok(())
+}
+
+pub fn run(params: &ExporterParams) -> Result<(), AppError> {
+    let cache = Arc::new(Cache::new(params.cache_ttl));
+
+    if let Some(addr) = params.listen {
+        let listener = TcpListener::bind(addr)
+            .map_err(|e| AppError::msg("exporter", format!("{addr}: {e}")))?;
+        let cache = Arc::clone(&cache);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+                respond(stream, &cache);
+            }
+        });
+        eprintln!("exporter: http://{addr}/metrics");
+    }
+
+    if let Some(path) = &params.unix {
+        let listener = socket::bind_unix(path, params.socket_mode, "exporter")?;
+        let cache = Arc::clone(&cache);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+                respond(stream, &cache);
+            }
+        });
+        eprintln!("exporter: unix:{} (mode {:o})", path.display(), params.socket_mode);
+    }
+
+    signal::install();
+    while signal::sleep(Duration::from_secs(1)) {}
+
+    if let Some(path) = &params.unix {
+        let _ = fs::remove_file(path);
+    }
+    Ok(())
+}
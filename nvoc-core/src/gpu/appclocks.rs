@@ -0,0 +1,71 @@
+//! Applications clocks
+//!
+//! Applications clocks set the memory and graphics clock the GPU boosts to
+//! under load, instead of bounding it like locked clocks. Only pairs from
+//! the device's supported clock table are accepted, so requests are checked
+//! against it before writing.
+
+use crate::nvml::{self, Device};
+use crate::{output, AppError};
+
+/// Supported clock closest to `clock`
+fn nearest(clock: u32, supported: &[u32]) -> Option<u32> {
+    supported.iter().copied().min_by_key(|&s| s.abs_diff(clock))
+}
+
+/// Refuse `clock` when the driver lists supported clocks without it
+fn check_supported(kind: &str, clock: u32, supported: &[u32]) -> Result<(), AppError> {
+    match nearest(clock, supported) {
+        Some(nearest) if nearest != clock => Err(AppError::msg(
+            "appclocks",
+            format!("{clock}MHz is not a supported {kind} clock, nearest is {nearest}MHz"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Set applications clocks to `memory` and `graphics` MHz
+pub fn apply(device: &Device, (memory, graphics): (u32, u32), dry_run: bool) -> Result<(), AppError> {
+    // Drivers that cannot list clocks leave the check to the set call
+    if let Ok(supported) = nvml::device_get_supported_memory_clocks(device) {
+        check_supported("memory", memory, &supported)?;
+    }
+    if let Ok(supported) = nvml::device_get_supported_graphics_clocks(device, memory) {
+        check_supported("graphics", graphics, &supported)?;
+    }
+
+    if dry_run {
+        println!("appclocks: mem {memory}MHz, gpu {graphics}MHz (dry run)");
+        return Ok(());
+    }
+    nvml::device_set_applications_clocks(device, memory, graphics).map_err(|e| AppError::new("appclocks", e))?;
+    output::status!("appclocks: mem {memory}MHz, gpu {graphics}MHz");
+    Ok(())
+}
+
+/// Return applications clocks to the driver defaults
+pub fn reset(device: &Device, dry_run: bool) -> Result<(), AppError> {
+    if dry_run {
+        println!("appclocks: reset (dry run)");
+        return Ok(());
+    }
+    nvml::device_reset_applications_clocks(device).map_err(|e| AppError::new("appclocks", e))?;
+    output::status!("appclocks: reset");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_supported_clock() {
+        assert_eq!(nearest(14000, &[405, 810, 14001]), Some(14001));
+        assert_eq!(nearest(700, &[405, 810, 14001]), Some(810));
+        assert_eq!(nearest(405, &[405, 810]), Some(405));
+        assert_eq!(nearest(405, &[]), None);
+        assert!(check_supported("memory", 405, &[405, 810]).is_ok());
+        assert!(check_supported("memory", 400, &[405, 810]).is_err());
+        assert!(check_supported("memory", 400, &[]).is_ok());
+    }
+}
@@ -0,0 +1,133 @@
+//! Driver and GPU feature probe
+//!
+//! `nvoc capabilities` reports which nvoc features work on a GPU before any
+//! of them is tried: each feature needs its NVML functions exported by the
+//! installed driver, and a harmless read through them to succeed on the
+//! GPU. Nothing is written, so it runs without root.
+
+use crate::gpu::overclock;
+use crate::nvml::{self, loader, Device, NvmlError, NvmlTemperatureThreshold, NVML_FI_DEV_TOTAL_ENERGY_CONSUMPTION};
+use crate::AppError;
+
+/// Whether a feature can be used, and why not
+#[derive(Debug, PartialEq)]
+enum Support {
+    Yes,
+    /// The driver does not export this function
+    MissingSymbol(&'static str),
+    /// The functions exist but the GPU rejects them
+    NotSupported,
+    /// The probe failed for another reason
+    Unknown(&'static str),
+}
+
+impl std::fmt::Display for Support {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Support::Yes => f.write_str("yes"),
+            Support::MissingSymbol(symbol) => write!(f, "no (driver lacks {symbol})"),
+            Support::NotSupported => f.write_str("no (not supported by this gpu)"),
+            Support::Unknown(reason) => write!(f, "unknown ({reason})"),
+        }
+    }
+}
+
+/// Judge a feature from the first symbol it lacks and the outcome of its
+/// probe, which is only run when every symbol is present
+fn judge(missing: Option<&'static str>, probe: impl FnOnce() -> nvml::Result<()>) -> Support {
+    if let Some(symbol) = missing {
+        return Support::MissingSymbol(symbol);
+    }
+    match probe() {
+        Ok(()) => Support::Yes,
+        Err(NvmlError::NotSupported) => Support::NotSupported,
+        Err(e) => Support::Unknown(e.user_message()),
+    }
+}
+
+/// Print one `feature: support` line per nvoc feature
+pub fn show(device: &Device) -> Result<(), AppError> {
+    let api = loader::api().map_err(|e| AppError::new("capabilities", e))?;
+    let missing = |symbols: &[&'static str]| symbols.iter().copied().find(|symbol| !api.has(symbol));
+
+    let features: [(&str, Support); 9] = [
+        (
+            "clock offsets",
+            judge(missing(overclock::offset_symbols(device)), || overclock::graphics_offset(device).map(drop)),
+        ),
+        (
+            "power limit",
+            judge(
+                missing(&["nvmlDeviceGetPowerManagementLimitConstraints", "nvmlDeviceSetPowerManagementLimit"]),
+                || nvml::device_get_power_limit_constraints(device).map(drop),
+            ),
+        ),
+        (
+            "locked gpu clocks",
+            judge(missing(&["nvmlDeviceSetGpuLockedClocks", "nvmlDeviceResetGpuLockedClocks"]), || {
+                overclock::supported_clock_range(device).map(drop)
+            }),
+        ),
+        (
+            "locked memory clocks",
+            judge(missing(&["nvmlDeviceSetMemoryLockedClocks", "nvmlDeviceResetMemoryLockedClocks"]), || {
+                overclock::supported_memory_clock_range(device).map(drop)
+            }),
+        ),
+        (
+            "applications clocks",
+            judge(missing(&["nvmlDeviceSetApplicationsClocks", "nvmlDeviceResetApplicationsClocks"]), || {
+                nvml::device_get_supported_memory_clocks(device).map(drop)
+            }),
+        ),
+        (
+            "fan control",
+            judge(
+                missing(&["nvmlDeviceGetNumFans", "nvmlDeviceSetFanSpeed_v2", "nvmlDeviceSetDefaultFanSpeed_v2"]),
+                || match nvml::device_get_num_fans(device)? {
+                    0 => Err(NvmlError::NotSupported),
+                    _ => Ok(()),
+                },
+            ),
+        ),
+        (
+            "thermal target",
+            judge(missing(&["nvmlDeviceGetTemperatureThreshold", "nvmlDeviceSetTemperatureThreshold"]), || {
+                nvml::device_get_temperature_threshold(device, NvmlTemperatureThreshold::AcousticCurrent).map(drop)
+            }),
+        ),
+        (
+            "field values",
+            judge(missing(&["nvmlDeviceGetFieldValues"]), || {
+                nvml::device_get_field_values(device, &[NVML_FI_DEV_TOTAL_ENERGY_CONSUMPTION]).map(drop)
+            }),
+        ),
+        (
+            "events",
+            judge(missing(&["nvmlEventSetCreate", "nvmlDeviceRegisterEvents", "nvmlEventSetWait_v2"]), || {
+                nvml::device_get_supported_event_types(device).map(drop)
+            }),
+        ),
+    ];
+    for (feature, support) in features {
+        println!("{feature}: {support}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_judge() {
+        let unreachable = || -> nvml::Result<()> { panic!("probed without its symbols") };
+        assert_eq!(
+            judge(Some("nvmlDeviceSetFanSpeed_v2"), unreachable).to_string(),
+            "no (driver lacks nvmlDeviceSetFanSpeed_v2)"
+        );
+        assert_eq!(judge(None, || Ok(())), Support::Yes);
+        assert_eq!(judge(None, || Err(NvmlError::NotSupported)).to_string(), "no (not supported by this gpu)");
+        assert!(matches!(judge(None, || Err(NvmlError::GpuIsLost)), Support::Unknown(_)));
+    }
+}
@@ -0,0 +1,87 @@
+//! Container environment detection and device ordinal mapping
+//!
+//! With the NVIDIA container toolkit, NVML normally only sees the GPUs
+//! given to the container, so ordinals already line up. Privileged
+//! containers see every host GPU though, and `-d 0` would then target host
+//! GPU 0 rather than the first GPU listed in `NVIDIA_VISIBLE_DEVICES`. In
+//! that case ordinals are mapped through the visible device list.
+
+use std::fs;
+use std::path::Path;
+
+/// Host GPU selected by a container device ordinal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceRef {
+    Index(u32),
+    Uuid(String),
+}
+
+/// Whether nvoc is running inside a container
+pub fn in_container() -> bool {
+    if Path::new("/.dockerenv").exists() || Path::new("/run/.containerenv").exists() {
+        return true;
+    }
+    if std::env::var_os("container").is_some() {
+        return true;
+    }
+    fs::read_to_string("/proc/1/cgroup").is_ok_and(|cgroup| {
+        ["docker", "kubepods", "containerd", "libpod", "lxc"]
+            .iter()
+            .any(|marker| cgroup.contains(marker))
+    })
+}
+
+/// Parse `NVIDIA_VISIBLE_DEVICES`. `None` means no restriction.
+fn parse_visible(value: &str) -> Option<Vec<DeviceRef>> {
+    match value.trim() {
+        "" | "all" => None,
+        "none" | "void" => Some(Vec::new()),
+        list => Some(
+            list.split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| match entry.parse::<u32>() {
+                    Ok(index) => DeviceRef::Index(index),
+                    Err(_) => DeviceRef::Uuid(entry.to_string()),
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Visible device list when ordinals need remapping: inside a container
+/// whose NVML sees more GPUs than it was given
+fn remapped_devices(nvml_count: u32) -> Option<Vec<DeviceRef>> {
+    let visible = parse_visible(&std::env::var("NVIDIA_VISIBLE_DEVICES").ok()?)?;
+    (in_container() && nvml_count as usize > visible.len()).then_some(visible)
+}
+
+/// Number of GPUs the container is meant to see
+pub fn visible_count(nvml_count: u32) -> u32 {
+    remapped_devices(nvml_count).map_or(nvml_count, |visible| visible.len() as u32)
+}
+
+/// Resolve a device ordinal as seen by the user to a host device, `None`
+/// when out of range
+pub fn resolve(ordinal: u32, nvml_count: u32) -> Option<DeviceRef> {
+    match remapped_devices(nvml_count) {
+        Some(visible) => visible.into_iter().nth(ordinal as usize),
+        None => (ordinal < nvml_count).then_some(DeviceRef::Index(ordinal)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_visible_devices() {
+        assert_eq!(parse_visible("all"), None);
+        assert_eq!(parse_visible(""), None);
+        assert_eq!(parse_visible("none"), Some(vec![]));
+        assert_eq!(
+            parse_visible("2, GPU-8f3c"),
+            Some(vec![DeviceRef::Index(2), DeviceRef::Uuid("GPU-8f3c".to_string())])
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Domain layer for GPU operations and business logic
+//!
+//! This module provides high-level abstractions over raw NVML operations,
+//! handling unit conversions, business logic, and domain-specific calculations.
+//! It keeps the NVML wrapper purely focused on API bindings.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::nvml::{NvmlBackend, Result};
+use crate::units::{Celsius, Milliwatts, Watts};
+use serde::{Deserialize, Serialize};
+
+/// Configured minimum power limit in percent of the default, 0 when unset
+static POWER_FLOOR_PERCENT: AtomicU32 = AtomicU32::new(0);
+
+/// Temperature above which raising power or offsets is refused, 0 when unset
+static THERMAL_GUARD_C: AtomicU32 = AtomicU32::new(0);
+
+/// Whether a laptop GPU on battery may have its power limit raised
+static POWER_RAISE_ON_BATTERY: AtomicBool = AtomicBool::new(false);
+
+/// Power information for a GPU device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerInfo {
+    /// Current power limit
+    pub limit_watts: Watts,
+    /// Default (100%) power limit
+    pub default_watts: Watts,
+    /// Minimum allowed power limit
+    pub min_watts: Watts,
+    /// Maximum allowed power limit
+    pub max_watts: Watts,
+}
+
+impl PowerInfo {
+    /// Get current power limit as percentage of default
+    pub fn current_percentage(&self) -> u32 {
+        (self.limit_watts.0 as f32 / self.default_watts.0 as f32 * 100.0) as u32
+    }
+
+    /// Calculate watts from percentage of default
+    pub fn calculate_watts_from_percentage(&self, percentage: u32) -> Watts {
+        Watts((self.default_watts.0 as f32 * percentage as f32 / 100.0) as u32)
+    }
+
+    /// Get the effective watts that would be set (clamped to hardware limits)
+    pub fn effective_watts_from_percentage(&self, percentage: u32) -> Watts {
+        let target_watts = self.calculate_watts_from_percentage(percentage);
+        target_watts.max(self.min_watts).min(self.max_watts)
+    }
+}
+
+/// Get comprehensive power information for a device
+pub fn get_power_info(device: impl NvmlBackend) -> Result<PowerInfo> {
+    let (min, max) = device.power_limit_constraints()?;
+
+    Ok(PowerInfo {
+        limit_watts: Watts::from(device.power_limit()?),
+        default_watts: Watts::from(device.power_default_limit()?),
+        min_watts: Watts::from(min),
+        max_watts: Watts::from(max),
+    })
+}
+
+/// Get current power usage
+pub fn get_power_usage_watts(device: impl NvmlBackend) -> Result<Watts> {
+    device.power_usage().map(Watts::from)
+}
+
+/// Set the floor from the config's `limits.min_power_limit`
+pub fn set_power_floor(percent: Option<u32>) {
+    POWER_FLOOR_PERCENT.store(percent.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Configured floor as a percentage of the default limit, if any
+pub fn power_floor_percent() -> Option<u32> {
+    Some(POWER_FLOOR_PERCENT.load(Ordering::Relaxed)).filter(|&p| p > 0)
+}
+
+/// Set the thermal guard from the config's `limits.max_apply_temperature`
+pub fn set_thermal_guard(celsius: Option<u32>) {
+    THERMAL_GUARD_C.store(celsius.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Temperature above which raising power or offsets is refused, if set
+pub fn thermal_guard_c() -> Option<Celsius> {
+    Some(THERMAL_GUARD_C.load(Ordering::Relaxed)).filter(|&c| c > 0).map(Celsius)
+}
+
+/// Set from the config's `limits.allow_power_raise_on_battery`
+pub fn set_power_raise_on_battery(allowed: bool) {
+    POWER_RAISE_ON_BATTERY.store(allowed, Ordering::Relaxed);
+}
+
+/// Whether raising the power limit on battery only warns instead of failing
+pub fn power_raise_on_battery() -> bool {
+    POWER_RAISE_ON_BATTERY.load(Ordering::Relaxed)
+}
+
+/// Lowest power limit the device may be set to: the configured floor, or 0
+/// without one
+pub fn power_floor_mw(device: impl NvmlBackend) -> Result<Milliwatts> {
+    match power_floor_percent() {
+        Some(percent) => Ok(floor_mw(device.power_default_limit()?, percent)),
+        None => Ok(Milliwatts(0)),
+    }
+}
+
+fn floor_mw(default: Milliwatts, percent: u32) -> Milliwatts {
+    Milliwatts((u64::from(default.0) * u64::from(percent) / 100) as u32)
+}
+
+/// Set the power limit, raised to the configured floor. Every power limit
+/// nvoc writes goes through here. Returns the limit set.
+pub fn set_power_limit(device: impl NvmlBackend, limit: Milliwatts) -> Result<Milliwatts> {
+    let limit = limit.max(power_floor_mw(device)?);
+    device.set_power_limit(limit)?;
+    Ok(limit)
+}
+
+/// Reset power limit to default
+pub fn reset_power_limit(device: impl NvmlBackend) -> Result<()> {
+    set_power_limit(device, device.power_default_limit()?).map(drop)
+}
@@ -0,0 +1,47 @@
+//! ECC error counters
+//!
+//! On GPUs with ECC memory, corrected errors climbing under load are an
+//! early sign of a memory offset the chips cannot hold, before artifacts or
+//! an XID error appear. Stability tests count the corrected errors of their
+//! run; GPUs without ECC report nothing.
+
+use serde::{Deserialize, Serialize};
+
+use crate::nvml::{self, Device, NvmlEccCounterType, NvmlMemoryErrorType};
+
+/// Corrected and uncorrected errors since the driver loaded and over the
+/// lifetime of the GPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EccErrors {
+    pub corrected_volatile: u64,
+    pub uncorrected_volatile: u64,
+    pub corrected_aggregate: u64,
+    pub uncorrected_aggregate: u64,
+}
+
+impl EccErrors {
+    pub fn read(device: &Device) -> nvml::Result<Self> {
+        let count = |error_type, counter_type| nvml::device_get_total_ecc_errors(device, error_type, counter_type);
+        Ok(Self {
+            corrected_volatile: corrected(device)?,
+            uncorrected_volatile: count(NvmlMemoryErrorType::Uncorrected, NvmlEccCounterType::Volatile)?,
+            corrected_aggregate: count(NvmlMemoryErrorType::Corrected, NvmlEccCounterType::Aggregate)?,
+            uncorrected_aggregate: count(NvmlMemoryErrorType::Uncorrected, NvmlEccCounterType::Aggregate)?,
+        })
+    }
+}
+
+impl std::fmt::Display for EccErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} corrected, {} uncorrected since boot ({} corrected, {} uncorrected lifetime)",
+            self.corrected_volatile, self.uncorrected_volatile, self.corrected_aggregate, self.uncorrected_aggregate
+        )
+    }
+}
+
+/// Corrected errors since the driver loaded
+pub fn corrected(device: &Device) -> nvml::Result<u64> {
+    nvml::device_get_total_ecc_errors(device, NvmlMemoryErrorType::Corrected, NvmlEccCounterType::Volatile)
+}
@@ -0,0 +1,171 @@
+//! NVML event subscription
+//!
+//! An [`EventSet`] collects the events of every GPU registered with it.
+//! XID errors are the driver reporting a fault, such as a graphics engine
+//! exception (13) or a GPU that fell off the bus (79), and are usually how
+//! an unstable overclock first shows. Clock and performance state changes
+//! show the GPU reacting to load. `nvoc events` streams them as JSON lines.
+
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use crate::constants::events::WAIT_TIMEOUT_MS;
+use crate::gpu::{self, telemetry};
+use crate::nvml::{
+    self, Device, NvmlClockType, NvmlError, NvmlEventData, NvmlEventSet, NVML_EVENT_TYPE_CLOCK,
+    NVML_EVENT_TYPE_POWER_SOURCE_CHANGE, NVML_EVENT_TYPE_PSTATE, NVML_EVENT_TYPE_XID_CRITICAL_ERROR,
+    NVML_POWER_SOURCE_AC, NVML_POWER_SOURCE_BATTERY, NVML_POWER_SOURCE_UNDERSIZED,
+};
+use crate::{signal, AppError};
+
+/// Events `nvoc events` subscribes to, by the name they are printed with
+const KINDS: &[(u64, &str)] = &[
+    (NVML_EVENT_TYPE_XID_CRITICAL_ERROR, "xid"),
+    (NVML_EVENT_TYPE_CLOCK, "clock"),
+    (NVML_EVENT_TYPE_PSTATE, "pstate"),
+    (NVML_EVENT_TYPE_POWER_SOURCE_CHANGE, "power_source"),
+];
+
+fn kind_names(mask: u64) -> Vec<&'static str> {
+    KINDS.iter().filter(|&&(bit, _)| mask & bit != 0).map(|&(_, name)| name).collect()
+}
+
+/// Devices registered for events, freed on drop
+pub struct EventSet {
+    set: NvmlEventSet,
+    /// Registered devices, to tell where an event came from
+    devices: Vec<Device>,
+}
+
+impl EventSet {
+    pub fn new() -> nvml::Result<Self> {
+        Ok(Self { set: nvml::event_set_create()?, devices: Vec::new() })
+    }
+
+    /// Register `device` for the events of `mask` it supports, returning
+    /// those that were registered
+    pub fn register(&mut self, device: &Device, mask: u64) -> nvml::Result<u64> {
+        let supported = nvml::device_get_supported_event_types(device)? & mask;
+        if supported != 0 {
+            nvml::device_register_events(device, supported, self.set)?;
+            self.devices.push(device.clone());
+        }
+        Ok(supported)
+    }
+
+    /// Next event and its GPU, or `None` when nothing arrived within
+    /// `timeout_ms`
+    pub fn wait(&self, timeout_ms: u32) -> nvml::Result<Option<(&Device, NvmlEventData)>> {
+        let data = match nvml::event_set_wait(self.set, timeout_ms) {
+            Ok(data) => data,
+            Err(NvmlError::Timeout) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(self.devices.iter().find(|device| device.handle() == data.device).map(|device| (device, data)))
+    }
+}
+
+impl Drop for EventSet {
+    fn drop(&mut self) {
+        let _ = nvml::event_set_free(self.set);
+    }
+}
+
+/// An event with the readings that explain it
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Xid { xid: u64 },
+    /// Clocks as read when the event was received
+    Clock { graphics_clock_mhz: Option<u32>, memory_clock_mhz: Option<u32> },
+    Pstate { pstate: Option<String> },
+    PowerSource { source: &'static str },
+    Unknown { event_type: u64 },
+}
+
+pub fn power_source(source: u64) -> &'static str {
+    match source {
+        NVML_POWER_SOURCE_AC => "ac",
+        NVML_POWER_SOURCE_BATTERY => "battery",
+        NVML_POWER_SOURCE_UNDERSIZED => "undersized",
+        _ => "unknown",
+    }
+}
+
+impl Event {
+    pub fn read(device: &Device, data: &NvmlEventData) -> Self {
+        match data.eventType {
+            NVML_EVENT_TYPE_XID_CRITICAL_ERROR => Event::Xid { xid: data.eventData },
+            NVML_EVENT_TYPE_CLOCK => Event::Clock {
+                graphics_clock_mhz: nvml::device_get_clock_info(device, NvmlClockType::Graphics).ok(),
+                memory_clock_mhz: nvml::device_get_clock_info(device, NvmlClockType::Memory).ok(),
+            },
+            NVML_EVENT_TYPE_PSTATE => Event::Pstate {
+                pstate: nvml::device_get_performance_state(device).ok().flatten().map(|p| p.to_string()),
+            },
+            NVML_EVENT_TYPE_POWER_SOURCE_CHANGE => Event::PowerSource { source: power_source(data.eventData) },
+            event_type => Event::Unknown { event_type },
+        }
+    }
+}
+
+/// One line of `nvoc events` output
+#[derive(Serialize)]
+struct Line {
+    timestamp: String,
+    gpu: u32,
+    #[serde(flatten)]
+    event: Event,
+}
+
+fn render(gpu: u32, event: Event, time: SystemTime) -> serde_json::Result<String> {
+    serde_json::to_string(&Line { timestamp: telemetry::utc_timestamp(time), gpu, event })
+}
+
+/// Print every event of the GPUs at `indices` as a JSON line until Ctrl-C
+pub fn stream(indices: &[u32]) -> Result<(), AppError> {
+    let wanted = KINDS.iter().fold(0, |mask, &(bit, _)| mask | bit);
+    let mut set = EventSet::new().map_err(|e| AppError::new("events", e))?;
+    for &index in indices {
+        let device = &gpu::get_device(index).map_err(|e| AppError::new("events", e))?;
+        let registered = set.register(device, wanted).map_err(|e| AppError::new("events", e))?;
+        let missing = kind_names(wanted & !registered);
+        if !missing.is_empty() {
+            eprintln!("warning[events]: gpu {index} does not report {} events", missing.join(", "));
+        }
+    }
+    if set.devices.is_empty() {
+        return Err(AppError::msg("events", "no selected gpu reports events".to_string()));
+    }
+
+    signal::install();
+    while !signal::stop_requested() {
+        if let Some((device, data)) = set.wait(WAIT_TIMEOUT_MS).map_err(|e| AppError::new("events", e))? {
+            let line = render(device.index(), Event::read(device, &data), SystemTime::now())
+                .map_err(|e| AppError::msg("events", e.to_string()))?;
+            println!("{line}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_render_event_lines() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_740_852_245);
+        assert_eq!(
+            render(1, Event::Xid { xid: 79 }, time).unwrap(),
+            r#"{"timestamp":"2025-03-01T18:04:05.000Z","gpu":1,"event":"xid","xid":79}"#
+        );
+        assert_eq!(
+            render(0, Event::PowerSource { source: power_source(NVML_POWER_SOURCE_BATTERY) }, time).unwrap(),
+            r#"{"timestamp":"2025-03-01T18:04:05.000Z","gpu":0,"event":"power_source","source":"battery"}"#
+        );
+        assert_eq!(kind_names(NVML_EVENT_TYPE_CLOCK | NVML_EVENT_TYPE_PSTATE), vec!["clock", "pstate"]);
+    }
+}
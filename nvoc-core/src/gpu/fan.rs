@@ -0,0 +1,292 @@
+//! GPU fan control operations
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gpu::state::DeviceState;
+use crate::gpu::virtualization::{self, Knob};
+use crate::nvml::{self, Device, NvmlBackend, NvmlError, NvmlFanControlPolicy, Result};
+use crate::{journal, output, AppError, ErrorKind};
+
+/// Whether the driver or a fixed duty cycle drives a fan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Policy {
+    Auto,
+    Manual,
+}
+
+impl From<NvmlFanControlPolicy> for Policy {
+    fn from(policy: NvmlFanControlPolicy) -> Self {
+        match policy {
+            NvmlFanControlPolicy::TemperatureContinuousSw => Policy::Auto,
+            NvmlFanControlPolicy::Manual => Policy::Manual,
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Policy::Auto => "auto",
+            Policy::Manual => "manual",
+        })
+    }
+}
+
+/// Duty cycle, measured speed and control policy of one fan. Each is
+/// `None` when the driver cannot report it; older drivers have no RPM
+/// readout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FanReading {
+    pub fan: u32,
+    pub percent: Option<u32>,
+    pub rpm: Option<u32>,
+    #[serde(default)]
+    pub policy: Option<Policy>,
+}
+
+impl FanReading {
+    /// Driven but not turning: a failed fan, or one blocked by a cable
+    pub fn stalled(&self) -> bool {
+        self.rpm == Some(0) && self.percent.is_some_and(|percent| percent > 0)
+    }
+}
+
+impl fmt::Display for FanReading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.percent {
+            Some(percent) => write!(f, "{percent}%")?,
+            None => write!(f, "n/a")?,
+        }
+        if let Some(policy) = self.policy {
+            write!(f, " {policy}")?;
+        }
+        if let Some(rpm) = self.rpm {
+            write!(f, ", {rpm}rpm")?;
+        }
+        if self.stalled() {
+            write!(f, " (stalled)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Read every fan on the device
+pub fn readings(device: &Device) -> Result<Vec<FanReading>> {
+    Ok((0..nvml::device_get_num_fans(device)?)
+        .map(|fan| FanReading {
+            fan,
+            percent: nvml::device_get_fan_speed(device, fan).ok(),
+            rpm: nvml::device_get_fan_speed_rpm(device, fan).ok(),
+            policy: nvml::device_get_fan_control_policy(device, fan).ok().map(Policy::from),
+        })
+        .collect())
+}
+
+/// Measured speed of each fan, e.g. `1650, 0 stalled`, or `None` when no
+/// fan reports one
+pub fn describe_rpm(fans: &[FanReading]) -> Option<String> {
+    if fans.iter().all(|fan| fan.rpm.is_none()) {
+        return None;
+    }
+    let speeds: Vec<String> = fans
+        .iter()
+        .map(|fan| match (fan.rpm, fan.stalled()) {
+            (Some(rpm), true) => format!("{rpm} stalled"),
+            (Some(rpm), false) => rpm.to_string(),
+            (None, _) => "n/a".to_string(),
+        })
+        .collect();
+    Some(speeds.join(", "))
+}
+
+/// Print each fan's duty cycle and speed, failing when one is stalled
+pub fn status(device: &Device) -> std::result::Result<(), AppError> {
+    let fans = readings(device).map_err(|e| AppError::new("fan", e))?;
+    if fans.is_empty() {
+        println!("no fans");
+    }
+    if let Some((min, max)) = range(device) {
+        println!("fan range: {min}-{max}%");
+    }
+    for fan in &fans {
+        println!("fan {}: {fan}", fan.fan);
+    }
+    let stalled: Vec<&FanReading> = fans.iter().filter(|fan| fan.stalled()).collect();
+    for fan in &stalled {
+        eprintln!("warning[fan]: fan {} is not turning at {}% duty", fan.fan, fan.percent.unwrap_or(0));
+    }
+    match stalled.is_empty() {
+        true => Ok(()),
+        false => Err(AppError::printed("fan")),
+    }
+}
+
+/// Set every fan on the device to the same duty cycle percentage
+pub fn set_speed(device: &Device, percent: u32) -> Result<()> {
+    for fan in 0..nvml::device_get_num_fans(device)? {
+        nvml::device_set_fan_speed(device, fan, percent)?;
+    }
+    Ok(())
+}
+
+/// Fan speed percentage, averaged over the device's fans
+pub fn speed(device: impl NvmlBackend) -> Result<u32> {
+    let count = device.num_fans()?;
+    if count == 0 {
+        return Err(nvml::NvmlError::NotSupported);
+    }
+    let mut total = 0;
+    for fan in 0..count {
+        total += device.fan_speed(fan)?;
+    }
+    Ok(total / count)
+}
+
+/// Return every fan on the device to its default speed and its control
+/// policy to automatic (vendor) control
+pub fn restore_auto(device: impl NvmlBackend) -> Result<()> {
+    let mut result = Ok(());
+    for fan in 0..device.num_fans()? {
+        result = result.and(device.set_default_fan_speed(fan));
+        let policy = device.set_fan_control_policy(fan, NvmlFanControlPolicy::TemperatureContinuousSw);
+        // Drivers without the policy call switch back through the default speed alone
+        if !matches!(policy, Err(NvmlError::FunctionNotFound | NvmlError::NotSupported)) {
+            result = result.and(policy);
+        }
+    }
+    result
+}
+
+/// Duty cycle range in percent the fans accept, if the driver reports one
+pub fn range(device: &Device) -> Option<(u32, u32)> {
+    nvml::device_get_min_max_fan_speed(device).ok()
+}
+
+fn check_range(percent: u32, range: Option<(u32, u32)>) -> std::result::Result<(), String> {
+    match range {
+        Some((min, max)) if !(min..=max).contains(&percent) => {
+            Err(format!("fan speed {percent}% is outside the {min}-{max}% the fans accept"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Refuse a duty cycle outside the range the driver reports
+pub fn check_speed(device: &Device, percent: u32) -> std::result::Result<(), AppError> {
+    check_range(percent, range(device)).map_err(|e| AppError::msg("fan", e).with_kind(ErrorKind::InvalidArgument))
+}
+
+/// `percent` moved into the range the fans accept, for curves that ask
+/// for less than the fans can spin at
+pub fn clamp_to_range(device: &Device, percent: u32) -> u32 {
+    match range(device) {
+        Some((min, max)) => percent.clamp(min, max),
+        None => percent,
+    }
+}
+
+/// Put every fan under manual control at `percent` duty
+pub fn set_manual(device: &Device, percent: u32) -> Result<()> {
+    for fan in 0..nvml::device_get_num_fans(device)? {
+        match nvml::device_set_fan_control_policy(device, fan, NvmlFanControlPolicy::Manual) {
+            Ok(()) | Err(NvmlError::FunctionNotFound | NvmlError::NotSupported) => {}
+            Err(e) => return Err(e),
+        }
+        nvml::device_set_fan_speed(device, fan, percent)?;
+    }
+    Ok(())
+}
+
+/// `nvoc fan auto`
+pub fn apply_auto(device: &Device, dry_run: bool) -> std::result::Result<(), AppError> {
+    virtualization::check_knobs(device, &[Knob::Fans])?;
+    if dry_run {
+        println!("fan: auto (dry run)");
+        return Ok(());
+    }
+    restore_auto(device)
+        .map_err(|e| AppError::new("fan", e).context("restore automatic fan control").on_device(device.index()))?;
+    output::status!("fan: auto");
+    Ok(())
+}
+
+/// `nvoc fan manual PERCENT`
+pub fn apply_manual(device: &Device, percent: u32, dry_run: bool) -> std::result::Result<(), AppError> {
+    virtualization::check_knobs(device, &[Knob::Fans])?;
+    check_speed(device, percent)?;
+    if dry_run {
+        println!("fan: manual {percent}% (dry run)");
+        return Ok(());
+    }
+    set_manual(device, percent)
+        .map_err(|e| AppError::new("fan", e).context(format!("set fan speed ({percent}%)")).on_device(device.index()))?;
+    output::status!("fan: manual {percent}%");
+    Ok(())
+}
+
+/// Guard that returns fans to automatic control when dropped, including
+/// during panic unwinding.
+pub struct AutoFanGuard {
+    device: Device,
+    /// Open from the first fan write until automatic control is restored
+    transaction: Option<journal::Transaction>,
+}
+
+impl AutoFanGuard {
+    pub fn new(device: &Device) -> Self {
+        Self { device: device.clone(), transaction: None }
+    }
+
+    /// Journal manual fan control before the first fan write, so fans a
+    /// killed daemon left manual go back to automatic on the next start
+    pub fn journal(&mut self) -> std::result::Result<(), AppError> {
+        if self.transaction.is_none() {
+            let change = journal::Change { operation: "fan".to_string(), manual_fans: true, ..journal::Change::default() };
+            self.transaction = Some(journal::hold(&self.device, self.device.index(), DeviceState::default(), change)?);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AutoFanGuard {
+    fn drop(&mut self) {
+        match restore_auto(&self.device) {
+            Ok(()) => output::status!("fan: auto"),
+            // The entry stays for the next start to retry
+            Err(e) => {
+                eprintln!("error[fan]: {}", e.user_message());
+                return;
+            }
+        }
+        if let Some(Err(e)) = self.transaction.take().map(journal::Transaction::commit) {
+            eprintln!("{e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stalled_fan() {
+        let spinning = FanReading { fan: 0, percent: Some(45), rpm: Some(1650), policy: None };
+        let stalled = FanReading { fan: 1, rpm: Some(0), ..spinning };
+        let stopped = FanReading { fan: 2, percent: Some(0), rpm: Some(0), ..spinning };
+        assert!(!spinning.stalled() && stalled.stalled() && !stopped.stalled());
+        assert_eq!(stalled.to_string(), "45%, 0rpm (stalled)");
+        assert_eq!(FanReading { policy: Some(Policy::Manual), ..spinning }.to_string(), "45% manual, 1650rpm");
+        assert_eq!(describe_rpm(&[spinning, stalled]).as_deref(), Some("1650, 0 stalled"));
+        assert_eq!(describe_rpm(&[FanReading { rpm: None, ..spinning }]), None);
+    }
+
+    #[test]
+    fn test_check_range() {
+        assert!(check_range(20, None).is_ok());
+        assert!(check_range(30, Some((30, 100))).is_ok());
+        assert_eq!(check_range(20, Some((30, 100))).unwrap_err(), "fan speed 20% is outside the 30-100% the fans accept");
+    }
+}
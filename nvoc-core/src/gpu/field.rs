@@ -0,0 +1,77 @@
+//! Raw NVML field queries
+//!
+//! Some metrics, such as the memory junction temperature, are only exposed
+//! through `nvmlDeviceGetFieldValues`. `nvoc field` reads any field by its
+//! `NVML_FI_*` ID, with names for the ones nvoc knows.
+
+use crate::nvml::{self, Device, NVML_FI_DEV_MEMORY_TEMP, NVML_FI_DEV_TOTAL_ENERGY_CONSUMPTION};
+use crate::AppError;
+
+/// Fields known by name, with their unit
+const NAMED: &[(&str, u32, &str)] = &[
+    ("memory_temp", NVML_FI_DEV_MEMORY_TEMP, "°C"),
+    ("energy", NVML_FI_DEV_TOTAL_ENERGY_CONSUMPTION, "mJ"),
+];
+
+/// Parse a field given by numeric ID or by name
+pub fn parse(s: &str) -> Result<u32, String> {
+    if let Ok(id) = s.parse() {
+        return Ok(id);
+    }
+    NAMED.iter().find(|(name, ..)| *name == s).map(|&(_, id, _)| id).ok_or_else(|| {
+        let names: Vec<&str> = NAMED.iter().map(|(name, ..)| *name).collect();
+        format!("unknown field '{s}', use a numeric ID or one of {}", names.join(", "))
+    })
+}
+
+/// Label and unit a field is printed with
+fn describe(id: u32) -> (String, &'static str) {
+    match NAMED.iter().find(|&&(_, known, _)| known == id) {
+        Some(&(name, _, unit)) => (format!("{name} ({id})"), unit),
+        None => (format!("field {id}"), ""),
+    }
+}
+
+/// Memory junction temperature in °C, on GPUs that report it
+pub fn memory_temperature(device: &Device) -> nvml::Result<u32> {
+    match nvml::device_get_field_value(device, NVML_FI_DEV_MEMORY_TEMP)? {
+        nvml::FieldValue::Unsigned(temp) => Ok(temp as u32),
+        nvml::FieldValue::Signed(temp) => Ok(temp.max(0) as u32),
+        nvml::FieldValue::Double(temp) => Ok(temp.max(0.0).round() as u32),
+    }
+}
+
+/// Print each field as a `label: value` line. Fields the GPU does not
+/// report are listed on stderr and fail the command after the rest print.
+pub fn show(device: &Device, ids: &[u32]) -> Result<(), AppError> {
+    let values = nvml::device_get_field_values(device, ids).map_err(|e| AppError::new("field", e))?;
+    let mut ok = true;
+    for (&id, value) in ids.iter().zip(values) {
+        let (label, unit) = describe(id);
+        match value {
+            Ok(value) => println!("{label}: {value}{unit}"),
+            Err(e) => {
+                eprintln!("error[field]: {label}: {}", e.user_message());
+                ok = false;
+            }
+        }
+    }
+    match ok {
+        true => Ok(()),
+        false => Err(AppError::printed("field")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_field() {
+        assert_eq!(parse("82"), Ok(82));
+        assert_eq!(parse("memory_temp"), Ok(NVML_FI_DEV_MEMORY_TEMP));
+        assert!(parse("hotspot").is_err());
+        assert_eq!(describe(82), ("memory_temp (82)".to_string(), "°C"));
+        assert_eq!(describe(1), ("field 1".to_string(), ""));
+    }
+}
@@ -0,0 +1,116 @@
+//! Memory health
+//!
+//! Data center GPUs replace failing memory rows with spares (row remapping,
+//! Ampere and newer) or stop using failing pages (page retirement, older
+//! cards). Either means the memory is wearing out, and pushing a memory
+//! offset on such a card only hastens it. `nvoc health` reports both and
+//! fails when anything was remapped or retired. Most GeForce cards report
+//! neither.
+
+use crate::nvml::{self, Device, NvmlPageRetirementCause, RemappedRows};
+use crate::AppError;
+
+/// Pages retired after repeated single-bit errors and after double-bit
+/// errors, and whether more are waiting for a driver reload
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct RetiredPages {
+    single_bit: u32,
+    double_bit: u32,
+    pending: bool,
+}
+
+impl RetiredPages {
+    fn read(device: &Device) -> nvml::Result<Self> {
+        Ok(Self {
+            single_bit: nvml::device_get_retired_page_count(device, NvmlPageRetirementCause::MultipleSingleBitEccErrors)?,
+            double_bit: nvml::device_get_retired_page_count(device, NvmlPageRetirementCause::DoubleBitEccError)?,
+            pending: nvml::device_get_retired_pages_pending(device)?,
+        })
+    }
+}
+
+/// Signs of degrading memory, one per line of the report
+fn problems(rows: Option<RemappedRows>, pages: Option<RetiredPages>) -> Vec<String> {
+    let mut problems = Vec::new();
+    if let Some(rows) = rows {
+        if rows.correctable + rows.uncorrectable > 0 {
+            problems.push(format!("{} rows remapped", rows.correctable + rows.uncorrectable));
+        }
+        if rows.pending {
+            problems.push("row remap pending, reset the gpu to apply it".to_string());
+        }
+        if rows.failed {
+            problems.push("row remapping failed, no spare rows left".to_string());
+        }
+    }
+    if let Some(pages) = pages {
+        if pages.single_bit + pages.double_bit > 0 {
+            problems.push(format!("{} pages retired", pages.single_bit + pages.double_bit));
+        }
+        if pages.pending {
+            problems.push("page retirement pending, reload the driver to apply it".to_string());
+        }
+    }
+    problems
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// Print the remapping and retirement counters and judge the memory
+pub fn show(device: &Device) -> Result<(), AppError> {
+    let rows = nvml::device_get_remapped_rows(device).ok();
+    let pages = RetiredPages::read(device).ok();
+    match rows {
+        Some(rows) => {
+            println!("remapped rows: {} correctable, {} uncorrectable", rows.correctable, rows.uncorrectable);
+            println!("remap pending: {}", yes_no(rows.pending));
+            println!("remap failed: {}", yes_no(rows.failed));
+        }
+        None => println!("remapped rows: n/a"),
+    }
+    match pages {
+        Some(pages) => {
+            println!("retired pages: {} single-bit, {} double-bit", pages.single_bit, pages.double_bit);
+            println!("retire pending: {}", yes_no(pages.pending));
+        }
+        None => println!("retired pages: n/a"),
+    }
+
+    if rows.is_none() && pages.is_none() {
+        println!("health: not reported by this gpu");
+        return Ok(());
+    }
+    let problems = problems(rows, pages);
+    if problems.is_empty() {
+        println!("health: ok");
+        return Ok(());
+    }
+    for problem in &problems {
+        eprintln!("warning[health]: {problem}");
+    }
+    println!("health: degraded, keep memory offsets at stock");
+    Err(AppError::printed("health"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problems() {
+        assert!(problems(None, None).is_empty());
+        assert!(problems(Some(RemappedRows::default()), Some(RetiredPages::default())).is_empty());
+        let rows = RemappedRows { correctable: 2, uncorrectable: 1, pending: true, failed: false };
+        let pages = RetiredPages { double_bit: 1, ..Default::default() };
+        assert_eq!(
+            problems(Some(rows), Some(pages)),
+            ["3 rows remapped", "row remap pending, reset the gpu to apply it", "1 pages retired"]
+        );
+    }
+}
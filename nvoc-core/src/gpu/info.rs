@@ -0,0 +1,288 @@
+//! GPU information display
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gpu::domain::{get_power_info, get_power_usage_watts, PowerInfo};
+use crate::gpu::ecc::EccErrors;
+use crate::gpu::mig::MigMode;
+use crate::gpu::pcie::{Link, Throughput};
+use crate::gpu::fan::{self, FanReading};
+use crate::gpu::{events, field, throttle};
+use crate::gpu::overclock::{self, OffsetRange};
+use crate::gpu::virtualization::{self, Mode};
+use crate::units::{MegaHertz, Watts};
+use crate::nvml::{
+    device_get_board_part_number, device_get_clock_info, device_get_inforom_version, device_get_memory_info,
+    device_get_name, device_get_pci_bus_id, device_get_pcie_replay_counter, device_get_power_source, device_get_serial,
+    device_get_temperature, device_get_temperature_threshold, device_get_utilization_rates, device_get_uuid,
+    device_get_vbios_version, Device, NvmlClockType, NvmlInforomObject, NvmlTemperatureThreshold, Result,
+};
+
+/// Everything `info` reports for one GPU. Optional fields are `None` when
+/// the driver could not provide them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GpuInfo {
+    pub index: u32,
+    pub name: String,
+    #[serde(default)]
+    pub uuid: Option<String>,
+    /// PCI address, e.g. `00000000:01:00.0`
+    #[serde(default)]
+    pub pci_bus_id: Option<String>,
+    /// Board serial number, on cards that report one
+    #[serde(default)]
+    pub serial: Option<String>,
+    #[serde(default)]
+    pub board_part_number: Option<String>,
+    pub graphics_clock_mhz: Option<u32>,
+    pub graphics_offset_mhz: Option<MegaHertz>,
+    /// Nonzero graphics offsets of pstates other than P0, keyed like `P2`
+    #[serde(default)]
+    pub graphics_pstate_offsets_mhz: BTreeMap<String, MegaHertz>,
+    pub memory_clock_mhz: Option<u32>,
+    /// Lowest and highest memory clock that can be locked
+    pub memory_clock_range_mhz: Option<(u32, u32)>,
+    pub memory_offset_mhz: Option<MegaHertz>,
+    /// Nonzero memory offsets of pstates other than P0, keyed like `P2`
+    #[serde(default)]
+    pub memory_pstate_offsets_mhz: BTreeMap<String, MegaHertz>,
+    /// Memory offsets the driver accepts
+    pub memory_offset_range: Option<OffsetRange>,
+    pub temperature_c: Option<u32>,
+    /// Memory junction temperature, on GPUs that report it
+    #[serde(default)]
+    pub memory_temperature_c: Option<u32>,
+    /// Temperature the driver manages boost clocks to stay under
+    #[serde(default)]
+    pub thermal_target_c: Option<u32>,
+    /// Hardware slowdown threshold
+    #[serde(default)]
+    pub slowdown_temperature_c: Option<u32>,
+    /// Shutdown threshold
+    #[serde(default)]
+    pub shutdown_temperature_c: Option<u32>,
+    pub power_watts: Option<Watts>,
+    pub power_limit_percent: Option<u32>,
+    pub power_limit: Option<PowerInfo>,
+    /// Duty cycle and speed of each fan
+    #[serde(default)]
+    pub fans: Vec<FanReading>,
+    /// Duty cycle range the fans accept, in percent
+    #[serde(default)]
+    pub fan_range_percent: Option<(u32, u32)>,
+    /// `ac` or `battery` on laptop GPUs that report it
+    #[serde(default)]
+    pub power_source: Option<String>,
+    /// GPU load over the last sample period
+    #[serde(default)]
+    pub utilization_percent: Option<u32>,
+    /// Memory controller load over the last sample period
+    #[serde(default)]
+    pub memory_utilization_percent: Option<u32>,
+    #[serde(default)]
+    pub vram_used_mib: Option<u64>,
+    #[serde(default)]
+    pub vram_total_mib: Option<u64>,
+    #[serde(default)]
+    pub pcie_link: Option<Link>,
+    #[serde(default)]
+    pub pcie_throughput: Option<Throughput>,
+    /// PCIe packets retransmitted since the driver loaded
+    #[serde(default)]
+    pub pcie_replays: Option<u32>,
+    /// Memory errors, on GPUs with ECC
+    #[serde(default)]
+    pub ecc_errors: Option<EccErrors>,
+    /// Why clocks are below their maximum, e.g. `power cap, sw thermal`
+    #[serde(default)]
+    pub throttle: Option<String>,
+    #[serde(default)]
+    pub vbios_version: Option<String>,
+    /// Version of the InfoROM's OEM object
+    #[serde(default)]
+    pub inforom_version: Option<String>,
+    /// MIG mode, on GPUs that support it
+    #[serde(default)]
+    pub mig_mode: Option<MigMode>,
+    pub virtualization: String,
+    /// Knobs usable from a VM guest; `None` outside a guest
+    pub guest_knobs: Option<Vec<String>>,
+}
+
+/// Top-level document for `info --json`
+#[derive(Debug, Serialize)]
+pub struct InfoReport {
+    pub driver_version: String,
+    pub gpus: Vec<GpuInfo>,
+}
+
+/// Gather GPU info. Only device name is required; individual fields
+/// degrade to `None` on error.
+pub fn collect(device: &Device, device_index: u32) -> Result<GpuInfo> {
+    let power_limit = get_power_info(device).ok();
+    let utilization = device_get_utilization_rates(device).ok();
+    let vram = device_get_memory_info(device).ok();
+    let mode = virtualization::detect(device);
+    let guest_knobs = mode
+        .is_guest()
+        .then(|| virtualization::probe_knobs(device, mode).iter().map(|k| k.label().to_string()).collect());
+    Ok(GpuInfo {
+        index: device_index,
+        name: device_get_name(device)?,
+        uuid: device_get_uuid(device).ok(),
+        pci_bus_id: device_get_pci_bus_id(device).ok(),
+        serial: device_get_serial(device).ok(),
+        board_part_number: device_get_board_part_number(device).ok(),
+        graphics_clock_mhz: device_get_clock_info(device, NvmlClockType::Graphics).ok(),
+        graphics_offset_mhz: overclock::graphics_offset(device).ok(),
+        graphics_pstate_offsets_mhz: pstate_offsets(device, NvmlClockType::Graphics),
+        memory_clock_mhz: device_get_clock_info(device, NvmlClockType::Memory).ok(),
+        memory_clock_range_mhz: overclock::supported_memory_clock_range(device).ok(),
+        memory_offset_mhz: overclock::memory_offset(device).ok(),
+        memory_pstate_offsets_mhz: pstate_offsets(device, NvmlClockType::Memory),
+        memory_offset_range: overclock::memory_offset_range(device).ok(),
+        temperature_c: device_get_temperature(device).ok(),
+        memory_temperature_c: field::memory_temperature(device).ok(),
+        thermal_target_c: device_get_temperature_threshold(device, NvmlTemperatureThreshold::AcousticCurrent).ok(),
+        slowdown_temperature_c: device_get_temperature_threshold(device, NvmlTemperatureThreshold::Slowdown).ok(),
+        shutdown_temperature_c: device_get_temperature_threshold(device, NvmlTemperatureThreshold::Shutdown).ok(),
+        power_watts: get_power_usage_watts(device).ok(),
+        power_limit_percent: power_limit.as_ref().map(PowerInfo::current_percentage),
+        power_limit,
+        fans: fan::readings(device).unwrap_or_default(),
+        fan_range_percent: fan::range(device),
+        power_source: device_get_power_source(device).ok().map(|s| events::power_source(s).to_string()),
+        utilization_percent: utilization.map(|u| u.gpu),
+        memory_utilization_percent: utilization.map(|u| u.memory),
+        vram_used_mib: vram.map(|m| m.used_mib()),
+        vram_total_mib: vram.map(|m| m.total_mib()),
+        pcie_link: Link::read(device).ok(),
+        pcie_throughput: Throughput::read(device).ok(),
+        pcie_replays: device_get_pcie_replay_counter(device).ok(),
+        ecc_errors: EccErrors::read(device).ok(),
+        throttle: throttle::reasons(device).ok().map(throttle::describe),
+        vbios_version: device_get_vbios_version(device).ok(),
+        inforom_version: device_get_inforom_version(device, NvmlInforomObject::Oem).ok(),
+        mig_mode: MigMode::read(device),
+        virtualization: mode.label().to_string(),
+        guest_knobs,
+    })
+}
+
+fn pstate_offsets(device: &Device, clock_type: NvmlClockType) -> BTreeMap<String, MegaHertz> {
+    overclock::pstate_offsets(device, clock_type)
+        .into_iter()
+        .map(|(pstate, offset)| (pstate.to_string(), offset))
+        .collect()
+}
+
+fn print_field<T: std::fmt::Display>(label: &str, unit: &str, value: Option<T>) {
+    match value {
+        Some(val) => println!("{label}: {val}{unit}"),
+        None => println!("{label}: n/a"),
+    }
+}
+
+/// Display GPU info as `label: value` lines
+pub fn show_gpu_info(device: &Device, device_index: u32) -> Result<()> {
+    print(&collect(device, device_index)?);
+    Ok(())
+}
+
+/// Print collected info as `label: value` lines
+pub fn print(info: &GpuInfo) {
+    println!("gpu {}: {}", info.index, info.name);
+    print_field("uuid", "", info.uuid.as_deref());
+    print_field("pci", "", info.pci_bus_id.as_deref());
+    if let Some(serial) = &info.serial {
+        println!("serial: {serial}");
+    }
+    if let Some(part) = &info.board_part_number {
+        println!("part number: {part}");
+    }
+
+    print_field("gpu clock", "MHz", info.graphics_clock_mhz);
+    print_field("gpu offset", "", info.graphics_offset_mhz);
+    for (pstate, offset) in &info.graphics_pstate_offsets_mhz {
+        println!("gpu offset {pstate}: {offset:+}");
+    }
+    print_field("mem clock", "MHz", info.memory_clock_mhz);
+    if let Some((min, max)) = info.memory_clock_range_mhz {
+        println!("mem clock range: {min}-{max}MHz");
+    }
+    print_field("mem offset", "", info.memory_offset_mhz);
+    for (pstate, offset) in &info.memory_pstate_offsets_mhz {
+        println!("mem offset {pstate}: {offset:+}");
+    }
+    if let Some(range) = info.memory_offset_range {
+        println!("mem offset range: {range}");
+    }
+    print_field("temp", "°C", info.temperature_c);
+    if let Some(temp) = info.memory_temperature_c {
+        println!("mem temp: {temp}°C");
+    }
+    if let Some(temp) = info.thermal_target_c {
+        println!("thermal target: {temp}°C");
+    }
+    let limits: Vec<String> = [("slowdown", info.slowdown_temperature_c), ("shutdown", info.shutdown_temperature_c)]
+        .into_iter()
+        .filter_map(|(label, temp)| Some(format!("{label} {}°C", temp?)))
+        .collect();
+    if !limits.is_empty() {
+        println!("temp limits: {}", limits.join(", "));
+    }
+    print_field("power", "", info.power_watts);
+
+    match &info.power_limit {
+        Some(power) => {
+            println!("power limit: {} ({}%)", power.limit_watts, power.current_percentage());
+            println!("power range: {}-{} ({} hard limit)", power.min_watts, power.default_watts, power.max_watts);
+        }
+        None => println!("power limit: n/a"),
+    }
+    if let Some(source) = &info.power_source {
+        println!("power source: {source}");
+    }
+    for fan in &info.fans {
+        println!("fan {}: {fan}", fan.fan);
+    }
+    if let Some((min, max)) = info.fan_range_percent {
+        println!("fan range: {min}-{max}%");
+    }
+    match (info.utilization_percent, info.memory_utilization_percent) {
+        (Some(gpu), Some(memory)) => println!("util: {gpu}% (mem controller {memory}%)"),
+        (gpu, _) => print_field("util", "%", gpu),
+    }
+    match (info.vram_used_mib, info.vram_total_mib) {
+        (Some(used), Some(total)) => println!("vram: {used}/{total}MiB"),
+        _ => println!("vram: n/a"),
+    }
+    print_field("pcie", "", info.pcie_link);
+    if let Some(throughput) = info.pcie_throughput {
+        println!("pcie throughput: {throughput}");
+    }
+    if let Some(replays) = info.pcie_replays {
+        println!("pcie replays: {replays}");
+    }
+    if let Some(errors) = info.ecc_errors {
+        println!("ecc errors: {errors}");
+    }
+    print_field("throttle", "", info.throttle.as_deref());
+
+    print_field("vbios", "", info.vbios_version.as_deref());
+    if let Some(version) = &info.inforom_version {
+        println!("inforom: {version}");
+    }
+    if let Some(mode) = info.mig_mode {
+        println!("mig: {mode}");
+    }
+
+    match &info.guest_knobs {
+        Some(knobs) if knobs.is_empty() => println!("virtualization: {} (available: none)", info.virtualization),
+        Some(knobs) => println!("virtualization: {} (available: {})", info.virtualization, knobs.join(", ")),
+        None if info.virtualization != Mode::BareMetal.label() => println!("virtualization: {}", info.virtualization),
+        None => {}
+    }
+}
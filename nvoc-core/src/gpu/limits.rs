@@ -0,0 +1,94 @@
+//! Ranges the driver accepts for each setting
+//!
+//! `nvoc limits` shows what the offsets, power limit and fan duty cycle may
+//! be set to before a value is tried. The ranges are the ones a requested
+//! value is checked against when it is applied. Nothing is written, so it
+//! runs without root.
+
+use std::fmt;
+
+use crate::gpu::domain::{self, PowerInfo};
+use crate::gpu::fan;
+use crate::gpu::overclock::{self, OffsetRange};
+use crate::nvml::Device;
+
+/// Accepted ranges; `None` where the driver reports none
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    pub graphics_offset: Option<OffsetRange>,
+    pub memory_offset: Option<OffsetRange>,
+    pub power: Option<PowerInfo>,
+    /// Fan duty cycle range in percent
+    pub fan: Option<(u32, u32)>,
+}
+
+/// Read the ranges of `device`
+pub fn read(device: &Device) -> Limits {
+    Limits {
+        graphics_offset: overclock::graphics_offset_range(device).ok(),
+        memory_offset: overclock::memory_offset_range(device).ok(),
+        power: domain::get_power_info(device).ok(),
+        fan: fan::range(device),
+    }
+}
+
+fn or_na(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "n/a".to_string())
+}
+
+/// The power range in watts and as the percentages `-p` takes, rounded
+/// inwards so both ends are accepted
+fn power_range(power: &PowerInfo) -> String {
+    let (min, max, default) = (power.min_watts.0, power.max_watts.0, power.default_watts.0.max(1));
+    format!(
+        "{} to {} ({}% to {}% of the default {})",
+        power.min_watts,
+        power.max_watts,
+        (min * 100).div_ceil(default),
+        max * 100 / default,
+        power.default_watts
+    )
+}
+
+/// One `setting: range` line per setting
+impl fmt::Display for Limits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "gpu offset: {}", or_na(self.graphics_offset.map(|range| range.to_string())))?;
+        writeln!(f, "mem offset: {}", or_na(self.memory_offset.map(|range| range.to_string())))?;
+        writeln!(f, "power limit: {}", or_na(self.power.as_ref().map(power_range)))?;
+        write!(f, "fan: {}", or_na(self.fan.map(|(min, max)| format!("{min}-{max}%"))))
+    }
+}
+
+/// Print the ranges of `device`
+pub fn show(device: &Device) {
+    println!("{}", read(device));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::{MegaHertz, Watts};
+
+    #[test]
+    fn test_display() {
+        let limits = Limits {
+            graphics_offset: Some(OffsetRange { min: MegaHertz(-1000), max: MegaHertz(1000) }),
+            memory_offset: None,
+            power: Some(PowerInfo {
+                limit_watts: Watts(575),
+                default_watts: Watts(575),
+                min_watts: Watts(400),
+                max_watts: Watts(600),
+            }),
+            fan: Some((30, 100)),
+        };
+        assert_eq!(
+            limits.to_string(),
+            "gpu offset: -1000MHz to +1000MHz\n\
+             mem offset: n/a\n\
+             power limit: 400W to 600W (70% to 104% of the default 575W)\n\
+             fan: 30-100%"
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! Multi-Instance GPU detection
+//!
+//! A GPU partitioned with MIG does not accept clock or power changes, and
+//! every NVML call that tries one fails with a bare "not supported". nvoc
+//! checks the mode once before modifying a GPU and refuses with the reason
+//! instead.
+
+use serde::{Deserialize, Serialize};
+
+use crate::nvml::{self, Device};
+use crate::{AppError, ErrorKind};
+
+/// MIG mode now and after the next GPU reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigMode {
+    pub enabled: bool,
+    pub pending: bool,
+}
+
+impl MigMode {
+    /// `None` on GPUs without MIG support, which includes every GeForce card
+    pub fn read(device: &Device) -> Option<Self> {
+        let (enabled, pending) = nvml::device_get_mig_mode(device).ok()?;
+        Some(Self { enabled, pending })
+    }
+}
+
+impl std::fmt::Display for MigMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(if self.enabled { "enabled" } else { "disabled" })?;
+        match (self.enabled, self.pending) {
+            (false, true) => f.write_str(" (enabled after the next gpu reset)"),
+            (true, false) => f.write_str(" (disabled after the next gpu reset)"),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn check_mode(mode: Option<MigMode>, device_index: u32) -> Result<(), String> {
+    match mode {
+        Some(MigMode { enabled: true, .. }) => Err(format!(
+            "gpu {device_index} is partitioned with MIG, which does not allow clock or power changes; \
+disable it with `nvidia-smi -i {device_index} -mig 0` and reset the gpu"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Refuse to modify a GPU with MIG enabled
+pub fn check(device: &Device, device_index: u32) -> Result<(), AppError> {
+    check_mode(MigMode::read(device), device_index)
+        .map_err(|e| AppError::msg("mig", e).with_kind(ErrorKind::UnsupportedGpu))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_mode() {
+        assert!(check_mode(None, 0).is_ok());
+        assert!(check_mode(Some(MigMode { enabled: false, pending: true }), 0).is_ok());
+        let enabled = MigMode { enabled: true, pending: false };
+        assert!(check_mode(Some(enabled), 1).unwrap_err().contains("nvidia-smi -i 1 -mig 0"));
+        assert_eq!(
+            MigMode { enabled: false, pending: true }.to_string(),
+            "disabled (enabled after the next gpu reset)"
+        );
+    }
+}
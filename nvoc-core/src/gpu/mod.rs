@@ -0,0 +1,94 @@
+//! GPU operations and device management
+
+use crate::constants::hardware;
+use std::sync::Arc;
+
+use crate::nvml::{
+    device_get_count, device_get_handle_by_index, device_get_handle_by_pci_bus_id, device_get_handle_by_uuid, init,
+    system_get_driver_version, Device, NvmlError, Result, Session,
+};
+use container::DeviceRef;
+
+pub mod appclocks;
+pub mod capabilities;
+pub mod container;
+pub mod domain;
+pub mod ecc;
+pub mod events;
+pub mod fan;
+pub mod field;
+pub mod health;
+pub mod info;
+pub mod limits;
+pub mod mig;
+pub mod overclock;
+pub mod pcie;
+pub mod power;
+pub mod process;
+pub mod reset;
+pub mod state;
+pub mod telemetry;
+pub mod thermal;
+pub mod throttle;
+pub mod validation;
+pub mod virtualization;
+
+/// Cleanup guard to ensure NVML is properly shut down. NVML stays up until
+/// the guard and every [`Device`] opened under it are dropped.
+pub struct CleanupGuard {
+    _session: Arc<Session>,
+}
+
+pub fn init_nvml() -> std::result::Result<(), crate::AppError> {
+    init().map_err(|e| crate::AppError::new("driver", e))?;
+    let driver_version = system_get_driver_version()
+        .map_err(|e| crate::AppError::new("driver", e))?;
+    let major = parse_major(&driver_version)
+        .ok_or_else(|| crate::AppError::msg("driver", format!("unparseable version: {driver_version}")))?;
+    if major < hardware::MIN_DRIVER_VERSION {
+        return Err(crate::AppError::msg("driver", format!("version {driver_version} too old, need {}+", hardware::MIN_DRIVER_VERSION)));
+    }
+    Ok(())
+}
+
+pub fn init_with_cleanup() -> std::result::Result<CleanupGuard, crate::AppError> {
+    init_nvml()?;
+    Ok(CleanupGuard { _session: Session::start() })
+}
+
+pub fn driver_version() -> Result<String> {
+    system_get_driver_version()
+}
+
+fn parse_major(version: &str) -> Option<u32> {
+    version.split('.').next().and_then(|s| s.parse().ok())
+}
+
+/// Number of GPUs visible to nvoc (to this container, when in one)
+pub fn device_count() -> Result<u32> {
+    Ok(container::visible_count(device_get_count()?))
+}
+
+pub fn get_device(device_index: u32) -> Result<Device> {
+    let device_count = device_get_count()?;
+
+    let handle = match container::resolve(device_index, device_count) {
+        Some(DeviceRef::Index(index)) => device_get_handle_by_index(index)?,
+        Some(DeviceRef::Uuid(uuid)) => device_get_handle_by_uuid(&uuid)?,
+        None => return Err(crate::nvml::NvmlError::InvalidArgument),
+    };
+
+    Device::open(handle, device_index)
+}
+
+/// The GPU at PCI bus id `bus_id`, with the index nvoc knows it by
+pub fn get_device_by_pci_bus_id(bus_id: &str) -> Result<Device> {
+    let handle = device_get_handle_by_pci_bus_id(bus_id)?;
+    for index in 0..device_count()? {
+        let device = get_device(index)?;
+        if device.handle() == handle {
+            return Ok(device);
+        }
+    }
+    Err(NvmlError::NotFound)
+}
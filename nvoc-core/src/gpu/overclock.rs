@@ -0,0 +1,912 @@
+//! GPU overclocking operations
+//!
+//! Clock offsets are written through the interface the GPU's architecture
+//! and driver support: the versioned clock offset struct on Blackwell with
+//! driver 555 or newer, and the older VF curve offset calls on Turing,
+//! Ampere and older drivers.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::params::OverclockParams;
+use crate::constants::hardware;
+use crate::gpu::power::apply_power_limit;
+use crate::gpu::state::{self, DeviceState};
+use crate::gpu::{self, domain};
+use crate::gpu::validation;
+use crate::nvml::{self, Device, GpuArchitecture, NvmlBackend, NvmlClockType, NvmlPerfState, NVML_POWER_SOURCE_BATTERY};
+use crate::gpu::virtualization::{self, Knob};
+use crate::units::{MegaHertz, Milliwatts, Watts};
+use crate::{journal, output, signal, AppError, ErrorKind};
+
+/// NVML interface used for clock offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OffsetInterface {
+    /// `nvmlDeviceGetClockOffsets`/`nvmlDeviceSetClockOffsets`
+    ClockOffsets,
+    /// `nvmlDevice{Get,Set}GpcClkVfOffset` and `nvmlDevice{Get,Set}MemClkVfOffset`
+    VfOffsets,
+}
+
+impl OffsetInterface {
+    /// Unknown architectures that got past validation are driven like
+    /// Blackwell, the layout nvoc was built around. Drivers that predate the
+    /// clock offset struct, or whose version cannot be read, get the VF calls.
+    fn select(arch: &GpuArchitecture, driver_major: Option<u32>) -> Self {
+        match arch {
+            GpuArchitecture::Turing | GpuArchitecture::Ampere => OffsetInterface::VfOffsets,
+            GpuArchitecture::Blackwell | GpuArchitecture::Unknown => match driver_major {
+                Some(major) if major >= hardware::CLOCK_OFFSETS_DRIVER_VERSION => OffsetInterface::ClockOffsets,
+                _ => OffsetInterface::VfOffsets,
+            },
+        }
+    }
+
+    fn for_device(device: impl NvmlBackend) -> Self {
+        let arch = validation::detect_architecture(device).unwrap_or(GpuArchitecture::Unknown);
+        let driver_major = device.driver_version().ok().and_then(|version| gpu::parse_major(&version));
+        Self::select(&arch, driver_major)
+    }
+}
+
+/// NVML functions the offsets of `device` are read and written with
+pub fn offset_symbols(device: &Device) -> &'static [&'static str] {
+    match OffsetInterface::for_device(device) {
+        OffsetInterface::ClockOffsets => &["nvmlDeviceGetClockOffsets", "nvmlDeviceSetClockOffsets"],
+        OffsetInterface::VfOffsets => &[
+            "nvmlDeviceGetGpcClkVfOffset",
+            "nvmlDeviceSetGpcClkVfOffset",
+            "nvmlDeviceGetMemClkVfOffset",
+            "nvmlDeviceSetMemClkVfOffset",
+        ],
+    }
+}
+
+/// Read the graphics clock offset
+pub fn graphics_offset(device: impl NvmlBackend) -> nvml::Result<MegaHertz> {
+    match OffsetInterface::for_device(device) {
+        OffsetInterface::ClockOffsets => {
+            device.clock_offsets(NvmlClockType::Graphics, NvmlPerfState::P0).map(|o| MegaHertz(o.clockOffsetMHz))
+        }
+        OffsetInterface::VfOffsets => device.gpc_vf_offset().map(MegaHertz),
+    }
+}
+
+/// Read the memory clock offset
+pub fn memory_offset(device: impl NvmlBackend) -> nvml::Result<MegaHertz> {
+    match OffsetInterface::for_device(device) {
+        OffsetInterface::ClockOffsets => {
+            device.clock_offsets(NvmlClockType::Memory, NvmlPerfState::P0).map(|o| MegaHertz(o.clockOffsetMHz))
+        }
+        OffsetInterface::VfOffsets => device.memory_vf_offset().map(MegaHertz),
+    }
+}
+
+/// Offsets the driver accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OffsetRange {
+    pub min: MegaHertz,
+    pub max: MegaHertz,
+}
+
+impl OffsetRange {
+    /// Refuse `offset` when it falls outside the range
+    pub fn check(&self, label: &'static str, offset: MegaHertz) -> Result<(), AppError> {
+        if (self.min..=self.max).contains(&offset) {
+            return Ok(());
+        }
+        Err(AppError::msg(label, format!("{offset:+} is outside the range the driver accepts ({self})"))
+            .with_kind(ErrorKind::InvalidArgument))
+    }
+}
+
+impl std::fmt::Display for OffsetRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:+} to {:+}", self.min, self.max)
+    }
+}
+
+/// Read the graphics clock offset range the driver accepts
+pub fn graphics_offset_range(device: impl NvmlBackend) -> nvml::Result<OffsetRange> {
+    let (min, max) = match OffsetInterface::for_device(device) {
+        OffsetInterface::ClockOffsets => device.clock_offsets(NvmlClockType::Graphics, NvmlPerfState::P0)
+            .map(|o| (o.minClockOffsetMHz, o.maxClockOffsetMHz))?,
+        OffsetInterface::VfOffsets => device.gpc_vf_offset_range()?,
+    };
+    Ok(OffsetRange { min: MegaHertz(min), max: MegaHertz(max) })
+}
+
+/// Read the memory clock offset range the driver accepts
+pub fn memory_offset_range(device: impl NvmlBackend) -> nvml::Result<OffsetRange> {
+    let (min, max) = match OffsetInterface::for_device(device) {
+        OffsetInterface::ClockOffsets => device.clock_offsets(NvmlClockType::Memory, NvmlPerfState::P0)
+            .map(|o| (o.minClockOffsetMHz, o.maxClockOffsetMHz))?,
+        OffsetInterface::VfOffsets => device.memory_vf_offset_range()?,
+    };
+    Ok(OffsetRange { min: MegaHertz(min), max: MegaHertz(max) })
+}
+
+/// Offsets of the pstates below P0 that are set. Only the clock offset
+/// struct addresses pstates, so this is empty elsewhere.
+pub fn pstate_offsets(device: impl NvmlBackend, clock_type: NvmlClockType) -> Vec<(NvmlPerfState, MegaHertz)> {
+    if OffsetInterface::for_device(device) != OffsetInterface::ClockOffsets {
+        return Vec::new();
+    }
+    NvmlPerfState::ALL[1..]
+        .iter()
+        .filter_map(|&pstate| {
+            let offset = MegaHertz(device.clock_offsets(clock_type, pstate).ok()?.clockOffsetMHz);
+            (offset != MegaHertz(0)).then_some((pstate, offset))
+        })
+        .collect()
+}
+
+/// Write the offset of a single pstate
+pub fn set_pstate_offset(
+    device: impl NvmlBackend,
+    clock_type: NvmlClockType,
+    pstate: NvmlPerfState,
+    offset: MegaHertz,
+) -> Result<(), AppError> {
+    let label = offset_label(clock_type);
+    if OffsetInterface::for_device(device) != OffsetInterface::ClockOffsets {
+        return Err(AppError::msg(
+            label,
+            format!("offsets for {pstate} need a Blackwell GPU with driver {} or newer", hardware::CLOCK_OFFSETS_DRIVER_VERSION),
+        )
+        .with_kind(ErrorKind::UnsupportedGpu));
+    }
+    device.set_clock_offset(clock_type, pstate, offset.0).map_err(|e| {
+        let clock = match clock_type {
+            NvmlClockType::Memory => "memory",
+            _ => "graphics",
+        };
+        AppError::new(label, e).context(format!("set {clock} offset {pstate} ({offset:+})")).on_device(device.index())
+    })
+}
+
+fn offset_label(clock_type: NvmlClockType) -> &'static str {
+    match clock_type {
+        NvmlClockType::Memory => "mem offset",
+        _ => "gpu offset",
+    }
+}
+
+fn apply_pstate_offset(
+    device: impl NvmlBackend,
+    clock_type: NvmlClockType,
+    pstate: NvmlPerfState,
+    offset: MegaHertz,
+    dry_run: bool,
+    verify: bool,
+) -> Result<(), AppError> {
+    let label = offset_label(clock_type);
+    let current = device.clock_offsets(clock_type, pstate).ok();
+    let range =
+        current.map(|o| OffsetRange { min: MegaHertz(o.minClockOffsetMHz), max: MegaHertz(o.maxClockOffsetMHz) });
+    if let Some(range) = range {
+        range.check(label, offset)?;
+    }
+    if current.is_some_and(|o| MegaHertz(o.clockOffsetMHz) == offset) {
+        output::unchanged(&format!("{label} {pstate}"), &format!("{offset:+}"), dry_run);
+        return Ok(());
+    }
+    if dry_run {
+        println!("{label} {pstate}: {offset:+} ({})", dry_run_note(range));
+        return Ok(());
+    }
+    set_pstate_offset(device, clock_type, pstate, offset)?;
+    output::status!("{label} {pstate}: {offset:+}");
+    if verify {
+        let read = device.clock_offsets(clock_type, pstate)
+            .map(|o| format!("{:+}", MegaHertz(o.clockOffsetMHz)));
+        check_readback(&format!("{label} {pstate}"), format!("{offset:+}"), read);
+    }
+    Ok(())
+}
+
+/// Check a requested offset against the driver's range. Drivers that do
+/// not report a range leave the check to the set call.
+fn check_offset(
+    label: &'static str,
+    offset: Option<MegaHertz>,
+    read_range: impl FnOnce() -> nvml::Result<OffsetRange>,
+) -> Result<Option<OffsetRange>, AppError> {
+    let Some(offset) = offset else {
+        return Ok(None);
+    };
+    let range = read_range().ok();
+    if let Some(range) = range {
+        range.check(label, offset)?;
+    }
+    Ok(range)
+}
+
+/// Whether `requested` raises the power limit or a P0 offset above
+/// `current`. An unreadable power limit counts as raised, an unreadable
+/// offset as 0.
+fn raises(current: &DeviceState, requested: &DeviceState) -> bool {
+    let offset_raised =
+        |requested: Option<MegaHertz>, current: Option<MegaHertz>| requested.is_some_and(|r| r > current.unwrap_or_default());
+    offset_raised(requested.graphics_offset, current.graphics_offset)
+        || offset_raised(requested.memory_offset, current.memory_offset)
+        || requested.power_limit_mw.is_some_and(|r| current.power_limit_mw.is_none_or(|c| r > c))
+}
+
+/// Power limit and P0 offsets `params` would set
+fn requested_state(device: impl NvmlBackend, params: &OverclockParams) -> DeviceState {
+    let power_limit_mw = params.power_limit.and_then(|percent| {
+        let info = domain::get_power_info(device).ok()?;
+        let percent = domain::power_floor_percent().map_or(percent, |floor| percent.max(floor));
+        Some(Milliwatts::from(info.effective_watts_from_percentage(percent)))
+    });
+    DeviceState {
+        graphics_offset: params.graphics_offset.map(MegaHertz),
+        memory_offset: params.memory_offset.map(MegaHertz),
+        power_limit_mw,
+    }
+}
+
+/// Whether a clock lock keeps the clock above the lowest of the
+/// `supported` range, holding the GPU at speed even when idle. A lock that
+/// only caps the maximum lowers heat instead. Locks cannot be read back, so
+/// this does not depend on any lock already in place; an unreadable range
+/// counts as raised.
+fn lock_raises(lock: (u32, u32), supported: Option<(u32, u32)>) -> bool {
+    supported.is_none_or(|(lowest, _)| lock.0 > lowest)
+}
+
+/// Whether `params` raises anything the thermal guard watches: the power
+/// limit, an offset at any pstate above its current value, or a clock lock
+/// that keeps clocks up
+fn raises_heat(device: impl NvmlBackend, params: &OverclockParams) -> bool {
+    let pstate_raised = |clock_type, offsets: &[(NvmlPerfState, i32)]| {
+        offsets.iter().any(|&(pstate, offset)| {
+            offset > device.clock_offsets(clock_type, pstate).map_or(0, |current| current.clockOffsetMHz)
+        })
+    };
+    raises(&state::capture(device), &requested_state(device, params))
+        || pstate_raised(NvmlClockType::Graphics, &params.graphics_pstate_offsets)
+        || pstate_raised(NvmlClockType::Memory, &params.memory_pstate_offsets)
+        || params.clocks.is_some_and(|lock| lock_raises(lock, supported_clock_range(device).ok()))
+        || params.mem_clocks.is_some_and(|lock| lock_raises(lock, supported_memory_clock_range(device).ok()))
+}
+
+/// Refuse to raise the power limit, an offset or the clocks while the GPU
+/// is above the configured `limits.max_apply_temperature`
+fn check_thermal_guard(device: impl NvmlBackend, params: &OverclockParams) -> Result<(), AppError> {
+    let Some(limit) = domain::thermal_guard_c() else {
+        return Ok(());
+    };
+    let Ok(temp) = device.temperature() else {
+        return Ok(());
+    };
+    if temp <= limit || !raises_heat(device, params) {
+        return Ok(());
+    }
+    Err(AppError::msg(
+        "thermal guard",
+        format!("gpu is at {temp}, above the {limit} limit for raising power or offsets; let it cool or pass --ignore-thermal-guard"),
+    )
+    .with_kind(ErrorKind::TooHot))
+}
+
+/// Refuse to raise the power limit of a laptop GPU running on battery,
+/// unless `limits.allow_power_raise_on_battery` is set
+fn check_power_source(device: impl NvmlBackend, params: &OverclockParams) -> Result<(), AppError> {
+    if params.power_limit.is_none() || !device.power_source().is_ok_and(|s| s == NVML_POWER_SOURCE_BATTERY) {
+        return Ok(());
+    }
+    // Offsets cost little power next to the limit, so only the limit counts
+    let current = DeviceState { power_limit_mw: device.power_limit().ok(), ..Default::default() };
+    let requested = DeviceState { power_limit_mw: requested_state(device, params).power_limit_mw, ..Default::default() };
+    if !raises(&current, &requested) {
+        return Ok(());
+    }
+    if domain::power_raise_on_battery() {
+        eprintln!("warning[power source]: raising the power limit while the gpu runs on battery");
+        return Ok(());
+    }
+    Err(AppError::msg(
+        "power source",
+        "gpu is running on battery; plug in to raise the power limit or set limits.allow_power_raise_on_battery".to_string(),
+    )
+    .with_kind(ErrorKind::OnBattery))
+}
+
+/// Wait until the GPU is back at or below the configured
+/// `limits.max_apply_temperature`, or Ctrl-C, for tools that apply one
+/// setting after another under load
+pub fn cool_down(device: impl NvmlBackend, device_index: u32) {
+    let Some(limit) = domain::thermal_guard_c() else {
+        return;
+    };
+    let mut waiting = false;
+    while device.temperature().is_ok_and(|temp| temp > limit) {
+        if !waiting {
+            output::status!("thermal guard: waiting for gpu {device_index} to cool to {limit}");
+            waiting = true;
+        }
+        if !signal::sleep(Duration::from_secs(1)) {
+            return;
+        }
+    }
+}
+
+/// Write the graphics clock offset
+pub fn set_graphics_offset(device: impl NvmlBackend, offset: MegaHertz) -> nvml::Result<()> {
+    match OffsetInterface::for_device(device) {
+        OffsetInterface::ClockOffsets => {
+            device.set_clock_offset(NvmlClockType::Graphics, NvmlPerfState::P0, offset.0)
+        }
+        OffsetInterface::VfOffsets => device.set_gpc_vf_offset(offset.0),
+    }
+}
+
+/// Write the memory clock offset
+pub fn set_memory_offset(device: impl NvmlBackend, offset: MegaHertz) -> nvml::Result<()> {
+    match OffsetInterface::for_device(device) {
+        OffsetInterface::ClockOffsets => {
+            device.set_clock_offset(NvmlClockType::Memory, NvmlPerfState::P0, offset.0)
+        }
+        OffsetInterface::VfOffsets => device.set_memory_vf_offset(offset.0),
+    }
+}
+
+/// Lowest and highest graphics clock the device supports at its top memory
+/// clock, in MHz
+pub fn supported_clock_range(device: impl NvmlBackend) -> nvml::Result<(u32, u32)> {
+    let memory = device.supported_memory_clocks()?;
+    let top = memory.into_iter().max().ok_or(nvml::NvmlError::NotSupported)?;
+    let graphics = device.supported_graphics_clocks(top)?;
+    match (graphics.iter().min(), graphics.iter().max()) {
+        (Some(min), Some(max)) => Ok((*min, *max)),
+        _ => Err(nvml::NvmlError::NotSupported),
+    }
+}
+
+/// The range the driver will actually lock to when `requested` reaches
+/// past `supported`, or `None` if it fits
+fn clamped_clocks(requested: (u32, u32), supported: (u32, u32)) -> Option<(u32, u32)> {
+    let (min, max) = requested;
+    let clamped = (min.clamp(supported.0, supported.1), max.clamp(supported.0, supported.1));
+    (clamped != requested).then_some(clamped)
+}
+
+/// Warning for a lock that `offset` shifts past `supported_max`. With the
+/// clock offset struct, the driver locks the clocks of the unshifted curve,
+/// so the GPU ends up running up to the lock's maximum plus the offset.
+fn lock_offset_warning(clocks: (u32, u32), offset: MegaHertz, supported_max: u32) -> Option<String> {
+    let (min, max) = clocks;
+    let (lo, hi) = (min.saturating_add_signed(offset.0), max.saturating_add_signed(offset.0));
+    (hi > supported_max).then(|| {
+        format!(
+            "the {offset:+} offset shifts the {min}-{max}MHz lock to {lo}-{hi}MHz, above the supported {supported_max}MHz; \
+             lower the lock maximum to {}MHz to stay within it",
+            supported_max.saturating_add_signed(-offset.0)
+        )
+    })
+}
+
+/// Warn when locked clocks and a graphics offset requested together add up
+/// to more than the GPU supports
+fn check_lock_offset(device: impl NvmlBackend, clocks: (u32, u32), offset: MegaHertz) {
+    if OffsetInterface::for_device(device) != OffsetInterface::ClockOffsets {
+        return;
+    }
+    let Ok((_, supported_max)) = supported_clock_range(device) else {
+        return;
+    };
+    if let Some(warning) = lock_offset_warning(clocks, offset, supported_max) {
+        eprintln!("warning[clocks]: {warning}");
+    }
+}
+
+fn apply_clocks(device: impl NvmlBackend, clocks: (u32, u32), dry_run: bool) -> Result<(), AppError> {
+    let (min, max) = clocks;
+    if let Ok(supported) = supported_clock_range(device) {
+        if let Some((lo, hi)) = clamped_clocks(clocks, supported) {
+            eprintln!(
+                "warning[clocks]: {min}-{max}MHz is outside the supported {}-{}MHz, the driver will lock {lo}-{hi}MHz",
+                supported.0, supported.1
+            );
+        }
+    }
+    if dry_run {
+        println!("clocks: {min}-{max}MHz (dry run)");
+        return Ok(());
+    }
+    device.set_gpu_locked_clocks(min, max).map_err(|e| {
+        AppError::new("clocks", e).context(format!("lock gpu clocks ({min}-{max}MHz)")).on_device(device.index())
+    })?;
+    output::status!("clocks: {min}-{max}MHz");
+    Ok(())
+}
+
+/// Lowest and highest memory clock the device supports, in MHz
+pub fn supported_memory_clock_range(device: impl NvmlBackend) -> nvml::Result<(u32, u32)> {
+    let memory = device.supported_memory_clocks()?;
+    match (memory.iter().min(), memory.iter().max()) {
+        (Some(min), Some(max)) => Ok((*min, *max)),
+        _ => Err(nvml::NvmlError::NotSupported),
+    }
+}
+
+fn apply_mem_clocks(device: impl NvmlBackend, clocks: (u32, u32), dry_run: bool) -> Result<(), AppError> {
+    let (min, max) = clocks;
+    if let Ok(supported) = supported_memory_clock_range(device) {
+        if let Some((lo, hi)) = clamped_clocks(clocks, supported) {
+            eprintln!(
+                "warning[mem clocks]: {min}-{max}MHz is outside the supported {}-{}MHz, the driver will lock {lo}-{hi}MHz",
+                supported.0, supported.1
+            );
+        }
+    }
+    if dry_run {
+        println!("mem clocks: {min}-{max}MHz (dry run)");
+        return Ok(());
+    }
+    device.set_memory_locked_clocks(min, max).map_err(|e| {
+        AppError::new("mem clocks", e).context(format!("lock mem clocks ({min}-{max}MHz)")).on_device(device.index())
+    })?;
+    output::status!("mem clocks: {min}-{max}MHz");
+    Ok(())
+}
+
+/// How a value read back after a write differs from what was written, as
+/// when the driver clamps or ignores a request. Values that cannot be read
+/// back are not checked.
+fn readback_mismatch(written: String, read: nvml::Result<String>) -> Option<String> {
+    match read {
+        Ok(read) if read != written => Some(format!("wrote {written}, the driver reports {read}")),
+        _ => None,
+    }
+}
+
+/// Warn when a value read back after a write differs from what was written
+pub fn check_readback(label: &str, written: String, read: nvml::Result<String>) {
+    if let Some(mismatch) = readback_mismatch(written, read) {
+        eprintln!("warning[{label}]: {mismatch}");
+    }
+}
+
+fn dry_run_note(range: Option<OffsetRange>) -> String {
+    match range {
+        Some(range) => format!("dry run, range {range}"),
+        None => "dry run".to_string(),
+    }
+}
+
+fn apply_graphics_offset(
+    device: impl NvmlBackend,
+    offset: MegaHertz,
+    range: Option<OffsetRange>,
+    dry_run: bool,
+    verify: bool,
+) -> Result<(), AppError> {
+    if graphics_offset(device).ok() == Some(offset) {
+        output::unchanged("gpu offset", &format!("{offset:+}"), dry_run);
+        return Ok(());
+    }
+    if dry_run {
+        println!("gpu offset: {:+} ({})", offset, dry_run_note(range));
+        return Ok(());
+    }
+    set_graphics_offset(device, offset).map_err(|e| {
+        AppError::new("gpu offset", e).context(format!("set graphics offset ({offset:+})")).on_device(device.index())
+    })?;
+    output::status!("gpu offset: {:+}", offset);
+    if verify {
+        check_readback("gpu offset", format!("{offset:+}"), graphics_offset(device).map(|o| format!("{o:+}")));
+    }
+    Ok(())
+}
+
+fn apply_memory_offset(
+    device: impl NvmlBackend,
+    offset: MegaHertz,
+    range: Option<OffsetRange>,
+    dry_run: bool,
+    verify: bool,
+) -> Result<(), AppError> {
+    if memory_offset(device).ok() == Some(offset) {
+        output::unchanged("mem offset", &format!("{offset:+}"), dry_run);
+        return Ok(());
+    }
+    if dry_run {
+        println!("mem offset: {:+} ({})", offset, dry_run_note(range));
+        return Ok(());
+    }
+    set_memory_offset(device, offset).map_err(|e| {
+        AppError::new("mem offset", e).context(format!("set memory offset ({offset:+})")).on_device(device.index())
+    })?;
+    output::status!("mem offset: {:+}", offset);
+    if verify {
+        check_readback("mem offset", format!("{offset:+}"), memory_offset(device).map(|o| format!("{o:+}")));
+    }
+    Ok(())
+}
+
+fn requested_knobs(params: &OverclockParams) -> Vec<Knob> {
+    let mut knobs = Vec::new();
+    if params.clocks.is_some() || params.mem_clocks.is_some() {
+        knobs.push(Knob::LockedClocks);
+    }
+    if params.graphics_offset.is_some()
+        || params.memory_offset.is_some()
+        || !params.graphics_pstate_offsets.is_empty()
+        || !params.memory_pstate_offsets.is_empty()
+    {
+        knobs.push(Knob::ClockOffsets);
+    }
+    if params.power_limit.is_some() {
+        knobs.push(Knob::PowerLimit);
+    }
+    knobs
+}
+
+/// Refuse `params` before anything is written. Returns the offset ranges
+/// the driver reports.
+fn check_params(
+    device: impl NvmlBackend,
+    params: &OverclockParams,
+) -> Result<(Option<OffsetRange>, Option<OffsetRange>), AppError> {
+    virtualization::check_knobs(device, &requested_knobs(params))?;
+    let graphics_offset = params.graphics_offset.map(MegaHertz);
+    let memory_offset = params.memory_offset.map(MegaHertz);
+    let graphics_range = check_offset("gpu offset", graphics_offset, || graphics_offset_range(device))?;
+    let memory_range = check_offset("mem offset", memory_offset, || memory_offset_range(device))?;
+    if let (Some(clocks), Some(offset)) = (params.clocks, graphics_offset) {
+        check_lock_offset(device, clocks, offset);
+    }
+    if !params.dry_run {
+        check_thermal_guard(device, params)?;
+        check_power_source(device, params)?;
+    }
+    Ok((graphics_range, memory_range))
+}
+
+pub fn apply(device: impl NvmlBackend, device_index: u32, params: &OverclockParams) -> Result<(), AppError> {
+    let (graphics_range, memory_range) = check_params(device, params)?;
+    if params.dry_run {
+        return apply_all(device, params, graphics_range, memory_range);
+    }
+    let transaction = journal::begin(device, device_index, intended_change(device, "overclock", params))?;
+    let pstate_prior = prior_pstate_offsets(device, params);
+    let prior = (&transaction.prior, pstate_prior.as_slice());
+    match apply_all_or_roll_back(device, params, graphics_range, memory_range, prior) {
+        // A rollback that fails leaves the journal entry for the next start
+        Err(e) if e.kind() == ErrorKind::PartialApply => Err(e),
+        result => transaction.commit().and(result),
+    }
+}
+
+/// Apply every setting in `params`, and if one fails put back the ones
+/// already written to `prior`. A rollback that does not complete turns the
+/// error into a [`ErrorKind::PartialApply`].
+fn apply_all_or_roll_back(
+    device: impl NvmlBackend,
+    params: &OverclockParams,
+    graphics_range: Option<OffsetRange>,
+    memory_range: Option<OffsetRange>,
+    (prior, pstate_prior): (&DeviceState, &[(NvmlClockType, NvmlPerfState, MegaHertz)]),
+) -> Result<(), AppError> {
+    let Err(e) = apply_all(device, params, graphics_range, memory_range) else {
+        return Ok(());
+    };
+    match roll_back("rollback", device, params, prior, pstate_prior) {
+        true => Err(e),
+        false => Err(e.with_kind(ErrorKind::PartialApply)),
+    }
+}
+
+/// The change `params` makes, as recorded in the journal under `operation`
+fn intended_change(device: impl NvmlBackend, operation: &str, params: &OverclockParams) -> journal::Change {
+    journal::Change {
+        operation: operation.to_string(),
+        target: requested_state(device, params),
+        locks_clocks: params.clocks.is_some() || params.mem_clocks.is_some(),
+        manual_fans: false,
+    }
+}
+
+/// Offsets of the pstates `params` changes, read before changing them
+fn prior_pstate_offsets(
+    device: impl NvmlBackend,
+    params: &OverclockParams,
+) -> Vec<(NvmlClockType, NvmlPerfState, MegaHertz)> {
+    let graphics = params.graphics_pstate_offsets.iter().map(|&(pstate, _)| (NvmlClockType::Graphics, pstate));
+    let memory = params.memory_pstate_offsets.iter().map(|&(pstate, _)| (NvmlClockType::Memory, pstate));
+    graphics
+        .chain(memory)
+        .filter_map(|(clock_type, pstate)| {
+            let prior = device.clock_offsets(clock_type, pstate).ok()?;
+            Some((clock_type, pstate, MegaHertz(prior.clockOffsetMHz)))
+        })
+        .collect()
+}
+
+/// Settings applied on probation, then kept or put back. The journal
+/// entry is written before the first setting and stays open until the
+/// trial ends, so a trial cut short by a crash is reverted on the next
+/// start.
+pub struct Trial<'a> {
+    device: Device,
+    params: &'a OverclockParams,
+    transaction: journal::Transaction,
+    pstate_prior: Vec<(NvmlClockType, NvmlPerfState, MegaHertz)>,
+}
+
+impl<'a> Trial<'a> {
+    pub fn start(device: &Device, device_index: u32, params: &'a OverclockParams) -> Result<Self, AppError> {
+        let (graphics_range, memory_range) = check_params(device, params)?;
+        let transaction = journal::begin(device, device_index, intended_change(device, "trial", params))?;
+        let pstate_prior = prior_pstate_offsets(device, params);
+        let prior = (&transaction.prior, pstate_prior.as_slice());
+        if let Err(e) = apply_all_or_roll_back(device, params, graphics_range, memory_range, prior) {
+            // A rollback that fails leaves the journal entry for the next start
+            if e.kind() != ErrorKind::PartialApply {
+                transaction.commit()?;
+            }
+            return Err(e);
+        }
+        Ok(Self { device: device.clone(), params, transaction, pstate_prior })
+    }
+
+    pub fn keep(self) -> Result<(), AppError> {
+        self.transaction.commit()
+    }
+
+    /// Put back the settings the trial replaced
+    pub fn revert(self) -> Result<(), AppError> {
+        if !roll_back("revert", &self.device, self.params, &self.transaction.prior, &self.pstate_prior) {
+            return Err(AppError::msg("revert", "not every setting was reverted, the next nvoc run retries".to_string())
+                .with_kind(ErrorKind::PartialApply));
+        }
+        self.transaction.commit()
+    }
+}
+
+/// Apply `params` for `duration`, or until Ctrl-C, then put the prior
+/// settings back
+pub fn trial(device: &Device, device_index: u32, params: &OverclockParams, duration: Duration) -> Result<(), AppError> {
+    signal::install();
+    let trial = Trial::start(device, device_index, params)?;
+    output::status!("trial: reverting in {}s, Ctrl-C to revert now", duration.as_secs());
+    signal::sleep(duration);
+    trial.revert()
+}
+
+/// Put back the settings `params` touched, reporting each one restored
+/// under `action`. Only values that differ from `prior` are written;
+/// locked clocks cannot be read back and are released. Returns whether
+/// everything was restored.
+fn roll_back(
+    action: &str,
+    device: impl NvmlBackend,
+    params: &OverclockParams,
+    prior: &DeviceState,
+    pstate_prior: &[(NvmlClockType, NvmlPerfState, MegaHertz)],
+) -> bool {
+    let mut restored = true;
+    let mut restore = |label: String, result: nvml::Result<()>| match result {
+        Ok(()) => output::status!("{action}: {label}"),
+        Err(e) => {
+            eprintln!("error[{action}]: {label}: {}", e.user_message());
+            restored = false;
+        }
+    };
+
+    if params.clocks.is_some() || params.mem_clocks.is_some() {
+        restore("clocks unlocked".to_string(), state::unlock_clocks(device));
+    }
+    if let (Some(_), Some(offset)) = (params.graphics_offset, prior.graphics_offset) {
+        if graphics_offset(device).ok() != Some(offset) {
+            restore(format!("gpu offset {offset:+}"), set_graphics_offset(device, offset));
+        }
+    }
+    if let (Some(_), Some(offset)) = (params.memory_offset, prior.memory_offset) {
+        if memory_offset(device).ok() != Some(offset) {
+            restore(format!("mem offset {offset:+}"), set_memory_offset(device, offset));
+        }
+    }
+    for &(clock_type, pstate, offset) in pstate_prior {
+        let current = device.clock_offsets(clock_type, pstate)
+            .ok()
+            .map(|o| MegaHertz(o.clockOffsetMHz));
+        if current != Some(offset) {
+            let label = format!("{} {pstate} {offset:+}", offset_label(clock_type));
+            restore(label, device.set_clock_offset(clock_type, pstate, offset.0));
+        }
+    }
+    if let (Some(_), Some(limit_mw)) = (params.power_limit, prior.power_limit_mw) {
+        if device.power_limit().ok() != Some(limit_mw) {
+            let label = format!("power limit {}", Watts::from(limit_mw));
+            restore(label, domain::set_power_limit(device, limit_mw).map(drop));
+        }
+    }
+    restored
+}
+
+fn apply_all(
+    device: impl NvmlBackend,
+    params: &OverclockParams,
+    graphics_range: Option<OffsetRange>,
+    memory_range: Option<OffsetRange>,
+) -> Result<(), AppError> {
+    if let Some(clocks) = params.clocks {
+        apply_clocks(device, clocks, params.dry_run)?;
+    }
+    if let Some(clocks) = params.mem_clocks {
+        apply_mem_clocks(device, clocks, params.dry_run)?;
+    }
+    if let Some(offset) = params.graphics_offset {
+        apply_graphics_offset(device, MegaHertz(offset), graphics_range, params.dry_run, params.verify)?;
+    }
+    if let Some(offset) = params.memory_offset {
+        apply_memory_offset(device, MegaHertz(offset), memory_range, params.dry_run, params.verify)?;
+    }
+    for &(pstate, offset) in &params.graphics_pstate_offsets {
+        apply_pstate_offset(device, NvmlClockType::Graphics, pstate, MegaHertz(offset), params.dry_run, params.verify)?;
+    }
+    for &(pstate, offset) in &params.memory_pstate_offsets {
+        apply_pstate_offset(device, NvmlClockType::Memory, pstate, MegaHertz(offset), params.dry_run, params.verify)?;
+    }
+    if let Some(percentage) = params.power_limit {
+        apply_power_limit(device, percentage, params.dry_run, params.verify)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nvml::backend::fake::{FakeGpu, Settings};
+
+    #[test]
+    fn test_offset_interface_selection() {
+        let current = Some(575);
+        assert_eq!(OffsetInterface::select(&GpuArchitecture::Blackwell, current), OffsetInterface::ClockOffsets);
+        assert_eq!(OffsetInterface::select(&GpuArchitecture::Unknown, current), OffsetInterface::ClockOffsets);
+        assert_eq!(OffsetInterface::select(&GpuArchitecture::Ampere, current), OffsetInterface::VfOffsets);
+        assert_eq!(OffsetInterface::select(&GpuArchitecture::Turing, current), OffsetInterface::VfOffsets);
+        // Drivers before the clock offset struct fall back to the VF calls
+        assert_eq!(OffsetInterface::select(&GpuArchitecture::Blackwell, Some(550)), OffsetInterface::VfOffsets);
+        assert_eq!(OffsetInterface::select(&GpuArchitecture::Blackwell, None), OffsetInterface::VfOffsets);
+    }
+
+    #[test]
+    fn test_clamped_clocks() {
+        assert_eq!(clamped_clocks((210, 2820), (180, 3090)), None);
+        assert_eq!(clamped_clocks((150, 3500), (180, 3090)), Some((180, 3090)));
+        assert_eq!(clamped_clocks((200, 2820), (210, 3090)), Some((210, 2820)));
+    }
+
+    #[test]
+    fn test_lock_offset_warning() {
+        assert_eq!(lock_offset_warning((200, 2200), MegaHertz(856), 3090), None);
+        assert_eq!(lock_offset_warning((200, 2820), MegaHertz(-200), 3090), None);
+        assert_eq!(
+            lock_offset_warning((200, 2820), MegaHertz(856), 3090).as_deref(),
+            Some(
+                "the +856MHz offset shifts the 200-2820MHz lock to 1056-3676MHz, above the supported 3090MHz; \
+                 lower the lock maximum to 2234MHz to stay within it"
+            )
+        );
+    }
+
+    #[test]
+    fn test_offset_range_check() {
+        let range = OffsetRange { min: MegaHertz(-1000), max: MegaHertz(3000) };
+        assert!(range.check("mem offset", MegaHertz(3000)).is_ok());
+        assert!(range.check("mem offset", MegaHertz(-1000)).is_ok());
+        let err = range.check("mem offset", MegaHertz(3100)).err().unwrap();
+        assert_eq!(err.to_string(), "error[mem offset]: +3100MHz is outside the range the driver accepts (-1000MHz to +3000MHz)");
+        // No range reported: nothing to check against
+        assert!(matches!(check_offset("gpu offset", Some(MegaHertz(5000)), || Err(nvml::NvmlError::NotSupported)), Ok(None)));
+    }
+
+    #[test]
+    fn test_raises() {
+        let requested = |graphics: Option<i32>, memory: Option<i32>, power_mw: Option<u32>| DeviceState {
+            graphics_offset: graphics.map(MegaHertz),
+            memory_offset: memory.map(MegaHertz),
+            power_limit_mw: power_mw.map(Milliwatts),
+        };
+        let current = requested(Some(100), None, Some(460_000));
+        assert!(!raises(&current, &requested(Some(100), None, Some(400_000))));
+        assert!(!raises(&current, &requested(Some(-50), Some(0), None)));
+        assert!(raises(&current, &requested(Some(150), None, None)));
+        assert!(raises(&current, &requested(None, Some(500), None)));
+        assert!(raises(&current, &requested(None, None, Some(575_000))));
+        assert!(raises(&DeviceState::default(), &requested(None, None, Some(400_000))));
+    }
+
+    #[test]
+    fn test_raises_heat() {
+        let gpu = FakeGpu::new(Settings::default());
+        gpu.settings.borrow_mut().graphics_offsets[NvmlPerfState::P2 as usize] = 50;
+        let pstate = |offset| OverclockParams {
+            graphics_pstate_offsets: vec![(NvmlPerfState::P2, offset)],
+            memory_pstate_offsets: vec![(NvmlPerfState::P0, 0)],
+            ..Default::default()
+        };
+        assert!(raises_heat(&gpu, &pstate(100)));
+        assert!(!raises_heat(&gpu, &pstate(0)));
+
+        // A lock holding clocks up counts, one that only caps them does not
+        assert!(raises_heat(&gpu, &OverclockParams { clocks: Some((1800, 2800)), ..Default::default() }));
+        assert!(!raises_heat(&gpu, &OverclockParams { clocks: Some((180, 1500)), ..Default::default() }));
+        assert!(raises_heat(&gpu, &OverclockParams { mem_clocks: Some((14001, 14001)), ..Default::default() }));
+        assert!(!raises_heat(&gpu, &OverclockParams { mem_clocks: Some((405, 810)), ..Default::default() }));
+        assert!(lock_raises((180, 1500), None));
+    }
+
+    fn tuning() -> OverclockParams {
+        OverclockParams {
+            clocks: Some((1800, 2800)),
+            graphics_offset: Some(150),
+            memory_offset: Some(1000),
+            graphics_pstate_offsets: vec![(NvmlPerfState::P2, 50)],
+            power_limit: Some(90),
+            ..Default::default()
+        }
+    }
+
+    fn apply_fake(gpu: &FakeGpu, params: &OverclockParams) -> Result<(), AppError> {
+        let prior = state::capture(gpu);
+        let pstate_prior = prior_pstate_offsets(gpu, params);
+        apply_all_or_roll_back(gpu, params, None, None, (&prior, &pstate_prior))
+    }
+
+    #[test]
+    fn test_apply_all() {
+        let gpu = FakeGpu::new(Settings::default());
+        assert!(apply_fake(&gpu, &tuning()).is_ok());
+        let settings = gpu.settings();
+        assert_eq!(settings.locked_clocks, Some((1800, 2800)));
+        assert_eq!(settings.graphics_offsets[NvmlPerfState::P0 as usize], 150);
+        assert_eq!(settings.graphics_offsets[NvmlPerfState::P2 as usize], 50);
+        assert_eq!(settings.memory_offsets[NvmlPerfState::P0 as usize], 1000);
+        assert_eq!(settings.power_limit, Milliwatts(517_000));
+    }
+
+    #[test]
+    fn test_failure_rolls_back_earlier_writes() {
+        let mut gpu = FakeGpu::new(Settings::default());
+        gpu.fail = Some("set_power_limit");
+        let err = apply_fake(&gpu, &tuning()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Permission);
+        assert_eq!(gpu.settings(), Settings::default());
+    }
+
+    #[test]
+    fn test_unchanged_values_not_written() {
+        let mut gpu = FakeGpu::new(Settings::default());
+        assert!(apply_fake(&gpu, &tuning()).is_ok());
+        let applied = gpu.settings();
+        // Any offset or power limit write now fails, so none may be issued
+        gpu.fail = Some("set_clock_offset");
+        let offsets_only = OverclockParams { clocks: None, power_limit: None, ..tuning() };
+        assert!(apply_fake(&gpu, &offsets_only).is_ok());
+        gpu.fail = Some("set_power_limit");
+        assert!(apply_fake(&gpu, &OverclockParams { power_limit: Some(90), ..Default::default() }).is_ok());
+        assert_eq!(gpu.settings(), applied);
+    }
+
+    #[test]
+    fn test_readback_mismatch() {
+        let mut gpu = FakeGpu::new(Settings::default());
+        gpu.ignore = Some("set_clock_offset");
+        let params = OverclockParams { graphics_offset: Some(150), verify: true, ..Default::default() };
+        assert!(apply_fake(&gpu, &params).is_ok());
+        let read = graphics_offset(&gpu).map(|o| format!("{o:+}"));
+        assert_eq!(
+            readback_mismatch(format!("{:+}", MegaHertz(150)), read),
+            Some("wrote +150MHz, the driver reports +0MHz".to_string())
+        );
+        assert_eq!(readback_mismatch("+150MHz".to_string(), Ok("+150MHz".to_string())), None);
+        assert_eq!(readback_mismatch("+150MHz".to_string(), Err(nvml::NvmlError::NotSupported)), None);
+    }
+}
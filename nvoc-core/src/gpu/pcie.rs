@@ -0,0 +1,90 @@
+//! PCIe link status
+//!
+//! A riser or cable that cannot hold the link at full width shows up as
+//! stutter and driver errors that are easily mistaken for an unstable
+//! overclock. The link generation drops on its own while the GPU idles to
+//! save power, so only a narrower link than the slot allows is flagged.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::nvml::{self, Device, NvmlPcieUtilCounter};
+
+/// Current and maximum link generation and width
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    pub generation: u32,
+    pub width: u32,
+    pub max_generation: u32,
+    pub max_width: u32,
+}
+
+impl Link {
+    pub fn read(device: &Device) -> nvml::Result<Self> {
+        Ok(Self {
+            generation: nvml::device_get_curr_pcie_link_generation(device)?,
+            width: nvml::device_get_curr_pcie_link_width(device)?,
+            max_generation: nvml::device_get_max_pcie_link_generation(device)?,
+            max_width: nvml::device_get_max_pcie_link_width(device)?,
+        })
+    }
+
+    /// Whether the link trained to fewer lanes than both ends support
+    pub fn reduced_width(&self) -> bool {
+        self.width < self.max_width
+    }
+
+    /// The current link alone, e.g. `Gen4 x16`
+    pub fn current(&self) -> String {
+        format!("Gen{} x{}", self.generation, self.width)
+    }
+}
+
+impl fmt::Display for Link {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (max Gen{} x{}", self.current(), self.max_generation, self.max_width)?;
+        if self.reduced_width() {
+            write!(f, ", reduced width")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Data moved over the link in each direction, in KB/s
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Throughput {
+    pub tx_kb_s: u32,
+    pub rx_kb_s: u32,
+}
+
+impl Throughput {
+    pub fn read(device: &Device) -> nvml::Result<Self> {
+        Ok(Self {
+            tx_kb_s: nvml::device_get_pcie_throughput(device, NvmlPcieUtilCounter::TxBytes)?,
+            rx_kb_s: nvml::device_get_pcie_throughput(device, NvmlPcieUtilCounter::RxBytes)?,
+        })
+    }
+}
+
+impl fmt::Display for Throughput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mb = |kb: u32| f64::from(kb) / 1000.0;
+        write!(f, "tx {:.1}MB/s, rx {:.1}MB/s", mb(self.tx_kb_s), mb(self.rx_kb_s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_display() {
+        let link = Link { generation: 1, width: 16, max_generation: 5, max_width: 16 };
+        assert_eq!(link.to_string(), "Gen1 x16 (max Gen5 x16)");
+        let narrow = Link { width: 8, ..link };
+        assert!(narrow.reduced_width());
+        assert_eq!(narrow.to_string(), "Gen1 x8 (max Gen5 x16, reduced width)");
+        assert_eq!(Throughput { tx_kb_s: 12_345, rx_kb_s: 260 }.to_string(), "tx 12.3MB/s, rx 0.3MB/s");
+    }
+}
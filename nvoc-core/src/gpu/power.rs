@@ -0,0 +1,60 @@
+//! GPU power management operations
+
+use crate::gpu::domain::{get_power_info, power_floor_percent, set_power_limit};
+use crate::gpu::overclock::check_readback;
+use crate::nvml::NvmlBackend;
+use crate::units::{Milliwatts, Watts};
+use crate::{output, AppError};
+
+pub fn apply_power_limit(
+    device: impl NvmlBackend,
+    percentage: u32,
+    dry_run: bool,
+    verify: bool,
+) -> Result<(), AppError> {
+    let power_info = get_power_info(device)
+        .map_err(|e| AppError::new("power limit", e).context("read power limits").on_device(device.index()))?;
+    let percentage = match power_floor_percent() {
+        Some(floor) if percentage < floor => {
+            eprintln!("warning[power limit]: {percentage}% is below the configured minimum, using {floor}%");
+            floor
+        }
+        _ => percentage,
+    };
+    let target = power_info.effective_watts_from_percentage(percentage);
+
+    // The target is in whole watts, so a limit such as 250.5W is truncated the same way before comparing
+    if device.power_limit().ok().map(Watts::from) == Some(target) {
+        output::unchanged("power limit", &format!("{percentage}% ({target})"), dry_run);
+        return Ok(());
+    }
+    if dry_run {
+        println!("power limit: {percentage}% ({target}) (dry run)");
+        return Ok(());
+    }
+
+    let written = set_power_limit(device, Milliwatts::from(target)).map_err(|e| {
+        AppError::new("power limit", e).context(format!("set power limit ({target})")).on_device(device.index())
+    })?;
+    output::status!("power limit: {percentage}% ({target})");
+    if verify {
+        let watts = |limit: Milliwatts| Watts::from(limit).to_string();
+        check_readback("power limit", watts(written), device.power_limit().map(watts));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nvml::backend::fake::{FakeGpu, Settings};
+
+    #[test]
+    fn test_fractional_limit_unchanged() {
+        // 90% of 575W is 517W, and the card sits at 517.5W
+        let mut gpu = FakeGpu::new(Settings { power_limit: Milliwatts(517_500), ..Settings::default() });
+        gpu.fail = Some("set_power_limit");
+        assert!(apply_power_limit(&gpu, 90, false, false).is_ok());
+        assert_eq!(gpu.settings().power_limit, Milliwatts(517_500));
+    }
+}
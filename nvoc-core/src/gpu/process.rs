@@ -0,0 +1,97 @@
+//! Processes running on the GPU
+//!
+//! `nvoc ps` lists the compute and graphics processes on a GPU with their
+//! VRAM use, to see what locking clocks or a reset would disrupt. Names
+//! come from `/proc`, so processes in another PID namespace show as
+//! `unknown`.
+
+use std::fs;
+
+use crate::nvml::{self, Device, NvmlProcessInfo, NVML_VALUE_NOT_AVAILABLE_ULL};
+use crate::AppError;
+
+#[derive(Debug, Default, PartialEq)]
+struct Process {
+    pid: u32,
+    compute: bool,
+    graphics: bool,
+    /// `None` when the driver cannot attribute memory to the process
+    vram_mib: Option<u64>,
+}
+
+impl Process {
+    fn line(&self, name: &str) -> String {
+        let kind = match (self.compute, self.graphics) {
+            (true, true) => "compute+graphics",
+            (true, false) => "compute",
+            _ => "graphics",
+        };
+        match self.vram_mib {
+            Some(mib) => format!("{} {name}: {kind}, {mib}MiB", self.pid),
+            None => format!("{} {name}: {kind}, vram n/a", self.pid),
+        }
+    }
+}
+
+/// One entry per PID, ordered by PID. A process that does both compute and
+/// graphics work appears in both lists.
+fn merge(compute: &[NvmlProcessInfo], graphics: &[NvmlProcessInfo]) -> Vec<Process> {
+    let mut processes: Vec<Process> = Vec::new();
+    let tagged = compute.iter().map(|p| (p, true)).chain(graphics.iter().map(|p| (p, false)));
+    for (info, is_compute) in tagged {
+        let vram_mib = (info.usedGpuMemory != NVML_VALUE_NOT_AVAILABLE_ULL).then_some(info.usedGpuMemory >> 20);
+        let process = match processes.iter_mut().find(|p| p.pid == info.pid) {
+            Some(process) => process,
+            None => {
+                processes.push(Process { pid: info.pid, ..Default::default() });
+                processes.last_mut().unwrap()
+            }
+        };
+        process.compute |= is_compute;
+        process.graphics |= !is_compute;
+        process.vram_mib = process.vram_mib.max(vram_mib);
+    }
+    processes.sort_by_key(|p| p.pid);
+    processes
+}
+
+fn name(pid: u32) -> String {
+    fs::read_to_string(format!("/proc/{pid}/comm"))
+        .map(|comm| comm.trim_end().to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Print one line per process using the GPU
+pub fn show(device: &Device) -> Result<(), AppError> {
+    let compute = nvml::device_get_compute_running_processes(device).map_err(|e| AppError::new("ps", e))?;
+    let graphics = nvml::device_get_graphics_running_processes(device).map_err(|e| AppError::new("ps", e))?;
+    let processes = merge(&compute, &graphics);
+    if processes.is_empty() {
+        println!("no processes");
+    }
+    for process in processes {
+        println!("{}", process.line(&name(process.pid)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(pid: u32, used: u64) -> NvmlProcessInfo {
+        NvmlProcessInfo { pid, usedGpuMemory: used, ..Default::default() }
+    }
+
+    #[test]
+    fn test_merge_processes() {
+        let compute = [info(5120, 20 << 30), info(1432, 412 << 20)];
+        let graphics = [info(1432, 412 << 20), info(2210, NVML_VALUE_NOT_AVAILABLE_ULL)];
+        let processes = merge(&compute, &graphics);
+        let lines: Vec<String> = processes.iter().map(|p| p.line("app")).collect();
+        assert_eq!(
+            lines,
+            ["1432 app: compute+graphics, 412MiB", "2210 app: graphics, vram n/a", "5120 app: compute, 20480MiB"]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! GPU reset operations
+//!
+//! Unlike other operations that bail on first error, reset attempts all
+//! operations and reports individual failures. Errors are printed at the
+//! call site rather than bubbled up because the caller needs to see each
+//! failure as it continues through remaining operations. A dry run reads
+//! the current values and lists what each reset would change.
+
+use crate::constants::clocks;
+use crate::gpu::domain::{get_power_info, reset_power_limit};
+use crate::gpu::{fan, overclock};
+use crate::gpu::virtualization::{self, Knob};
+use crate::nvml::{NvmlBackend, NvmlClockType, Result};
+use crate::units::MegaHertz;
+use crate::{output, AppError, ErrorKind};
+
+/// Offsets kept per pstate besides P0
+const PSTATE_OFFSETS: [(&str, NvmlClockType); 2] =
+    [("gpu offset", NvmlClockType::Graphics), ("mem offset", NvmlClockType::Memory)];
+
+fn try_reset(domain: &str, f: impl FnOnce() -> Result<()>) -> bool {
+    match f() {
+        Ok(()) => { output::status!("{domain}: reset"); true }
+        Err(e) => { eprintln!("error[{domain}]: {}", e.user_message()); false }
+    }
+}
+
+/// One dry-run line for a setting going from `current` to `target`
+fn planned(label: &str, current: Option<String>, target: &str) -> String {
+    match current {
+        Some(current) if current == target => format!("{label}: {current}, unchanged (dry run)"),
+        Some(current) => format!("{label}: {current} -> {target} (dry run)"),
+        None => format!("{label}: n/a -> {target} (dry run)"),
+    }
+}
+
+/// Print what a reset would change, from the values currently applied
+fn print_plan(device: impl NvmlBackend, available: impl Fn(Knob) -> bool) {
+    if available(Knob::LockedClocks) {
+        // Locked clocks cannot be read back
+        println!("gpu clocks: unlock (dry run)");
+        println!("mem clocks: unlock (dry run)");
+    }
+    if available(Knob::ClockOffsets) {
+        let offset = |o: MegaHertz| format!("{o:+}");
+        let graphics = overclock::graphics_offset(device).ok().map(offset);
+        println!("{}", planned("gpu offset", graphics, &offset(clocks::DEFAULT_GRAPHICS_OFFSET)));
+        let memory = overclock::memory_offset(device).ok().map(offset);
+        println!("{}", planned("mem offset", memory, &offset(clocks::DEFAULT_MEMORY_OFFSET)));
+        for (label, clock_type) in PSTATE_OFFSETS {
+            for (pstate, current) in overclock::pstate_offsets(device, clock_type) {
+                println!("{}", planned(&format!("{label} {pstate}"), Some(offset(current)), &offset(MegaHertz(0))));
+            }
+        }
+    }
+    if available(Knob::PowerLimit) {
+        match get_power_info(device) {
+            Ok(power) => println!(
+                "{}",
+                planned("power limit", Some(power.limit_watts.to_string()), &power.default_watts.to_string())
+            ),
+            Err(e) => eprintln!("error[power limit]: {}", e.user_message()),
+        }
+    }
+    if available(Knob::Fans) && device.num_fans().is_ok_and(|n| n > 0) {
+        let speed = fan::speed(device).ok().map(|s| format!("{s}%"));
+        println!("{}", planned("fan", speed, "auto"));
+    }
+}
+
+pub fn reset_gpu_settings(device: impl NvmlBackend, dry_run: bool) -> std::result::Result<(), AppError> {
+    let mut ok = true;
+
+    // In a VM guest, skip knobs the hypervisor does not expose instead of
+    // failing on each of them
+    let guest = virtualization::guest_knobs(device);
+    let available = |knob: Knob| match &guest {
+        Some((mode, knobs)) if !knobs.contains(&knob) => {
+            eprintln!("warning[{}]: unavailable in {}, skipped", knob.label(), mode.label());
+            false
+        }
+        _ => true,
+    };
+
+    if dry_run {
+        print_plan(device, available);
+        return Ok(());
+    }
+
+    if available(Knob::LockedClocks) {
+        // Blackwell requires setting idle clocks before reset will succeed
+        let idle_ok = device.set_gpu_locked_clocks(clocks::BLACKWELL_IDLE_MIN, clocks::BLACKWELL_IDLE_MAX).is_ok();
+        if idle_ok {
+            ok &= try_reset("gpu clocks", || device.reset_gpu_locked_clocks());
+        } else {
+            eprintln!("error[gpu clocks]: failed to set idle clocks for reset");
+            ok = false;
+        }
+
+        ok &= try_reset("mem clocks", || device.reset_memory_locked_clocks());
+    }
+
+    if available(Knob::ClockOffsets) {
+        if !try_reset("gpu offset", || {
+            overclock::set_graphics_offset(device, clocks::DEFAULT_GRAPHICS_OFFSET)
+        }) {
+            eprintln!("  hint: clocks may remain elevated, try sudo nvoc -o 0");
+            ok = false;
+        }
+
+        ok &= try_reset("mem offset", || {
+            overclock::set_memory_offset(device, clocks::DEFAULT_MEMORY_OFFSET)
+        });
+
+        // Offsets of lower pstates are only listed when set
+        for (label, clock_type) in PSTATE_OFFSETS {
+            for (pstate, _) in overclock::pstate_offsets(device, clock_type) {
+                ok &= try_reset(&format!("{label} {pstate}"), || {
+                    device.set_clock_offset(clock_type, pstate, 0)
+                });
+            }
+        }
+    }
+
+    if available(Knob::PowerLimit) {
+        ok &= try_reset("power limit", || reset_power_limit(device));
+    }
+
+    // Profiles may fix the fan speed; passively cooled cards have nothing to reset
+    if available(Knob::Fans) && device.num_fans().is_ok_and(|n| n > 0) {
+        ok &= try_reset("fan", || fan::restore_auto(device));
+    }
+
+    if !ok {
+        return Err(AppError::printed("reset").with_kind(ErrorKind::PartialApply));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nvml::backend::fake::{FakeGpu, Settings};
+    use crate::nvml::NvmlPerfState;
+    use crate::units::Milliwatts;
+
+    fn tuned() -> Settings {
+        let mut settings = Settings {
+            locked_clocks: Some((2400, 2400)),
+            memory_clocks_locked: true,
+            power_limit: Milliwatts(500_000),
+            fan_speed: Some(80),
+            ..Settings::default()
+        };
+        settings.graphics_offsets[NvmlPerfState::P0 as usize] = 150;
+        settings.graphics_offsets[NvmlPerfState::P2 as usize] = 50;
+        settings.memory_offsets[NvmlPerfState::P0 as usize] = 1000;
+        settings
+    }
+
+    #[test]
+    fn test_reset_restores_defaults() {
+        let gpu = FakeGpu::new(tuned());
+        assert!(reset_gpu_settings(&gpu, false).is_ok());
+        assert_eq!(gpu.settings(), Settings::default());
+    }
+
+    #[test]
+    fn test_dry_run_changes_nothing() {
+        let gpu = FakeGpu::new(tuned());
+        assert!(reset_gpu_settings(&gpu, true).is_ok());
+        assert_eq!(gpu.settings(), tuned());
+    }
+
+    #[test]
+    fn test_planned() {
+        assert_eq!(planned("gpu offset", Some("+150MHz".into()), "+0MHz"), "gpu offset: +150MHz -> +0MHz (dry run)");
+        assert_eq!(planned("mem offset", Some("+0MHz".into()), "+0MHz"), "mem offset: +0MHz, unchanged (dry run)");
+        assert_eq!(planned("fan", None, "auto"), "fan: n/a -> auto (dry run)");
+    }
+}
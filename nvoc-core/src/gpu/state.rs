@@ -0,0 +1,80 @@
+//! Device state capture and restore
+//!
+//! Snapshots the settings nvoc modifies so they can be journaled before a
+//! change and written back if that change has to be undone.
+
+use crate::constants::clocks;
+use crate::gpu::{domain, overclock};
+use crate::nvml::{NvmlBackend, Result};
+use crate::units::{MegaHertz, Milliwatts};
+
+/// Settings that can be read back from a device. `None` means the value
+/// could not be read and will not be restored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceState {
+    pub graphics_offset: Option<MegaHertz>,
+    pub memory_offset: Option<MegaHertz>,
+    pub power_limit_mw: Option<Milliwatts>,
+}
+
+/// Read the current values of all restorable settings
+pub fn capture(device: impl NvmlBackend) -> DeviceState {
+    DeviceState {
+        graphics_offset: overclock::graphics_offset(device).ok(),
+        memory_offset: overclock::memory_offset(device).ok(),
+        power_limit_mw: device.power_limit().ok(),
+    }
+}
+
+/// Write back every captured value. Attempts all settings and returns the
+/// first error encountered.
+pub fn restore(device: impl NvmlBackend, state: &DeviceState) -> Result<()> {
+    let mut result = Ok(());
+
+    if let Some(offset) = state.graphics_offset {
+        result = result.and(overclock::set_graphics_offset(device, offset));
+    }
+    if let Some(offset) = state.memory_offset {
+        result = result.and(overclock::set_memory_offset(device, offset));
+    }
+    if let Some(limit) = state.power_limit_mw {
+        result = result.and(domain::set_power_limit(device, limit).map(drop));
+    }
+
+    result
+}
+
+/// Release locked graphics and memory clocks. Blackwell requires setting
+/// idle clocks before the graphics reset will succeed.
+pub fn unlock_clocks(device: impl NvmlBackend) -> Result<()> {
+    device.set_gpu_locked_clocks(clocks::BLACKWELL_IDLE_MIN, clocks::BLACKWELL_IDLE_MAX)?;
+    device.reset_gpu_locked_clocks()?;
+    device.reset_memory_locked_clocks()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nvml::backend::fake::{FakeGpu, Settings};
+
+    #[test]
+    fn test_restore_round_trip() {
+        let gpu = FakeGpu::new(Settings::default());
+        let before = capture(&gpu);
+        assert_eq!(
+            before,
+            DeviceState {
+                graphics_offset: Some(MegaHertz(0)),
+                memory_offset: Some(MegaHertz(0)),
+                power_limit_mw: Some(Milliwatts(575_000)),
+            }
+        );
+
+        overclock::set_graphics_offset(&gpu, MegaHertz(150)).unwrap();
+        domain::set_power_limit(&gpu, Milliwatts(450_000)).unwrap();
+        assert_ne!(capture(&gpu), before);
+
+        restore(&gpu, &before).unwrap();
+        assert_eq!(capture(&gpu), before);
+    }
+}
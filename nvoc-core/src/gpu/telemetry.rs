@@ -0,0 +1,107 @@
+//! Periodic readings for the watch, log and monitor modes
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::gpu::domain::{get_power_info, get_power_usage_watts};
+use crate::gpu::fan::FanReading;
+use crate::gpu::pcie::{Link, Throughput};
+use crate::gpu::{self, fan, field, overclock, throttle};
+use crate::nvml::{
+    device_get_clock_info, device_get_memory_info, device_get_name, device_get_temperature,
+    device_get_utilization_rates, NvmlClockType,
+};
+
+/// One reading of a GPU. Values the driver could not provide are `None`.
+#[derive(Debug, Clone, Default)]
+pub struct Sample {
+    pub index: u32,
+    pub name: String,
+    pub temperature_c: Option<u32>,
+    /// Memory junction temperature, on GPUs that report it
+    pub memory_temperature_c: Option<u32>,
+    pub graphics_clock_mhz: Option<u32>,
+    pub memory_clock_mhz: Option<u32>,
+    pub graphics_offset_mhz: Option<i32>,
+    pub memory_offset_mhz: Option<i32>,
+    pub power_watts: Option<u32>,
+    pub power_limit_watts: Option<u32>,
+    pub fan_percent: Option<u32>,
+    pub fans: Vec<FanReading>,
+    pub utilization_percent: Option<u32>,
+    /// Memory controller load
+    pub memory_utilization_percent: Option<u32>,
+    pub vram_used_mib: Option<u64>,
+    pub vram_total_mib: Option<u64>,
+    pub pcie_link: Option<Link>,
+    pub pcie_throughput: Option<Throughput>,
+    /// `NVML_CLOCKS_EVENT_REASON_*` bitmask
+    pub throttle_reasons: Option<u64>,
+}
+
+/// Read the GPU at `index`, or `None` if it cannot be opened
+pub fn sample(index: u32) -> Option<Sample> {
+    let device = &gpu::get_device(index).ok()?;
+    let utilization = device_get_utilization_rates(device).ok();
+    let vram = device_get_memory_info(device).ok();
+    Some(Sample {
+        index,
+        name: device_get_name(device).unwrap_or_default(),
+        temperature_c: device_get_temperature(device).ok(),
+        memory_temperature_c: field::memory_temperature(device).ok(),
+        graphics_clock_mhz: device_get_clock_info(device, NvmlClockType::Graphics).ok(),
+        memory_clock_mhz: device_get_clock_info(device, NvmlClockType::Memory).ok(),
+        graphics_offset_mhz: overclock::graphics_offset(device).ok().map(|o| o.0),
+        memory_offset_mhz: overclock::memory_offset(device).ok().map(|o| o.0),
+        power_watts: get_power_usage_watts(device).ok().map(|w| w.0),
+        power_limit_watts: get_power_info(device).ok().map(|p| p.limit_watts.0),
+        fan_percent: fan::speed(device).ok(),
+        fans: fan::readings(device).unwrap_or_default(),
+        utilization_percent: utilization.map(|u| u.gpu),
+        memory_utilization_percent: utilization.map(|u| u.memory),
+        vram_used_mib: vram.map(|m| m.used_mib()),
+        vram_total_mib: vram.map(|m| m.total_mib()),
+        pcie_link: Link::read(device).ok(),
+        pcie_throughput: Throughput::read(device).ok(),
+        throttle_reasons: throttle::reasons(device).ok(),
+    })
+}
+
+/// Format a time as ISO 8601 UTC with milliseconds, e.g.
+/// `2025-03-01T18:04:05.250Z`
+pub fn utc_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_utc_timestamp() {
+        assert_eq!(utc_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let leap_day = UNIX_EPOCH + Duration::from_millis(1_709_215_445_250);
+        assert_eq!(utc_timestamp(leap_day), "2024-02-29T14:04:05.250Z");
+    }
+}
@@ -0,0 +1,85 @@
+//! Temperature thresholds
+//!
+//! The thermal target is the temperature the driver manages boost clocks
+//! to stay under, trading clocks for lower temperatures and fan noise.
+//! `nvoc thermal --target` moves it within the range the GPU allows. The
+//! slowdown and shutdown thresholds are fixed and only reported.
+
+use crate::gpu::overclock::check_readback;
+use crate::nvml::{self, Device, NvmlTemperatureThreshold};
+use crate::{output, AppError};
+
+fn threshold(device: &Device, threshold: NvmlTemperatureThreshold) -> Option<u32> {
+    nvml::device_get_temperature_threshold(device, threshold).ok()
+}
+
+/// Lowest and highest settable thermal target, when the GPU reports them
+fn target_range(device: &Device) -> Option<(u32, u32)> {
+    Some((
+        threshold(device, NvmlTemperatureThreshold::AcousticMin)?,
+        threshold(device, NvmlTemperatureThreshold::AcousticMax)?,
+    ))
+}
+
+fn check_target(celsius: u32, range: Option<(u32, u32)>) -> Result<(), String> {
+    match range {
+        Some((min, max)) if !(min..=max).contains(&celsius) => {
+            Err(format!("{celsius}°C is outside the range the gpu accepts ({min}-{max}°C)"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Print the thermal target and the fixed thresholds
+pub fn show(device: &Device) {
+    let print = |label: &str, value: Option<u32>| match value {
+        Some(celsius) => println!("{label}: {celsius}°C"),
+        None => println!("{label}: n/a"),
+    };
+    match (threshold(device, NvmlTemperatureThreshold::AcousticCurrent), target_range(device)) {
+        (Some(target), Some((min, max))) => println!("thermal target: {target}°C ({min}-{max}°C)"),
+        (target, _) => print("thermal target", target),
+    }
+    print("gpu max", threshold(device, NvmlTemperatureThreshold::GpuMax));
+    print("mem max", threshold(device, NvmlTemperatureThreshold::MemoryMax));
+    print("slowdown", threshold(device, NvmlTemperatureThreshold::Slowdown));
+    print("shutdown", threshold(device, NvmlTemperatureThreshold::Shutdown));
+}
+
+/// Set the thermal target to `celsius`
+pub fn set_target(device: &Device, celsius: u32, dry_run: bool) -> Result<(), AppError> {
+    check_target(celsius, target_range(device)).map_err(|e| AppError::msg("thermal target", e))?;
+    if threshold(device, NvmlTemperatureThreshold::AcousticCurrent) == Some(celsius) {
+        output::unchanged("thermal target", &format!("{celsius}°C"), dry_run);
+        return Ok(());
+    }
+    if dry_run {
+        println!("thermal target: {celsius}°C (dry run)");
+        return Ok(());
+    }
+
+    nvml::device_set_temperature_threshold(device, NvmlTemperatureThreshold::AcousticCurrent, celsius as i32)
+        .map_err(|e| AppError::new("thermal target", e))?;
+    output::status!("thermal target: {celsius}°C");
+    check_readback(
+        "thermal target",
+        format!("{celsius}°C"),
+        nvml::device_get_temperature_threshold(device, NvmlTemperatureThreshold::AcousticCurrent).map(|t| format!("{t}°C")),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_target() {
+        assert!(check_target(75, Some((65, 91))).is_ok());
+        assert!(check_target(75, None).is_ok());
+        assert_eq!(
+            check_target(95, Some((65, 91))),
+            Err("95°C is outside the range the gpu accepts (65-91°C)".to_string())
+        );
+    }
+}
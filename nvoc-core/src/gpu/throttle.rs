@@ -0,0 +1,58 @@
+//! Why clocks are below their maximum
+//!
+//! Decodes the clock event reason bitmask NVML reports into short labels
+//! for `info`, `monitor` and `watch`.
+
+use crate::nvml::{
+    self, Device, NVML_CLOCKS_EVENT_REASON_APPLICATIONS_CLOCKS_SETTING,
+    NVML_CLOCKS_EVENT_REASON_DISPLAY_CLOCK_SETTING, NVML_CLOCKS_EVENT_REASON_GPU_IDLE,
+    NVML_CLOCKS_EVENT_REASON_HW_POWER_BRAKE_SLOWDOWN, NVML_CLOCKS_EVENT_REASON_HW_SLOWDOWN,
+    NVML_CLOCKS_EVENT_REASON_HW_THERMAL_SLOWDOWN, NVML_CLOCKS_EVENT_REASON_SW_POWER_CAP,
+    NVML_CLOCKS_EVENT_REASON_SW_THERMAL_SLOWDOWN, NVML_CLOCKS_EVENT_REASON_SYNC_BOOST, Result,
+};
+
+const LABELS: &[(u64, &str)] = &[
+    (NVML_CLOCKS_EVENT_REASON_SW_POWER_CAP, "power cap"),
+    (NVML_CLOCKS_EVENT_REASON_SW_THERMAL_SLOWDOWN, "sw thermal"),
+    (NVML_CLOCKS_EVENT_REASON_HW_THERMAL_SLOWDOWN, "hw thermal"),
+    (NVML_CLOCKS_EVENT_REASON_HW_POWER_BRAKE_SLOWDOWN, "power brake"),
+    (NVML_CLOCKS_EVENT_REASON_HW_SLOWDOWN, "hw slowdown"),
+    (NVML_CLOCKS_EVENT_REASON_SYNC_BOOST, "sync boost"),
+    (NVML_CLOCKS_EVENT_REASON_APPLICATIONS_CLOCKS_SETTING, "app clocks"),
+    (NVML_CLOCKS_EVENT_REASON_DISPLAY_CLOCK_SETTING, "display clocks"),
+    (NVML_CLOCKS_EVENT_REASON_GPU_IDLE, "idle"),
+];
+
+/// Labels of the reasons set in `mask`, most actionable first
+fn labels(mask: u64) -> Vec<&'static str> {
+    LABELS.iter().filter(|&&(bit, _)| mask & bit != 0).map(|&(_, label)| label).collect()
+}
+
+/// Reasons as a comma-separated list, or `none` when clocks are unrestricted
+pub fn describe(mask: u64) -> String {
+    match labels(mask) {
+        labels if labels.is_empty() => "none".to_string(),
+        labels => labels.join(", "),
+    }
+}
+
+/// Current clock event reasons of the device
+pub fn reasons(device: &Device) -> Result<u64> {
+    nvml::device_get_current_clocks_event_reasons(device)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_reasons() {
+        assert_eq!(describe(0), "none");
+        assert_eq!(describe(NVML_CLOCKS_EVENT_REASON_GPU_IDLE), "idle");
+        assert_eq!(
+            describe(NVML_CLOCKS_EVENT_REASON_SW_POWER_CAP | NVML_CLOCKS_EVENT_REASON_HW_THERMAL_SLOWDOWN),
+            "power cap, hw thermal"
+        );
+        assert_eq!(describe(0x1000), "none");
+    }
+}
@@ -0,0 +1,85 @@
+//! GPU validation and safety checks
+
+use std::str::FromStr;
+
+use crate::nvml::{Device, GpuArchitecture, NvmlBackend, Result};
+
+/// How GPUs of an unrecognized architecture are handled, set by
+/// `[architecture] unknown` in the config file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum UnknownArchitecture {
+    /// Refuse to operate on the GPU
+    #[default]
+    Reject,
+    /// Warn, then drive it with the Blackwell clock offset layout
+    Warn,
+    /// Drive it as the given architecture without a warning
+    Assume(GpuArchitecture),
+}
+
+impl FromStr for UnknownArchitecture {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "warn" => Ok(Self::Warn),
+            "blackwell" => Ok(Self::Assume(GpuArchitecture::Blackwell)),
+            _ => Err(format!("invalid unknown architecture policy '{s}', use reject, warn or blackwell")),
+        }
+    }
+}
+
+/// Detect the architecture of a device
+///
+/// The driver's own architecture id is used when available, so OEM-renamed
+/// cards and SKUs newer than the name list are recognized. Drivers without
+/// `nvmlDeviceGetArchitecture` fall back to matching the device name.
+pub fn detect_architecture(device: impl NvmlBackend) -> Result<GpuArchitecture> {
+    match device.architecture() {
+        Ok(arch) => Ok(GpuArchitecture::from_nvml(arch)),
+        Err(_) => Ok(GpuArchitecture::from_device_name(&device.name()?)),
+    }
+}
+
+/// Validate that the device is a supported GPU, returning the architecture
+/// it is driven as. Unrecognized GPUs are handled according to `policy`.
+pub fn validate_architecture(device: &Device, policy: &UnknownArchitecture) -> Result<GpuArchitecture> {
+    let arch = device.architecture();
+    if *arch != GpuArchitecture::Unknown {
+        return Ok(arch.clone());
+    }
+
+    match policy {
+        UnknownArchitecture::Reject => Err(crate::nvml::NvmlError::NotSupported),
+        UnknownArchitecture::Warn => {
+            eprintln!("warning[gpu]: unrecognized architecture, treating as blackwell");
+            Ok(GpuArchitecture::Blackwell)
+        }
+        UnknownArchitecture::Assume(arch) => Ok(arch.clone()),
+    }
+}
+
+/// Check system requirements for operations that modify GPU settings
+pub fn check_system_for_modification() -> Result<()> {
+    let is_root = unsafe { libc::getuid() == 0 };
+
+    if !is_root {
+        return Err(crate::nvml::NvmlError::NoPermission);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_unknown_architecture_policy() {
+        assert_eq!("reject".parse(), Ok(UnknownArchitecture::Reject));
+        assert_eq!("warn".parse(), Ok(UnknownArchitecture::Warn));
+        assert_eq!("blackwell".parse(), Ok(UnknownArchitecture::Assume(GpuArchitecture::Blackwell)));
+        assert!("unknown".parse::<UnknownArchitecture>().is_err());
+    }
+}
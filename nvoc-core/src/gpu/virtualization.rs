@@ -0,0 +1,122 @@
+//! Virtual machine guest detection
+//!
+//! Inside a VM the driver may refuse host-only operations. Rather than
+//! letting each NVML call fail on its own, nvoc works out up front which
+//! knobs the guest can actually use and reports the rest as unavailable.
+
+use crate::nvml::{
+    NvmlBackend, NVML_GPU_VIRTUALIZATION_MODE_HOST_VGPU,
+    NVML_GPU_VIRTUALIZATION_MODE_HOST_VSGA, NVML_GPU_VIRTUALIZATION_MODE_NONE,
+    NVML_GPU_VIRTUALIZATION_MODE_PASSTHROUGH, NVML_GPU_VIRTUALIZATION_MODE_VGPU,
+};
+use crate::gpu::overclock;
+use crate::{AppError, ErrorKind};
+
+/// A group of settings nvoc can modify
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Knob {
+    LockedClocks,
+    ClockOffsets,
+    PowerLimit,
+    Fans,
+}
+
+impl Knob {
+    const ALL: [Knob; 4] = [Knob::LockedClocks, Knob::ClockOffsets, Knob::PowerLimit, Knob::Fans];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Knob::LockedClocks => "clocks",
+            Knob::ClockOffsets => "offsets",
+            Knob::PowerLimit => "power limit",
+            Knob::Fans => "fans",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    BareMetal,
+    /// Whole GPU passed through to this VM
+    Passthrough,
+    /// Mediated vGPU slice inside this VM
+    VgpuGuest,
+    /// Hypervisor host sharing the GPU with guests
+    Host,
+}
+
+impl Mode {
+    pub fn is_guest(&self) -> bool {
+        matches!(self, Mode::Passthrough | Mode::VgpuGuest)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Mode::BareMetal => "none",
+            Mode::Passthrough => "passthrough guest",
+            Mode::VgpuGuest => "vgpu guest",
+            Mode::Host => "vgpu host",
+        }
+    }
+}
+
+/// Detect the virtualization mode, assuming bare metal when NVML cannot say
+pub fn detect(device: impl NvmlBackend) -> Mode {
+    match device.virtualization_mode() {
+        Ok(NVML_GPU_VIRTUALIZATION_MODE_PASSTHROUGH) => Mode::Passthrough,
+        Ok(NVML_GPU_VIRTUALIZATION_MODE_VGPU) => Mode::VgpuGuest,
+        Ok(NVML_GPU_VIRTUALIZATION_MODE_HOST_VGPU | NVML_GPU_VIRTUALIZATION_MODE_HOST_VSGA) => Mode::Host,
+        Ok(NVML_GPU_VIRTUALIZATION_MODE_NONE) | Ok(_) | Err(_) => Mode::BareMetal,
+    }
+}
+
+/// Knobs usable in a guest. A vGPU slice exposes none; a passthrough GPU
+/// is probed for each one.
+pub fn probe_knobs(device: impl NvmlBackend, mode: Mode) -> Vec<Knob> {
+    if mode == Mode::VgpuGuest {
+        return Vec::new();
+    }
+    Knob::ALL
+        .into_iter()
+        .filter(|knob| match knob {
+            Knob::LockedClocks => true,
+            Knob::ClockOffsets => overclock::graphics_offset(device).is_ok(),
+            Knob::PowerLimit => device.power_limit_constraints().is_ok(),
+            Knob::Fans => device.num_fans().is_ok_and(|n| n > 0),
+        })
+        .collect()
+}
+
+/// Knobs available when running as a guest, `None` outside a VM
+pub fn guest_knobs(device: impl NvmlBackend) -> Option<(Mode, Vec<Knob>)> {
+    let mode = detect(device);
+    mode.is_guest().then(|| (mode, probe_knobs(device, mode)))
+}
+
+pub fn format_knobs(knobs: &[Knob]) -> String {
+    match knobs.is_empty() {
+        true => "none".to_string(),
+        false => knobs.iter().map(Knob::label).collect::<Vec<_>>().join(", "),
+    }
+}
+
+/// Refuse up front when a guest cannot use every requested knob
+pub fn check_knobs(device: impl NvmlBackend, requested: &[Knob]) -> Result<(), AppError> {
+    let Some((mode, available)) = guest_knobs(device) else {
+        return Ok(());
+    };
+    let missing: Vec<Knob> = requested.iter().copied().filter(|k| !available.contains(k)).collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(AppError::msg(
+        "gpu",
+        format!(
+            "{} unavailable in {} (available: {})",
+            format_knobs(&missing),
+            mode.label(),
+            format_knobs(&available)
+        ),
+    )
+    .with_kind(ErrorKind::UnsupportedGpu))
+}
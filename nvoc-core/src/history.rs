@@ -0,0 +1,481 @@
+//! SQLite telemetry history
+//!
+//! `nvoc log --db` and the daemon's `[history]` section store a row per GPU
+//! at a fixed interval in an SQLite database, pruned by age and row count
+//! so it can run indefinitely. `nvoc stats` summarizes a time range of that
+//! history per metric and advises on power limit headroom, and
+//! `nvoc history` shows how one metric trended.
+
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection};
+
+use crate::constants::history;
+use crate::gpu::telemetry::{self, Sample};
+use crate::output::{self, Decimal};
+use crate::{signal, AppError};
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS samples (
+    timestamp_ms INTEGER NOT NULL,
+    gpu INTEGER NOT NULL,
+    temperature_c INTEGER,
+    graphics_clock_mhz INTEGER,
+    memory_clock_mhz INTEGER,
+    graphics_offset_mhz INTEGER,
+    memory_offset_mhz INTEGER,
+    power_watts INTEGER,
+    power_limit_watts INTEGER,
+    fan_percent INTEGER,
+    utilization_percent INTEGER
+);
+CREATE INDEX IF NOT EXISTS samples_gpu_time ON samples (gpu, timestamp_ms);";
+
+/// Columns summarized by `nvoc stats`, in output order
+const METRICS: &[&str] = &[
+    "temperature_c",
+    "graphics_clock_mhz",
+    "memory_clock_mhz",
+    "graphics_offset_mhz",
+    "memory_offset_mhz",
+    "power_watts",
+    "power_limit_watts",
+    "fan_percent",
+    "utilization_percent",
+];
+
+/// Short names accepted by `nvoc history --metric`, besides the column names
+const METRIC_ALIASES: &[(&str, &str)] = &[
+    ("temp", "temperature_c"),
+    ("clock", "graphics_clock_mhz"),
+    ("mem-clock", "memory_clock_mhz"),
+    ("offset", "graphics_offset_mhz"),
+    ("mem-offset", "memory_offset_mhz"),
+    ("power", "power_watts"),
+    ("power-limit", "power_limit_watts"),
+    ("fan", "fan_percent"),
+    ("util", "utilization_percent"),
+];
+
+/// Column holding `metric`, given as an alias or a column name
+pub fn metric_column(metric: &str) -> Result<&'static str, String> {
+    METRIC_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == metric)
+        .map(|(_, column)| *column)
+        .or_else(|| METRICS.iter().copied().find(|column| *column == metric))
+        .ok_or_else(|| {
+            let aliases: Vec<&str> = METRIC_ALIASES.iter().map(|(alias, _)| *alias).collect();
+            format!("unknown metric '{metric}', use {} or a column name", aliases.join(", "))
+        })
+}
+
+/// How much history is kept
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Retention {
+    /// Drop samples older than this
+    pub max_age: Option<Duration>,
+    /// Drop the oldest samples beyond this many rows
+    pub max_rows: Option<u64>,
+}
+
+/// Minimum, mean, maximum and 99th percentile of one metric
+#[derive(Debug, Clone, PartialEq)]
+struct Summary {
+    min: f64,
+    avg: f64,
+    max: f64,
+    p99: f64,
+}
+
+/// Summarize `values`, or `None` if there are none. Uses the nearest-rank
+/// percentile, so p99 is always a value that was actually recorded.
+fn summarize(values: &mut [f64]) -> Option<Summary> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let rank = (values.len() as f64 * 0.99).ceil() as usize;
+    Some(Summary {
+        min: values[0],
+        avg: values.iter().sum::<f64>() / values.len() as f64,
+        max: values[values.len() - 1],
+        p99: values[rank.saturating_sub(1)],
+    })
+}
+
+/// Power draw, power limit, graphics clock and utilization of one sample
+type PowerRow = (f64, f64, f64, u32);
+
+/// How often a GPU was power limited, and what more power would buy
+#[derive(Debug, Clone, PartialEq)]
+struct Headroom {
+    /// Share of samples drawing at the limit
+    capped: f64,
+    /// Graphics clock gained per power limit step, in MHz, when enough busy
+    /// samples show clocks rising with power
+    gain_mhz: Option<f64>,
+}
+
+/// Estimate power limit headroom from recorded samples. The gain is the
+/// least-squares slope of graphics clock over power draw across busy
+/// samples, scaled to one step of the average limit.
+fn headroom(rows: &[PowerRow]) -> Option<Headroom> {
+    if rows.is_empty() {
+        return None;
+    }
+    let capped = rows.iter().filter(|(power, limit, ..)| *power >= limit * history::POWER_CAP_RATIO).count();
+    let capped = capped as f64 / rows.len() as f64;
+
+    let busy: Vec<&PowerRow> = rows.iter().filter(|row| row.3 >= history::BUSY_UTILIZATION).collect();
+    if busy.len() < history::MIN_HEADROOM_SAMPLES {
+        return Some(Headroom { capped, gain_mhz: None });
+    }
+    let n = busy.len() as f64;
+    let mean_power = busy.iter().map(|row| row.0).sum::<f64>() / n;
+    let mean_clock = busy.iter().map(|row| row.2).sum::<f64>() / n;
+    let mean_limit = busy.iter().map(|row| row.1).sum::<f64>() / n;
+    let covariance: f64 = busy.iter().map(|row| (row.0 - mean_power) * (row.2 - mean_clock)).sum();
+    let variance: f64 = busy.iter().map(|row| (row.0 - mean_power).powi(2)).sum();
+    let gain_mhz = (variance > 0.0)
+        .then(|| covariance / variance * mean_limit * history::HEADROOM_STEP_PERCENT / 100.0)
+        .filter(|gain| *gain > 0.0);
+    Some(Headroom { capped, gain_mhz })
+}
+
+fn print_headroom(gpu: u32, headroom: Option<Headroom>) {
+    let Some(headroom) = headroom else {
+        println!("gpu {gpu} headroom: n/a");
+        return;
+    };
+    let percent = headroom.capped * 100.0;
+    let step = history::HEADROOM_STEP_PERCENT;
+    match headroom.gain_mhz {
+        _ if headroom.capped < history::MIN_CAPPED_FRACTION => {
+            println!("gpu {gpu} headroom: at power cap {percent:.0}% of the time, a higher limit is unlikely to help")
+        }
+        Some(gain) => println!("gpu {gpu} headroom: at power cap {percent:.0}% of the time, about +{gain:.0}MHz per +{step}% power limit"),
+        None => println!("gpu {gpu} headroom: at power cap {percent:.0}% of the time, too few busy samples to estimate the gain"),
+    }
+}
+
+fn unix_ms(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64
+}
+
+fn db_error(path: &Path) -> impl Fn(rusqlite::Error) -> AppError + '_ {
+    move |e| AppError::msg("history", format!("{}: {e}", path.display()))
+}
+
+fn open(path: &Path) -> Result<Connection, AppError> {
+    let conn = Connection::open(path).map_err(db_error(path))?;
+    // The daemons of several GPUs may share one database
+    conn.busy_timeout(Duration::from_millis(history::BUSY_TIMEOUT_MS)).map_err(db_error(path))?;
+    conn.execute_batch(SCHEMA).map_err(db_error(path))?;
+    Ok(conn)
+}
+
+fn insert(conn: &Connection, timestamp_ms: i64, sample: &Sample) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO samples VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            timestamp_ms,
+            sample.index,
+            sample.temperature_c,
+            sample.graphics_clock_mhz,
+            sample.memory_clock_mhz,
+            sample.graphics_offset_mhz,
+            sample.memory_offset_mhz,
+            sample.power_watts,
+            sample.power_limit_watts,
+            sample.fan_percent,
+            sample.utilization_percent,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Delete samples outside the retention settings, returning how many
+fn prune(conn: &Connection, retention: Retention, now_ms: i64) -> rusqlite::Result<usize> {
+    let mut deleted = 0;
+    if let Some(max_age) = retention.max_age {
+        let cutoff = now_ms - max_age.as_millis() as i64;
+        deleted += conn.execute("DELETE FROM samples WHERE timestamp_ms < ?1", [cutoff])?;
+    }
+    if let Some(max_rows) = retention.max_rows {
+        deleted += conn.execute(
+            "DELETE FROM samples WHERE rowid <= (SELECT MAX(rowid) FROM samples) - ?1",
+            [max_rows as i64],
+        )?;
+    }
+    Ok(deleted)
+}
+
+pub fn log(indices: &[u32], path: &Path, retention: Retention, interval: Duration) -> Result<(), AppError> {
+    let err = db_error(path);
+    let conn = open(path)?;
+    println!("log: writing to {} every {}ms, Ctrl-C to stop", path.display(), interval.as_millis());
+
+    signal::install();
+    let (mut rows, mut pruned) = (0u64, 0usize);
+    loop {
+        let now = unix_ms(SystemTime::now());
+        let tx = conn.unchecked_transaction().map_err(&err)?;
+        for &index in indices {
+            let sample = telemetry::sample(index).unwrap_or(Sample { index, ..Default::default() });
+            insert(&tx, now, &sample).map_err(&err)?;
+            rows += 1;
+        }
+        pruned += prune(&tx, retention, now).map_err(&err)?;
+        tx.commit().map_err(&err)?;
+        if !signal::sleep(interval) {
+            break;
+        }
+    }
+    println!("log: {rows} rows written, {pruned} pruned");
+    Ok(())
+}
+
+/// Records one GPU into the history database from the daemon loop, at most
+/// once per `resolution`
+pub struct Recorder {
+    conn: Connection,
+    retention: Retention,
+    resolution: Duration,
+    last: Option<Instant>,
+}
+
+impl Recorder {
+    pub fn open(path: &Path, retention: Retention, resolution: Duration) -> Result<Self, AppError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| AppError::msg("history", format!("{}: {e}", dir.display())))?;
+        }
+        Ok(Self { conn: open(path)?, retention, resolution, last: None })
+    }
+
+    /// Record a sample of `index` if `resolution` has passed since the last
+    pub fn record(&mut self, index: u32) -> Result<(), AppError> {
+        if self.last.is_some_and(|last| last.elapsed() < self.resolution) {
+            return Ok(());
+        }
+        self.last = Some(Instant::now());
+        let Some(sample) = telemetry::sample(index) else {
+            return Ok(());
+        };
+        let err = |e: rusqlite::Error| AppError::msg("history", e.to_string());
+        let now = unix_ms(SystemTime::now());
+        let tx = self.conn.unchecked_transaction().map_err(err)?;
+        insert(&tx, now, &sample).map_err(err)?;
+        prune(&tx, self.retention, now).map_err(err)?;
+        tx.commit().map_err(err)
+    }
+}
+
+/// Split `values`, given as `(timestamp_ms, value)`, into `count` equal
+/// time buckets starting at `from_ms` and summarize each
+fn buckets(values: &[(i64, f64)], from_ms: i64, width_ms: i64, count: usize) -> Vec<Option<Summary>> {
+    let mut grouped: Vec<Vec<f64>> = vec![Vec::new(); count];
+    for &(timestamp, value) in values {
+        let bucket = ((timestamp - from_ms) / width_ms.max(1)) as usize;
+        if let Some(group) = grouped.get_mut(bucket.min(count - 1)) {
+            group.push(value);
+        }
+    }
+    grouped.iter_mut().map(|group| summarize(group)).collect()
+}
+
+/// Print how `column` of GPU `gpu` trended over the last `last`, as
+/// `count` time buckets followed by a summary of the whole range
+pub fn trend(path: &Path, gpu: u32, column: &str, last: Duration, count: usize) -> Result<(), AppError> {
+    if !path.exists() {
+        return Err(AppError::msg("history", format!("{}: no such database", path.display())));
+    }
+    let err = db_error(path);
+    let conn = open(path)?;
+    let now = unix_ms(SystemTime::now());
+    let from = now - last.as_millis() as i64;
+
+    // `column` comes from metric_column, never directly from user input
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT timestamp_ms, {column} FROM samples
+             WHERE gpu = ?1 AND timestamp_ms >= ?2 AND {column} IS NOT NULL ORDER BY timestamp_ms"
+        ))
+        .map_err(&err)?;
+    let rows = stmt.query_map(params![gpu, from], |row| Ok((row.get(0)?, row.get(1)?))).map_err(&err)?;
+    let values: Vec<(i64, f64)> = rows.collect::<rusqlite::Result<_>>().map_err(&err)?;
+    if values.is_empty() {
+        println!("gpu {gpu} {column}: no samples in the last {}s", last.as_secs());
+        return Ok(());
+    }
+
+    let width = (last.as_millis() as i64 / count as i64).max(1);
+    let time = |ms: i64| output::local_timestamp(UNIX_EPOCH + Duration::from_millis(ms as u64));
+    for (i, bucket) in buckets(&values, from, width, count).into_iter().enumerate() {
+        if let Some(s) = bucket {
+            println!(
+                "{}: avg {:.1}, min {}, max {}",
+                time(from + i as i64 * width),
+                Decimal(s.avg),
+                Decimal(s.min),
+                Decimal(s.max)
+            );
+        }
+    }
+    let mut all: Vec<f64> = values.iter().map(|(_, value)| *value).collect();
+    if let Some(s) = summarize(&mut all) {
+        println!(
+            "gpu {gpu} {column}: min {}, avg {:.1}, max {}, p99 {} ({} samples)",
+            Decimal(s.min),
+            Decimal(s.avg),
+            Decimal(s.max),
+            Decimal(s.p99),
+            all.len()
+        );
+    }
+    Ok(())
+}
+
+/// Print a summary of each metric for every GPU in `indices` over the
+/// samples between `since` and `until` ago
+pub fn stats(path: &Path, indices: Option<&[u32]>, since: Option<Duration>, until: Option<Duration>) -> Result<(), AppError> {
+    if !path.exists() {
+        return Err(AppError::msg("history", format!("{}: no such database", path.display())));
+    }
+    let err = db_error(path);
+    let conn = open(path)?;
+    let now = unix_ms(SystemTime::now());
+    let from = since.map_or(i64::MIN, |d| now - d.as_millis() as i64);
+    let to = until.map_or(i64::MAX, |d| now - d.as_millis() as i64);
+
+    let gpus: Vec<u32> = match indices {
+        Some(indices) => indices.to_vec(),
+        None => {
+            let mut stmt = conn.prepare("SELECT DISTINCT gpu FROM samples ORDER BY gpu").map_err(&err)?;
+            let gpus = stmt.query_map([], |row| row.get(0)).map_err(&err)?;
+            gpus.collect::<rusqlite::Result<_>>().map_err(&err)?
+        }
+    };
+
+    for gpu in gpus {
+        let (count, first, last): (i64, Option<i64>, Option<i64>) = conn
+            .query_row(
+                "SELECT COUNT(*), MIN(timestamp_ms), MAX(timestamp_ms) FROM samples
+                 WHERE gpu = ?1 AND timestamp_ms BETWEEN ?2 AND ?3",
+                params![gpu, from, to],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .map_err(&err)?;
+        let (Some(first), Some(last)) = (first, last) else {
+            println!("gpu {gpu}: no samples in range");
+            continue;
+        };
+        let time = |ms: i64| output::local_timestamp(UNIX_EPOCH + Duration::from_millis(ms as u64));
+        println!("gpu {gpu}: {count} samples from {} to {}", time(first), time(last));
+
+        for metric in METRICS {
+            // Column names come from METRICS, never from user input
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {metric} FROM samples
+                     WHERE gpu = ?1 AND timestamp_ms BETWEEN ?2 AND ?3 AND {metric} IS NOT NULL"
+                ))
+                .map_err(&err)?;
+            let values = stmt.query_map(params![gpu, from, to], |row| row.get::<_, f64>(0)).map_err(&err)?;
+            let mut values: Vec<f64> = values.collect::<rusqlite::Result<_>>().map_err(&err)?;
+            match summarize(&mut values) {
+                Some(s) => println!(
+                    "gpu {gpu} {metric}: min {}, avg {:.1}, max {}, p99 {}",
+                    Decimal(s.min),
+                    Decimal(s.avg),
+                    Decimal(s.max),
+                    Decimal(s.p99)
+                ),
+                None => println!("gpu {gpu} {metric}: n/a"),
+            }
+        }
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT power_watts, power_limit_watts, graphics_clock_mhz, utilization_percent FROM samples
+                 WHERE gpu = ?1 AND timestamp_ms BETWEEN ?2 AND ?3 AND power_watts IS NOT NULL
+                 AND power_limit_watts > 0 AND graphics_clock_mhz IS NOT NULL AND utilization_percent IS NOT NULL",
+            )
+            .map_err(&err)?;
+        let rows = stmt
+            .query_map(params![gpu, from, to], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .map_err(&err)?;
+        let rows: Vec<PowerRow> = rows.collect::<rusqlite::Result<_>>().map_err(&err)?;
+        print_headroom(gpu, headroom(&rows));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(summarize(&mut []), None);
+        let mut values: Vec<f64> = (1..=200).rev().map(f64::from).collect();
+        let summary = summarize(&mut values).unwrap();
+        assert_eq!(summary, Summary { min: 1.0, avg: 100.5, max: 200.0, p99: 198.0 });
+    }
+
+    #[test]
+    fn test_headroom() {
+        assert_eq!(headroom(&[]), None);
+        // Busy samples gaining 2MHz per watt near a 500W limit; half at the cap
+        let rows: Vec<PowerRow> = (0..40)
+            .map(|i| {
+                let power = 440.0 + f64::from(i);
+                (power, 500.0, 2000.0 + 2.0 * (power - 440.0), 99)
+            })
+            .chain((0..40).map(|_| (495.0, 500.0, 2110.0, 99)))
+            .collect();
+        let estimate = headroom(&rows).unwrap();
+        assert_eq!(estimate.capped, 0.5);
+        // Slope is 2MHz/W, and 5% of 500W is 25W
+        assert!((estimate.gain_mhz.unwrap() - 50.0).abs() < 1.0);
+
+        // Idle samples are counted for the cap but not for the gain
+        let idle: Vec<PowerRow> = (0..40).map(|i| (30.0 + f64::from(i), 500.0, 300.0, 0)).collect();
+        assert_eq!(headroom(&idle), Some(Headroom { capped: 0.0, gain_mhz: None }));
+    }
+
+    #[test]
+    fn test_buckets() {
+        let values = [(0, 10.0), (999, 20.0), (1_000, 30.0), (3_500, 40.0), (9_000, 50.0)];
+        let summaries = buckets(&values, 0, 1_000, 4);
+        assert_eq!(summaries.len(), 4);
+        assert_eq!(summaries[0].as_ref().map(|s| s.avg), Some(15.0));
+        assert_eq!(summaries[1].as_ref().map(|s| s.max), Some(30.0));
+        assert!(summaries[2].is_none());
+        // Samples past the range land in the last bucket
+        assert_eq!(summaries[3].as_ref().map(|s| s.max), Some(50.0));
+    }
+
+    #[test]
+    fn test_metric_column() {
+        assert_eq!(metric_column("temp"), Ok("temperature_c"));
+        assert_eq!(metric_column("power_watts"), Ok("power_watts"));
+        assert!(metric_column("voltage").is_err());
+    }
+
+    #[test]
+    fn test_prune_by_age_and_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        for (i, ms) in [1_000, 2_000, 3_000, 4_000, 5_000].into_iter().enumerate() {
+            insert(&conn, ms, &Sample { index: 0, temperature_c: Some(60 + i as u32), ..Default::default() }).unwrap();
+        }
+        let retention = Retention { max_age: Some(Duration::from_millis(3_500)), max_rows: Some(3) };
+        assert_eq!(prune(&conn, retention, 5_000).unwrap(), 2);
+        let oldest: i64 = conn.query_row("SELECT MIN(timestamp_ms) FROM samples", [], |r| r.get(0)).unwrap();
+        assert_eq!(oldest, 3_000);
+
+        let retention = Retention { max_age: None, max_rows: Some(1) };
+        assert_eq!(prune(&conn, retention, 5_000).unwrap(), 2);
+    }
+}
@@ -0,0 +1,676 @@
+//! Crash-safe journal of in-flight GPU changes
+//!
+//! Before a modifying operation touches the device, the prior state and the
+//! intended changes are written to the journal and synced to disk. Each GPU
+//! has its own entry, named after its UUID rather than its index, so changes
+//! to several GPUs at once, as the daemon makes, do not overwrite each other
+//! and an entry still finds its GPU when the enumeration order changes. The entry is removed once every
+//! change has been issued. If nvoc is killed or the
+//! machine loses power in between, the leftover entry is found on the next
+//! start and the half-applied change is reverted to the recorded prior state.
+//!
+//! The process making a change holds an exclusive `flock` on the entry's
+//! lock file until it commits, which the kernel drops when the process
+//! dies. An entry whose lock is still held belongs to a live change, such
+//! as a trial waiting out `--revert-after`, and is neither reverted nor
+//! replaced by another invocation.
+//!
+//! Changes the daemon keeps in place while it runs, the power budget and
+//! the fan curve, are held in entries of their own beside the device's, so
+//! one-shot changes can still be made meanwhile. They are reverted the same
+//! way when the daemon is killed before it could restore them.
+//!
+//! The journal also remembers which profile is active on each GPU. The boot
+//! service removes those markers on a clean shutdown, so a marker left by an
+//! earlier boot means the machine went down uncleanly with the profile
+//! applied, and the next boot can step down to a safer fallback profile.
+//!
+//! Overclocks reverted after an XID error are appended to a failure log, so
+//! an unstable profile is not mistaken for a driver reset later.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::constants::paths;
+use crate::gpu::state::{self, DeviceState};
+use crate::gpu::{fan, telemetry};
+use crate::nvml::NvmlBackend;
+use crate::units::{MegaHertz, Milliwatts, Watts};
+use crate::{output, AppError};
+
+/// The change a journal entry guards
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Change {
+    /// What makes the change, such as `overclock` or `autotune`
+    pub operation: String,
+    /// Values being written; `None` for settings left alone
+    pub target: DeviceState,
+    /// Whether the change locks graphics or memory clocks (which cannot be read back)
+    pub locks_clocks: bool,
+    /// Whether the change puts the fans under manual control, undone by
+    /// returning them to automatic control
+    pub manual_fans: bool,
+}
+
+/// Reads as `overclock (gpu offset +150MHz, power limit 460W)`
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(offset) = self.target.graphics_offset {
+            parts.push(format!("gpu offset {offset:+}"));
+        }
+        if let Some(offset) = self.target.memory_offset {
+            parts.push(format!("mem offset {offset:+}"));
+        }
+        if let Some(limit) = self.target.power_limit_mw {
+            parts.push(format!("power limit {}", Watts::from(limit)));
+        }
+        if self.locks_clocks {
+            parts.push("locked clocks".to_string());
+        }
+        if self.manual_fans {
+            parts.push("manual fans".to_string());
+        }
+        let operation = match self.operation.as_str() {
+            "" => "change",
+            operation => operation,
+        };
+        match parts.is_empty() {
+            true => write!(f, "{operation}"),
+            false => write!(f, "{operation} ({})", parts.join(", ")),
+        }
+    }
+}
+
+/// An uncommitted change recorded in the journal
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Entry {
+    pub device_index: u32,
+    /// UUID of the GPU at `device_index` when the entry was written, since
+    /// enumeration order can change across reboots
+    pub uuid: String,
+    pub prior: DeviceState,
+    pub change: Change,
+}
+
+fn push_state(out: &mut String, prefix: &str, state: &DeviceState) {
+    if let Some(v) = state.graphics_offset {
+        out.push_str(&format!("{prefix}graphics_offset={}\n", v.0));
+    }
+    if let Some(v) = state.memory_offset {
+        out.push_str(&format!("{prefix}memory_offset={}\n", v.0));
+    }
+    if let Some(v) = state.power_limit_mw {
+        out.push_str(&format!("{prefix}power_limit_mw={}\n", v.0));
+    }
+}
+
+impl Entry {
+    fn serialize(&self) -> String {
+        let mut out = format!("device={}\nuuid={}\n", self.device_index, self.uuid);
+        push_state(&mut out, "", &self.prior);
+        if !self.change.operation.is_empty() {
+            out.push_str(&format!("operation={}\n", self.change.operation));
+        }
+        push_state(&mut out, "target_", &self.change.target);
+        if self.change.locks_clocks {
+            out.push_str("locks_clocks=1\n");
+        }
+        if self.change.manual_fans {
+            out.push_str("manual_fans=1\n");
+        }
+        out
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let mut entry = Entry::default();
+        let mut has_device = false;
+
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let (key, value) = line.split_once('=')?;
+            match key {
+                "device" => {
+                    entry.device_index = value.parse().ok()?;
+                    has_device = true;
+                }
+                "uuid" => entry.uuid = value.to_string(),
+                "graphics_offset" => entry.prior.graphics_offset = Some(MegaHertz(value.parse().ok()?)),
+                "memory_offset" => entry.prior.memory_offset = Some(MegaHertz(value.parse().ok()?)),
+                "power_limit_mw" => entry.prior.power_limit_mw = Some(Milliwatts(value.parse().ok()?)),
+                "operation" => entry.change.operation = value.to_string(),
+                "target_graphics_offset" => entry.change.target.graphics_offset = Some(MegaHertz(value.parse().ok()?)),
+                "target_memory_offset" => entry.change.target.memory_offset = Some(MegaHertz(value.parse().ok()?)),
+                "target_power_limit_mw" => {
+                    entry.change.target.power_limit_mw = Some(Milliwatts(value.parse().ok()?))
+                }
+                "locks_clocks" => entry.change.locks_clocks = value == "1",
+                "manual_fans" => entry.change.manual_fans = value == "1",
+                _ => return None,
+            }
+        }
+
+        has_device.then_some(entry)
+    }
+}
+
+/// Handle to an open journal entry. Dropping it without calling `commit`
+/// leaves the entry on disk so the next start reverts it.
+pub struct Transaction {
+    path: PathBuf,
+    /// Released on drop, after the entry is removed or left behind
+    _lock: File,
+    /// Settings recorded before the change, for rolling it back
+    pub prior: DeviceState,
+}
+
+impl Transaction {
+    /// Mark the change as fully applied, removing only this device's entry
+    pub fn commit(self) -> Result<(), AppError> {
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(AppError::msg("journal", format!("{}: {e}", self.path.display()))),
+        }
+    }
+}
+
+/// Journal file holding the one-shot entry for the GPU with `uuid`
+fn entry_path(dir: &Path, uuid: &str) -> PathBuf {
+    dir.join(uuid)
+}
+
+/// Journal file holding the change `operation` keeps in place on the GPU with `uuid`
+fn held_path(dir: &Path, uuid: &str, operation: &str) -> PathBuf {
+    dir.join(format!("{uuid}.{operation}"))
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Whether `entry` was recorded for the GPU with `uuid`. Entries written
+/// before they carried a UUID can only be matched by index.
+fn belongs_to(entry: &Entry, uuid: &str, device_index: u32) -> bool {
+    match entry.uuid.as_str() {
+        "" => entry.device_index == device_index,
+        recorded => recorded == uuid,
+    }
+}
+
+/// Every entry file recorded for the GPU with `uuid`, wherever it was
+/// enumerated at the time: its one-shot entries, then the held ones
+fn entry_paths(dir: &Path, uuid: &str, device_index: u32) -> Vec<PathBuf> {
+    let mut paths: Vec<(bool, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            if name.ends_with(".lock") || name.ends_with(".tmp") {
+                return None;
+            }
+            let held = name.contains('.');
+            read_entry(&path).filter(|entry| belongs_to(entry, uuid, device_index)).map(|_| (held, path))
+        })
+        .collect();
+    // Held entries go last, as a one-shot change made meanwhile recorded their values as its prior state
+    paths.sort();
+    paths.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Take the lock guarding the entry at `path` without waiting. `None`
+/// means another open transaction holds it.
+fn try_lock(path: &Path) -> io::Result<Option<File>> {
+    fs::create_dir_all(path.parent().unwrap_or(Path::new(".")))?;
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(with_suffix(path, ".lock"))?;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(Some(file));
+    }
+    match io::Error::last_os_error() {
+        e if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+        e => Err(e),
+    }
+}
+
+fn device_uuid(device: impl NvmlBackend) -> Result<String, AppError> {
+    device.uuid().map_err(|e| AppError::new("journal", e))
+}
+
+/// Record the prior state of a device and the change about to be made to
+/// it. Refused while another transaction on the device is open.
+pub fn begin(device: impl NvmlBackend, device_index: u32, change: Change) -> Result<Transaction, AppError> {
+    begin_in(&paths::system(paths::JOURNAL_DIR), device, device_index, change)
+}
+
+fn begin_in(dir: &Path, device: impl NvmlBackend, device_index: u32, change: Change) -> Result<Transaction, AppError> {
+    let uuid = device_uuid(device)?;
+    open(entry_path(dir, &uuid), device_index, uuid, state::capture(device), change)
+}
+
+/// Record a change kept in place for as long as the daemon runs, with the
+/// `prior` settings it puts back on exit. The entry is named after
+/// `change.operation` and is separate from the device's one-shot entry.
+pub fn hold(
+    device: impl NvmlBackend,
+    device_index: u32,
+    prior: DeviceState,
+    change: Change,
+) -> Result<Transaction, AppError> {
+    hold_in(&paths::system(paths::JOURNAL_DIR), device, device_index, prior, change)
+}
+
+fn hold_in(
+    dir: &Path,
+    device: impl NvmlBackend,
+    device_index: u32,
+    prior: DeviceState,
+    change: Change,
+) -> Result<Transaction, AppError> {
+    let uuid = device_uuid(device)?;
+    open(held_path(dir, &uuid, &change.operation), device_index, uuid, prior, change)
+}
+
+fn open(
+    path: PathBuf,
+    device_index: u32,
+    uuid: String,
+    prior: DeviceState,
+    change: Change,
+) -> Result<Transaction, AppError> {
+    let err = |e: io::Error| AppError::msg("journal", format!("{}: {e}", path.display()));
+    let Some(lock) = try_lock(&path).map_err(err)? else {
+        return Err(AppError::msg(
+            "journal",
+            format!("gpu {device_index} is being changed by another nvoc process, try again when it finishes"),
+        ));
+    };
+    // Never replace the only record of another GPU's prior state
+    if let Some(other) = read_entry(&path).filter(|entry| !entry.uuid.is_empty() && entry.uuid != uuid) {
+        return Err(AppError::msg(
+            "journal",
+            format!(
+                "{} holds an interrupted change to {}, not gpu {device_index} ({uuid}), refusing to overwrite it",
+                path.display(),
+                other.uuid
+            ),
+        ));
+    }
+    let entry = Entry { device_index, uuid, prior, change };
+    write_synced(&path, &entry.serialize()).map_err(err)?;
+    Ok(Transaction { path, _lock: lock, prior: entry.prior })
+}
+
+/// Write to a temporary file, fsync, then rename over the target so a
+/// partially written journal is never observed.
+fn write_synced(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = with_suffix(path, ".tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    if let Some(dir) = path.parent() {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+fn read_entry(path: &Path) -> Option<Entry> {
+    Entry::parse(&fs::read_to_string(path).ok()?)
+}
+
+/// Read the leftover one-shot journal entry for `device`, if any. An entry
+/// still being applied by a running process is not leftover.
+pub fn pending(device: impl NvmlBackend) -> Option<Entry> {
+    let path = entry_path(&paths::system(paths::JOURNAL_DIR), &device.uuid().ok()?);
+    let _lock = try_lock(&path).ok()??;
+    read_entry(&path)
+}
+
+/// Revert changes to the GPU at `device_index` interrupted by a crash or
+/// power loss, including ones recorded while it was enumerated at another
+/// index. Entries for other GPUs are left for the invocation that targets
+/// them, and so are ones whose process is still applying or holding them.
+pub fn reconcile(device: impl NvmlBackend, device_index: u32) -> Result<(), AppError> {
+    reconcile_in(&paths::system(paths::JOURNAL_DIR), device, device_index)
+}
+
+fn reconcile_in(dir: &Path, device: impl NvmlBackend, device_index: u32) -> Result<(), AppError> {
+    let uuid = device_uuid(device)?;
+    entry_paths(dir, &uuid, device_index)
+        .into_iter()
+        .try_for_each(|path| reconcile_entry(path, device, device_index, &uuid))
+}
+
+fn reconcile_entry(path: PathBuf, device: impl NvmlBackend, device_index: u32, uuid: &str) -> Result<(), AppError> {
+    // Locked before reading, so the entry cannot be committed or replaced meanwhile
+    let lock = match try_lock(&path) {
+        Ok(Some(lock)) => lock,
+        Ok(None) => return Ok(()),
+        Err(e) => return Err(AppError::msg("journal", format!("{}: {e}", path.display()))),
+    };
+    let Some(entry) = read_entry(&path).filter(|entry| belongs_to(entry, uuid, device_index)) else {
+        return Ok(());
+    };
+
+    if entry.change.locks_clocks {
+        state::unlock_clocks(device).map_err(|e| AppError::new("journal", e))?;
+    }
+    if entry.change.manual_fans {
+        fan::restore_auto(device).map_err(|e| AppError::new("journal", e))?;
+    }
+    state::restore(device, &entry.prior).map_err(|e| AppError::new("journal", e))?;
+    output::status!("journal: reverted interrupted {} on gpu {device_index}", entry.change);
+
+    Transaction { path, _lock: lock, prior: entry.prior }.commit()
+}
+
+fn failure_line(timestamp: &str, device_index: u32, xid: u64, profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("{timestamp} gpu {device_index}: xid {xid}, reverted profile '{profile}'\n"),
+        None => format!("{timestamp} gpu {device_index}: xid {xid}, reverted\n"),
+    }
+}
+
+/// Append an overclock reverted after XID error `xid` to the failure log
+pub fn record_xid(device_index: u32, xid: u64, profile: Option<&str>) -> Result<(), AppError> {
+    let path = &paths::system(paths::XID_LOG_FILE);
+    let line = failure_line(&telemetry::utc_timestamp(SystemTime::now()), device_index, xid, profile);
+    let append = || -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_all()
+    };
+    append().map_err(|e| AppError::msg("journal", format!("{}: {e}", path.display())))
+}
+
+/// Profile recorded as active
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveProfile {
+    pub profile: String,
+    pub boot_id: String,
+    /// Applied as a fallback after an unclean shutdown
+    pub fallback: bool,
+}
+
+impl ActiveProfile {
+    fn serialize(&self) -> String {
+        format!("profile={}\nboot_id={}\nfallback={}\n", self.profile, self.boot_id, self.fallback as u8)
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let (mut profile, mut boot_id, mut fallback) = (None, None, false);
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            match line.split_once('=')? {
+                ("profile", value) => profile = Some(value.to_string()),
+                ("boot_id", value) => boot_id = Some(value.to_string()),
+                ("fallback", value) => fallback = value == "1",
+                _ => return None,
+            }
+        }
+        Some(Self { profile: profile?, boot_id: boot_id?, fallback })
+    }
+}
+
+fn current_boot_id() -> String {
+    fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .map(|id| id.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// File recording the profile active on `device_index`
+fn marker_path(device_index: u32) -> PathBuf {
+    paths::system(paths::ACTIVE_PROFILE_DIR).join(format!("gpu{device_index}"))
+}
+
+/// Record the profile now applied to `device_index`
+pub fn mark_active(device_index: u32, profile: &str, fallback: bool) -> Result<(), AppError> {
+    let active = ActiveProfile { profile: profile.to_string(), boot_id: current_boot_id(), fallback };
+    let path = marker_path(device_index);
+    write_synced(&path, &active.serialize()).map_err(|e| AppError::msg("journal", format!("{}: {e}", path.display())))
+}
+
+/// Forget the profile active on `device_index` after the device was reset
+/// or changed by hand
+pub fn clear_active(device_index: u32) -> Result<(), AppError> {
+    let path = marker_path(device_index);
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(AppError::msg("journal", format!("{}: {e}", path.display()))),
+    }
+}
+
+/// Decide which profile to apply at boot, given the requested one and the
+/// configured fallback. Returns the profile and whether it is the fallback.
+fn choose_boot_profile(
+    requested: &str,
+    fallback: Option<&str>,
+    marker: Option<&ActiveProfile>,
+    boot_id: &str,
+) -> (String, bool) {
+    match (marker, fallback) {
+        // The previous boot ended without the shutdown hook clearing the marker
+        (Some(marker), Some(fallback)) if marker.boot_id != boot_id => (fallback.to_string(), true),
+        // Already stepped down this boot, e.g. re-applying after a driver reload
+        (Some(marker), Some(fallback)) if marker.fallback => (fallback.to_string(), true),
+        _ => (requested.to_string(), false),
+    }
+}
+
+/// Profile to apply to `device_index` at boot: `requested`, or `fallback`
+/// when the previous boot ended uncleanly with a profile active on it
+pub fn boot_profile(device_index: u32, requested: &str, fallback: Option<&str>) -> (String, bool) {
+    let marker = fs::read_to_string(marker_path(device_index))
+        .ok()
+        .and_then(|text| ActiveProfile::parse(&text));
+    let boot_id = current_boot_id();
+    let (profile, stepped_down) = choose_boot_profile(requested, fallback, marker.as_ref(), &boot_id);
+    if let Some(marker) = marker.filter(|m| stepped_down && m.boot_id != boot_id) {
+        eprintln!(
+            "warning[journal]: previous boot ended uncleanly with profile '{}' active on gpu {device_index}, applying fallback '{profile}'",
+            marker.profile
+        );
+    }
+    (profile, stepped_down)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu::domain;
+    use crate::nvml::backend::fake::{FakeGpu, Settings};
+    use crate::nvml::mock;
+
+    /// Journal directory of the test's own
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nvoc-journal-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_entry_roundtrip() {
+        let entry = Entry {
+            device_index: 1,
+            uuid: "GPU-8f6b1a52-3c1e-4b1e-9a43-1f2d7c0e6a11".to_string(),
+            prior: DeviceState {
+                graphics_offset: Some(MegaHertz(-50)),
+                memory_offset: None,
+                power_limit_mw: Some(Milliwatts(575000)),
+            },
+            change: Change {
+                operation: "overclock".to_string(),
+                target: DeviceState {
+                    graphics_offset: Some(MegaHertz(150)),
+                    memory_offset: None,
+                    power_limit_mw: Some(Milliwatts(460000)),
+                },
+                locks_clocks: true,
+                manual_fans: false,
+            },
+        };
+        assert_eq!(Entry::parse(&entry.serialize()), Some(entry.clone()));
+        assert_eq!(entry.change.to_string(), "overclock (gpu offset +150MHz, power limit 460W, locked clocks)");
+    }
+
+    #[test]
+    fn test_boot_profile_steps_down_after_unclean_shutdown() {
+        let marker = |boot_id: &str, fallback| ActiveProfile {
+            profile: "aggressive".to_string(),
+            boot_id: boot_id.to_string(),
+            fallback,
+        };
+        let choose = |m: Option<&ActiveProfile>| choose_boot_profile("aggressive", Some("safe"), m, "b2");
+
+        assert_eq!(choose(None), ("aggressive".to_string(), false));
+        assert_eq!(choose(Some(&marker("b1", false))), ("safe".to_string(), true));
+        assert_eq!(choose(Some(&marker("b2", true))), ("safe".to_string(), true));
+        assert_eq!(choose(Some(&marker("b2", false))), ("aggressive".to_string(), false));
+        assert_eq!(
+            choose_boot_profile("aggressive", None, Some(&marker("b1", false)), "b2"),
+            ("aggressive".to_string(), false)
+        );
+        let active = marker("b1", true);
+        assert_eq!(ActiveProfile::parse(&active.serialize()), Some(active));
+    }
+
+    #[test]
+    fn test_corrupt_entry_rejected() {
+        assert_eq!(Entry::parse("graphics_offset=10\n"), None);
+        assert_eq!(Entry::parse("device=0\nbogus\n"), None);
+    }
+
+    #[test]
+    fn test_failure_line() {
+        assert_eq!(
+            failure_line("2025-03-01T18:09:41.003Z", 0, 79, Some("daily")),
+            "2025-03-01T18:09:41.003Z gpu 0: xid 79, reverted profile 'daily'\n"
+        );
+        assert_eq!(failure_line("t", 1, 13, None), "t gpu 1: xid 13, reverted\n");
+    }
+
+    #[test]
+    fn test_open_transaction_left_alone() {
+        let dir = test_dir("open");
+        let gpu = FakeGpu::new(Settings::default());
+        let change = Change { operation: "trial".to_string(), ..Change::default() };
+        let trial = begin_in(&dir, &gpu, 0, change.clone()).ok().unwrap();
+        domain::set_power_limit(&gpu, Milliwatts(450_000)).unwrap();
+
+        // A second invocation neither replaces the trial's prior state nor reverts it
+        assert!(begin_in(&dir, &gpu, 0, change.clone()).is_err());
+        assert!(reconcile_in(&dir, &gpu, 0).is_ok());
+        assert_eq!(gpu.settings().power_limit, Milliwatts(450_000));
+        let other = FakeGpu { uuid: mock::uuid_of(1), ..FakeGpu::new(Settings::default()) };
+        assert!(begin_in(&dir, &other, 1, change).and_then(Transaction::commit).is_ok());
+
+        // Once the trial's process is gone, its entry is reverted
+        drop(trial);
+        assert!(reconcile_in(&dir, &gpu, 0).is_ok());
+        assert_eq!(gpu.settings().power_limit, FakeGpu::DEFAULT_POWER_LIMIT);
+        assert_eq!(read_entry(&entry_path(&dir, &gpu.uuid)), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_entry_follows_gpu_to_new_index() {
+        let dir = test_dir("moved");
+        let gpu = FakeGpu::new(Settings::default());
+        let overclock = Change { operation: "overclock".to_string(), ..Change::default() };
+        drop(begin_in(&dir, &gpu, 0, overclock.clone()).ok().unwrap());
+        domain::set_power_limit(&gpu, Milliwatts(450_000)).unwrap();
+
+        // After a reboot another card enumerates first; its changes leave the entry alone
+        let other = FakeGpu { uuid: mock::uuid_of(1), ..FakeGpu::new(Settings::default()) };
+        assert!(reconcile_in(&dir, &other, 0).is_ok());
+        assert!(begin_in(&dir, &other, 0, overclock).and_then(Transaction::commit).is_ok());
+        assert!(read_entry(&entry_path(&dir, &gpu.uuid)).is_some());
+
+        // and the interrupted change is reverted on the card it was made to, at its new index
+        assert!(reconcile_in(&dir, &gpu, 1).is_ok());
+        assert_eq!(gpu.settings().power_limit, FakeGpu::DEFAULT_POWER_LIMIT);
+        assert_eq!(entry_paths(&dir, &gpu.uuid, 1), Vec::<PathBuf>::new());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_entry_of_other_gpu_not_overwritten() {
+        let dir = test_dir("overwrite");
+        let gpu = FakeGpu::new(Settings::default());
+        let recorded = Entry {
+            device_index: 0,
+            uuid: mock::uuid_of(1),
+            prior: DeviceState { power_limit_mw: Some(Milliwatts(450_000)), ..DeviceState::default() },
+            change: Change::default(),
+        };
+        let path = entry_path(&dir, &gpu.uuid);
+        write_synced(&path, &recorded.serialize()).unwrap();
+
+        assert!(begin_in(&dir, &gpu, 0, Change::default()).is_err());
+        assert!(reconcile_in(&dir, &gpu, 0).is_ok());
+        assert_eq!(gpu.settings().power_limit, FakeGpu::DEFAULT_POWER_LIMIT);
+        assert_eq!(read_entry(&path), Some(recorded));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_entry_without_uuid_matched_by_index() {
+        let dir = test_dir("legacy");
+        let gpu = FakeGpu::new(Settings::default());
+        domain::set_power_limit(&gpu, Milliwatts(450_000)).unwrap();
+        let path = dir.join("gpu0");
+        write_synced(&path, "device=0\npower_limit_mw=575000\noperation=overclock\n").unwrap();
+
+        // Only the GPU now at the recorded index can be told apart
+        let other = FakeGpu { uuid: mock::uuid_of(1), ..FakeGpu::new(Settings::default()) };
+        assert!(reconcile_in(&dir, &other, 1).is_ok());
+        assert!(path.exists());
+        assert!(reconcile_in(&dir, &gpu, 0).is_ok());
+        assert_eq!(gpu.settings().power_limit, FakeGpu::DEFAULT_POWER_LIMIT);
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_held_entry_reverted_after_one_shot() {
+        let dir = test_dir("held");
+        let gpu = FakeGpu::new(Settings::default());
+        let prior = DeviceState { power_limit_mw: Some(FakeGpu::DEFAULT_POWER_LIMIT), ..DeviceState::default() };
+        let budget = Change { operation: "budget".to_string(), ..Change::default() };
+        let held = hold_in(&dir, &gpu, 0, prior, budget).ok().unwrap();
+        domain::set_power_limit(&gpu, Milliwatts(450_000)).unwrap();
+        let fans = Change { operation: "fan".to_string(), manual_fans: true, ..Change::default() };
+        let held_fans = hold_in(&dir, &gpu, 0, DeviceState::default(), fans).ok().unwrap();
+        gpu.settings.borrow_mut().fan_speed = Some(80);
+
+        // A one-shot change can still be made while the budget is held
+        let overclock = Change { operation: "overclock".to_string(), ..Change::default() };
+        let one_shot = begin_in(&dir, &gpu, 0, overclock).ok().unwrap();
+        domain::set_power_limit(&gpu, Milliwatts(500_000)).unwrap();
+        drop((held, held_fans, one_shot));
+
+        assert!(reconcile_in(&dir, &gpu, 0).is_ok());
+        assert_eq!(gpu.settings().power_limit, FakeGpu::DEFAULT_POWER_LIMIT);
+        assert_eq!(gpu.settings().fan_speed, None);
+        assert_eq!(entry_paths(&dir, &gpu.uuid, 0), Vec::<PathBuf>::new());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
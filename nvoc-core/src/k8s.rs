@@ -0,0 +1,92 @@
+//! Kubernetes node labels describing GPU tuning state
+//!
+//! The default output is a node-feature-discovery feature file: one
+//! `name=value` line per label, to be dropped into NFD's `features.d`
+//! directory. NFD adds its `feature.node.kubernetes.io/` prefix. `--json`
+//! emits the same labels, fully prefixed, as a node patch for `kubectl`.
+
+use std::collections::BTreeMap;
+
+use crate::gpu::{self, domain::get_power_info, validation};
+use crate::nvml;
+use crate::profile;
+use crate::AppError;
+
+/// Namespace NFD applies to labels from feature files
+const NFD_PREFIX: &str = "feature.node.kubernetes.io/";
+
+/// Maximum length of a label name or value
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// Make a string a valid label value: alphanumerics, `-`, `_` and `.`,
+/// beginning and ending with an alphanumeric
+fn label_value(raw: &str) -> String {
+    let value: String = raw
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+            true => c,
+            false => '-',
+        })
+        .take(MAX_LABEL_LENGTH)
+        .collect();
+    value.trim_matches(|c: char| !c.is_ascii_alphanumeric()).to_string()
+}
+
+/// Labels for every GPU, keyed without the NFD prefix
+fn collect() -> Result<BTreeMap<String, String>, AppError> {
+    let count = gpu::device_count().map_err(|e| AppError::new("device", e))?;
+    let mut labels = BTreeMap::new();
+    labels.insert("nvoc-gpu.count".to_string(), count.to_string());
+
+    for index in 0..count {
+        let device = &gpu::get_device(index).map_err(|e| AppError::new("device", e))?;
+        let mut add = |key: &str, value: String| {
+            let value = label_value(&value);
+            if !value.is_empty() {
+                labels.insert(format!("nvoc-gpu-{index}.{key}"), value);
+            }
+        };
+
+        if let Ok(arch) = validation::detect_architecture(device) {
+            add("architecture", arch.to_string());
+        }
+        if let Ok(vbios) = nvml::device_get_vbios_version(device) {
+            add("vbios", vbios);
+        }
+        if let Ok(power) = get_power_info(device) {
+            add("max-power-watts", power.max_watts.0.to_string());
+        }
+        add("profile", profile::applied(index).unwrap_or_else(|| "none".to_string()));
+    }
+    Ok(labels)
+}
+
+/// Print labels for all GPUs
+pub fn run(json: bool) -> Result<(), AppError> {
+    let labels = collect()?;
+    if json {
+        let prefixed: BTreeMap<String, String> =
+            labels.into_iter().map(|(key, value)| (format!("{NFD_PREFIX}{key}"), value)).collect();
+        let patch = serde_json::json!({ "metadata": { "labels": prefixed } });
+        let text = serde_json::to_string_pretty(&patch).map_err(|e| AppError::msg("k8s", e.to_string()))?;
+        println!("{text}");
+        return Ok(());
+    }
+    for (key, value) in labels {
+        println!("{key}={value}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_value() {
+        assert_eq!(label_value("98.02.2E.00.02"), "98.02.2E.00.02");
+        assert_eq!(label_value("daily driver"), "daily-driver");
+        assert_eq!(label_value("(blackwell)"), "blackwell");
+        assert_eq!(label_value(&"x".repeat(80)).len(), MAX_LABEL_LENGTH);
+    }
+}
@@ -0,0 +1,248 @@
+//! nvoc-core - the library behind the nvoc command
+//!
+//! Device enumeration, overclocking, power and fan control, profiles and
+//! telemetry for NVIDIA GPUs on Linux, through NVML. The `nvoc` binary is a
+//! thin command line parser over this crate; GUIs, daemons and status bar
+//! modules can call the same code instead of shelling out to it.
+//!
+//! The entry points most programs need:
+//!
+//! - [`gpu`]: NVML setup ([`gpu::init_with_cleanup`]), device lookup
+//!   ([`gpu::get_device`], returning an [`nvml::Device`] that keeps NVML
+//!   initialized while it exists) and the per-device operations under it, e.g.
+//!   [`gpu::overclock::apply`] and [`gpu::reset::reset_gpu_settings`]
+//! - [`gpu::domain`]: power limits in watts and percent of the default, typed
+//!   by [`units`]
+//! - [`profile`]: saved profiles and their conditions
+//! - [`gpu::telemetry`] and [`history`]: live samples and the recorded history
+//! - [`params`]: what an operation is asked to do, built without the CLI
+//!
+//! Settings code is written against [`nvml::NvmlBackend`], so it also runs
+//! on the simulated GPUs of `NVOC_MOCK=1`.
+//!
+//! ```no_run
+//! use nvoc_core::gpu::{self, domain, overclock};
+//!
+//! fn main() -> Result<(), String> {
+//!     let _nvml = gpu::init_with_cleanup().map_err(|e| e.to_string())?;
+//!     let device = &gpu::get_device(0).map_err(|e| e.to_string())?;
+//!     let power = domain::get_power_info(device).map_err(|e| e.to_string())?;
+//!     let offset = overclock::graphics_offset(device).map_err(|e| e.to_string())?;
+//!     println!("power limit {}, gpu offset {offset:+}", power.limit_watts);
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Failures of whole operations are reported as [`AppError`], whose
+//! `Display` is the `error[domain]: message` line nvoc prints and whose
+//! [`ErrorKind`] is the status nvoc exits with.
+
+pub mod autotune;
+pub mod bench;
+pub mod config;
+pub mod constants;
+pub mod csv_log;
+pub mod daemon;
+pub mod doctor;
+pub mod exporter;
+pub mod gpu;
+pub mod history;
+pub mod journal;
+pub mod k8s;
+pub mod monitor;
+pub mod nvml;
+pub mod output;
+pub mod params;
+pub mod profile;
+pub mod ratelimit;
+pub mod service;
+pub mod setup;
+pub mod signal;
+pub mod stress;
+pub mod sweep;
+pub mod socket;
+pub mod timing;
+pub mod trace;
+pub mod units;
+pub mod watch;
+pub mod xid;
+
+use nvml::NvmlError;
+
+/// Category of a failure, which nvoc exits with so scripts can tell "run
+/// as root" from "reset the GPU" without parsing messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Anything not covered below
+    Other,
+    /// A value nvoc or the driver refuses, e.g. an offset outside the range
+    /// the driver accepts. Command line parsing errors exit with the same
+    /// code.
+    InvalidArgument,
+    /// Needs root
+    Permission,
+    /// NVML or the NVIDIA driver is missing, not loaded, or mismatched
+    DriverMissing,
+    /// The GPU, or its current mode, does not support the operation
+    UnsupportedGpu,
+    /// Some settings or GPUs were changed and others not
+    PartialApply,
+    /// Any other error NVML returned, e.g. a lost GPU
+    Nvml,
+    /// Refused by the thermal guard, the GPU is too hot to raise settings
+    TooHot,
+    /// Refused to raise the power limit of a GPU running on battery
+    OnBattery,
+}
+
+impl ErrorKind {
+    /// Category of an NVML error
+    pub fn of(error: &NvmlError) -> Self {
+        match error {
+            NvmlError::NoPermission => ErrorKind::Permission,
+            NvmlError::LibraryNotFound
+            | NvmlError::DriverNotLoaded
+            | NvmlError::LibRmVersionMismatch
+            | NvmlError::FunctionNotFound => ErrorKind::DriverMissing,
+            NvmlError::NotSupported => ErrorKind::UnsupportedGpu,
+            NvmlError::InvalidArgument => ErrorKind::InvalidArgument,
+            _ => ErrorKind::Nvml,
+        }
+    }
+
+    /// Process exit status, documented in the README
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::InvalidArgument => 2,
+            ErrorKind::Permission => 3,
+            ErrorKind::DriverMissing => 4,
+            ErrorKind::UnsupportedGpu => 5,
+            ErrorKind::PartialApply => 6,
+            ErrorKind::Nvml => 7,
+            ErrorKind::TooHot => 8,
+            ErrorKind::OnBattery => 9,
+        }
+    }
+}
+
+/// Failure of an nvoc operation, tagged with the domain it happened in.
+/// NVML failures can name the operation and GPU they hit, and remember the
+/// NVML function that returned the error, so they print as a short chain:
+/// `error[gpu offset]: set graphics offset (+200MHz) on gpu 1:
+/// nvmlDeviceSetClockOffsets: not supported by this gpu`.
+pub struct AppError {
+    domain: &'static str,
+    source: Option<NvmlError>,
+    message: Option<String>,
+    /// What was being done, e.g. `set power limit (460W)`
+    operation: Option<String>,
+    device: Option<u32>,
+    /// NVML function that returned `source`
+    function: Option<&'static str>,
+    /// Set when the category does not follow from `source`
+    kind: Option<ErrorKind>,
+    printed: bool,
+}
+
+impl AppError {
+    fn from_parts(domain: &'static str, source: Option<NvmlError>, message: Option<String>, printed: bool) -> Self {
+        Self { domain, source, message, operation: None, device: None, function: None, kind: None, printed }
+    }
+
+    /// An NVML failure. The NVML function is taken from the last call made
+    /// on this thread when that call returned `source`.
+    pub fn new(domain: &'static str, source: NvmlError) -> Self {
+        let function = trace::failed_function(&source);
+        Self { function, ..Self::from_parts(domain, Some(source), None, false) }
+    }
+
+    pub fn msg(domain: &'static str, message: String) -> Self {
+        Self::from_parts(domain, None, Some(message), false)
+    }
+
+    pub fn printed(domain: &'static str) -> Self {
+        Self::from_parts(domain, None, None, true)
+    }
+
+    /// Name the operation that failed
+    pub fn context(mut self, operation: impl Into<String>) -> Self {
+        self.operation = Some(operation.into());
+        self
+    }
+
+    /// Attribute the failure to the GPU at `index`, unless it already is
+    pub fn on_device(mut self, index: u32) -> Self {
+        self.device.get_or_insert(index);
+        self
+    }
+
+    /// Categorize the failure as `kind` instead of by its NVML error
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Category of the failure: as set, else that of the NVML error, else
+    /// `Other`
+    pub fn kind(&self) -> ErrorKind {
+        self.kind.or(self.source.as_ref().map(ErrorKind::of)).unwrap_or(ErrorKind::Other)
+    }
+
+    /// Whether the failure was already reported where it happened
+    pub fn is_printed(&self) -> bool {
+        self.printed
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut chain = Vec::new();
+        match (&self.operation, self.device) {
+            (Some(operation), Some(device)) => chain.push(format!("{operation} on gpu {device}")),
+            (Some(operation), None) => chain.push(operation.clone()),
+            (None, Some(device)) => chain.push(format!("gpu {device}")),
+            (None, None) => {}
+        }
+        chain.extend(self.function.map(str::to_string));
+        match (&self.source, &self.message) {
+            (Some(source), _) => chain.push(source.user_message().to_string()),
+            (_, Some(msg)) => chain.push(msg.clone()),
+            _ => {}
+        }
+        match chain.is_empty() {
+            true => write!(f, "error[{}]", self.domain),
+            false => write!(f, "error[{}]: {}", self.domain, chain.join(": ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_chain() {
+        let err = AppError::msg("profile", "'daily' has no settings".to_string());
+        assert_eq!(err.to_string(), "error[profile]: 'daily' has no settings");
+        assert_eq!(err.on_device(0).to_string(), "error[profile]: gpu 0: 'daily' has no settings");
+
+        let err = AppError {
+            function: Some("nvmlDeviceSetClockOffsets"),
+            ..AppError::new("gpu offset", NvmlError::NotSupported)
+        };
+        assert_eq!(
+            err.context("set graphics offset (+200MHz)").on_device(1).on_device(0).to_string(),
+            "error[gpu offset]: set graphics offset (+200MHz) on gpu 1: nvmlDeviceSetClockOffsets: not supported by this gpu"
+        );
+    }
+
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(AppError::new("power limit", NvmlError::NoPermission).kind().exit_code(), 3);
+        assert_eq!(AppError::new("gpu", NvmlError::GpuIsLost).kind(), ErrorKind::Nvml);
+        assert_eq!(AppError::msg("profile", String::new()).kind(), ErrorKind::Other);
+        let rolled_back = AppError::new("mem offset", NvmlError::NoPermission).with_kind(ErrorKind::PartialApply);
+        assert_eq!(rolled_back.kind().exit_code(), 6);
+    }
+}
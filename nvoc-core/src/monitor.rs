@@ -0,0 +1,199 @@
+//! Live terminal dashboard
+//!
+//! `nvoc monitor` redraws GPU and memory temperature, clocks, power, fan
+//! speed, GPU and memory controller utilization, VRAM use, the PCIe link
+//! and applied offsets for each GPU at a fixed interval, with sparklines of
+//! recent history. It only reads from the driver, so it runs without root.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Sparkline};
+use ratatui::Frame;
+
+use crate::constants::monitor;
+use crate::gpu::telemetry::{self, Sample};
+use crate::gpu::{fan, throttle};
+use crate::AppError;
+
+/// Most recent readings of one value, oldest first
+#[derive(Debug, Default)]
+struct History {
+    values: VecDeque<u64>,
+}
+
+impl History {
+    /// Record a reading; missing readings are drawn as zero so the
+    /// sparklines of a GPU stay aligned in time
+    fn push(&mut self, value: Option<u32>) {
+        if self.values.len() == monitor::HISTORY_LEN {
+            self.values.pop_front();
+        }
+        self.values.push_back(value.map_or(0, u64::from));
+    }
+
+    /// The newest `width` readings
+    fn last(&self, width: usize) -> Vec<u64> {
+        self.values.iter().skip(self.values.len().saturating_sub(width)).copied().collect()
+    }
+}
+
+struct Panel {
+    index: u32,
+    sample: Option<Sample>,
+    temperature_history: History,
+    clock_history: History,
+    power_history: History,
+    utilization_history: History,
+}
+
+impl Panel {
+    fn new(index: u32) -> Self {
+        Self {
+            index,
+            sample: None,
+            temperature_history: History::default(),
+            clock_history: History::default(),
+            power_history: History::default(),
+            utilization_history: History::default(),
+        }
+    }
+
+    fn sample(&mut self) {
+        self.sample = telemetry::sample(self.index);
+        let sample = self.sample.as_ref();
+        self.temperature_history.push(sample.and_then(|s| s.temperature_c));
+        self.clock_history.push(sample.and_then(|s| s.graphics_clock_mhz));
+        self.power_history.push(sample.and_then(|s| s.power_watts));
+        self.utilization_history.push(sample.and_then(|s| s.utilization_percent));
+    }
+
+    fn render(&self, frame: &mut Frame, area: Rect) {
+        let title = match &self.sample {
+            Some(sample) => format!(" gpu {}: {} ", self.index, sample.name),
+            None => format!(" gpu {}: unavailable ", self.index),
+        };
+        let block = Block::bordered().title(title);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let [values, graphs] = Layout::horizontal([Constraint::Length(28), Constraint::Min(0)]).areas(inner);
+        frame.render_widget(Paragraph::new(self.lines()), values);
+
+        let rows = Layout::vertical([Constraint::Ratio(1, 4); 4]).split(graphs);
+        let sparklines = [
+            ("temp", &self.temperature_history),
+            ("gpu clock", &self.clock_history),
+            ("power", &self.power_history),
+            ("util", &self.utilization_history),
+        ];
+        for ((label, history), row) in sparklines.into_iter().zip(rows.iter()) {
+            let sparkline = Sparkline::default()
+                .block(Block::new().title(label))
+                .data(history.last(row.width as usize));
+            frame.render_widget(sparkline, *row);
+        }
+    }
+
+    fn lines(&self) -> Vec<Line<'static>> {
+        fn field<T: std::fmt::Display>(label: &str, unit: &str, value: Option<T>) -> Line<'static> {
+            match value {
+                Some(value) => Line::from(format!("{label}: {value}{unit}")),
+                None => Line::from(format!("{label}: n/a")),
+            }
+        }
+        let sample = self.sample.as_ref();
+        vec![
+            field("temp", "°C", sample.and_then(|s| s.temperature_c)),
+            field("mem temp", "°C", sample.and_then(|s| s.memory_temperature_c)),
+            field("gpu clock", "MHz", sample.and_then(|s| s.graphics_clock_mhz)),
+            field("gpu offset", "MHz", sample.and_then(|s| s.graphics_offset_mhz)),
+            field("mem clock", "MHz", sample.and_then(|s| s.memory_clock_mhz)),
+            field("mem offset", "MHz", sample.and_then(|s| s.memory_offset_mhz)),
+            field("power", "W", sample.and_then(|s| s.power_watts)),
+            field("power limit", "W", sample.and_then(|s| s.power_limit_watts)),
+            field("fan", "%", sample.and_then(|s| s.fan_percent)),
+            field("fan rpm", "", sample.and_then(|s| fan::describe_rpm(&s.fans))),
+            field("util", "%", sample.and_then(|s| s.utilization_percent)),
+            field("mem load", "%", sample.and_then(|s| s.memory_utilization_percent)),
+            field(
+                "vram",
+                "MiB",
+                sample.and_then(|s| Some(format!("{}/{}", s.vram_used_mib?, s.vram_total_mib?))),
+            ),
+            field("pcie", "", sample.and_then(|s| s.pcie_link).map(|l| l.current())),
+            field(
+                "pcie tx/rx",
+                "MB/s",
+                sample.and_then(|s| s.pcie_throughput).map(|t| format!("{}/{}", t.tx_kb_s / 1000, t.rx_kb_s / 1000)),
+            ),
+            field("throttle", "", sample.and_then(|s| s.throttle_reasons).map(throttle::describe)),
+        ]
+    }
+}
+
+fn draw(frame: &mut Frame, panels: &[Panel], interval: Duration) {
+    let [body, footer] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let areas = Layout::vertical(panels.iter().map(|_| Constraint::Ratio(1, panels.len() as u32))).split(body);
+    for (panel, area) in panels.iter().zip(areas.iter()) {
+        panel.render(frame, *area);
+    }
+    let footer_text = format!("refreshing every {}ms, q to quit", interval.as_millis());
+    frame.render_widget(Paragraph::new(footer_text), footer);
+}
+
+/// Wait up to `timeout` for a key press; returns true when the user quits
+fn quit_requested(timeout: Duration) -> Result<bool, AppError> {
+    let io_error = |e: std::io::Error| AppError::msg("monitor", e.to_string());
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || !event::poll(remaining).map_err(io_error)? {
+            return Ok(false);
+        }
+        if let Event::Key(key) = event::read().map_err(io_error)? {
+            let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if key.kind == KeyEventKind::Press && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) || ctrl_c) {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+pub fn run(indices: Vec<u32>, interval: Duration) -> Result<(), AppError> {
+    let mut panels: Vec<Panel> = indices.into_iter().map(Panel::new).collect();
+    let mut terminal = ratatui::init();
+    let result = (|| loop {
+        for panel in &mut panels {
+            panel.sample();
+        }
+        terminal
+            .draw(|frame| draw(frame, &panels, interval))
+            .map_err(|e| AppError::msg("monitor", e.to_string()))?;
+        if quit_requested(interval)? {
+            return Ok(());
+        }
+    })();
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_keeps_newest_readings() {
+        let mut history = History::default();
+        for value in 0..monitor::HISTORY_LEN as u32 + 5 {
+            history.push(Some(value));
+        }
+        history.push(None);
+        assert_eq!(history.values.len(), monitor::HISTORY_LEN);
+        let newest = monitor::HISTORY_LEN as u64 + 4;
+        assert_eq!(history.last(3), vec![newest - 1, newest, 0]);
+    }
+}
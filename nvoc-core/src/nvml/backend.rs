@@ -0,0 +1,400 @@
+//! Device operations the gpu layer is written against
+//!
+//! Settings code that reads and writes offsets, clocks, power limits and
+//! fans takes any [`NvmlBackend`] rather than calling the NVML wrappers
+//! directly. A `&Device` is the backend used at runtime; unit tests pass an
+//! in-memory GPU instead.
+
+use crate::nvml::{self, Device, NvmlClockOffset, NvmlClockType, NvmlFanControlPolicy, NvmlPerfState, Result};
+use crate::units::{Celsius, Milliwatts};
+
+/// One GPU as seen through NVML. Implementors are cheap handles, so
+/// methods take `self` by value.
+pub trait NvmlBackend: Copy {
+    /// Index of the GPU as given on the command line
+    fn index(self) -> u32;
+    fn name(self) -> Result<String>;
+    /// `GPU-...` identifier, stable across reboots and slot changes
+    fn uuid(self) -> Result<String>;
+    /// `nvmlDeviceArchitecture_t` id
+    fn architecture(self) -> Result<u32>;
+    /// Version of the driver the GPU is run by
+    fn driver_version(self) -> Result<String>;
+    /// `nvmlGpuVirtualizationMode_t` id
+    fn virtualization_mode(self) -> Result<u32>;
+    fn clock_offsets(self, clock_type: NvmlClockType, pstate: NvmlPerfState) -> Result<NvmlClockOffset>;
+    fn set_clock_offset(self, clock_type: NvmlClockType, pstate: NvmlPerfState, offset: i32) -> Result<()>;
+    fn gpc_vf_offset(self) -> Result<i32>;
+    fn set_gpc_vf_offset(self, offset: i32) -> Result<()>;
+    /// `(min, max)` of the VF curve graphics offset, in MHz
+    fn gpc_vf_offset_range(self) -> Result<(i32, i32)>;
+    fn memory_vf_offset(self) -> Result<i32>;
+    fn set_memory_vf_offset(self, offset: i32) -> Result<()>;
+    fn memory_vf_offset_range(self) -> Result<(i32, i32)>;
+    /// Clocks are in MHz
+    fn supported_memory_clocks(self) -> Result<Vec<u32>>;
+    fn supported_graphics_clocks(self, memory_clock: u32) -> Result<Vec<u32>>;
+    fn set_gpu_locked_clocks(self, min: u32, max: u32) -> Result<()>;
+    fn set_memory_locked_clocks(self, min: u32, max: u32) -> Result<()>;
+    fn reset_gpu_locked_clocks(self) -> Result<()>;
+    fn reset_memory_locked_clocks(self) -> Result<()>;
+    fn temperature(self) -> Result<Celsius>;
+    /// One of the `NVML_POWER_SOURCE_*` values
+    fn power_source(self) -> Result<u64>;
+    /// Power drawn by the whole board
+    fn power_usage(self) -> Result<Milliwatts>;
+    fn power_limit(self) -> Result<Milliwatts>;
+    fn power_default_limit(self) -> Result<Milliwatts>;
+    fn power_limit_constraints(self) -> Result<(Milliwatts, Milliwatts)>;
+    fn set_power_limit(self, limit: Milliwatts) -> Result<()>;
+    fn num_fans(self) -> Result<u32>;
+    /// Duty cycle of `fan` in percent
+    fn fan_speed(self, fan: u32) -> Result<u32>;
+    fn set_default_fan_speed(self, fan: u32) -> Result<()>;
+    fn set_fan_control_policy(self, fan: u32, policy: NvmlFanControlPolicy) -> Result<()>;
+}
+
+impl NvmlBackend for &Device {
+    fn index(self) -> u32 {
+        Device::index(self)
+    }
+
+    fn name(self) -> Result<String> {
+        nvml::device_get_name(self)
+    }
+
+    fn uuid(self) -> Result<String> {
+        nvml::device_get_uuid(self)
+    }
+
+    fn architecture(self) -> Result<u32> {
+        nvml::device_get_architecture(self)
+    }
+
+    fn driver_version(self) -> Result<String> {
+        nvml::system_get_driver_version()
+    }
+
+    fn virtualization_mode(self) -> Result<u32> {
+        nvml::device_get_virtualization_mode(self)
+    }
+
+    fn clock_offsets(self, clock_type: NvmlClockType, pstate: NvmlPerfState) -> Result<NvmlClockOffset> {
+        nvml::device_get_clock_offsets(self, clock_type, pstate)
+    }
+
+    fn set_clock_offset(self, clock_type: NvmlClockType, pstate: NvmlPerfState, offset: i32) -> Result<()> {
+        nvml::device_set_clock_offset(self, clock_type, pstate, offset)
+    }
+
+    fn gpc_vf_offset(self) -> Result<i32> {
+        nvml::device_get_gpc_vf_offset(self)
+    }
+
+    fn set_gpc_vf_offset(self, offset: i32) -> Result<()> {
+        nvml::device_set_gpc_vf_offset(self, offset)
+    }
+
+    fn gpc_vf_offset_range(self) -> Result<(i32, i32)> {
+        nvml::device_get_gpc_vf_offset_range(self)
+    }
+
+    fn memory_vf_offset(self) -> Result<i32> {
+        nvml::device_get_memory_vf_offset(self)
+    }
+
+    fn set_memory_vf_offset(self, offset: i32) -> Result<()> {
+        nvml::device_set_memory_vf_offset(self, offset)
+    }
+
+    fn memory_vf_offset_range(self) -> Result<(i32, i32)> {
+        nvml::device_get_memory_vf_offset_range(self)
+    }
+
+    fn supported_memory_clocks(self) -> Result<Vec<u32>> {
+        nvml::device_get_supported_memory_clocks(self)
+    }
+
+    fn supported_graphics_clocks(self, memory_clock: u32) -> Result<Vec<u32>> {
+        nvml::device_get_supported_graphics_clocks(self, memory_clock)
+    }
+
+    fn set_gpu_locked_clocks(self, min: u32, max: u32) -> Result<()> {
+        nvml::device_set_gpu_locked_clocks(self, min, max)
+    }
+
+    fn set_memory_locked_clocks(self, min: u32, max: u32) -> Result<()> {
+        nvml::device_set_memory_locked_clocks(self, min, max)
+    }
+
+    fn reset_gpu_locked_clocks(self) -> Result<()> {
+        nvml::device_reset_gpu_locked_clocks(self)
+    }
+
+    fn reset_memory_locked_clocks(self) -> Result<()> {
+        nvml::device_reset_memory_locked_clocks(self)
+    }
+
+    fn temperature(self) -> Result<Celsius> {
+        nvml::device_get_temperature(self).map(Celsius)
+    }
+
+    fn power_source(self) -> Result<u64> {
+        nvml::device_get_power_source(self)
+    }
+
+    fn power_usage(self) -> Result<Milliwatts> {
+        nvml::device_get_power_usage(self).map(Milliwatts)
+    }
+
+    fn power_limit(self) -> Result<Milliwatts> {
+        nvml::device_get_power_limit(self).map(Milliwatts)
+    }
+
+    fn power_default_limit(self) -> Result<Milliwatts> {
+        nvml::device_get_power_default_limit(self).map(Milliwatts)
+    }
+
+    fn power_limit_constraints(self) -> Result<(Milliwatts, Milliwatts)> {
+        let (min, max) = nvml::device_get_power_limit_constraints(self)?;
+        Ok((Milliwatts(min), Milliwatts(max)))
+    }
+
+    fn set_power_limit(self, limit: Milliwatts) -> Result<()> {
+        nvml::device_set_power_limit(self, limit.0)
+    }
+
+    fn num_fans(self) -> Result<u32> {
+        nvml::device_get_num_fans(self)
+    }
+
+    fn fan_speed(self, fan: u32) -> Result<u32> {
+        nvml::device_get_fan_speed(self, fan)
+    }
+
+    fn set_default_fan_speed(self, fan: u32) -> Result<()> {
+        nvml::device_set_default_fan_speed(self, fan)
+    }
+
+    fn set_fan_control_policy(self, fan: u32, policy: NvmlFanControlPolicy) -> Result<()> {
+        nvml::device_set_fan_control_policy(self, fan, policy)
+    }
+}
+
+/// In-memory Blackwell GPU for unit tests of the settings logic, reporting
+/// the same hardware as the `NVOC_MOCK` simulator
+#[cfg(test)]
+pub mod fake {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::nvml::mock;
+    use crate::nvml::types::{NVML_DEVICE_ARCH_BLACKWELL, NVML_POWER_SOURCE_AC};
+    use crate::nvml::NvmlError;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Settings {
+        pub graphics_offsets: [i32; 16],
+        pub memory_offsets: [i32; 16],
+        pub locked_clocks: Option<(u32, u32)>,
+        pub memory_clocks_locked: bool,
+        pub power_limit: Milliwatts,
+        /// Fixed duty cycle, `None` under the driver's control
+        pub fan_speed: Option<u32>,
+    }
+
+    impl Default for Settings {
+        fn default() -> Self {
+            Self {
+                graphics_offsets: [0; 16],
+                memory_offsets: [0; 16],
+                locked_clocks: None,
+                memory_clocks_locked: false,
+                power_limit: FakeGpu::DEFAULT_POWER_LIMIT,
+                fan_speed: None,
+            }
+        }
+    }
+
+    pub struct FakeGpu {
+        pub settings: RefCell<Settings>,
+        pub uuid: String,
+        pub fans: u32,
+        /// Setter, by method name, the driver refuses
+        pub fail: Option<&'static str>,
+        /// Setter, by method name, the driver accepts but ignores
+        pub ignore: Option<&'static str>,
+    }
+
+    impl FakeGpu {
+        pub const DEFAULT_POWER_LIMIT: Milliwatts = Milliwatts(mock::DEFAULT_POWER_LIMIT_MW);
+
+        pub fn new(settings: Settings) -> Self {
+            Self {
+                settings: RefCell::new(settings),
+                uuid: mock::uuid_of(0),
+                fans: mock::FAN_COUNT,
+                fail: None,
+                ignore: None,
+            }
+        }
+
+        pub fn settings(&self) -> Settings {
+            self.settings.borrow().clone()
+        }
+
+        fn write(&self, method: &str, write: impl FnOnce(&mut Settings)) -> Result<()> {
+            if self.fail == Some(method) {
+                return Err(NvmlError::NoPermission);
+            }
+            if self.ignore != Some(method) {
+                write(&mut self.settings.borrow_mut());
+            }
+            Ok(())
+        }
+    }
+
+    impl NvmlBackend for &FakeGpu {
+        fn index(self) -> u32 {
+            0
+        }
+
+        fn name(self) -> Result<String> {
+            Ok(mock::NAME.to_string())
+        }
+
+        fn uuid(self) -> Result<String> {
+            Ok(self.uuid.clone())
+        }
+
+        fn architecture(self) -> Result<u32> {
+            Ok(NVML_DEVICE_ARCH_BLACKWELL)
+        }
+
+        fn driver_version(self) -> Result<String> {
+            Ok(mock::DRIVER_VERSION.to_string())
+        }
+
+        fn virtualization_mode(self) -> Result<u32> {
+            Ok(0)
+        }
+
+        fn clock_offsets(self, clock_type: NvmlClockType, pstate: NvmlPerfState) -> Result<NvmlClockOffset> {
+            let settings = self.settings.borrow();
+            let (offsets, (min, max)) = match clock_type {
+                NvmlClockType::Graphics => (&settings.graphics_offsets, mock::GRAPHICS_OFFSET_RANGE),
+                NvmlClockType::Memory => (&settings.memory_offsets, mock::MEMORY_OFFSET_RANGE),
+            };
+            let mut offset = NvmlClockOffset::new_v1(clock_type, pstate, offsets[pstate as usize]);
+            (offset.minClockOffsetMHz, offset.maxClockOffsetMHz) = (min, max);
+            Ok(offset)
+        }
+
+        fn set_clock_offset(self, clock_type: NvmlClockType, pstate: NvmlPerfState, offset: i32) -> Result<()> {
+            self.write("set_clock_offset", |settings| {
+                let offsets = match clock_type {
+                    NvmlClockType::Graphics => &mut settings.graphics_offsets,
+                    NvmlClockType::Memory => &mut settings.memory_offsets,
+                };
+                offsets[pstate as usize] = offset;
+            })
+        }
+
+        fn gpc_vf_offset(self) -> Result<i32> {
+            Err(NvmlError::NotSupported)
+        }
+
+        fn set_gpc_vf_offset(self, _offset: i32) -> Result<()> {
+            Err(NvmlError::NotSupported)
+        }
+
+        fn gpc_vf_offset_range(self) -> Result<(i32, i32)> {
+            Err(NvmlError::NotSupported)
+        }
+
+        fn memory_vf_offset(self) -> Result<i32> {
+            Err(NvmlError::NotSupported)
+        }
+
+        fn set_memory_vf_offset(self, _offset: i32) -> Result<()> {
+            Err(NvmlError::NotSupported)
+        }
+
+        fn memory_vf_offset_range(self) -> Result<(i32, i32)> {
+            Err(NvmlError::NotSupported)
+        }
+
+        fn supported_memory_clocks(self) -> Result<Vec<u32>> {
+            Ok(mock::MEMORY_CLOCKS.to_vec())
+        }
+
+        fn supported_graphics_clocks(self, _memory_clock: u32) -> Result<Vec<u32>> {
+            Ok(mock::GRAPHICS_CLOCKS.to_vec())
+        }
+
+        fn set_gpu_locked_clocks(self, min: u32, max: u32) -> Result<()> {
+            self.write("set_gpu_locked_clocks", |settings| settings.locked_clocks = Some((min, max)))
+        }
+
+        fn set_memory_locked_clocks(self, _min: u32, _max: u32) -> Result<()> {
+            self.write("set_memory_locked_clocks", |settings| settings.memory_clocks_locked = true)
+        }
+
+        fn reset_gpu_locked_clocks(self) -> Result<()> {
+            self.write("reset_gpu_locked_clocks", |settings| settings.locked_clocks = None)
+        }
+
+        fn reset_memory_locked_clocks(self) -> Result<()> {
+            self.write("reset_memory_locked_clocks", |settings| settings.memory_clocks_locked = false)
+        }
+
+        fn temperature(self) -> Result<Celsius> {
+            Ok(Celsius(mock::TEMPERATURE_C))
+        }
+
+        fn power_source(self) -> Result<u64> {
+            Ok(NVML_POWER_SOURCE_AC)
+        }
+
+        fn power_usage(self) -> Result<Milliwatts> {
+            Ok(Milliwatts(mock::POWER_USAGE_MW))
+        }
+
+        fn power_limit(self) -> Result<Milliwatts> {
+            Ok(self.settings.borrow().power_limit)
+        }
+
+        fn power_default_limit(self) -> Result<Milliwatts> {
+            Ok(FakeGpu::DEFAULT_POWER_LIMIT)
+        }
+
+        fn power_limit_constraints(self) -> Result<(Milliwatts, Milliwatts)> {
+            let (min, max) = mock::POWER_LIMIT_RANGE_MW;
+            Ok((Milliwatts(min), Milliwatts(max)))
+        }
+
+        fn set_power_limit(self, limit: Milliwatts) -> Result<()> {
+            self.write("set_power_limit", |settings| settings.power_limit = limit)
+        }
+
+        fn num_fans(self) -> Result<u32> {
+            Ok(self.fans)
+        }
+
+        fn fan_speed(self, _fan: u32) -> Result<u32> {
+            Ok(self.settings.borrow().fan_speed.unwrap_or(mock::FAN_RANGE.0))
+        }
+
+        fn set_default_fan_speed(self, _fan: u32) -> Result<()> {
+            self.write("set_default_fan_speed", |settings| settings.fan_speed = None)
+        }
+
+        fn set_fan_control_policy(self, _fan: u32, policy: NvmlFanControlPolicy) -> Result<()> {
+            self.write("set_fan_control_policy", |settings| {
+                if policy == NvmlFanControlPolicy::TemperatureContinuousSw {
+                    settings.fan_speed = None;
+                }
+            })
+        }
+    }
+}
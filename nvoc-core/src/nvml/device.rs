@@ -0,0 +1,96 @@
+//! Owned GPU handles tied to the NVML session
+//!
+//! NVML hands out raw device pointers that stay valid only until
+//! `nvmlShutdown`. [`Device`] wraps one together with what nvoc knows about
+//! the GPU and keeps NVML initialized for as long as it exists, so a device
+//! can never outlive the library it came from. The pointer itself is only
+//! reachable inside this crate.
+
+use std::fmt;
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::nvml::types::NvmlDevice;
+use crate::nvml::{self, GpuArchitecture, NvmlError, Result};
+
+/// The running NVML session. NVML is shut down when the last reference, held
+/// by the guard from `gpu::init_with_cleanup` and by every [`Device`], drops.
+pub(crate) struct Session(());
+
+/// The current session, if one is running
+static SESSION: Mutex<Weak<Session>> = Mutex::new(Weak::new());
+
+impl Session {
+    /// Track a session once `nvmlInit` succeeded
+    pub(crate) fn start() -> Arc<Session> {
+        let session = Arc::new(Session(()));
+        if let Ok(mut current) = SESSION.lock() {
+            *current = Arc::downgrade(&session);
+        }
+        session
+    }
+
+    fn current() -> Result<Arc<Session>> {
+        SESSION.lock().ok().and_then(|current| current.upgrade()).ok_or(NvmlError::Uninitialized)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = nvml::shutdown();
+    }
+}
+
+/// One GPU, as nvoc's device `index`. Clones share the same handle.
+#[derive(Clone)]
+pub struct Device {
+    handle: NvmlDevice,
+    index: u32,
+    name: String,
+    architecture: GpuArchitecture,
+    _session: Arc<Session>,
+}
+
+impl Device {
+    /// Wrap `handle`, reading the name and architecture of the GPU. Fails
+    /// when no NVML session is running.
+    pub(crate) fn open(handle: NvmlDevice, index: u32) -> Result<Self> {
+        let mut device = Device {
+            handle,
+            index,
+            name: String::new(),
+            architecture: GpuArchitecture::Unknown,
+            _session: Session::current()?,
+        };
+        device.name = nvml::device_get_name(&device)?;
+        // Drivers without nvmlDeviceGetArchitecture fall back to the name
+        device.architecture = match nvml::device_get_architecture(&device) {
+            Ok(arch) => GpuArchitecture::from_nvml(arch),
+            Err(_) => GpuArchitecture::from_device_name(&device.name),
+        };
+        Ok(device)
+    }
+
+    /// Index of the GPU as given on the command line
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Architecture the driver reports, `Unknown` when unrecognized
+    pub fn architecture(&self) -> &GpuArchitecture {
+        &self.architecture
+    }
+
+    pub(crate) fn handle(&self) -> NvmlDevice {
+        self.handle
+    }
+}
+
+impl fmt::Debug for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Device").field("index", &self.index).field("name", &self.name).finish()
+    }
+}
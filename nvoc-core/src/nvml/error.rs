@@ -130,7 +130,7 @@ impl NvmlError {
             Self::Timeout => "operation timed out",
             Self::IrqIssue => "hardware interrupt issue",
             Self::LibraryNotFound => "nvml not found, install nvidia-utils",
-            Self::FunctionNotFound => "required function not available",
+            Self::FunctionNotFound => "required function not available, see nvoc doctor --symbols",
             Self::CorruptedInforom => "inforom corrupted",
             Self::GpuIsLost => "gpu lost, reset required",
             Self::ResetRequired => "gpu reset required",
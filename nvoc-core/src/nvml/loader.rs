@@ -0,0 +1,1178 @@
+//! Runtime NVML library loader
+//!
+//! Dynamically loads the NVML library at runtime instead of build-time linking.
+//! This allows distributing standalone binaries without requiring NVML at build time.
+//! Every function is looked up once when the library opens, so polling loops
+//! call through a plain function pointer.
+
+use libloading::Library;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{mpsc, OnceLock};
+use std::time::Duration;
+
+use crate::constants::library::PROBE_TIMEOUT_MS;
+
+use crate::nvml::types::{
+    NvmlClockOffset, NvmlClockType, NvmlDevice, NvmlEventData, NvmlEventSet, NvmlFanControlPolicy, NvmlFanSpeedInfo,
+    NvmlFieldValue,
+    NvmlMemory, NvmlPciInfo, NvmlProcessInfo, NvmlReturn, NvmlUtilization,
+};
+use crate::nvml::mock;
+use crate::{timing, trace};
+use libc::{c_char, c_int, c_uint};
+
+/// Global NVML library instance, the name it was opened by and its
+/// resolved functions
+static NVML_LIB: OnceLock<Result<Loaded, crate::nvml::NvmlError>> = OnceLock::new();
+
+/// Candidate paths skipped because they did not respond in time
+static TIMED_OUT: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+/// Library from `--nvml-path` or the config's `paths.library`, searched
+/// for when unset
+static CONFIGURED: OnceLock<PathBuf> = OnceLock::new();
+
+struct Loaded {
+    /// Never unloaded, which keeps the pointers in `api` valid. `None` for
+    /// the simulated GPUs of `NVOC_MOCK`.
+    lib: Option<Library>,
+    name: String,
+    api: NvmlApi,
+}
+
+/// First of `names` the library exports, with the name it was found under
+fn resolve_first<T: Copy>(lib: &Library, names: &[&'static str]) -> Option<(T, &'static str)> {
+    names.iter().find_map(|&name| unsafe { lib.get::<T>(name.as_bytes()) }.ok().map(|func| (*func, name)))
+}
+
+/// Declares `NvmlApi` with one optional function pointer per NVML symbol,
+/// and `SYMBOLS` listing them all. Older versions of a symbol follow it
+/// after `|`, newest first, and are only used when the newer one is
+/// missing; each must take the same arguments.
+macro_rules! nvml_api {
+    ($($field:ident = $symbol:literal $(| $fallback:literal)*: fn($($arg:ty),*);)*) => {
+        /// NVML functions resolved once when the library is opened. A
+        /// symbol the installed driver does not export in any version is
+        /// `None`, and calling its wrapper fails with `FunctionNotFound`.
+        pub struct NvmlApi {
+            $(pub $field: Option<unsafe extern "C" fn($($arg),*) -> NvmlReturn>,)*
+            names: ResolvedNames,
+        }
+
+        /// Name each function was found under
+        struct ResolvedNames {
+            $($field: Option<&'static str>,)*
+        }
+
+        impl NvmlApi {
+            /// A table with every function missing
+            pub(super) fn empty() -> Self {
+                Self {
+                    $($field: None,)*
+                    names: ResolvedNames { $($field: None,)* },
+                }
+            }
+
+            /// Record the functions set by hand as resolved under their own name
+            pub(super) fn mark_resolved(&mut self) {
+                $(if self.$field.is_some() && self.names.$field.is_none() {
+                    self.names.$field = Some($symbol);
+                })*
+            }
+
+            /// The pointers stay valid as long as `lib` is loaded, which
+            /// for the global instance is the life of the process
+            fn resolve(lib: &Library) -> Self {
+                $(let $field = resolve_first(lib, &[$symbol $(, $fallback)*]);)*
+                Self {
+                    $($field: $field.map(|(func, _)| func),)*
+                    names: ResolvedNames { $($field: $field.map(|(_, name)| name),)* },
+                }
+            }
+
+            /// Name `symbol` resolved under, an older version when the
+            /// driver lacks it. `None` when missing or not bound by nvoc.
+            pub fn resolved_name(&self, symbol: &str) -> Option<&'static str> {
+                match symbol {
+                    $($symbol => self.names.$field,)*
+                    _ => None,
+                }
+            }
+
+            /// Whether `symbol` resolved in any version
+            pub fn has(&self, symbol: &str) -> bool {
+                self.resolved_name(symbol).is_some()
+            }
+        }
+
+        /// Every NVML function nvoc may resolve
+        pub const SYMBOLS: &[&str] = &[$($symbol),*];
+    };
+}
+
+nvml_api! {
+    init_v2 = "nvmlInit_v2" | "nvmlInit": fn();
+    shutdown = "nvmlShutdown": fn();
+    system_get_driver_version = "nvmlSystemGetDriverVersion": fn(*mut c_char, c_uint);
+    device_get_count_v2 = "nvmlDeviceGetCount_v2" | "nvmlDeviceGetCount": fn(*mut c_uint);
+    device_get_handle_by_index_v2 = "nvmlDeviceGetHandleByIndex_v2" | "nvmlDeviceGetHandleByIndex":
+        fn(c_uint, *mut NvmlDevice);
+    device_get_name = "nvmlDeviceGetName": fn(NvmlDevice, *mut c_char, c_uint);
+    device_get_architecture = "nvmlDeviceGetArchitecture": fn(NvmlDevice, *mut c_uint);
+    device_get_clock_offsets = "nvmlDeviceGetClockOffsets": fn(NvmlDevice, *mut NvmlClockOffset);
+    device_set_clock_offsets = "nvmlDeviceSetClockOffsets": fn(NvmlDevice, *const NvmlClockOffset);
+    device_set_gpu_locked_clocks = "nvmlDeviceSetGpuLockedClocks": fn(NvmlDevice, c_uint, c_uint);
+    device_reset_gpu_locked_clocks = "nvmlDeviceResetGpuLockedClocks": fn(NvmlDevice);
+    device_set_memory_locked_clocks = "nvmlDeviceSetMemoryLockedClocks": fn(NvmlDevice, c_uint, c_uint);
+    device_reset_memory_locked_clocks = "nvmlDeviceResetMemoryLockedClocks": fn(NvmlDevice);
+    device_set_applications_clocks = "nvmlDeviceSetApplicationsClocks": fn(NvmlDevice, c_uint, c_uint);
+    device_reset_applications_clocks = "nvmlDeviceResetApplicationsClocks": fn(NvmlDevice);
+    device_set_mem_clk_vf_offset = "nvmlDeviceSetMemClkVfOffset": fn(NvmlDevice, c_int);
+    device_get_mem_clk_vf_offset = "nvmlDeviceGetMemClkVfOffset": fn(NvmlDevice, *mut c_int);
+    device_get_mem_clk_min_max_vf_offset = "nvmlDeviceGetMemClkMinMaxVfOffset": fn(NvmlDevice, *mut c_int, *mut c_int);
+    device_set_gpc_clk_vf_offset = "nvmlDeviceSetGpcClkVfOffset": fn(NvmlDevice, c_int);
+    device_get_gpc_clk_vf_offset = "nvmlDeviceGetGpcClkVfOffset": fn(NvmlDevice, *mut c_int);
+    device_get_gpc_clk_min_max_vf_offset = "nvmlDeviceGetGpcClkMinMaxVfOffset": fn(NvmlDevice, *mut c_int, *mut c_int);
+    device_get_clock_info = "nvmlDeviceGetClockInfo": fn(NvmlDevice, NvmlClockType, *mut c_uint);
+    device_get_supported_memory_clocks = "nvmlDeviceGetSupportedMemoryClocks": fn(NvmlDevice, *mut c_uint, *mut c_uint);
+    device_get_supported_graphics_clocks = "nvmlDeviceGetSupportedGraphicsClocks":
+        fn(NvmlDevice, c_uint, *mut c_uint, *mut c_uint);
+    device_get_temperature = "nvmlDeviceGetTemperature": fn(NvmlDevice, c_uint, *mut c_uint);
+    device_get_field_values = "nvmlDeviceGetFieldValues": fn(NvmlDevice, c_int, *mut NvmlFieldValue);
+    device_get_power_usage = "nvmlDeviceGetPowerUsage": fn(NvmlDevice, *mut c_uint);
+    device_get_power_management_limit_constraints = "nvmlDeviceGetPowerManagementLimitConstraints":
+        fn(NvmlDevice, *mut c_uint, *mut c_uint);
+    device_get_power_management_limit = "nvmlDeviceGetPowerManagementLimit": fn(NvmlDevice, *mut c_uint);
+    device_get_power_management_default_limit = "nvmlDeviceGetPowerManagementDefaultLimit": fn(NvmlDevice, *mut c_uint);
+    device_set_power_management_limit = "nvmlDeviceSetPowerManagementLimit": fn(NvmlDevice, c_uint);
+    device_get_num_fans = "nvmlDeviceGetNumFans": fn(NvmlDevice, *mut c_uint);
+    device_get_fan_speed_v2 = "nvmlDeviceGetFanSpeed_v2": fn(NvmlDevice, c_uint, *mut c_uint);
+    device_get_fan_speed_rpm = "nvmlDeviceGetFanSpeedRPM": fn(NvmlDevice, *mut NvmlFanSpeedInfo);
+    device_get_min_max_fan_speed = "nvmlDeviceGetMinMaxFanSpeed": fn(NvmlDevice, *mut c_uint, *mut c_uint);
+    device_get_fan_control_policy_v2 = "nvmlDeviceGetFanControlPolicy_v2": fn(NvmlDevice, c_uint, *mut c_uint);
+    device_set_fan_control_policy = "nvmlDeviceSetFanControlPolicy": fn(NvmlDevice, c_uint, NvmlFanControlPolicy);
+    device_set_fan_speed_v2 = "nvmlDeviceSetFanSpeed_v2": fn(NvmlDevice, c_uint, c_uint);
+    device_set_default_fan_speed_v2 = "nvmlDeviceSetDefaultFanSpeed_v2": fn(NvmlDevice, c_uint);
+    device_get_utilization_rates = "nvmlDeviceGetUtilizationRates": fn(NvmlDevice, *mut NvmlUtilization);
+    device_get_memory_info = "nvmlDeviceGetMemoryInfo": fn(NvmlDevice, *mut NvmlMemory);
+    device_get_virtualization_mode = "nvmlDeviceGetVirtualizationMode": fn(NvmlDevice, *mut c_uint);
+    device_get_handle_by_uuid = "nvmlDeviceGetHandleByUUID": fn(*const c_char, *mut NvmlDevice);
+    device_get_handle_by_pci_bus_id_v2 = "nvmlDeviceGetHandleByPciBusId_v2" | "nvmlDeviceGetHandleByPciBusId":
+        fn(*const c_char, *mut NvmlDevice);
+    device_get_uuid = "nvmlDeviceGetUUID": fn(NvmlDevice, *mut c_char, c_uint);
+    device_get_vbios_version = "nvmlDeviceGetVbiosVersion": fn(NvmlDevice, *mut c_char, c_uint);
+    device_get_inforom_version = "nvmlDeviceGetInforomVersion": fn(NvmlDevice, c_uint, *mut c_char, c_uint);
+    device_get_serial = "nvmlDeviceGetSerial": fn(NvmlDevice, *mut c_char, c_uint);
+    device_get_board_part_number = "nvmlDeviceGetBoardPartNumber": fn(NvmlDevice, *mut c_char, c_uint);
+    device_get_pci_info_v3 = "nvmlDeviceGetPciInfo_v3" | "nvmlDeviceGetPciInfo_v2": fn(NvmlDevice, *mut NvmlPciInfo);
+    device_get_mig_mode = "nvmlDeviceGetMigMode": fn(NvmlDevice, *mut c_uint, *mut c_uint);
+    device_get_current_clocks_event_reasons = "nvmlDeviceGetCurrentClocksEventReasons": fn(NvmlDevice, *mut u64);
+    device_get_performance_state = "nvmlDeviceGetPerformanceState": fn(NvmlDevice, *mut c_uint);
+    device_get_supported_event_types = "nvmlDeviceGetSupportedEventTypes": fn(NvmlDevice, *mut u64);
+    device_register_events = "nvmlDeviceRegisterEvents": fn(NvmlDevice, u64, NvmlEventSet);
+    event_set_create = "nvmlEventSetCreate": fn(*mut NvmlEventSet);
+    event_set_wait_v2 = "nvmlEventSetWait_v2" | "nvmlEventSetWait": fn(NvmlEventSet, *mut NvmlEventData, c_uint);
+    event_set_free = "nvmlEventSetFree": fn(NvmlEventSet);
+    device_get_persistence_mode = "nvmlDeviceGetPersistenceMode": fn(NvmlDevice, *mut c_uint);
+    device_set_persistence_mode = "nvmlDeviceSetPersistenceMode": fn(NvmlDevice, c_uint);
+    device_get_temperature_threshold = "nvmlDeviceGetTemperatureThreshold": fn(NvmlDevice, c_uint, *mut c_uint);
+    device_set_temperature_threshold = "nvmlDeviceSetTemperatureThreshold": fn(NvmlDevice, c_uint, *mut c_int);
+    device_get_curr_pcie_link_generation = "nvmlDeviceGetCurrPcieLinkGeneration": fn(NvmlDevice, *mut c_uint);
+    device_get_max_pcie_link_generation = "nvmlDeviceGetMaxPcieLinkGeneration": fn(NvmlDevice, *mut c_uint);
+    device_get_curr_pcie_link_width = "nvmlDeviceGetCurrPcieLinkWidth": fn(NvmlDevice, *mut c_uint);
+    device_get_max_pcie_link_width = "nvmlDeviceGetMaxPcieLinkWidth": fn(NvmlDevice, *mut c_uint);
+    device_get_pcie_throughput = "nvmlDeviceGetPcieThroughput": fn(NvmlDevice, c_uint, *mut c_uint);
+    device_get_pcie_replay_counter = "nvmlDeviceGetPcieReplayCounter": fn(NvmlDevice, *mut c_uint);
+    device_get_power_source = "nvmlDeviceGetPowerSource": fn(NvmlDevice, *mut c_uint);
+    device_get_total_ecc_errors = "nvmlDeviceGetTotalEccErrors": fn(NvmlDevice, c_uint, c_uint, *mut u64);
+    device_get_remapped_rows = "nvmlDeviceGetRemappedRows":
+        fn(NvmlDevice, *mut c_uint, *mut c_uint, *mut c_uint, *mut c_uint);
+    device_get_retired_pages = "nvmlDeviceGetRetiredPages": fn(NvmlDevice, c_uint, *mut c_uint, *mut u64);
+    device_get_retired_pages_pending_status = "nvmlDeviceGetRetiredPagesPendingStatus": fn(NvmlDevice, *mut c_uint);
+    device_get_compute_running_processes_v3 = "nvmlDeviceGetComputeRunningProcesses_v3"
+        | "nvmlDeviceGetComputeRunningProcesses_v2":
+        fn(NvmlDevice, *mut c_uint, *mut NvmlProcessInfo);
+    device_get_graphics_running_processes_v3 = "nvmlDeviceGetGraphicsRunningProcesses_v3"
+        | "nvmlDeviceGetGraphicsRunningProcesses_v2":
+        fn(NvmlDevice, *mut c_uint, *mut NvmlProcessInfo);
+}
+
+/// Architecture tag `ldconfig -p` prints for libraries of this build
+fn ld_cache_arch() -> Option<&'static str> {
+    match std::env::consts::ARCH {
+        "x86_64" => Some("x86-64"),
+        "aarch64" => Some("AArch64"),
+        _ => None,
+    }
+}
+
+/// Paths of NVML libraries listed by `ldconfig -p`, skipping those built
+/// for another architecture (such as 32-bit x86 on a multilib system)
+fn parse_ld_cache(output: &str, arch: Option<&str>) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (entry, path) = line.trim().split_once(" => ")?;
+            let (name, tags) = entry.split_once(' ')?;
+            if !name.starts_with("libnvidia-ml.so") {
+                return None;
+            }
+            let arch_matches = match arch {
+                Some(arch) => tags.split([',', '(', ')']).any(|tag| tag == arch),
+                None => true,
+            };
+            arch_matches.then(|| path.to_string())
+        })
+        .collect()
+}
+
+/// NVML libraries known to the dynamic linker cache
+fn ld_cache_candidates() -> Vec<String> {
+    ["/sbin/ldconfig", "/usr/sbin/ldconfig", "ldconfig"]
+        .iter()
+        .find_map(|ldconfig| Command::new(ldconfig).arg("-p").output().ok())
+        .map(|output| parse_ld_cache(&String::from_utf8_lossy(&output.stdout), ld_cache_arch()))
+        .unwrap_or_default()
+}
+
+/// Debian-style multiarch directory name for this build's architecture
+fn multiarch_triplet() -> Option<&'static str> {
+    match std::env::consts::ARCH {
+        "x86_64" => Some("x86_64-linux-gnu"),
+        "aarch64" => Some("aarch64-linux-gnu"),
+        _ => None,
+    }
+}
+
+/// File names the NVML library is installed under, the versioned one first
+const SONAMES: [&str; 2] = ["libnvidia-ml.so.1", "libnvidia-ml.so"];
+
+/// Directories listed in `LD_LIBRARY_PATH`
+fn ld_library_dirs(ld_library_path: Option<&str>) -> impl Iterator<Item = &str> {
+    ld_library_path.unwrap_or_default().split(':').filter(|dir| !dir.is_empty())
+}
+
+/// Standard library directories, most specific first. The multiarch
+/// directory follows the architecture nvoc was built for, so ARM hosts
+/// such as Grace workstations look in `/usr/lib/aarch64-linux-gnu`.
+fn system_dirs(triplet: Option<&str>) -> Vec<String> {
+    let mut dirs: Vec<String> = triplet.iter().map(|t| format!("/usr/lib/{t}")).collect();
+    dirs.extend(["/usr/lib64".to_string(), "/usr/lib".to_string()]);
+    dirs
+}
+
+/// Library paths to try, most specific first: the `LD_LIBRARY_PATH`
+/// directories, then the standard ones. Only full paths, so each can be
+/// probed before dlopen is given it.
+fn library_candidates(triplet: Option<&str>, ld_library_path: Option<&str>) -> Vec<String> {
+    let mut dirs: Vec<String> = ld_library_dirs(ld_library_path).map(str::to_string).collect();
+    dirs.extend(system_dirs(triplet));
+    SONAMES
+        .iter()
+        .flat_map(|file| dirs.iter().map(move |dir| format!("{dir}/{file}")))
+        .collect()
+}
+
+enum Probe {
+    Readable,
+    Unreadable,
+    TimedOut,
+}
+
+/// Read the start of `path` on a separate thread. A file on a hung network
+/// mount blocks in the kernel, and dlopen would block with it while holding
+/// the dynamic linker's lock, so a probe that does not answer within the
+/// timeout is abandoned and the path skipped.
+fn probe(path: &Path) -> Probe {
+    let (tx, rx) = mpsc::channel();
+    let path = path.to_path_buf();
+    std::thread::spawn(move || {
+        let mut magic = [0u8; 4];
+        let _ = tx.send(File::open(&path).and_then(|mut f| f.read_exact(&mut magic)).is_ok());
+    });
+    match rx.recv_timeout(Duration::from_millis(PROBE_TIMEOUT_MS)) {
+        Ok(true) => Probe::Readable,
+        Ok(false) => Probe::Unreadable,
+        Err(_) => Probe::TimedOut,
+    }
+}
+
+/// Files the dynamic linker may open for `name`: the path itself, or for a
+/// bare name its place in each `LD_LIBRARY_PATH` directory, each matching
+/// linker cache entry in `ld_cache` and each standard directory
+fn probe_paths(name: &str, ld_library_path: Option<&str>, ld_cache: &[String], triplet: Option<&str>) -> Vec<PathBuf> {
+    if name.contains('/') {
+        return vec![PathBuf::from(name)];
+    }
+    let mut paths: Vec<PathBuf> = ld_library_dirs(ld_library_path).map(|dir| Path::new(dir).join(name)).collect();
+    paths.extend(
+        ld_cache
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| path.file_name().is_some_and(|file| file == name)),
+    );
+    paths.extend(system_dirs(triplet).iter().map(|dir| Path::new(dir).join(name)));
+    paths
+}
+
+/// Open `name` unless a file it resolves to does not respond in time. A
+/// bare name is only handed to dlopen once every file it could resolve to
+/// has been probed, since dlopen itself cannot be timed out.
+fn open_candidate(name: &str, ld_cache: &[String], timed_out: &mut Vec<PathBuf>) -> Option<Library> {
+    let ld_library_path = std::env::var("LD_LIBRARY_PATH").ok();
+    for path in probe_paths(name, ld_library_path.as_deref(), ld_cache, multiarch_triplet()) {
+        if timed_out.contains(&path) {
+            return None;
+        }
+        match probe(&path) {
+            Probe::TimedOut => {
+                eprintln!(
+                    "warning[library]: {} did not respond within {}s, skipping it",
+                    path.display(),
+                    Duration::from_millis(PROBE_TIMEOUT_MS).as_secs_f64()
+                );
+                timed_out.push(path);
+                return None;
+            }
+            Probe::Unreadable if name.contains('/') => return None,
+            _ => {}
+        }
+    }
+    unsafe { Library::new(name) }.ok()
+}
+
+/// Open the first NVML library found and resolve its functions
+fn open_library() -> Result<Loaded, crate::nvml::NvmlError> {
+    let mut timed_out = Vec::new();
+    let result = open_first(&mut timed_out);
+    let _ = TIMED_OUT.set(timed_out);
+    let (lib, name) = result?;
+    let api = timing::measure("resolve nvml symbols", || NvmlApi::resolve(&lib));
+    Ok(Loaded { lib: Some(lib), name, api })
+}
+
+fn open_first(timed_out: &mut Vec<PathBuf>) -> Result<(Library, String), crate::nvml::NvmlError> {
+    // A configured library is the only candidate, so a typo is not papered over
+    if let Some(path) = CONFIGURED.get() {
+        let name = path.to_string_lossy().into_owned();
+        let ld_cache = match name.contains('/') {
+            true => Vec::new(),
+            false => ld_cache_candidates(),
+        };
+        return match open_candidate(&name, &ld_cache, timed_out) {
+            Some(lib) => Ok((lib, name)),
+            None => {
+                eprintln!("warning[library]: cannot load {name}, check --nvml-path or paths.library");
+                Err(crate::nvml::NvmlError::LibraryNotFound)
+            }
+        };
+    }
+
+    // Full paths only until every file a bare name could resolve to has been probed
+    let ld_library_path = std::env::var("LD_LIBRARY_PATH").ok();
+    for name in library_candidates(multiarch_triplet(), ld_library_path.as_deref()) {
+        if let Some(lib) = open_candidate(&name, &[], timed_out) {
+            return Ok((lib, name));
+        }
+    }
+
+    // Layouts the list above misses, as long as ldconfig knows them
+    let ld_cache = ld_cache_candidates();
+    for path in &ld_cache {
+        if let Some(lib) = open_candidate(path, &[], timed_out) {
+            return Ok((lib, path.clone()));
+        }
+    }
+
+    // Let the system search, for anything it resolves by other means
+    for name in SONAMES {
+        if let Some(lib) = open_candidate(name, &ld_cache, timed_out) {
+            return Ok((lib, name.to_string()));
+        }
+    }
+    Err(crate::nvml::NvmlError::LibraryNotFound)
+}
+
+/// Load `path` instead of searching for the library. Only takes effect
+/// before the first NVML call.
+pub fn set_library_path(path: PathBuf) {
+    let _ = CONFIGURED.set(path);
+}
+
+/// Load the NVML library at runtime
+fn loaded() -> Result<&'static Loaded, crate::nvml::NvmlError> {
+    let lib_result = NVML_LIB.get_or_init(|| {
+        if mock::enabled() {
+            eprintln!("warning[mock]: NVOC_MOCK is set, using simulated gpus instead of libnvidia-ml");
+            return Ok(Loaded { lib: None, name: "mock".to_string(), api: mock::api() });
+        }
+        timing::measure("load libnvidia-ml", open_library)
+    });
+    lib_result.as_ref().map_err(|e| e.clone())
+}
+
+/// Functions of the loaded library, loading it on first use
+pub fn api() -> Result<&'static NvmlApi, crate::nvml::NvmlError> {
+    loaded().map(|loaded| &loaded.api)
+}
+
+/// Name or path the NVML library was loaded from
+pub fn library_name() -> Result<&'static str, crate::nvml::NvmlError> {
+    loaded().map(|loaded| loaded.name.as_str())
+}
+
+/// File the loaded library was mapped from, as resolved by the dynamic
+/// linker when it was opened by a bare name
+pub fn library_path() -> Option<PathBuf> {
+    let loaded = loaded().ok()?;
+    loaded.lib.as_ref()?;
+    let init = loaded.api.init_v2?;
+    let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+    if unsafe { libc::dladdr(init as *const libc::c_void, &mut info) } == 0 || info.dli_fname.is_null() {
+        return None;
+    }
+    let path = unsafe { std::ffi::CStr::from_ptr(info.dli_fname) };
+    Some(PathBuf::from(path.to_string_lossy().into_owned()))
+}
+
+/// Candidate paths skipped while loading because they did not respond
+pub fn timed_out_paths() -> &'static [PathBuf] {
+    let _ = loaded();
+    TIMED_OUT.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+// Individual function wrappers
+pub fn nvml_init_v2() -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.init_v2.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlInit_v2", &[], || unsafe { func() }))
+}
+
+pub fn nvml_shutdown() -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.shutdown.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlShutdown", &[], || unsafe { func() }))
+}
+
+pub fn nvml_system_get_driver_version(
+    version: *mut c_char,
+    length: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.system_get_driver_version.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlSystemGetDriverVersion", &[&version, &length], || unsafe { func(version, length) }))
+}
+
+pub fn nvml_device_get_count_v2(
+    device_count: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_count_v2.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetCount_v2", &[&device_count], || unsafe { func(device_count) }))
+}
+
+pub fn nvml_device_get_handle_by_index_v2(
+    index: c_uint,
+    device: *mut NvmlDevice,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_handle_by_index_v2.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetHandleByIndex_v2", &[&index, &device], || unsafe { func(index, device) }))
+}
+
+pub fn nvml_device_get_name(
+    device: NvmlDevice,
+    name: *mut c_char,
+    length: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_name.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetName", &[&device, &name, &length], || unsafe { func(device, name, length) }))
+}
+
+pub fn nvml_device_get_clock_offsets(
+    device: NvmlDevice,
+    clock_offsets: *mut NvmlClockOffset,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_clock_offsets.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetClockOffsets", &[&device, &clock_offsets], || unsafe { func(device, clock_offsets) }))
+}
+
+pub fn nvml_device_set_clock_offsets(
+    device: NvmlDevice,
+    clock_offsets: *const NvmlClockOffset,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_set_clock_offsets.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    // The offset itself rather than the address it is passed at
+    let offset = unsafe { *clock_offsets };
+    Ok(trace::call("nvmlDeviceSetClockOffsets", &[&device, &offset], || unsafe { func(device, clock_offsets) }))
+}
+
+pub fn nvml_device_set_gpu_locked_clocks(
+    device: NvmlDevice,
+    min_gpu_clock: c_uint,
+    max_gpu_clock: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_set_gpu_locked_clocks.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceSetGpuLockedClocks", &[&device, &min_gpu_clock, &max_gpu_clock], || unsafe {
+        func(device, min_gpu_clock, max_gpu_clock)
+    }))
+}
+
+pub fn nvml_device_set_memory_locked_clocks(
+    device: NvmlDevice,
+    min_mem_clock: c_uint,
+    max_mem_clock: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_set_memory_locked_clocks.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceSetMemoryLockedClocks", &[&device, &min_mem_clock, &max_mem_clock], || unsafe {
+        func(device, min_mem_clock, max_mem_clock)
+    }))
+}
+
+pub fn nvml_device_set_applications_clocks(
+    device: NvmlDevice,
+    mem_clock_mhz: c_uint,
+    graphics_clock_mhz: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_set_applications_clocks.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceSetApplicationsClocks", &[&device, &mem_clock_mhz, &graphics_clock_mhz], || unsafe {
+        func(device, mem_clock_mhz, graphics_clock_mhz)
+    }))
+}
+
+pub fn nvml_device_reset_applications_clocks(
+    device: NvmlDevice,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_reset_applications_clocks.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceResetApplicationsClocks", &[&device], || unsafe { func(device) }))
+}
+
+pub fn nvml_device_reset_gpu_locked_clocks(
+    device: NvmlDevice,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_reset_gpu_locked_clocks.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceResetGpuLockedClocks", &[&device], || unsafe { func(device) }))
+}
+
+pub fn nvml_device_reset_memory_locked_clocks(
+    device: NvmlDevice,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_reset_memory_locked_clocks.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceResetMemoryLockedClocks", &[&device], || unsafe { func(device) }))
+}
+
+pub fn nvml_device_set_mem_clk_vf_offset(
+    device: NvmlDevice,
+    offset: c_int,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_set_mem_clk_vf_offset.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceSetMemClkVfOffset", &[&device, &offset], || unsafe { func(device, offset) }))
+}
+
+pub fn nvml_device_get_mem_clk_vf_offset(
+    device: NvmlDevice,
+    offset: *mut c_int,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_mem_clk_vf_offset.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetMemClkVfOffset", &[&device, &offset], || unsafe { func(device, offset) }))
+}
+
+pub fn nvml_device_get_mem_clk_min_max_vf_offset(
+    device: NvmlDevice,
+    min_offset: *mut c_int,
+    max_offset: *mut c_int,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_mem_clk_min_max_vf_offset.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetMemClkMinMaxVfOffset", &[&device, &min_offset, &max_offset], || unsafe {
+        func(device, min_offset, max_offset)
+    }))
+}
+
+pub fn nvml_device_set_gpc_clk_vf_offset(
+    device: NvmlDevice,
+    offset: c_int,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_set_gpc_clk_vf_offset.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceSetGpcClkVfOffset", &[&device, &offset], || unsafe { func(device, offset) }))
+}
+
+pub fn nvml_device_get_gpc_clk_vf_offset(
+    device: NvmlDevice,
+    offset: *mut c_int,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_gpc_clk_vf_offset.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetGpcClkVfOffset", &[&device, &offset], || unsafe { func(device, offset) }))
+}
+
+pub fn nvml_device_get_gpc_clk_min_max_vf_offset(
+    device: NvmlDevice,
+    min_offset: *mut c_int,
+    max_offset: *mut c_int,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_gpc_clk_min_max_vf_offset.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetGpcClkMinMaxVfOffset", &[&device, &min_offset, &max_offset], || unsafe {
+        func(device, min_offset, max_offset)
+    }))
+}
+
+pub fn nvml_device_get_clock_info(
+    device: NvmlDevice,
+    clock_type: NvmlClockType,
+    clock: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_clock_info.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetClockInfo", &[&device, &clock_type, &clock], || unsafe {
+        func(device, clock_type, clock)
+    }))
+}
+
+pub fn nvml_device_get_supported_memory_clocks(
+    device: NvmlDevice,
+    count: *mut c_uint,
+    clocks: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_supported_memory_clocks.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetSupportedMemoryClocks", &[&device, &count, &clocks], || unsafe {
+        func(device, count, clocks)
+    }))
+}
+
+pub fn nvml_device_get_supported_graphics_clocks(
+    device: NvmlDevice,
+    memory_clock: c_uint,
+    count: *mut c_uint,
+    clocks: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_supported_graphics_clocks.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetSupportedGraphicsClocks", &[&device, &memory_clock, &count, &clocks], || unsafe {
+        func(device, memory_clock, count, clocks)
+    }))
+}
+
+pub fn nvml_device_get_temperature(
+    device: NvmlDevice,
+    sensor_type: c_uint,
+    temp: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_temperature.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetTemperature", &[&device, &sensor_type, &temp], || unsafe {
+        func(device, sensor_type, temp)
+    }))
+}
+
+pub fn nvml_device_get_temperature_threshold(
+    device: NvmlDevice,
+    threshold: c_uint,
+    temp: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_temperature_threshold.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetTemperatureThreshold", &[&device, &threshold, &temp], || unsafe {
+        func(device, threshold, temp)
+    }))
+}
+
+pub fn nvml_device_set_temperature_threshold(
+    device: NvmlDevice,
+    threshold: c_uint,
+    temp: *mut c_int,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_set_temperature_threshold.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceSetTemperatureThreshold", &[&device, &threshold, &temp], || unsafe {
+        func(device, threshold, temp)
+    }))
+}
+
+pub fn nvml_device_get_curr_pcie_link_generation(device: NvmlDevice, value: *mut c_uint) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_curr_pcie_link_generation.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetCurrPcieLinkGeneration", &[&device, &value], || unsafe { func(device, value) }))
+}
+
+pub fn nvml_device_get_max_pcie_link_generation(device: NvmlDevice, value: *mut c_uint) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_max_pcie_link_generation.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetMaxPcieLinkGeneration", &[&device, &value], || unsafe { func(device, value) }))
+}
+
+pub fn nvml_device_get_curr_pcie_link_width(device: NvmlDevice, value: *mut c_uint) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_curr_pcie_link_width.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetCurrPcieLinkWidth", &[&device, &value], || unsafe { func(device, value) }))
+}
+
+pub fn nvml_device_get_max_pcie_link_width(device: NvmlDevice, value: *mut c_uint) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_max_pcie_link_width.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetMaxPcieLinkWidth", &[&device, &value], || unsafe { func(device, value) }))
+}
+
+pub fn nvml_device_get_pcie_throughput(
+    device: NvmlDevice,
+    counter: c_uint,
+    value: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_pcie_throughput.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetPcieThroughput", &[&device, &counter, &value], || unsafe {
+        func(device, counter, value)
+    }))
+}
+
+pub fn nvml_device_get_total_ecc_errors(
+    device: NvmlDevice,
+    error_type: c_uint,
+    counter_type: c_uint,
+    count: *mut u64,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_total_ecc_errors.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetTotalEccErrors", &[&device, &error_type, &counter_type, &count], || unsafe {
+        func(device, error_type, counter_type, count)
+    }))
+}
+
+pub fn nvml_device_get_remapped_rows(
+    device: NvmlDevice,
+    correctable: *mut c_uint,
+    uncorrectable: *mut c_uint,
+    pending: *mut c_uint,
+    failed: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_remapped_rows.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetRemappedRows", &[&device, &correctable, &uncorrectable, &pending, &failed], || unsafe {
+        func(device, correctable, uncorrectable, pending, failed)
+    }))
+}
+
+pub fn nvml_device_get_retired_pages(
+    device: NvmlDevice,
+    cause: c_uint,
+    count: *mut c_uint,
+    addresses: *mut u64,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_retired_pages.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetRetiredPages", &[&device, &cause, &count, &addresses], || unsafe {
+        func(device, cause, count, addresses)
+    }))
+}
+
+pub fn nvml_device_get_retired_pages_pending_status(
+    device: NvmlDevice,
+    pending: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_retired_pages_pending_status.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetRetiredPagesPendingStatus", &[&device, &pending], || unsafe { func(device, pending) }))
+}
+
+pub fn nvml_device_get_pcie_replay_counter(device: NvmlDevice, value: *mut c_uint) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_pcie_replay_counter.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetPcieReplayCounter", &[&device, &value], || unsafe { func(device, value) }))
+}
+
+pub fn nvml_device_get_power_source(device: NvmlDevice, source: *mut c_uint) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_power_source.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetPowerSource", &[&device, &source], || unsafe { func(device, source) }))
+}
+
+pub fn nvml_device_get_compute_running_processes_v3(
+    device: NvmlDevice,
+    count: *mut c_uint,
+    infos: *mut NvmlProcessInfo,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_compute_running_processes_v3.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetComputeRunningProcesses_v3", &[&device, &count, &infos], || unsafe {
+        func(device, count, infos)
+    }))
+}
+
+pub fn nvml_device_get_graphics_running_processes_v3(
+    device: NvmlDevice,
+    count: *mut c_uint,
+    infos: *mut NvmlProcessInfo,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_graphics_running_processes_v3.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetGraphicsRunningProcesses_v3", &[&device, &count, &infos], || unsafe {
+        func(device, count, infos)
+    }))
+}
+
+pub fn nvml_device_get_field_values(
+    device: NvmlDevice,
+    values_count: c_int,
+    values: *mut NvmlFieldValue,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_field_values.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetFieldValues", &[&device, &values_count, &values], || unsafe {
+        func(device, values_count, values)
+    }))
+}
+
+pub fn nvml_device_get_power_usage(
+    device: NvmlDevice,
+    power: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_power_usage.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetPowerUsage", &[&device, &power], || unsafe { func(device, power) }))
+}
+
+pub fn nvml_device_get_power_management_limit_constraints(
+    device: NvmlDevice,
+    min_limit: *mut c_uint,
+    max_limit: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_power_management_limit_constraints.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetPowerManagementLimitConstraints", &[&device, &min_limit, &max_limit], || unsafe {
+        func(device, min_limit, max_limit)
+    }))
+}
+
+pub fn nvml_device_get_power_management_limit(
+    device: NvmlDevice,
+    limit: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_power_management_limit.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetPowerManagementLimit", &[&device, &limit], || unsafe { func(device, limit) }))
+}
+
+pub fn nvml_device_get_power_management_default_limit(
+    device: NvmlDevice,
+    default_limit: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_power_management_default_limit.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetPowerManagementDefaultLimit", &[&device, &default_limit], || unsafe {
+        func(device, default_limit)
+    }))
+}
+
+pub fn nvml_device_set_power_management_limit(
+    device: NvmlDevice,
+    limit: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_set_power_management_limit.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceSetPowerManagementLimit", &[&device, &limit], || unsafe { func(device, limit) }))
+}
+
+pub fn nvml_device_get_num_fans(
+    device: NvmlDevice,
+    num_fans: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_num_fans.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetNumFans", &[&device, &num_fans], || unsafe { func(device, num_fans) }))
+}
+
+pub fn nvml_device_get_architecture(
+    device: NvmlDevice,
+    arch: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_architecture.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetArchitecture", &[&device, &arch], || unsafe { func(device, arch) }))
+}
+
+pub fn nvml_device_get_fan_speed_v2(
+    device: NvmlDevice,
+    fan: c_uint,
+    speed: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_fan_speed_v2.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetFanSpeed_v2", &[&device, &fan, &speed], || unsafe { func(device, fan, speed) }))
+}
+
+pub fn nvml_device_get_fan_speed_rpm(
+    device: NvmlDevice,
+    info: *mut NvmlFanSpeedInfo,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_fan_speed_rpm.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetFanSpeedRPM", &[&device, &info], || unsafe { func(device, info) }))
+}
+
+pub fn nvml_device_get_min_max_fan_speed(
+    device: NvmlDevice,
+    min_speed: *mut c_uint,
+    max_speed: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_min_max_fan_speed.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetMinMaxFanSpeed", &[&device, &min_speed, &max_speed], || unsafe {
+        func(device, min_speed, max_speed)
+    }))
+}
+
+pub fn nvml_device_get_fan_control_policy_v2(
+    device: NvmlDevice,
+    fan: c_uint,
+    policy: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_fan_control_policy_v2.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetFanControlPolicy_v2", &[&device, &fan, &policy], || unsafe {
+        func(device, fan, policy)
+    }))
+}
+
+pub fn nvml_device_set_fan_control_policy(
+    device: NvmlDevice,
+    fan: c_uint,
+    policy: NvmlFanControlPolicy,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_set_fan_control_policy.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceSetFanControlPolicy", &[&device, &fan, &policy], || unsafe { func(device, fan, policy) }))
+}
+
+pub fn nvml_device_set_fan_speed_v2(
+    device: NvmlDevice,
+    fan: c_uint,
+    speed: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_set_fan_speed_v2.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceSetFanSpeed_v2", &[&device, &fan, &speed], || unsafe { func(device, fan, speed) }))
+}
+
+pub fn nvml_device_set_default_fan_speed_v2(
+    device: NvmlDevice,
+    fan: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_set_default_fan_speed_v2.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceSetDefaultFanSpeed_v2", &[&device, &fan], || unsafe { func(device, fan) }))
+}
+
+pub fn nvml_device_get_utilization_rates(
+    device: NvmlDevice,
+    utilization: *mut NvmlUtilization,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_utilization_rates.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetUtilizationRates", &[&device, &utilization], || unsafe { func(device, utilization) }))
+}
+
+pub fn nvml_device_get_memory_info(device: NvmlDevice, memory: *mut NvmlMemory) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_memory_info.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetMemoryInfo", &[&device, &memory], || unsafe { func(device, memory) }))
+}
+
+pub fn nvml_device_get_virtualization_mode(
+    device: NvmlDevice,
+    mode: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_virtualization_mode.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetVirtualizationMode", &[&device, &mode], || unsafe { func(device, mode) }))
+}
+
+pub fn nvml_device_get_handle_by_uuid(
+    uuid: *const c_char,
+    device: *mut NvmlDevice,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_handle_by_uuid.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetHandleByUUID", &[&uuid, &device], || unsafe { func(uuid, device) }))
+}
+
+pub fn nvml_device_get_handle_by_pci_bus_id_v2(
+    pci_bus_id: *const c_char,
+    device: *mut NvmlDevice,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_handle_by_pci_bus_id_v2.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetHandleByPciBusId_v2", &[&pci_bus_id, &device], || unsafe { func(pci_bus_id, device) }))
+}
+
+pub fn nvml_device_get_uuid(
+    device: NvmlDevice,
+    uuid: *mut c_char,
+    length: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_uuid.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetUUID", &[&device, &uuid, &length], || unsafe { func(device, uuid, length) }))
+}
+
+pub fn nvml_device_get_vbios_version(
+    device: NvmlDevice,
+    version: *mut c_char,
+    length: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_vbios_version.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetVbiosVersion", &[&device, &version, &length], || unsafe {
+        func(device, version, length)
+    }))
+}
+
+pub fn nvml_device_get_serial(
+    device: NvmlDevice,
+    value: *mut c_char,
+    length: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_serial.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetSerial", &[&device, &value, &length], || unsafe { func(device, value, length) }))
+}
+
+pub fn nvml_device_get_board_part_number(
+    device: NvmlDevice,
+    value: *mut c_char,
+    length: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_board_part_number.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetBoardPartNumber", &[&device, &value, &length], || unsafe {
+        func(device, value, length)
+    }))
+}
+
+pub fn nvml_device_get_pci_info_v3(device: NvmlDevice, pci: *mut NvmlPciInfo) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_pci_info_v3.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetPciInfo_v3", &[&device, &pci], || unsafe { func(device, pci) }))
+}
+
+pub fn nvml_device_get_mig_mode(
+    device: NvmlDevice,
+    current: *mut c_uint,
+    pending: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_mig_mode.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetMigMode", &[&device, &current, &pending], || unsafe {
+        func(device, current, pending)
+    }))
+}
+
+pub fn nvml_device_get_inforom_version(
+    device: NvmlDevice,
+    object: c_uint,
+    version: *mut c_char,
+    length: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_inforom_version.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetInforomVersion", &[&device, &object, &version, &length], || unsafe {
+        func(device, object, version, length)
+    }))
+}
+
+pub fn nvml_device_get_current_clocks_event_reasons(
+    device: NvmlDevice,
+    reasons: *mut u64,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_current_clocks_event_reasons.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetCurrentClocksEventReasons", &[&device, &reasons], || unsafe { func(device, reasons) }))
+}
+
+pub fn nvml_device_get_performance_state(
+    device: NvmlDevice,
+    pstate: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_performance_state.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetPerformanceState", &[&device, &pstate], || unsafe { func(device, pstate) }))
+}
+
+pub fn nvml_device_get_persistence_mode(
+    device: NvmlDevice,
+    mode: *mut c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_persistence_mode.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetPersistenceMode", &[&device, &mode], || unsafe { func(device, mode) }))
+}
+
+pub fn nvml_device_set_persistence_mode(device: NvmlDevice, mode: c_uint) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_set_persistence_mode.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceSetPersistenceMode", &[&device, &mode], || unsafe { func(device, mode) }))
+}
+
+pub fn nvml_device_get_supported_event_types(
+    device: NvmlDevice,
+    event_types: *mut u64,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_get_supported_event_types.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceGetSupportedEventTypes", &[&device, &event_types], || unsafe {
+        func(device, event_types)
+    }))
+}
+
+pub fn nvml_device_register_events(
+    device: NvmlDevice,
+    event_types: u64,
+    set: NvmlEventSet,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.device_register_events.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlDeviceRegisterEvents", &[&device, &event_types, &set], || unsafe {
+        func(device, event_types, set)
+    }))
+}
+
+pub fn nvml_event_set_create(set: *mut NvmlEventSet) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.event_set_create.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlEventSetCreate", &[&set], || unsafe { func(set) }))
+}
+
+pub fn nvml_event_set_wait_v2(
+    set: NvmlEventSet,
+    data: *mut NvmlEventData,
+    timeout_ms: c_uint,
+) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.event_set_wait_v2.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlEventSetWait_v2", &[&set, &data, &timeout_ms], || unsafe { func(set, data, timeout_ms) }))
+}
+
+pub fn nvml_event_set_free(set: NvmlEventSet) -> Result<NvmlReturn, crate::nvml::NvmlError> {
+    let func = api()?.event_set_free.ok_or(crate::nvml::NvmlError::FunctionNotFound)?;
+    Ok(trace::call("nvmlEventSetFree", &[&set], || unsafe { func(set) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_library_candidates() {
+        let candidates = library_candidates(Some("aarch64-linux-gnu"), Some("/nfs/lib::/opt/lib"));
+        assert_eq!(
+            candidates,
+            [
+                "/nfs/lib/libnvidia-ml.so.1",
+                "/opt/lib/libnvidia-ml.so.1",
+                "/usr/lib/aarch64-linux-gnu/libnvidia-ml.so.1",
+                "/usr/lib64/libnvidia-ml.so.1",
+                "/usr/lib/libnvidia-ml.so.1",
+                "/nfs/lib/libnvidia-ml.so",
+                "/opt/lib/libnvidia-ml.so",
+                "/usr/lib/aarch64-linux-gnu/libnvidia-ml.so",
+                "/usr/lib64/libnvidia-ml.so",
+                "/usr/lib/libnvidia-ml.so",
+            ]
+        );
+        // Bare names would go to dlopen unprobed
+        assert!(library_candidates(None, None).iter().all(|name| name.starts_with('/')));
+        assert_eq!(library_candidates(None, None).len(), 4);
+    }
+
+    #[test]
+    fn test_probe_paths() {
+        let ld_cache = ["/srv/nfs/libnvidia-ml.so.1".to_string(), "/srv/nfs/libnvidia-ml.so".to_string()];
+        assert_eq!(
+            probe_paths("/usr/lib/libnvidia-ml.so.1", Some("/nfs/lib"), &ld_cache, None),
+            vec![PathBuf::from("/usr/lib/libnvidia-ml.so.1")]
+        );
+        assert_eq!(
+            probe_paths("libnvidia-ml.so.1", Some("/nfs/lib::/opt/lib"), &ld_cache, None),
+            vec![
+                PathBuf::from("/nfs/lib/libnvidia-ml.so.1"),
+                PathBuf::from("/opt/lib/libnvidia-ml.so.1"),
+                PathBuf::from("/srv/nfs/libnvidia-ml.so.1"),
+                PathBuf::from("/usr/lib64/libnvidia-ml.so.1"),
+                PathBuf::from("/usr/lib/libnvidia-ml.so.1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_ld_cache() {
+        let output = "4 libs found in cache `/etc/ld.so.cache'
+\tlibnvidia-ml.so.1 (libc6,x86-64) => /usr/lib/x86_64-linux-gnu/libnvidia-ml.so.1
+\tlibnvidia-ml.so.1 (libc6) => /usr/lib/i386-linux-gnu/libnvidia-ml.so.1
+\tlibnvidia-ml.so.1 (libc6,AArch64) => /usr/lib/aarch64-linux-gnu/libnvidia-ml.so.1
+\tlibz.so.1 (libc6,x86-64) => /lib/x86_64-linux-gnu/libz.so.1
+";
+        assert_eq!(
+            parse_ld_cache(output, Some("x86-64")),
+            vec!["/usr/lib/x86_64-linux-gnu/libnvidia-ml.so.1".to_string()]
+        );
+        assert_eq!(parse_ld_cache(output, None).len(), 3);
+    }
+
+    #[test]
+    fn test_resolve_first() {
+        let lib = unsafe { Library::new("libc.so.6") }.unwrap();
+        let found = resolve_first::<unsafe extern "C" fn() -> c_int>(&lib, &["nvmlNotExported", "getpid"]);
+        assert_eq!(found.map(|(_, name)| name), Some("getpid"));
+        assert!(resolve_first::<unsafe extern "C" fn()>(&lib, &["nvmlNotExported"]).is_none());
+    }
+
+    #[test]
+    fn test_symbol_list_covers_every_binding() {
+        let source = include_str!("loader.rs");
+        for symbol in source.split("trace::call(\"").skip(1).filter_map(|rest| rest.split('"').next()) {
+            assert!(SYMBOLS.contains(&symbol), "{symbol} missing from the NvmlApi table");
+        }
+    }
+}
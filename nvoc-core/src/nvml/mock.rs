@@ -0,0 +1,583 @@
+//! Simulated NVML for running nvoc without NVIDIA hardware
+//!
+//! With `NVOC_MOCK=1` the loader fills its function table from this module
+//! instead of opening libnvidia-ml, so everything above the loader runs
+//! unchanged against RTX 5090-like GPUs. Settings written through the mock
+//! are read back, and with `NVOC_MOCK_STATE` they are kept in a JSON file
+//! between runs, so an apply followed by `nvoc reset` can be tested. The
+//! simulated machine then has system files of its own too, under the
+//! state file's directory (see `paths::system`).
+//!
+//! - `NVOC_MOCK_GPUS` sets the number of GPUs, 1 by default
+//! - `NVOC_MOCK_FAIL` makes functions fail, as a comma-separated list of
+//!   NVML function names with an optional `=CODE`, e.g.
+//!   `nvmlDeviceSetClockOffsets=4` for `NVML_ERROR_NO_PERMISSION`; without a
+//!   code they return `NVML_ERROR_NOT_SUPPORTED`
+//!
+//! Functions the mock does not simulate are missing, as on an old driver.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use libc::{c_char, c_int, c_uint, c_void};
+use serde::{Deserialize, Serialize};
+
+use crate::nvml::loader::NvmlApi;
+use crate::nvml::types::{
+    NvmlClockOffset, NvmlClockType, NvmlDevice, NvmlFanControlPolicy, NvmlMemory, NvmlPciInfo, NvmlReturn, NvmlUtilization,
+    NVML_DEVICE_ARCH_BLACKWELL, NVML_ERROR_INSUFFICIENT_SIZE, NVML_ERROR_INVALID_ARGUMENT, NVML_ERROR_NOT_SUPPORTED,
+    NVML_POWER_SOURCE_AC,
+};
+use crate::nvml::NVML_SUCCESS;
+
+// The unit-test GPU in `backend::fake` reports the same hardware, so
+// these are shared with it
+pub(crate) const NAME: &str = "NVIDIA GeForce RTX 5090";
+pub(crate) const DRIVER_VERSION: &str = "575.64.05";
+pub(crate) const GRAPHICS_OFFSET_RANGE: (i32, i32) = (-1000, 1000);
+pub(crate) const MEMORY_OFFSET_RANGE: (i32, i32) = (-2000, 3000);
+pub(crate) const POWER_LIMIT_RANGE_MW: (u32, u32) = (400_000, 600_000);
+pub(crate) const DEFAULT_POWER_LIMIT_MW: u32 = 575_000;
+pub(crate) const MEMORY_CLOCKS: [c_uint; 3] = [14001, 810, 405];
+pub(crate) const GRAPHICS_CLOCKS: [c_uint; 4] = [3105, 2407, 1500, 180];
+pub(crate) const FAN_RANGE: (c_uint, c_uint) = (30, 100);
+pub(crate) const FAN_COUNT: c_uint = 2;
+pub(crate) const TEMPERATURE_C: c_uint = 45;
+pub(crate) const POWER_USAGE_MW: c_uint = 80_000;
+const VRAM_BYTES: u64 = 32 << 30;
+
+/// Settings of one simulated GPU
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct MockGpu {
+    /// Offsets by pstate, in MHz
+    graphics_offsets: [i32; 16],
+    memory_offsets: [i32; 16],
+    power_limit_mw: u32,
+    locked_clocks: Option<(u32, u32)>,
+    locked_memory_clocks: Option<(u32, u32)>,
+    applications_clocks: Option<(u32, u32)>,
+    /// Fixed duty cycle, `None` under the driver's control
+    fan_speed: Option<u32>,
+    persistence: bool,
+    thermal_target: u32,
+}
+
+impl Default for MockGpu {
+    fn default() -> Self {
+        Self {
+            graphics_offsets: [0; 16],
+            memory_offsets: [0; 16],
+            power_limit_mw: DEFAULT_POWER_LIMIT_MW,
+            locked_clocks: None,
+            locked_memory_clocks: None,
+            applications_clocks: None,
+            fan_speed: None,
+            persistence: false,
+            thermal_target: 83,
+        }
+    }
+}
+
+static GPUS: Mutex<Vec<MockGpu>> = Mutex::new(Vec::new());
+static FAILURES: OnceLock<Vec<(String, NvmlReturn)>> = OnceLock::new();
+
+/// Whether `NVOC_MOCK` asks for the simulated GPUs
+pub fn enabled() -> bool {
+    std::env::var("NVOC_MOCK").is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+fn state_file() -> Option<PathBuf> {
+    std::env::var_os("NVOC_MOCK_STATE").map(PathBuf::from)
+}
+
+/// Directory standing in for `/` while the simulated GPUs keep their
+/// state in a file: the one holding `NVOC_MOCK_STATE`
+pub fn root() -> Option<PathBuf> {
+    let file = state_file().filter(|_| enabled())?;
+    Some(file.parent().map(Path::to_path_buf).unwrap_or_default())
+}
+
+fn gpu_count() -> usize {
+    std::env::var("NVOC_MOCK_GPUS").ok().and_then(|count| count.parse().ok()).unwrap_or(1)
+}
+
+/// Functions to fail and the code each returns, from `NVOC_MOCK_FAIL`
+fn parse_failures(spec: &str) -> Result<Vec<(String, NvmlReturn)>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((symbol, code)) => code
+                .parse()
+                .map(|code| (symbol.to_string(), code))
+                .map_err(|_| format!("invalid return code in NVOC_MOCK_FAIL entry '{entry}'")),
+            None => Ok((entry.to_string(), NVML_ERROR_NOT_SUPPORTED)),
+        })
+        .collect()
+}
+
+fn injected(symbol: &str) -> Option<NvmlReturn> {
+    let failures = FAILURES.get_or_init(|| {
+        let spec = std::env::var("NVOC_MOCK_FAIL").unwrap_or_default();
+        parse_failures(&spec).unwrap_or_else(|e| {
+            eprintln!("warning[mock]: {e}, ignoring it");
+            Vec::new()
+        })
+    });
+    failures.iter().find(|(failing, _)| failing == symbol).map(|&(_, code)| code)
+}
+
+/// Start from the state file when there is one, else from stock settings
+fn load_state() -> Vec<MockGpu> {
+    let mut gpus: Vec<MockGpu> = state_file()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    gpus.resize(gpu_count(), MockGpu::default());
+    gpus
+}
+
+fn save_state(gpus: &[MockGpu]) {
+    let Some(path) = state_file() else {
+        return;
+    };
+    if let Ok(json) = serde_json::to_string_pretty(gpus) {
+        if let Err(e) = std::fs::write(&path, json) {
+            eprintln!("warning[mock]: cannot write {}: {e}", path.display());
+        }
+    }
+}
+
+fn handle(index: usize) -> NvmlDevice {
+    (index + 1) as *mut c_void
+}
+
+/// UUID of the simulated GPU at `index`
+pub(crate) fn uuid_of(index: usize) -> String {
+    format!("GPU-00000000-0000-0000-0000-{:012x}", index + 1)
+}
+
+/// Run `f` on the GPU behind `device`, saving the state when `f` changed it
+fn with_gpu(symbol: &str, device: NvmlDevice, f: impl FnOnce(&mut MockGpu) -> NvmlReturn) -> NvmlReturn {
+    if let Some(code) = injected(symbol) {
+        return code;
+    }
+    let Ok(mut gpus) = GPUS.lock() else {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    };
+    let Some(gpu) = (device as usize).checked_sub(1).and_then(|index| gpus.get_mut(index)) else {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    };
+    let before = gpu.clone();
+    let code = f(gpu);
+    if *gpu != before {
+        save_state(&gpus);
+    }
+    code
+}
+
+/// Like `with_gpu` for functions that only read
+fn read_gpu(symbol: &str, device: NvmlDevice, f: impl FnOnce(&MockGpu)) -> NvmlReturn {
+    with_gpu(symbol, device, |gpu| {
+        f(gpu);
+        NVML_SUCCESS
+    })
+}
+
+unsafe fn write_str(value: &str, buffer: *mut c_char, length: c_uint) -> NvmlReturn {
+    if value.len() >= length as usize {
+        return NVML_ERROR_INSUFFICIENT_SIZE;
+    }
+    std::ptr::copy_nonoverlapping(value.as_ptr().cast(), buffer, value.len());
+    *buffer.add(value.len()) = 0;
+    NVML_SUCCESS
+}
+
+unsafe fn write_clocks(clocks: &[c_uint], count: *mut c_uint, out: *mut c_uint) -> NvmlReturn {
+    let room = *count as usize;
+    *count = clocks.len() as c_uint;
+    if room < clocks.len() {
+        return NVML_ERROR_INSUFFICIENT_SIZE;
+    }
+    std::ptr::copy_nonoverlapping(clocks.as_ptr(), out, clocks.len());
+    NVML_SUCCESS
+}
+
+unsafe extern "C" fn init() -> NvmlReturn {
+    if let Some(code) = injected("nvmlInit_v2") {
+        return code;
+    }
+    // Settings survive a shutdown and init within one run, as on real GPUs
+    if let Ok(mut gpus) = GPUS.lock() {
+        if gpus.is_empty() || state_file().is_some() {
+            *gpus = load_state();
+        }
+    }
+    NVML_SUCCESS
+}
+
+unsafe extern "C" fn shutdown() -> NvmlReturn {
+    injected("nvmlShutdown").unwrap_or(NVML_SUCCESS)
+}
+
+unsafe extern "C" fn driver_version(version: *mut c_char, length: c_uint) -> NvmlReturn {
+    injected("nvmlSystemGetDriverVersion").unwrap_or_else(|| write_str(DRIVER_VERSION, version, length))
+}
+
+unsafe extern "C" fn device_count(count: *mut c_uint) -> NvmlReturn {
+    if let Some(code) = injected("nvmlDeviceGetCount_v2") {
+        return code;
+    }
+    *count = GPUS.lock().map_or(0, |gpus| gpus.len()) as c_uint;
+    NVML_SUCCESS
+}
+
+unsafe extern "C" fn handle_by_index(index: c_uint, device: *mut NvmlDevice) -> NvmlReturn {
+    if let Some(code) = injected("nvmlDeviceGetHandleByIndex_v2") {
+        return code;
+    }
+    if index as usize >= GPUS.lock().map_or(0, |gpus| gpus.len()) {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    *device = handle(index as usize);
+    NVML_SUCCESS
+}
+
+unsafe extern "C" fn name(device: NvmlDevice, name: *mut c_char, length: c_uint) -> NvmlReturn {
+    with_gpu("nvmlDeviceGetName", device, |_| write_str(NAME, name, length))
+}
+
+unsafe extern "C" fn uuid(device: NvmlDevice, uuid: *mut c_char, length: c_uint) -> NvmlReturn {
+    let value = uuid_of((device as usize).saturating_sub(1));
+    with_gpu("nvmlDeviceGetUUID", device, |_| write_str(&value, uuid, length))
+}
+
+unsafe extern "C" fn pci_info(device: NvmlDevice, pci: *mut NvmlPciInfo) -> NvmlReturn {
+    let bus = device as usize as c_uint;
+    read_gpu("nvmlDeviceGetPciInfo_v3", device, |_| {
+        let mut info = NvmlPciInfo { bus, pciDeviceId: 0x2b85_10de, ..Default::default() };
+        let bus_id = format!("00000000:{bus:02x}:00.0");
+        for (dst, src) in info.busId.iter_mut().zip(bus_id.bytes()) {
+            *dst = src as c_char;
+        }
+        *pci = info;
+    })
+}
+
+unsafe extern "C" fn architecture(device: NvmlDevice, arch: *mut c_uint) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetArchitecture", device, |_| *arch = NVML_DEVICE_ARCH_BLACKWELL)
+}
+
+unsafe extern "C" fn get_clock_offsets(device: NvmlDevice, offset: *mut NvmlClockOffset) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetClockOffsets", device, |gpu| {
+        let offset = &mut *offset;
+        let ((min, max), offsets) = match offset.type_ {
+            NvmlClockType::Graphics => (GRAPHICS_OFFSET_RANGE, &gpu.graphics_offsets),
+            NvmlClockType::Memory => (MEMORY_OFFSET_RANGE, &gpu.memory_offsets),
+        };
+        offset.clockOffsetMHz = offsets[offset.pstate as usize];
+        offset.minClockOffsetMHz = min;
+        offset.maxClockOffsetMHz = max;
+    })
+}
+
+unsafe extern "C" fn set_clock_offsets(device: NvmlDevice, offset: *const NvmlClockOffset) -> NvmlReturn {
+    let offset = *offset;
+    with_gpu("nvmlDeviceSetClockOffsets", device, |gpu| {
+        let ((min, max), offsets) = match offset.type_ {
+            NvmlClockType::Graphics => (GRAPHICS_OFFSET_RANGE, &mut gpu.graphics_offsets),
+            NvmlClockType::Memory => (MEMORY_OFFSET_RANGE, &mut gpu.memory_offsets),
+        };
+        if !(min..=max).contains(&offset.clockOffsetMHz) {
+            return NVML_ERROR_INVALID_ARGUMENT;
+        }
+        offsets[offset.pstate as usize] = offset.clockOffsetMHz;
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn set_locked_clocks(device: NvmlDevice, min: c_uint, max: c_uint) -> NvmlReturn {
+    with_gpu("nvmlDeviceSetGpuLockedClocks", device, |gpu| {
+        gpu.locked_clocks = Some((min, max));
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn reset_locked_clocks(device: NvmlDevice) -> NvmlReturn {
+    with_gpu("nvmlDeviceResetGpuLockedClocks", device, |gpu| {
+        gpu.locked_clocks = None;
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn set_locked_memory_clocks(device: NvmlDevice, min: c_uint, max: c_uint) -> NvmlReturn {
+    with_gpu("nvmlDeviceSetMemoryLockedClocks", device, |gpu| {
+        gpu.locked_memory_clocks = Some((min, max));
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn reset_locked_memory_clocks(device: NvmlDevice) -> NvmlReturn {
+    with_gpu("nvmlDeviceResetMemoryLockedClocks", device, |gpu| {
+        gpu.locked_memory_clocks = None;
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn set_applications_clocks(device: NvmlDevice, memory: c_uint, graphics: c_uint) -> NvmlReturn {
+    with_gpu("nvmlDeviceSetApplicationsClocks", device, |gpu| {
+        gpu.applications_clocks = Some((memory, graphics));
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn reset_applications_clocks(device: NvmlDevice) -> NvmlReturn {
+    with_gpu("nvmlDeviceResetApplicationsClocks", device, |gpu| {
+        gpu.applications_clocks = None;
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn clock_info(device: NvmlDevice, clock_type: NvmlClockType, clock: *mut c_uint) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetClockInfo", device, |gpu| {
+        *clock = match clock_type {
+            NvmlClockType::Graphics => (2407 + gpu.graphics_offsets[0]).max(0) as c_uint,
+            NvmlClockType::Memory => (14001 + gpu.memory_offsets[0]).max(0) as c_uint,
+        }
+    })
+}
+
+unsafe extern "C" fn supported_memory_clocks(device: NvmlDevice, count: *mut c_uint, clocks: *mut c_uint) -> NvmlReturn {
+    with_gpu("nvmlDeviceGetSupportedMemoryClocks", device, |_| write_clocks(&MEMORY_CLOCKS, count, clocks))
+}
+
+unsafe extern "C" fn supported_graphics_clocks(
+    device: NvmlDevice,
+    _memory_clock: c_uint,
+    count: *mut c_uint,
+    clocks: *mut c_uint,
+) -> NvmlReturn {
+    with_gpu("nvmlDeviceGetSupportedGraphicsClocks", device, |_| write_clocks(&GRAPHICS_CLOCKS, count, clocks))
+}
+
+unsafe extern "C" fn temperature(device: NvmlDevice, _sensor: c_uint, temp: *mut c_uint) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetTemperature", device, |_| *temp = TEMPERATURE_C)
+}
+
+unsafe extern "C" fn temperature_threshold(device: NvmlDevice, threshold: c_uint, temp: *mut c_uint) -> NvmlReturn {
+    with_gpu("nvmlDeviceGetTemperatureThreshold", device, |gpu| {
+        *temp = match threshold {
+            0 => 98,
+            1 => 95,
+            2 => 105,
+            3 => 90,
+            4 => 65,
+            5 => gpu.thermal_target,
+            6 => 90,
+            _ => return NVML_ERROR_INVALID_ARGUMENT,
+        };
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn set_temperature_threshold(device: NvmlDevice, threshold: c_uint, temp: *mut c_int) -> NvmlReturn {
+    let temp = *temp as c_uint;
+    with_gpu("nvmlDeviceSetTemperatureThreshold", device, |gpu| match threshold {
+        5 if (65..=90).contains(&temp) => {
+            gpu.thermal_target = temp;
+            NVML_SUCCESS
+        }
+        5 => NVML_ERROR_INVALID_ARGUMENT,
+        _ => NVML_ERROR_NOT_SUPPORTED,
+    })
+}
+
+unsafe extern "C" fn power_usage(device: NvmlDevice, power: *mut c_uint) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetPowerUsage", device, |_| *power = POWER_USAGE_MW)
+}
+
+unsafe extern "C" fn power_limit_constraints(device: NvmlDevice, min: *mut c_uint, max: *mut c_uint) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetPowerManagementLimitConstraints", device, |_| {
+        (*min, *max) = POWER_LIMIT_RANGE_MW;
+    })
+}
+
+unsafe extern "C" fn power_limit(device: NvmlDevice, limit: *mut c_uint) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetPowerManagementLimit", device, |gpu| *limit = gpu.power_limit_mw)
+}
+
+unsafe extern "C" fn default_power_limit(device: NvmlDevice, limit: *mut c_uint) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetPowerManagementDefaultLimit", device, |_| *limit = DEFAULT_POWER_LIMIT_MW)
+}
+
+unsafe extern "C" fn set_power_limit(device: NvmlDevice, limit: c_uint) -> NvmlReturn {
+    with_gpu("nvmlDeviceSetPowerManagementLimit", device, |gpu| {
+        let (min, max) = POWER_LIMIT_RANGE_MW;
+        if !(min..=max).contains(&limit) {
+            return NVML_ERROR_INVALID_ARGUMENT;
+        }
+        gpu.power_limit_mw = limit;
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn power_source(device: NvmlDevice, source: *mut c_uint) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetPowerSource", device, |_| *source = NVML_POWER_SOURCE_AC as c_uint)
+}
+
+unsafe extern "C" fn num_fans(device: NvmlDevice, count: *mut c_uint) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetNumFans", device, |_| *count = FAN_COUNT)
+}
+
+unsafe extern "C" fn fan_speed(device: NvmlDevice, fan: c_uint, speed: *mut c_uint) -> NvmlReturn {
+    with_gpu("nvmlDeviceGetFanSpeed_v2", device, |gpu| {
+        if fan >= FAN_COUNT {
+            return NVML_ERROR_INVALID_ARGUMENT;
+        }
+        *speed = gpu.fan_speed.unwrap_or(FAN_RANGE.0);
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn min_max_fan_speed(device: NvmlDevice, min: *mut c_uint, max: *mut c_uint) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetMinMaxFanSpeed", device, |_| (*min, *max) = FAN_RANGE)
+}
+
+unsafe extern "C" fn fan_control_policy(device: NvmlDevice, fan: c_uint, policy: *mut c_uint) -> NvmlReturn {
+    with_gpu("nvmlDeviceGetFanControlPolicy_v2", device, |gpu| {
+        if fan >= FAN_COUNT {
+            return NVML_ERROR_INVALID_ARGUMENT;
+        }
+        *policy = gpu.fan_speed.is_some() as c_uint;
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn set_fan_control_policy(device: NvmlDevice, fan: c_uint, policy: NvmlFanControlPolicy) -> NvmlReturn {
+    with_gpu("nvmlDeviceSetFanControlPolicy", device, |gpu| {
+        if fan >= FAN_COUNT {
+            return NVML_ERROR_INVALID_ARGUMENT;
+        }
+        match policy {
+            NvmlFanControlPolicy::TemperatureContinuousSw => gpu.fan_speed = None,
+            NvmlFanControlPolicy::Manual => gpu.fan_speed = gpu.fan_speed.or(Some(FAN_RANGE.0)),
+        }
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn set_fan_speed(device: NvmlDevice, fan: c_uint, speed: c_uint) -> NvmlReturn {
+    with_gpu("nvmlDeviceSetFanSpeed_v2", device, |gpu| {
+        if fan >= FAN_COUNT || !(FAN_RANGE.0..=FAN_RANGE.1).contains(&speed) {
+            return NVML_ERROR_INVALID_ARGUMENT;
+        }
+        gpu.fan_speed = Some(speed);
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn set_default_fan_speed(device: NvmlDevice, fan: c_uint) -> NvmlReturn {
+    with_gpu("nvmlDeviceSetDefaultFanSpeed_v2", device, |gpu| {
+        if fan >= FAN_COUNT {
+            return NVML_ERROR_INVALID_ARGUMENT;
+        }
+        gpu.fan_speed = None;
+        NVML_SUCCESS
+    })
+}
+
+unsafe extern "C" fn utilization(device: NvmlDevice, utilization: *mut NvmlUtilization) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetUtilizationRates", device, |_| *utilization = NvmlUtilization { gpu: 3, memory: 1 })
+}
+
+unsafe extern "C" fn memory_info(device: NvmlDevice, memory: *mut NvmlMemory) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetMemoryInfo", device, |_| {
+        *memory = NvmlMemory { total: VRAM_BYTES, free: VRAM_BYTES - (512 << 20), used: 512 << 20 }
+    })
+}
+
+unsafe extern "C" fn performance_state(device: NvmlDevice, pstate: *mut c_uint) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetPerformanceState", device, |_| *pstate = 8)
+}
+
+unsafe extern "C" fn clocks_event_reasons(device: NvmlDevice, reasons: *mut u64) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetCurrentClocksEventReasons", device, |_| *reasons = 0)
+}
+
+unsafe extern "C" fn persistence_mode(device: NvmlDevice, mode: *mut c_uint) -> NvmlReturn {
+    read_gpu("nvmlDeviceGetPersistenceMode", device, |gpu| *mode = gpu.persistence as c_uint)
+}
+
+unsafe extern "C" fn set_persistence_mode(device: NvmlDevice, mode: c_uint) -> NvmlReturn {
+    with_gpu("nvmlDeviceSetPersistenceMode", device, |gpu| {
+        gpu.persistence = mode != 0;
+        NVML_SUCCESS
+    })
+}
+
+/// Function table pointing at the simulated GPUs
+pub fn api() -> NvmlApi {
+    let mut api = NvmlApi::empty();
+    api.init_v2 = Some(init);
+    api.shutdown = Some(shutdown);
+    api.system_get_driver_version = Some(driver_version);
+    api.device_get_count_v2 = Some(device_count);
+    api.device_get_handle_by_index_v2 = Some(handle_by_index);
+    api.device_get_name = Some(name);
+    api.device_get_uuid = Some(uuid);
+    api.device_get_pci_info_v3 = Some(pci_info);
+    api.device_get_architecture = Some(architecture);
+    api.device_get_clock_offsets = Some(get_clock_offsets);
+    api.device_set_clock_offsets = Some(set_clock_offsets);
+    api.device_set_gpu_locked_clocks = Some(set_locked_clocks);
+    api.device_reset_gpu_locked_clocks = Some(reset_locked_clocks);
+    api.device_set_memory_locked_clocks = Some(set_locked_memory_clocks);
+    api.device_reset_memory_locked_clocks = Some(reset_locked_memory_clocks);
+    api.device_set_applications_clocks = Some(set_applications_clocks);
+    api.device_reset_applications_clocks = Some(reset_applications_clocks);
+    api.device_get_clock_info = Some(clock_info);
+    api.device_get_supported_memory_clocks = Some(supported_memory_clocks);
+    api.device_get_supported_graphics_clocks = Some(supported_graphics_clocks);
+    api.device_get_temperature = Some(temperature);
+    api.device_get_temperature_threshold = Some(temperature_threshold);
+    api.device_set_temperature_threshold = Some(set_temperature_threshold);
+    api.device_get_power_usage = Some(power_usage);
+    api.device_get_power_management_limit_constraints = Some(power_limit_constraints);
+    api.device_get_power_management_limit = Some(power_limit);
+    api.device_get_power_management_default_limit = Some(default_power_limit);
+    api.device_set_power_management_limit = Some(set_power_limit);
+    api.device_get_power_source = Some(power_source);
+    api.device_get_num_fans = Some(num_fans);
+    api.device_get_fan_speed_v2 = Some(fan_speed);
+    api.device_get_min_max_fan_speed = Some(min_max_fan_speed);
+    api.device_get_fan_control_policy_v2 = Some(fan_control_policy);
+    api.device_set_fan_control_policy = Some(set_fan_control_policy);
+    api.device_set_fan_speed_v2 = Some(set_fan_speed);
+    api.device_set_default_fan_speed_v2 = Some(set_default_fan_speed);
+    api.device_get_utilization_rates = Some(utilization);
+    api.device_get_memory_info = Some(memory_info);
+    api.device_get_performance_state = Some(performance_state);
+    api.device_get_current_clocks_event_reasons = Some(clocks_event_reasons);
+    api.device_get_persistence_mode = Some(persistence_mode);
+    api.device_set_persistence_mode = Some(set_persistence_mode);
+    api.mark_resolved();
+    api
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_failures() {
+        assert_eq!(
+            parse_failures("nvmlDeviceSetClockOffsets, nvmlDeviceGetPowerUsage=15").unwrap(),
+            [
+                ("nvmlDeviceSetClockOffsets".to_string(), NVML_ERROR_NOT_SUPPORTED),
+                ("nvmlDeviceGetPowerUsage".to_string(), 15)
+            ]
+        );
+        assert!(parse_failures("").unwrap().is_empty());
+        assert!(parse_failures("nvmlInit_v2=lost").is_err());
+    }
+}
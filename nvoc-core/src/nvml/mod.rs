@@ -0,0 +1,870 @@
+//! NVML wrapper for GPU overclocking operations
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::constants::{buffers, hardware};
+use libc::{c_char, c_int, c_uint};
+use std::ffi::{CStr, CString};
+use std::ptr;
+
+pub mod backend;
+mod device;
+pub mod error;
+pub(crate) mod loader;
+pub(crate) mod mock;
+pub mod types;
+
+pub use backend::NvmlBackend;
+pub use device::Device;
+pub use loader::set_library_path;
+pub(crate) use device::Session;
+pub use error::{NvmlError, Result};
+pub use types::{
+    NVML_CLOCKS_EVENT_REASON_APPLICATIONS_CLOCKS_SETTING, NVML_CLOCKS_EVENT_REASON_DISPLAY_CLOCK_SETTING,
+    NVML_CLOCKS_EVENT_REASON_GPU_IDLE, NVML_CLOCKS_EVENT_REASON_SW_POWER_CAP, NVML_CLOCKS_EVENT_REASON_SYNC_BOOST,
+    FieldValue, GpuArchitecture, NvmlClockOffset, NvmlFieldValue, NvmlClockType, NvmlEventData, NvmlEventSet, NvmlEccCounterType, NvmlFanControlPolicy, NvmlInforomObject, NvmlMemory, NvmlMemoryErrorType, NvmlPageRetirementCause, NvmlPcieUtilCounter,
+    NvmlPciInfo, NvmlProcessInfo, RemappedRows, NVML_VALUE_NOT_AVAILABLE_ULL,
+    NvmlPerfState, NvmlTemperatureThreshold, NvmlUtilization, NVML_EVENT_TYPE_CLOCK, NVML_EVENT_TYPE_POWER_SOURCE_CHANGE, NVML_EVENT_TYPE_PSTATE,
+    NVML_EVENT_TYPE_XID_CRITICAL_ERROR, NVML_POWER_SOURCE_AC, NVML_POWER_SOURCE_BATTERY, NVML_POWER_SOURCE_UNDERSIZED,
+    NVML_CLOCKS_EVENT_REASON_HW_POWER_BRAKE_SLOWDOWN, NVML_CLOCKS_EVENT_REASON_HW_SLOWDOWN,
+    NVML_CLOCKS_EVENT_REASON_HW_THERMAL_SLOWDOWN, NVML_CLOCKS_EVENT_REASON_SW_THERMAL_SLOWDOWN,
+    NVML_DEVICE_NAME_BUFFER_SIZE, NVML_FI_DEV_MEMORY_TEMP, NVML_FI_DEV_TOTAL_ENERGY_CONSUMPTION, NVML_GPU_VIRTUALIZATION_MODE_HOST_VGPU,
+    NVML_GPU_VIRTUALIZATION_MODE_HOST_VSGA, NVML_GPU_VIRTUALIZATION_MODE_NONE,
+    NVML_GPU_VIRTUALIZATION_MODE_PASSTHROUGH, NVML_GPU_VIRTUALIZATION_MODE_VGPU, NVML_SUCCESS,
+};
+use types::{NvmlDevice, NvmlFanSpeedInfo, NvmlReturn, NVML_DEVICE_MIG_ENABLE, NVML_ERROR_INSUFFICIENT_SIZE};
+
+pub fn init() -> Result<()> {
+    let result = loader::nvml_init_v2()?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn shutdown() -> Result<()> {
+    let result = loader::nvml_shutdown()?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn system_get_driver_version() -> Result<String> {
+    let mut version = [0 as c_char; buffers::DRIVER_VERSION_BUFFER_SIZE];
+    let result = loader::nvml_system_get_driver_version(
+        version.as_mut_ptr(),
+        buffers::DRIVER_VERSION_BUFFER_SIZE as c_uint,
+    )?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    unsafe {
+        let c_str = CStr::from_ptr(version.as_ptr());
+        Ok(c_str.to_string_lossy().to_string())
+    }
+}
+
+pub fn device_get_count() -> Result<u32> {
+    let mut device_count: c_uint = 0;
+    let result = loader::nvml_device_get_count_v2(&mut device_count)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(device_count)
+}
+
+pub(crate) fn device_get_handle_by_index(index: u32) -> Result<NvmlDevice> {
+    let mut device: NvmlDevice = ptr::null_mut();
+    let result = loader::nvml_device_get_handle_by_index_v2(index, &mut device)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(device)
+}
+
+pub(crate) fn device_get_handle_by_uuid(uuid: &str) -> Result<NvmlDevice> {
+    let uuid = CString::new(uuid).map_err(|_| NvmlError::InvalidArgument)?;
+    let mut device: NvmlDevice = ptr::null_mut();
+    let result = loader::nvml_device_get_handle_by_uuid(uuid.as_ptr(), &mut device)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(device)
+}
+
+pub fn device_get_name(device: &Device) -> Result<String> {
+    let mut name = [0 as c_char; NVML_DEVICE_NAME_BUFFER_SIZE];
+    let result = loader::nvml_device_get_name(
+        device.handle(),
+        name.as_mut_ptr(),
+        NVML_DEVICE_NAME_BUFFER_SIZE as c_uint,
+    )?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    unsafe {
+        let c_str = CStr::from_ptr(name.as_ptr());
+        Ok(c_str.to_string_lossy().to_string())
+    }
+}
+
+pub(crate) fn device_get_handle_by_pci_bus_id(pci_bus_id: &str) -> Result<NvmlDevice> {
+    let pci_bus_id = CString::new(pci_bus_id).map_err(|_| NvmlError::InvalidArgument)?;
+    let mut device: NvmlDevice = ptr::null_mut();
+    let result = loader::nvml_device_get_handle_by_pci_bus_id_v2(pci_bus_id.as_ptr(), &mut device)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(device)
+}
+
+pub fn device_get_uuid(device: &Device) -> Result<String> {
+    let mut uuid = [0 as c_char; buffers::DEVICE_UUID_BUFFER_SIZE];
+    let result = loader::nvml_device_get_uuid(
+        device.handle(),
+        uuid.as_mut_ptr(),
+        buffers::DEVICE_UUID_BUFFER_SIZE as c_uint,
+    )?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    unsafe {
+        let c_str = CStr::from_ptr(uuid.as_ptr());
+        Ok(c_str.to_string_lossy().to_string())
+    }
+}
+
+pub fn device_get_vbios_version(device: &Device) -> Result<String> {
+    let mut version = [0 as c_char; buffers::VBIOS_VERSION_BUFFER_SIZE];
+    let result = loader::nvml_device_get_vbios_version(
+        device.handle(),
+        version.as_mut_ptr(),
+        buffers::VBIOS_VERSION_BUFFER_SIZE as c_uint,
+    )?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    unsafe {
+        let c_str = CStr::from_ptr(version.as_ptr());
+        Ok(c_str.to_string_lossy().to_string())
+    }
+}
+
+/// Board serial number, printed on the card. Most GeForce cards do not
+/// report one.
+pub fn device_get_serial(device: &Device) -> Result<String> {
+    let mut value = [0 as c_char; buffers::SERIAL_BUFFER_SIZE];
+    let result = loader::nvml_device_get_serial(
+        device.handle(),
+        value.as_mut_ptr(),
+        buffers::SERIAL_BUFFER_SIZE as c_uint,
+    )?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    unsafe {
+        let c_str = CStr::from_ptr(value.as_ptr());
+        Ok(c_str.to_string_lossy().to_string())
+    }
+}
+
+pub fn device_get_board_part_number(device: &Device) -> Result<String> {
+    let mut value = [0 as c_char; buffers::PART_NUMBER_BUFFER_SIZE];
+    let result = loader::nvml_device_get_board_part_number(
+        device.handle(),
+        value.as_mut_ptr(),
+        buffers::PART_NUMBER_BUFFER_SIZE as c_uint,
+    )?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    unsafe {
+        let c_str = CStr::from_ptr(value.as_ptr());
+        Ok(c_str.to_string_lossy().to_string())
+    }
+}
+
+/// PCI address as `domain:bus:device.function`
+pub fn device_get_pci_bus_id(device: &Device) -> Result<String> {
+    let mut pci = NvmlPciInfo::default();
+    let result = loader::nvml_device_get_pci_info_v3(device.handle(), &mut pci)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    unsafe {
+        let c_str = CStr::from_ptr(pci.busId.as_ptr());
+        Ok(c_str.to_string_lossy().to_string())
+    }
+}
+
+/// Whether MIG is enabled now and after the next GPU reset, on GPUs that
+/// support it
+pub fn device_get_mig_mode(device: &Device) -> Result<(bool, bool)> {
+    let (mut current, mut pending): (c_uint, c_uint) = (0, 0);
+    let result = loader::nvml_device_get_mig_mode(device.handle(), &mut current, &mut pending)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok((current == NVML_DEVICE_MIG_ENABLE, pending == NVML_DEVICE_MIG_ENABLE))
+}
+
+/// Version of one InfoROM object, e.g. `1.1`
+pub fn device_get_inforom_version(device: &Device, object: NvmlInforomObject) -> Result<String> {
+    let mut version = [0 as c_char; buffers::INFOROM_VERSION_BUFFER_SIZE];
+    let result = loader::nvml_device_get_inforom_version(
+        device.handle(),
+        object as c_uint,
+        version.as_mut_ptr(),
+        buffers::INFOROM_VERSION_BUFFER_SIZE as c_uint,
+    )?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    unsafe {
+        let c_str = CStr::from_ptr(version.as_ptr());
+        Ok(c_str.to_string_lossy().to_string())
+    }
+}
+
+pub fn device_get_clock_offsets(
+    device: &Device,
+    clock_type: NvmlClockType,
+    perf_state: NvmlPerfState,
+) -> Result<NvmlClockOffset> {
+    let mut offset = NvmlClockOffset::new_v1(clock_type, perf_state, 0);
+    let result = loader::nvml_device_get_clock_offsets(device.handle(), &mut offset)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(offset)
+}
+
+pub fn device_set_clock_offset(
+    device: &Device,
+    clock_type: NvmlClockType,
+    perf_state: NvmlPerfState,
+    offset: i32,
+) -> Result<()> {
+    let clock_offset = NvmlClockOffset::new_v1(clock_type, perf_state, offset);
+    let result = loader::nvml_device_set_clock_offsets(device.handle(), &clock_offset)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_set_memory_vf_offset(device: &Device, offset: i32) -> Result<()> {
+    let result = loader::nvml_device_set_mem_clk_vf_offset(device.handle(), offset)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_get_memory_vf_offset(device: &Device) -> Result<i32> {
+    let mut offset: c_int = 0;
+    let result = loader::nvml_device_get_mem_clk_vf_offset(device.handle(), &mut offset)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(offset)
+}
+
+/// Allowed memory clock offset range as `(min, max)` in MHz
+pub fn device_get_memory_vf_offset_range(device: &Device) -> Result<(i32, i32)> {
+    let (mut min, mut max): (c_int, c_int) = (0, 0);
+    let result = loader::nvml_device_get_mem_clk_min_max_vf_offset(device.handle(), &mut min, &mut max)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok((min, max))
+}
+
+/// Graphics clock offset through the VF curve interface used before Blackwell
+pub fn device_set_gpc_vf_offset(device: &Device, offset: i32) -> Result<()> {
+    let result = loader::nvml_device_set_gpc_clk_vf_offset(device.handle(), offset)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_get_gpc_vf_offset(device: &Device) -> Result<i32> {
+    let mut offset: c_int = 0;
+    let result = loader::nvml_device_get_gpc_clk_vf_offset(device.handle(), &mut offset)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(offset)
+}
+
+/// Allowed graphics clock offset range as `(min, max)` in MHz
+pub fn device_get_gpc_vf_offset_range(device: &Device) -> Result<(i32, i32)> {
+    let (mut min, mut max): (c_int, c_int) = (0, 0);
+    let result = loader::nvml_device_get_gpc_clk_min_max_vf_offset(device.handle(), &mut min, &mut max)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok((min, max))
+}
+
+pub fn device_set_gpu_locked_clocks(
+    device: &Device,
+    min_gpu_clock: u32,
+    max_gpu_clock: u32,
+) -> Result<()> {
+    let result = loader::nvml_device_set_gpu_locked_clocks(device.handle(), min_gpu_clock, max_gpu_clock)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_set_memory_locked_clocks(
+    device: &Device,
+    min_mem_clock: u32,
+    max_mem_clock: u32,
+) -> Result<()> {
+    let result = loader::nvml_device_set_memory_locked_clocks(device.handle(), min_mem_clock, max_mem_clock)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_set_applications_clocks(device: &Device, mem_clock: u32, graphics_clock: u32) -> Result<()> {
+    let result = loader::nvml_device_set_applications_clocks(device.handle(), mem_clock, graphics_clock)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_reset_applications_clocks(device: &Device) -> Result<()> {
+    let result = loader::nvml_device_reset_applications_clocks(device.handle())?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_reset_gpu_locked_clocks(device: &Device) -> Result<()> {
+    let result = loader::nvml_device_reset_gpu_locked_clocks(device.handle())?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_reset_memory_locked_clocks(device: &Device) -> Result<()> {
+    let result = loader::nvml_device_reset_memory_locked_clocks(device.handle())?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_get_temperature(device: &Device) -> Result<u32> {
+    let mut temp: c_uint = 0;
+    let result = loader::nvml_device_get_temperature(device.handle(), hardware::GPU_TEMP_SENSOR, &mut temp)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(temp)
+}
+
+pub fn device_get_temperature_threshold(device: &Device, threshold: NvmlTemperatureThreshold) -> Result<u32> {
+    let mut temp: c_uint = 0;
+    let result = loader::nvml_device_get_temperature_threshold(device.handle(), threshold as c_uint, &mut temp)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(temp)
+}
+
+/// Set a threshold in °C; drivers accept only the thermal target
+/// (`AcousticCurrent`)
+pub fn device_set_temperature_threshold(
+    device: &Device,
+    threshold: NvmlTemperatureThreshold,
+    celsius: i32,
+) -> Result<()> {
+    let mut temp: c_int = celsius;
+    let result = loader::nvml_device_set_temperature_threshold(device.handle(), threshold as c_uint, &mut temp)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_get_curr_pcie_link_generation(device: &Device) -> Result<u32> {
+    let mut value: c_uint = 0;
+    let result = loader::nvml_device_get_curr_pcie_link_generation(device.handle(), &mut value)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(value)
+}
+
+pub fn device_get_max_pcie_link_generation(device: &Device) -> Result<u32> {
+    let mut value: c_uint = 0;
+    let result = loader::nvml_device_get_max_pcie_link_generation(device.handle(), &mut value)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(value)
+}
+
+pub fn device_get_curr_pcie_link_width(device: &Device) -> Result<u32> {
+    let mut value: c_uint = 0;
+    let result = loader::nvml_device_get_curr_pcie_link_width(device.handle(), &mut value)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(value)
+}
+
+pub fn device_get_max_pcie_link_width(device: &Device) -> Result<u32> {
+    let mut value: c_uint = 0;
+    let result = loader::nvml_device_get_max_pcie_link_width(device.handle(), &mut value)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(value)
+}
+
+/// Bytes moved over PCIe in one direction, in KB/s over the last 20ms
+pub fn device_get_pcie_throughput(device: &Device, counter: NvmlPcieUtilCounter) -> Result<u32> {
+    let mut value: c_uint = 0;
+    let result = loader::nvml_device_get_pcie_throughput(device.handle(), counter as c_uint, &mut value)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(value)
+}
+
+/// Memory errors of one kind counted over `counter_type`'s period. GPUs
+/// without ECC, which includes most GeForce cards, report not supported.
+pub fn device_get_total_ecc_errors(
+    device: &Device,
+    error_type: NvmlMemoryErrorType,
+    counter_type: NvmlEccCounterType,
+) -> Result<u64> {
+    let mut count: u64 = 0;
+    let result =
+        loader::nvml_device_get_total_ecc_errors(
+            device.handle(),
+            error_type as c_uint,
+            counter_type as c_uint,
+            &mut count,
+        )?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(count)
+}
+
+/// Rows remapped to spares, on GPUs with row remapping (Ampere and newer
+/// data center cards)
+pub fn device_get_remapped_rows(device: &Device) -> Result<RemappedRows> {
+    let (mut correctable, mut uncorrectable, mut pending, mut failed): (c_uint, c_uint, c_uint, c_uint) = (0, 0, 0, 0);
+    let result =
+        loader::nvml_device_get_remapped_rows(
+            device.handle(),
+            &mut correctable,
+            &mut uncorrectable,
+            &mut pending,
+            &mut failed,
+        )?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(RemappedRows { correctable, uncorrectable, pending: pending != 0, failed: failed != 0 })
+}
+
+/// Number of pages retired for `cause`, on GPUs with page retirement
+pub fn device_get_retired_page_count(device: &Device, cause: NvmlPageRetirementCause) -> Result<u32> {
+    let mut count: c_uint = 0;
+    let result = loader::nvml_device_get_retired_pages(device.handle(), cause as c_uint, &mut count, ptr::null_mut())?;
+    // With no room for addresses the count is still filled in
+    if result != NVML_SUCCESS && result != NVML_ERROR_INSUFFICIENT_SIZE {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(count)
+}
+
+/// Whether pages are waiting to be retired on the next driver reload
+pub fn device_get_retired_pages_pending(device: &Device) -> Result<bool> {
+    let mut pending: c_uint = 0;
+    let result = loader::nvml_device_get_retired_pages_pending_status(device.handle(), &mut pending)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(pending != 0)
+}
+
+/// PCIe packets retransmitted since the driver loaded
+pub fn device_get_pcie_replay_counter(device: &Device) -> Result<u32> {
+    let mut value: c_uint = 0;
+    let result = loader::nvml_device_get_pcie_replay_counter(device.handle(), &mut value)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(value)
+}
+
+/// Whether a laptop GPU runs on AC or battery, one of the
+/// `NVML_POWER_SOURCE_*` values
+pub fn device_get_power_source(device: &Device) -> Result<u64> {
+    let mut source: c_uint = 0;
+    let result = loader::nvml_device_get_power_source(device.handle(), &mut source)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(u64::from(source))
+}
+
+/// Read fields by `NVML_FI_*` ID. The call as a whole can fail, and so can
+/// each field on its own.
+pub fn device_get_field_values(device: &Device, field_ids: &[u32]) -> Result<Vec<Result<FieldValue>>> {
+    let mut values: Vec<NvmlFieldValue> = field_ids.iter().map(|&id| NvmlFieldValue::request(id)).collect();
+    let result = loader::nvml_device_get_field_values(device.handle(), values.len() as c_int, values.as_mut_ptr())?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(values
+        .iter()
+        .map(|value| match value.nvmlReturn {
+            NVML_SUCCESS => value.decode().ok_or(NvmlError::Unknown(value.valueType)),
+            code => Err(NvmlError::from_nvml_return(code)),
+        })
+        .collect())
+}
+
+/// Call one of the `nvmlDevice*RunningProcesses` functions, growing the
+/// buffer until the list fits, as processes can start between calls
+fn running_processes(
+    device: &Device,
+    get: fn(NvmlDevice, *mut c_uint, *mut NvmlProcessInfo) -> Result<NvmlReturn>,
+) -> Result<Vec<NvmlProcessInfo>> {
+    let mut count: c_uint = 0;
+    let mut infos = Vec::new();
+    loop {
+        let result = get(device.handle(), &mut count, infos.as_mut_ptr())?;
+        match result {
+            NVML_SUCCESS => {
+                infos.truncate(count as usize);
+                return Ok(infos);
+            }
+            NVML_ERROR_INSUFFICIENT_SIZE => {
+                // Room for a few more, in case processes start meanwhile
+                count += 4;
+                infos = vec![NvmlProcessInfo::default(); count as usize];
+            }
+            code => return Err(NvmlError::from_nvml_return(code)),
+        }
+    }
+}
+
+/// Processes using the GPU for compute (CUDA, OpenCL)
+pub fn device_get_compute_running_processes(device: &Device) -> Result<Vec<NvmlProcessInfo>> {
+    running_processes(device, loader::nvml_device_get_compute_running_processes_v3)
+}
+
+/// Processes using the GPU for graphics (OpenGL, Vulkan, the display server)
+pub fn device_get_graphics_running_processes(device: &Device) -> Result<Vec<NvmlProcessInfo>> {
+    running_processes(device, loader::nvml_device_get_graphics_running_processes_v3)
+}
+
+/// Read a single field by `NVML_FI_*` ID
+pub fn device_get_field_value(device: &Device, field_id: u32) -> Result<FieldValue> {
+    device_get_field_values(device, &[field_id])?.remove(0)
+}
+
+pub fn device_get_power_usage(device: &Device) -> Result<u32> {
+    let mut power: c_uint = 0;
+    let result = loader::nvml_device_get_power_usage(device.handle(), &mut power)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(power)
+}
+
+pub fn device_get_clock_info(device: &Device, clock_type: NvmlClockType) -> Result<u32> {
+    let mut clock: c_uint = 0;
+    let result = loader::nvml_device_get_clock_info(device.handle(), clock_type, &mut clock)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(clock)
+}
+
+/// Memory clocks the device supports, in MHz
+pub fn device_get_supported_memory_clocks(device: &Device) -> Result<Vec<u32>> {
+    let mut clocks = [0 as c_uint; buffers::SUPPORTED_CLOCKS_BUFFER_SIZE];
+    let mut count = clocks.len() as c_uint;
+    let result = loader::nvml_device_get_supported_memory_clocks(device.handle(), &mut count, clocks.as_mut_ptr())?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(clocks[..count as usize].to_vec())
+}
+
+/// Graphics clocks the device supports at `memory_clock`, in MHz
+pub fn device_get_supported_graphics_clocks(device: &Device, memory_clock: u32) -> Result<Vec<u32>> {
+    let mut clocks = [0 as c_uint; buffers::SUPPORTED_CLOCKS_BUFFER_SIZE];
+    let mut count = clocks.len() as c_uint;
+    let result =
+        loader::nvml_device_get_supported_graphics_clocks(
+            device.handle(),
+            memory_clock,
+            &mut count,
+            clocks.as_mut_ptr(),
+        )?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(clocks[..count as usize].to_vec())
+}
+
+pub fn device_get_power_limit_constraints(device: &Device) -> Result<(u32, u32)> {
+    let mut min_limit: c_uint = 0;
+    let mut max_limit: c_uint = 0;
+    let result = loader::nvml_device_get_power_management_limit_constraints(
+        device.handle(),
+        &mut min_limit,
+        &mut max_limit,
+    )?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok((min_limit, max_limit))
+}
+
+pub fn device_get_power_limit(device: &Device) -> Result<u32> {
+    let mut limit: c_uint = 0;
+    let result = loader::nvml_device_get_power_management_limit(device.handle(), &mut limit)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(limit)
+}
+
+pub fn device_get_power_default_limit(device: &Device) -> Result<u32> {
+    let mut default_limit: c_uint = 0;
+    let result =
+        loader::nvml_device_get_power_management_default_limit(device.handle(), &mut default_limit)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(default_limit)
+}
+
+pub fn device_set_power_limit(device: &Device, limit_mw: u32) -> Result<()> {
+    let result = loader::nvml_device_set_power_management_limit(device.handle(), limit_mw)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_get_num_fans(device: &Device) -> Result<u32> {
+    let mut num_fans: c_uint = 0;
+    let result = loader::nvml_device_get_num_fans(device.handle(), &mut num_fans)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(num_fans)
+}
+
+/// Intended fan speed as a percentage of the maximum
+/// Raw `nvmlDeviceArchitecture_t` id, see [`GpuArchitecture::from_nvml`]
+pub fn device_get_architecture(device: &Device) -> Result<u32> {
+    let mut arch: c_uint = 0;
+    let result = loader::nvml_device_get_architecture(device.handle(), &mut arch)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(arch)
+}
+
+pub fn device_get_fan_speed(device: &Device, fan: u32) -> Result<u32> {
+    let mut speed: c_uint = 0;
+    let result = loader::nvml_device_get_fan_speed_v2(device.handle(), fan, &mut speed)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(speed)
+}
+
+/// Measured speed of one fan in RPM
+pub fn device_get_fan_speed_rpm(device: &Device, fan: u32) -> Result<u32> {
+    let mut info = NvmlFanSpeedInfo::new_v1(fan);
+    let result = loader::nvml_device_get_fan_speed_rpm(device.handle(), &mut info)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(info.speed)
+}
+
+/// Lowest and highest duty cycle in percent the fans accept
+pub fn device_get_min_max_fan_speed(device: &Device) -> Result<(u32, u32)> {
+    let mut min_speed: c_uint = 0;
+    let mut max_speed: c_uint = 0;
+    let result = loader::nvml_device_get_min_max_fan_speed(device.handle(), &mut min_speed, &mut max_speed)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok((min_speed, max_speed))
+}
+
+pub fn device_get_fan_control_policy(device: &Device, fan: u32) -> Result<NvmlFanControlPolicy> {
+    let mut policy: c_uint = 0;
+    let result = loader::nvml_device_get_fan_control_policy_v2(device.handle(), fan, &mut policy)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    match policy {
+        0 => Ok(NvmlFanControlPolicy::TemperatureContinuousSw),
+        1 => Ok(NvmlFanControlPolicy::Manual),
+        other => Err(NvmlError::Unknown(other)),
+    }
+}
+
+pub fn device_set_fan_control_policy(device: &Device, fan: u32, policy: NvmlFanControlPolicy) -> Result<()> {
+    let result = loader::nvml_device_set_fan_control_policy(device.handle(), fan, policy)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_set_fan_speed(device: &Device, fan: u32, speed: u32) -> Result<()> {
+    let result = loader::nvml_device_set_fan_speed_v2(device.handle(), fan, speed)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_set_default_fan_speed(device: &Device, fan: u32) -> Result<()> {
+    let result = loader::nvml_device_set_default_fan_speed_v2(device.handle(), fan)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_get_utilization_rates(device: &Device) -> Result<NvmlUtilization> {
+    let mut utilization = NvmlUtilization::default();
+    let result = loader::nvml_device_get_utilization_rates(device.handle(), &mut utilization)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(utilization)
+}
+
+pub fn device_get_memory_info(device: &Device) -> Result<NvmlMemory> {
+    let mut memory = NvmlMemory::default();
+    let result = loader::nvml_device_get_memory_info(device.handle(), &mut memory)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(memory)
+}
+
+/// Bitmask of `NVML_CLOCKS_EVENT_REASON_*` flags explaining why clocks are
+/// below their maximum
+pub fn device_get_current_clocks_event_reasons(device: &Device) -> Result<u64> {
+    let mut reasons: u64 = 0;
+    let result = loader::nvml_device_get_current_clocks_event_reasons(device.handle(), &mut reasons)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(reasons)
+}
+
+/// Current performance state, `None` when the driver reports it as unknown
+pub fn device_get_performance_state(device: &Device) -> Result<Option<NvmlPerfState>> {
+    let mut pstate: c_uint = 0;
+    let result = loader::nvml_device_get_performance_state(device.handle(), &mut pstate)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(NvmlPerfState::ALL.get(pstate as usize).copied())
+}
+
+/// Whether the driver keeps the device initialized with no client attached
+pub fn device_get_persistence_mode(device: &Device) -> Result<bool> {
+    let mut mode: c_uint = 0;
+    let result = loader::nvml_device_get_persistence_mode(device.handle(), &mut mode)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(mode != 0)
+}
+
+/// Legacy persistence mode, which lasts until the driver is unloaded.
+/// `nvidia-persistenced` is the supported way to keep it enabled.
+pub fn device_set_persistence_mode(device: &Device, enabled: bool) -> Result<()> {
+    let result = loader::nvml_device_set_persistence_mode(device.handle(), enabled as c_uint)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+/// Bitmask of the `NVML_EVENT_TYPE_*` events the device can report
+pub fn device_get_supported_event_types(device: &Device) -> Result<u64> {
+    let mut event_types: u64 = 0;
+    let result = loader::nvml_device_get_supported_event_types(device.handle(), &mut event_types)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(event_types)
+}
+
+pub fn device_register_events(device: &Device, event_types: u64, set: NvmlEventSet) -> Result<()> {
+    let result = loader::nvml_device_register_events(device.handle(), event_types, set)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn event_set_create() -> Result<NvmlEventSet> {
+    let mut set: NvmlEventSet = std::ptr::null_mut();
+    let result = loader::nvml_event_set_create(&mut set)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(set)
+}
+
+/// Wait up to `timeout_ms` for the next event; `NvmlError::Timeout` when none arrived
+pub fn event_set_wait(set: NvmlEventSet, timeout_ms: u32) -> Result<NvmlEventData> {
+    let mut data = NvmlEventData::default();
+    let result = loader::nvml_event_set_wait_v2(set, &mut data, timeout_ms)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(data)
+}
+
+pub fn event_set_free(set: NvmlEventSet) -> Result<()> {
+    let result = loader::nvml_event_set_free(set)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(())
+}
+
+pub fn device_get_virtualization_mode(device: &Device) -> Result<u32> {
+    let mut mode: c_uint = 0;
+    let result = loader::nvml_device_get_virtualization_mode(device.handle(), &mut mode)?;
+    if result != NVML_SUCCESS {
+        return Err(NvmlError::from_nvml_return(result));
+    }
+    Ok(mode)
+}
@@ -0,0 +1,624 @@
+//! NVML type definitions and constants
+//!
+//! This module defines the types, enums, and constants used by the NVML API
+//! for GPU management and overclocking operations.
+
+use crate::constants::buffers;
+
+use libc::{c_char, c_int, c_uint, c_void};
+
+/// NVML device handle (opaque pointer)
+pub(crate) type NvmlDevice = *mut c_void;
+
+/// NVML return codes
+pub type NvmlReturn = c_uint;
+
+/// NVML clock types
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum NvmlClockType {
+    #[default]
+    Graphics = 0,
+    Memory = 2,
+}
+
+/// Temperature thresholds (`nvmlTemperatureThresholds_t`)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NvmlTemperatureThreshold {
+    /// The GPU shuts down to protect itself
+    Shutdown = 0,
+    /// Hardware slowdown begins
+    Slowdown = 1,
+    /// Highest memory temperature before slowdown
+    MemoryMax = 2,
+    /// Highest GPU temperature before software slowdown
+    GpuMax = 3,
+    /// Lowest settable thermal target
+    AcousticMin = 4,
+    /// Current thermal target, which boost clocks are managed to stay under
+    AcousticCurrent = 5,
+    /// Highest settable thermal target
+    AcousticMax = 6,
+}
+
+/// Whether ECC fixed an error (`nvmlMemoryErrorType_t`)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NvmlMemoryErrorType {
+    Corrected = 0,
+    Uncorrected = 1,
+}
+
+/// How long ECC errors are counted for (`nvmlEccCounterType_t`)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NvmlEccCounterType {
+    /// Since the driver loaded
+    Volatile = 0,
+    /// Over the lifetime of the GPU
+    Aggregate = 1,
+}
+
+/// PCI location and IDs of a GPU (`nvmlPciInfo_t`)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+#[allow(non_snake_case)]
+pub struct NvmlPciInfo {
+    pub busIdLegacy: [c_char; 16],
+    pub domain: c_uint,
+    pub bus: c_uint,
+    pub device: c_uint,
+    pub pciDeviceId: c_uint,
+    pub pciSubSystemId: c_uint,
+    /// `domain:bus:device.function`, e.g. `00000000:01:00.0`
+    pub busId: [c_char; 32],
+}
+
+/// MIG mode reported by `nvmlDeviceGetMigMode`
+pub const NVML_DEVICE_MIG_ENABLE: c_uint = 1;
+
+/// Objects stored in the InfoROM (`nvmlInforomObject_t`); nvoc only reads
+/// the OEM object, whose version identifies the InfoROM as a whole
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NvmlInforomObject {
+    Oem = 0,
+}
+
+/// Why a memory page was retired (`nvmlPageRetirementCause_t`)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NvmlPageRetirementCause {
+    MultipleSingleBitEccErrors = 0,
+    DoubleBitEccError = 1,
+}
+
+/// Rows the memory controller replaced with spares, from
+/// `nvmlDeviceGetRemappedRows`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RemappedRows {
+    /// Rows remapped after correctable errors
+    pub correctable: u32,
+    /// Rows remapped after uncorrectable errors
+    pub uncorrectable: u32,
+    /// A remap takes effect on the next GPU reset
+    pub pending: bool,
+    /// A row could not be remapped, e.g. because no spares were left
+    pub failed: bool,
+}
+
+/// PCIe throughput counters (`nvmlPcieUtilCounter_t`)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NvmlPcieUtilCounter {
+    TxBytes = 0,
+    RxBytes = 1,
+}
+
+/// Who drives a fan (`nvmlFanControlPolicy_t`)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NvmlFanControlPolicy {
+    /// The driver's temperature-based control
+    TemperatureContinuousSw = 0,
+    /// A fixed duty cycle set through `nvmlDeviceSetFanSpeed_v2`
+    Manual = 1,
+}
+
+/// NVML performance states (P-states)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum NvmlPerfState {
+    #[default]
+    P0 = 0, // Maximum performance
+    P1 = 1,
+    P2 = 2, // Video playback and light compute on most cards
+    P3 = 3,
+    P4 = 4,
+    P5 = 5,
+    P6 = 6,
+    P7 = 7,
+    P8 = 8, // Idle
+    P9 = 9,
+    P10 = 10,
+    P11 = 11,
+    P12 = 12,
+    P13 = 13,
+    P14 = 14,
+    P15 = 15, // Minimum performance
+}
+
+impl NvmlPerfState {
+    pub const ALL: [NvmlPerfState; 16] = [
+        Self::P0, Self::P1, Self::P2, Self::P3, Self::P4, Self::P5, Self::P6, Self::P7,
+        Self::P8, Self::P9, Self::P10, Self::P11, Self::P12, Self::P13, Self::P14, Self::P15,
+    ];
+}
+
+impl std::str::FromStr for NvmlPerfState {
+    type Err = String;
+
+    /// `P0` through `P15`, case-insensitive
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.strip_prefix(['P', 'p'])
+            .and_then(|n| n.parse::<usize>().ok())
+            .and_then(|n| Self::ALL.get(n).copied())
+            .ok_or_else(|| format!("invalid pstate '{s}', use P0 to P15"))
+    }
+}
+
+// NVML Return Codes
+pub const NVML_SUCCESS: NvmlReturn = 0;
+pub const NVML_ERROR_UNINITIALIZED: NvmlReturn = 1;
+pub const NVML_ERROR_INVALID_ARGUMENT: NvmlReturn = 2;
+pub const NVML_ERROR_NOT_SUPPORTED: NvmlReturn = 3;
+pub const NVML_ERROR_NO_PERMISSION: NvmlReturn = 4;
+pub const NVML_ERROR_ALREADY_INITIALIZED: NvmlReturn = 5;
+pub const NVML_ERROR_NOT_FOUND: NvmlReturn = 6;
+pub const NVML_ERROR_INSUFFICIENT_SIZE: NvmlReturn = 7;
+pub const NVML_ERROR_INSUFFICIENT_POWER: NvmlReturn = 8;
+pub const NVML_ERROR_DRIVER_NOT_LOADED: NvmlReturn = 9;
+pub const NVML_ERROR_TIMEOUT: NvmlReturn = 10;
+pub const NVML_ERROR_IRQ_ISSUE: NvmlReturn = 11;
+pub const NVML_ERROR_LIBRARY_NOT_FOUND: NvmlReturn = 12;
+pub const NVML_ERROR_FUNCTION_NOT_FOUND: NvmlReturn = 13;
+pub const NVML_ERROR_CORRUPTED_INFOROM: NvmlReturn = 14;
+pub const NVML_ERROR_GPU_IS_LOST: NvmlReturn = 15;
+pub const NVML_ERROR_RESET_REQUIRED: NvmlReturn = 16;
+pub const NVML_ERROR_OPERATING_SYSTEM: NvmlReturn = 17;
+pub const NVML_ERROR_LIB_RM_VERSION_MISMATCH: NvmlReturn = 18;
+pub const NVML_ERROR_IN_USE: NvmlReturn = 19;
+pub const NVML_ERROR_MEMORY: NvmlReturn = 20;
+pub const NVML_ERROR_NO_DATA: NvmlReturn = 21;
+pub const NVML_ERROR_VGPU_ECC_NOT_SUPPORTED: NvmlReturn = 22;
+pub const NVML_ERROR_INSUFFICIENT_RESOURCES: NvmlReturn = 23;
+pub const NVML_ERROR_FREQ_NOT_SUPPORTED: NvmlReturn = 24;
+pub const NVML_ERROR_ARGUMENT_VERSION_MISMATCH: NvmlReturn = 25;
+pub const NVML_ERROR_DEPRECATED: NvmlReturn = 26;
+pub const NVML_ERROR_NOT_READY: NvmlReturn = 27;
+pub const NVML_ERROR_UNKNOWN: NvmlReturn = 999;
+
+// Buffer sizes
+pub const NVML_DEVICE_NAME_BUFFER_SIZE: usize = buffers::DEVICE_NAME_BUFFER_SIZE;
+
+// NVML Clock Offset Version Constants
+pub const NVML_CLOCK_OFFSET_V1: u32 = 0x1000018; // 16777240 - Blackwell
+
+/// Clock offset structure for NVML (v1: Blackwell)
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_snake_case)]
+pub struct NvmlClockOffset {
+    pub version: u32,
+    pub type_: NvmlClockType,
+    pub pstate: NvmlPerfState,
+    pub clockOffsetMHz: c_int,    // Clock offset in MHz
+    pub minClockOffsetMHz: c_int, // Minimum clock offset in MHz
+    pub maxClockOffsetMHz: c_int, // Maximum clock offset in MHz
+}
+
+impl NvmlClockOffset {
+    /// Create v1 struct (Blackwell)
+    pub fn new_v1(clock_type: NvmlClockType, pstate: NvmlPerfState, offset: i32) -> Self {
+        NvmlClockOffset {
+            version: NVML_CLOCK_OFFSET_V1,
+            type_: clock_type,
+            pstate,
+            clockOffsetMHz: offset,
+            minClockOffsetMHz: 0,
+            maxClockOffsetMHz: 0,
+        }
+    }
+}
+
+impl Default for NvmlClockOffset {
+    fn default() -> Self {
+        Self::new_v1(NvmlClockType::Graphics, NvmlPerfState::P0, 0)
+    }
+}
+
+// NVML Fan Speed Info Version Constants
+pub const NVML_FAN_SPEED_INFO_V1: u32 = 0x100000C;
+
+/// Measured speed of one fan, for `nvmlDeviceGetFanSpeedRPM`
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct NvmlFanSpeedInfo {
+    pub version: u32,
+    pub fan: c_uint,
+    /// Speed in RPM
+    pub speed: c_uint,
+}
+
+impl NvmlFanSpeedInfo {
+    pub fn new_v1(fan: u32) -> Self {
+        Self { version: NVML_FAN_SPEED_INFO_V1, fan, speed: 0 }
+    }
+}
+
+// NVML field IDs (`NVML_FI_*`) for `nvmlDeviceGetFieldValues`
+/// Memory (HBM or GDDR junction) temperature in °C
+pub const NVML_FI_DEV_MEMORY_TEMP: c_uint = 82;
+/// Energy consumed since the driver was loaded, in mJ
+pub const NVML_FI_DEV_TOTAL_ENERGY_CONSUMPTION: c_uint = 83;
+
+// NVML value types (`nvmlValueType_t`)
+pub const NVML_VALUE_TYPE_DOUBLE: c_uint = 0;
+pub const NVML_VALUE_TYPE_UNSIGNED_INT: c_uint = 1;
+pub const NVML_VALUE_TYPE_UNSIGNED_LONG: c_uint = 2;
+pub const NVML_VALUE_TYPE_UNSIGNED_LONG_LONG: c_uint = 3;
+pub const NVML_VALUE_TYPE_SIGNED_LONG_LONG: c_uint = 4;
+pub const NVML_VALUE_TYPE_SIGNED_INT: c_uint = 5;
+pub const NVML_VALUE_TYPE_UNSIGNED_SHORT: c_uint = 6;
+
+/// Value of a field (`nvmlValue_t`); `valueType` of the enclosing
+/// `NvmlFieldValue` tells which member is set
+#[repr(C)]
+#[derive(Copy, Clone)]
+#[allow(non_snake_case)]
+pub union NvmlValue {
+    pub dVal: f64,
+    pub uiVal: c_uint,
+    /// `unsigned long`, 64 bits on the Linux targets NVML ships for
+    pub ulVal: u64,
+    pub ullVal: std::ffi::c_ulonglong,
+    pub sllVal: std::ffi::c_longlong,
+    pub siVal: c_int,
+    pub usVal: std::ffi::c_ushort,
+}
+
+/// Field value request and result (`nvmlFieldValue_t`)
+#[repr(C)]
+#[derive(Copy, Clone)]
+#[allow(non_snake_case)]
+pub struct NvmlFieldValue {
+    pub fieldId: c_uint,
+    pub scopeId: c_uint,
+    pub timestamp: i64,
+    pub latencyUsec: i64,
+    pub valueType: c_uint,
+    pub nvmlReturn: NvmlReturn,
+    pub value: NvmlValue,
+}
+
+impl NvmlFieldValue {
+    /// Request for `field_id`, to be filled in by the driver
+    pub fn request(field_id: c_uint) -> Self {
+        NvmlFieldValue {
+            fieldId: field_id,
+            scopeId: 0,
+            timestamp: 0,
+            latencyUsec: 0,
+            valueType: 0,
+            nvmlReturn: 0,
+            value: NvmlValue { ullVal: 0 },
+        }
+    }
+}
+
+/// A field value, widened from the type NVML reported it as
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldValue {
+    Double(f64),
+    Unsigned(u64),
+    Signed(i64),
+}
+
+impl NvmlFieldValue {
+    /// The value the driver filled in, or `None` for an unknown value type
+    pub fn decode(&self) -> Option<FieldValue> {
+        // SAFETY: `valueType` names the union member the driver wrote
+        let value = unsafe {
+            match self.valueType {
+                NVML_VALUE_TYPE_DOUBLE => FieldValue::Double(self.value.dVal),
+                NVML_VALUE_TYPE_UNSIGNED_INT => FieldValue::Unsigned(u64::from(self.value.uiVal)),
+                NVML_VALUE_TYPE_UNSIGNED_LONG => FieldValue::Unsigned(self.value.ulVal),
+                NVML_VALUE_TYPE_UNSIGNED_LONG_LONG => FieldValue::Unsigned(self.value.ullVal),
+                NVML_VALUE_TYPE_SIGNED_LONG_LONG => FieldValue::Signed(self.value.sllVal),
+                NVML_VALUE_TYPE_SIGNED_INT => FieldValue::Signed(i64::from(self.value.siVal)),
+                NVML_VALUE_TYPE_UNSIGNED_SHORT => FieldValue::Unsigned(u64::from(self.value.usVal)),
+                _ => return None,
+            }
+        };
+        Some(value)
+    }
+}
+
+impl std::fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldValue::Double(v) => write!(f, "{v}"),
+            FieldValue::Unsigned(v) => write!(f, "{v}"),
+            FieldValue::Signed(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// NVML event set handle (opaque pointer)
+pub type NvmlEventSet = *mut c_void;
+
+/// Event types for `nvmlDeviceRegisterEvents` (`nvmlEventType*`)
+pub const NVML_EVENT_TYPE_PSTATE: u64 = 0x4;
+pub const NVML_EVENT_TYPE_XID_CRITICAL_ERROR: u64 = 0x8;
+pub const NVML_EVENT_TYPE_CLOCK: u64 = 0x10;
+pub const NVML_EVENT_TYPE_POWER_SOURCE_CHANGE: u64 = 0x80;
+
+/// Power sources reported in power source change events (`nvmlPowerSource_t`)
+pub const NVML_POWER_SOURCE_AC: u64 = 0;
+pub const NVML_POWER_SOURCE_BATTERY: u64 = 1;
+pub const NVML_POWER_SOURCE_UNDERSIZED: u64 = 2;
+
+/// An event returned by `nvmlEventSetWait_v2` (`nvmlEventData_t`)
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_snake_case)]
+pub struct NvmlEventData {
+    pub(crate) device: NvmlDevice,
+    pub eventType: u64,
+    /// XID number for XID errors, the new source for power source changes
+    pub eventData: u64,
+    pub gpuInstanceId: c_uint,
+    pub computeInstanceId: c_uint,
+}
+
+impl Default for NvmlEventData {
+    fn default() -> Self {
+        NvmlEventData {
+            device: std::ptr::null_mut(),
+            eventType: 0,
+            eventData: 0,
+            gpuInstanceId: 0,
+            computeInstanceId: 0,
+        }
+    }
+}
+
+/// `usedGpuMemory` of a process whose usage the driver cannot see, e.g.
+/// under Windows WDDM or in a container without access to the PID
+pub const NVML_VALUE_NOT_AVAILABLE_ULL: u64 = u64::MAX;
+
+/// A process running on the GPU (`nvmlProcessInfo_t`)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+#[allow(non_snake_case)]
+pub struct NvmlProcessInfo {
+    pub pid: c_uint,
+    /// Bytes of VRAM in use, or `NVML_VALUE_NOT_AVAILABLE_ULL`
+    pub usedGpuMemory: u64,
+    pub gpuInstanceId: c_uint,
+    pub computeInstanceId: c_uint,
+}
+
+/// NVML GPU virtualization modes (`nvmlGpuVirtualizationMode_t`)
+pub const NVML_GPU_VIRTUALIZATION_MODE_NONE: c_uint = 0;
+pub const NVML_GPU_VIRTUALIZATION_MODE_PASSTHROUGH: c_uint = 1;
+pub const NVML_GPU_VIRTUALIZATION_MODE_VGPU: c_uint = 2;
+pub const NVML_GPU_VIRTUALIZATION_MODE_HOST_VGPU: c_uint = 3;
+pub const NVML_GPU_VIRTUALIZATION_MODE_HOST_VSGA: c_uint = 4;
+
+/// NVML architecture ids (`nvmlDeviceArchitecture_t`)
+pub const NVML_DEVICE_ARCH_TURING: c_uint = 6;
+pub const NVML_DEVICE_ARCH_AMPERE: c_uint = 7;
+pub const NVML_DEVICE_ARCH_BLACKWELL: c_uint = 10;
+
+/// Clock event reasons (`nvmlClocksEventReasons`)
+pub const NVML_CLOCKS_EVENT_REASON_GPU_IDLE: u64 = 0x1;
+pub const NVML_CLOCKS_EVENT_REASON_APPLICATIONS_CLOCKS_SETTING: u64 = 0x2;
+pub const NVML_CLOCKS_EVENT_REASON_SW_POWER_CAP: u64 = 0x4;
+pub const NVML_CLOCKS_EVENT_REASON_SYNC_BOOST: u64 = 0x10;
+pub const NVML_CLOCKS_EVENT_REASON_DISPLAY_CLOCK_SETTING: u64 = 0x100;
+
+/// Clock event reasons that indicate the GPU is being held back rather
+/// than idling or sitting at its power limit
+pub const NVML_CLOCKS_EVENT_REASON_HW_SLOWDOWN: u64 = 0x8;
+pub const NVML_CLOCKS_EVENT_REASON_SW_THERMAL_SLOWDOWN: u64 = 0x20;
+pub const NVML_CLOCKS_EVENT_REASON_HW_THERMAL_SLOWDOWN: u64 = 0x40;
+pub const NVML_CLOCKS_EVENT_REASON_HW_POWER_BRAKE_SLOWDOWN: u64 = 0x80;
+
+/// GPU and memory controller utilization over the last sample period (%)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NvmlUtilization {
+    pub gpu: c_uint,
+    pub memory: c_uint,
+}
+
+/// Framebuffer memory (bytes)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NvmlMemory {
+    pub total: u64,
+    pub free: u64,
+    pub used: u64,
+}
+
+impl NvmlMemory {
+    pub fn used_mib(&self) -> u64 {
+        self.used >> 20
+    }
+
+    pub fn total_mib(&self) -> u64 {
+        self.total >> 20
+    }
+}
+
+/// GPU Architecture detection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GpuArchitecture {
+    Turing,    // RTX 20-series
+    Ampere,    // RTX 30-series
+    Blackwell, // RTX 50-series
+    Unknown,
+}
+
+impl GpuArchitecture {
+    /// Map an architecture id reported by `nvmlDeviceGetArchitecture`
+    pub fn from_nvml(arch: c_uint) -> Self {
+        match arch {
+            NVML_DEVICE_ARCH_TURING => GpuArchitecture::Turing,
+            NVML_DEVICE_ARCH_AMPERE => GpuArchitecture::Ampere,
+            NVML_DEVICE_ARCH_BLACKWELL => GpuArchitecture::Blackwell,
+            _ => GpuArchitecture::Unknown,
+        }
+    }
+
+    /// Detect GPU architecture from device name
+    pub fn from_device_name(name: &str) -> Self {
+        let name_upper = name.to_uppercase();
+
+        // Blackwell (RTX 50-series)
+        if name_upper.contains("RTX 50")
+            || name_upper.contains("5090")
+            || name_upper.contains("5080")
+            || name_upper.contains("5070")
+            || name_upper.contains("5060")
+        {
+            GpuArchitecture::Blackwell
+        } else if ["3090", "3080", "3070", "3060", "3050"].iter().any(|m| name_upper.contains(m)) {
+            GpuArchitecture::Ampere
+        } else if ["2080", "2070", "2060"].iter().any(|m| name_upper.contains(m)) {
+            GpuArchitecture::Turing
+        } else {
+            GpuArchitecture::Unknown
+        }
+    }
+}
+
+impl std::fmt::Display for GpuArchitecture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GpuArchitecture::Turing => write!(f, "turing"),
+            GpuArchitecture::Ampere => write!(f, "ampere"),
+            GpuArchitecture::Blackwell => write!(f, "blackwell"),
+            GpuArchitecture::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl std::fmt::Display for NvmlClockType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NvmlClockType::Graphics => write!(f, "Graphics"),
+            NvmlClockType::Memory => write!(f, "Memory"),
+        }
+    }
+}
+
+/// Convert performance state to string representation
+impl std::fmt::Display for NvmlPerfState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "P{}", *self as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perf_state_parse() {
+        assert_eq!("P2".parse(), Ok(NvmlPerfState::P2));
+        assert_eq!("p15".parse(), Ok(NvmlPerfState::P15));
+        assert!("P16".parse::<NvmlPerfState>().is_err());
+        assert!("2".parse::<NvmlPerfState>().is_err());
+        assert_eq!(NvmlPerfState::P8.to_string(), "P8");
+    }
+
+    #[test]
+    fn test_field_value_decode() {
+        let mut field = NvmlFieldValue::request(NVML_FI_DEV_MEMORY_TEMP);
+        field.valueType = NVML_VALUE_TYPE_UNSIGNED_INT;
+        field.value = NvmlValue { uiVal: 78 };
+        assert_eq!(field.decode(), Some(FieldValue::Unsigned(78)));
+        field.valueType = NVML_VALUE_TYPE_SIGNED_INT;
+        field.value = NvmlValue { siVal: -5 };
+        assert_eq!(field.decode(), Some(FieldValue::Signed(-5)));
+        field.valueType = NVML_VALUE_TYPE_DOUBLE;
+        field.value = NvmlValue { dVal: 1.5 };
+        assert_eq!(field.decode(), Some(FieldValue::Double(1.5)));
+        field.valueType = 99;
+        assert_eq!(field.decode(), None);
+        assert_eq!(std::mem::size_of::<NvmlFieldValue>(), 40);
+    }
+
+    #[test]
+    fn test_blackwell_detection() {
+        assert_eq!(
+            GpuArchitecture::from_device_name("NVIDIA GeForce RTX 5090"),
+            GpuArchitecture::Blackwell
+        );
+        assert_eq!(
+            GpuArchitecture::from_device_name("GeForce RTX 5080"),
+            GpuArchitecture::Blackwell
+        );
+        assert_eq!(
+            GpuArchitecture::from_device_name("RTX 5070 Ti"),
+            GpuArchitecture::Blackwell
+        );
+        assert_eq!(
+            GpuArchitecture::from_device_name("GeForce RTX 5060"),
+            GpuArchitecture::Blackwell
+        );
+    }
+
+    #[test]
+    fn test_legacy_detection() {
+        assert_eq!(
+            GpuArchitecture::from_device_name("NVIDIA GeForce RTX 3080 Ti"),
+            GpuArchitecture::Ampere
+        );
+        assert_eq!(
+            GpuArchitecture::from_device_name("NVIDIA GeForce RTX 2070 SUPER"),
+            GpuArchitecture::Turing
+        );
+    }
+
+    #[test]
+    fn test_architecture_from_nvml() {
+        assert_eq!(GpuArchitecture::from_nvml(NVML_DEVICE_ARCH_BLACKWELL), GpuArchitecture::Blackwell);
+        assert_eq!(GpuArchitecture::from_nvml(NVML_DEVICE_ARCH_AMPERE), GpuArchitecture::Ampere);
+        // Ada Lovelace and NVML_DEVICE_ARCH_UNKNOWN
+        assert_eq!(GpuArchitecture::from_nvml(8), GpuArchitecture::Unknown);
+        assert_eq!(GpuArchitecture::from_nvml(0xffff_ffff), GpuArchitecture::Unknown);
+    }
+
+    #[test]
+    fn test_unknown_gpu() {
+        assert_eq!(
+            GpuArchitecture::from_device_name("Some Unknown GPU"),
+            GpuArchitecture::Unknown
+        );
+    }
+
+    #[test]
+    fn test_struct_creation() {
+        let v1_struct = NvmlClockOffset::new_v1(NvmlClockType::Graphics, NvmlPerfState::P0, 100);
+        assert_eq!(v1_struct.version, NVML_CLOCK_OFFSET_V1);
+        assert_eq!(v1_struct.clockOffsetMHz, 100);
+        assert_eq!(
+            v1_struct.minClockOffsetMHz,
+            crate::constants::clocks::DEFAULT_GRAPHICS_OFFSET.0
+        );
+    }
+}
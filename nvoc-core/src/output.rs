@@ -0,0 +1,140 @@
+//! Where nvoc writes what
+//!
+//! stdout carries results only: values that were read (`info`, `stats`,
+//! `history`), documents asked for with `--json`, `--format` or
+//! `--emit-dashboard`, and one `label: value` line per setting a command
+//! changed. Everything else goes to stderr: `error[domain]:` and
+//! `warning[domain]:` lines, hints, progress notes and `--timing`. Piping
+//! stdout therefore never picks up diagnostics, and a machine-readable
+//! document is the only thing on stdout when one is requested.
+//!
+//! `--quiet` drops the confirmation lines of commands that change settings,
+//! leaving stdout empty on success. Requested data, errors and warnings are
+//! always written.
+//!
+//! Human-readable output follows the user's locale: timestamps are in the
+//! local time zone and decimals use the `LC_NUMERIC` decimal point. JSON,
+//! CSV and metrics output always uses UTC and C-locale numbers, so it
+//! parses the same on every machine.
+
+use std::ffi::CStr;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet() {
+    QUIET.store(true, Ordering::Relaxed);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Confirm a change on stdout, unless `--quiet` was given
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::output::quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+pub use status;
+
+/// Report a setting that already has the requested value, so no write is
+/// issued. Like a change, this is a confirmation and `--quiet` drops it.
+pub fn unchanged(label: &str, value: &str, dry_run: bool) {
+    match dry_run {
+        true => println!("{label}: {value}, unchanged (dry run)"),
+        false => status!("{label}: {value}, unchanged"),
+    }
+}
+
+/// Decimal point of the user's `LC_NUMERIC` locale
+fn decimal_point() -> &'static str {
+    static POINT: OnceLock<String> = OnceLock::new();
+    POINT.get_or_init(|| {
+        // Only read the locale, then return to "C" so libraries formatting
+        // numbers for machines are unaffected
+        unsafe {
+            if libc::setlocale(libc::LC_NUMERIC, c"".as_ptr()).is_null() {
+                return ".".to_string();
+            }
+            let conv = libc::localeconv();
+            let point = match conv.is_null() || (*conv).decimal_point.is_null() {
+                true => ".".to_string(),
+                false => CStr::from_ptr((*conv).decimal_point).to_string_lossy().into_owned(),
+            };
+            libc::setlocale(libc::LC_NUMERIC, c"C".as_ptr());
+            point
+        }
+    })
+}
+
+/// A number for human-readable output, written with the locale's decimal
+/// point and honoring the precision of the format spec. JSON, CSV and
+/// metrics always use `.` and format numbers directly.
+pub struct Decimal(pub f64);
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match f.precision() {
+            Some(precision) => format!("{:.precision$}", self.0),
+            None => self.0.to_string(),
+        };
+        f.write_str(&localize(&text, decimal_point()))
+    }
+}
+
+fn localize(number: &str, point: &str) -> String {
+    number.replacen('.', point, 1)
+}
+
+/// `+HH:MM` offset from UTC
+fn utc_offset(seconds: i64) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.abs() / 60;
+    format!("{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Timestamp for human-readable output in the local time zone (`TZ`), e.g.
+/// `2025-03-01T19:04:05.250+01:00`. Machine-readable output uses
+/// [`crate::gpu::telemetry::utc_timestamp`] instead.
+pub fn local_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: both pointers are valid for the duration of the call
+    if unsafe { libc::localtime_r(&secs, &mut tm) }.is_null() {
+        return crate::gpu::telemetry::utc_timestamp(time);
+    }
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}{}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        since_epoch.subsec_millis(),
+        utc_offset(tm.tm_gmtoff)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_formatting() {
+        assert_eq!(localize("41.5", ","), "41,5");
+        assert_eq!(localize("41", ","), "41");
+        assert_eq!(utc_offset(3600), "+01:00");
+        assert_eq!(utc_offset(-12_600), "-03:30");
+        assert_eq!(utc_offset(0), "+00:00");
+    }
+}
@@ -0,0 +1,96 @@
+//! Parameters of the operations nvoc performs
+//!
+//! These are filled in by the command line parser, but carry no CLI types,
+//! so embedding programs can build them directly.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::nvml::NvmlPerfState;
+
+#[derive(Debug, Default)]
+pub struct OverclockParams {
+    pub clocks: Option<(u32, u32)>,
+    pub mem_clocks: Option<(u32, u32)>,
+    /// P0 offsets
+    pub graphics_offset: Option<i32>,
+    pub memory_offset: Option<i32>,
+    /// Offsets of pstates other than P0
+    pub graphics_pstate_offsets: Vec<(NvmlPerfState, i32)>,
+    pub memory_pstate_offsets: Vec<(NvmlPerfState, i32)>,
+    pub power_limit: Option<u32>,
+    pub dry_run: bool,
+    /// Read each written value back and warn when the driver did not apply it
+    pub verify: bool,
+    /// Trial mode: put the prior settings back after this long or on Ctrl-C
+    pub revert_after: Option<Duration>,
+}
+
+impl OverclockParams {
+    pub fn is_empty(&self) -> bool {
+        self.clocks.is_none()
+            && self.mem_clocks.is_none()
+            && self.graphics_offset.is_none()
+            && self.memory_offset.is_none()
+            && self.graphics_pstate_offsets.is_empty()
+            && self.memory_pstate_offsets.is_empty()
+            && self.power_limit.is_none()
+    }
+}
+
+#[derive(Debug)]
+pub struct ExporterParams {
+    pub listen: Option<SocketAddr>,
+    pub unix: Option<PathBuf>,
+    pub socket_mode: u32,
+    pub cache_ttl: Duration,
+    pub emit_dashboard: bool,
+}
+
+/// What `nvoc autotune` optimizes for
+#[derive(Debug)]
+pub enum AutotuneTarget {
+    /// Highest stable graphics offset, up to `max` (the top of the driver's
+    /// range when `None`). The search stops once the stable and unstable
+    /// bounds are `resolution` MHz apart.
+    Offset { max: Option<i32>, resolution: i32 },
+    /// Best performance per watt
+    Efficiency(EfficiencyParams),
+}
+
+/// Combinations tried by `nvoc autotune --target efficiency`
+#[derive(Debug)]
+pub struct EfficiencyParams {
+    /// Power limits in percent of the default
+    pub power: Vec<u32>,
+    /// Graphics clock caps in MHz; derived from the supported range when empty
+    pub clock_caps: Vec<u32>,
+    /// Offset also tried with each clock cap, 0 to skip
+    pub offset: i32,
+    /// Least performance to keep, in percent of stock
+    pub min_performance: u32,
+}
+
+#[derive(Debug)]
+pub struct AutotuneParams {
+    /// Profile the result is saved into
+    pub name: String,
+    /// Workload or benchmark run at each step, through `sh -c`
+    pub command: String,
+    /// How long each step runs at most
+    pub step: Duration,
+    pub target: AutotuneTarget,
+}
+
+#[derive(Debug)]
+pub struct SweepParams {
+    /// Power limits in percent of the default, in the order tried
+    pub power: Vec<u32>,
+    /// Benchmark run at each limit, through `sh -c`
+    pub command: String,
+    /// How long each limit runs at most
+    pub step: Duration,
+    /// Also write the results to this CSV file
+    pub csv: Option<PathBuf>,
+}
@@ -0,0 +1,72 @@
+//! Export of profiles as MSI Afterburner profile slots
+//!
+//! Afterburner keeps per-GPU settings in an INI file under its `Profiles`
+//! directory, named after the card's PCI IDs. Each `[ProfileN]` section is
+//! one of the numbered slots in its UI:
+//!
+//! ```ini
+//! [Profile1]
+//! Format=2
+//! PowerLimit=105
+//! CoreClkBoost=856000
+//! MemClkBoost=1000000
+//! ```
+//!
+//! Offsets are stored in kHz. Afterburner shows memory offsets at half the
+//! rate NVML uses, so a +2000MHz nvoc memory offset is +1000MHz there.
+
+use crate::profile::Settings;
+
+/// Highest profile slot Afterburner offers
+pub const MAX_SLOT: u32 = 5;
+
+/// Render `settings` as an Afterburner profile slot. Returns the INI text
+/// and the settings Afterburner cannot represent.
+pub fn render(settings: &Settings, slot: u32) -> (String, Vec<&'static str>) {
+    let mut text = format!("[Profile{slot}]\nFormat=2\n");
+    if let Some(percentage) = settings.power_limit {
+        text.push_str(&format!("PowerLimit={percentage}\n"));
+    }
+    if let Some(offset) = settings.graphics_offset {
+        text.push_str(&format!("CoreClkBoost={}\n", offset * 1000));
+    }
+    if let Some(offset) = settings.memory_offset {
+        text.push_str(&format!("MemClkBoost={}\n", offset / 2 * 1000));
+    }
+
+    // Afterburner locks clocks through its voltage/frequency curve editor,
+    // and keeps fan settings outside of profile slots
+    let mut skipped = Vec::new();
+    if settings.clocks.is_some() {
+        skipped.push("clocks");
+    }
+    if settings.mem_clocks.is_some() {
+        skipped.push("mem clocks");
+    }
+    if settings.fan_speed.is_some() || settings.fan_curve.is_some() {
+        skipped.push("fans");
+    }
+    (text, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_converts_units() {
+        let settings = Settings {
+            clocks: Some((200, 2820)),
+            graphics_offset: Some(856),
+            memory_offset: Some(2000),
+            power_limit: Some(105),
+            ..Default::default()
+        };
+        let (text, skipped) = render(&settings, 2);
+        assert_eq!(
+            text,
+            "[Profile2]\nFormat=2\nPowerLimit=105\nCoreClkBoost=856000\nMemClkBoost=1000000\n"
+        );
+        assert_eq!(skipped, vec!["clocks"]);
+    }
+}
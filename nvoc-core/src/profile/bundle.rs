@@ -0,0 +1,187 @@
+//! Shareable profile bundles
+//!
+//! `profile bundle` prints one self-contained TOML document: the profile's
+//! settings for the selected GPU, the GPU model and driver it was tuned on,
+//! its stability record and the nvoc version that wrote it.
+//! `profile import --format bundle` checks the document against the
+//! recipient's GPU before saving it as a profile.
+//!
+//! ```toml
+//! format = 1
+//! nvoc_version = "0.4.0"
+//! name = "daily"
+//!
+//! [device]
+//! name = "NVIDIA GeForce RTX 5090"
+//! architecture = "blackwell"
+//! driver = "590.48.01"
+//!
+//! [stability]
+//! score = 97
+//! runs = 3
+//! seconds = 3600
+//!
+//! [profile]
+//! graphics_offset = 856
+//! memory_offset = 2000
+//! power_limit = 105
+//!
+//! [profile.requires]
+//! architecture = "blackwell"
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::app;
+use crate::gpu::validation;
+use crate::nvml::{self, Device, GpuArchitecture};
+use crate::profile::stability::{self, Record};
+use crate::profile::{self, Profile};
+use crate::{output, AppError};
+
+/// Bundle layout written by this version; newer layouts are refused
+const FORMAT: u32 = 1;
+
+/// The GPU and driver a bundle was tuned on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Identity {
+    /// Model name as reported by the driver
+    pub name: String,
+    pub architecture: String,
+    pub driver: String,
+}
+
+impl Identity {
+    pub fn of(device: &Device) -> Result<Self, AppError> {
+        Ok(Self {
+            name: nvml::device_get_name(device).map_err(|e| AppError::new("profile", e))?,
+            architecture: validation::detect_architecture(device).unwrap_or(GpuArchitecture::Unknown).to_string(),
+            driver: nvml::system_get_driver_version().map_err(|e| AppError::new("profile", e))?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bundle {
+    pub format: u32,
+    pub nvoc_version: String,
+    pub name: String,
+    pub device: Identity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stability: Option<Record>,
+    pub profile: Profile,
+}
+
+/// Bundle a saved profile as it applies to `device`. Per-GPU sections are
+/// keyed by UUIDs that mean nothing elsewhere, so the device's effective
+/// settings are written as the profile's defaults.
+pub fn create(device: &Device, name: &str) -> Result<Bundle, AppError> {
+    let saved = profile::load(name)?;
+    let uuid = nvml::device_get_uuid(device).map_err(|e| AppError::new("profile", e))?;
+    let identity = Identity::of(device)?;
+
+    let mut bundled = Profile::from_settings(saved.settings_for(&uuid));
+    bundled.requires = saved.requires;
+    if bundled.requires.architecture.is_none() && identity.architecture != GpuArchitecture::Unknown.to_string() {
+        bundled.requires.architecture = Some(identity.architecture.clone());
+    }
+    Ok(Bundle {
+        format: FORMAT,
+        nvoc_version: app::VERSION.to_string(),
+        name: name.to_string(),
+        device: identity,
+        stability: stability::load().remove(name),
+        profile: bundled,
+    })
+}
+
+pub fn render(bundle: &Bundle) -> Result<String, AppError> {
+    toml::to_string(bundle).map_err(|e| AppError::msg("profile", e.to_string()))
+}
+
+fn parse(text: &str) -> Result<Bundle, String> {
+    let bundle: Bundle = toml::from_str(text).map_err(|e| e.message().to_string())?;
+    if bundle.format > FORMAT {
+        return Err(format!(
+            "bundle format {} was written by nvoc {}, this version reads format {FORMAT}",
+            bundle.format, bundle.nvoc_version
+        ));
+    }
+    Ok(bundle)
+}
+
+/// Reasons a bundle tuned on `tuned` must not be used on `local`
+fn mismatches(tuned: &Identity, local: &Identity) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    if tuned.name != local.name {
+        mismatches.push(format!("tuned on {}, this gpu is {}", tuned.name, local.name));
+    }
+    if tuned.architecture != local.architecture {
+        mismatches.push(format!("tuned on {}, this gpu is {}", tuned.architecture, local.architecture));
+    }
+    mismatches
+}
+
+/// Read a bundle and check it against `device`, returning the name it
+/// was shared under and the profile to save
+pub fn import(device: &Device, path: &Path) -> Result<(String, Profile), AppError> {
+    let read_error = |e: String| AppError::msg("profile", format!("{}: {e}", path.display()));
+    let text = fs::read_to_string(path).map_err(|e| read_error(e.to_string()))?;
+    let bundle = parse(&text).map_err(read_error)?;
+
+    let local = Identity::of(device)?;
+    let mismatches = mismatches(&bundle.device, &local);
+    if !mismatches.is_empty() {
+        return Err(read_error(format!("not imported: {}", mismatches.join(", "))));
+    }
+    if bundle.device.driver != local.driver {
+        eprintln!(
+            "warning[profile]: tuned on driver {}, this system runs {}; soak it before relying on it",
+            bundle.device.driver, local.driver
+        );
+    }
+    match &bundle.stability {
+        Some(record) => output::status!("profile: {} {record} where it was tuned", bundle.name),
+        None => eprintln!("warning[profile]: '{}' was shared without a stability record", bundle.name),
+    }
+    Ok((bundle.name, bundle.profile))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(name: &str, architecture: &str) -> Identity {
+        Identity { name: name.to_string(), architecture: architecture.to_string(), driver: "590.48.01".to_string() }
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let bundle = Bundle {
+            format: FORMAT,
+            nvoc_version: app::VERSION.to_string(),
+            name: "daily".to_string(),
+            device: identity("NVIDIA GeForce RTX 5090", "blackwell"),
+            stability: Some(Record { score: 97, runs: 3, seconds: 3600 }),
+            profile: Profile { graphics_offset: Some(856), power_limit: Some(105), ..Default::default() },
+        };
+        assert_eq!(parse(&toml::to_string(&bundle).unwrap()), Ok(bundle));
+        assert!(parse("format = 2\nnvoc_version = \"9.0.0\"\nname = \"x\"\n[device]\nname = \"a\"\narchitecture = \"b\"\ndriver = \"c\"\n[profile]\n").is_err());
+    }
+
+    #[test]
+    fn test_identity_mismatches() {
+        let tuned = identity("NVIDIA GeForce RTX 5090", "blackwell");
+        assert!(mismatches(&tuned, &tuned).is_empty());
+        assert_eq!(
+            mismatches(&tuned, &identity("NVIDIA GeForce RTX 5080", "blackwell")),
+            vec!["tuned on NVIDIA GeForce RTX 5090, this gpu is NVIDIA GeForce RTX 5080"]
+        );
+        assert_eq!(mismatches(&tuned, &identity("NVIDIA GeForce RTX 3090", "ampere")).len(), 2);
+    }
+}
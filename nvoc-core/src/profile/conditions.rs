@@ -0,0 +1,152 @@
+//! Preconditions a profile places on the machine it is applied to
+//!
+//! A profile shared between machines may only be safe on some of them. Its
+//! `[requires]` section is checked against the driver, the GPU and the power
+//! source before any setting is changed, and the profile is refused if a
+//! condition does not hold.
+//!
+//! ```toml
+//! [requires]
+//! min_driver = "575.51"
+//! architecture = "blackwell"
+//! max_temp = 50
+//! ac_power = true
+//! ```
+
+use std::cmp::Ordering;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gpu::validation;
+use crate::nvml::{self, Device, GpuArchitecture};
+
+/// Kernel power supply class, listing mains adapters and batteries
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Conditions {
+    /// Lowest driver version, e.g. "575.51"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_driver: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub architecture: Option<String>,
+    /// Highest GPU temperature in °C before applying. A GPU that has been
+    /// idle sits a few degrees above ambient, so this keeps an aggressive
+    /// profile off a machine in a hot room or an already loaded card.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_temp: Option<u32>,
+    /// Only apply while running on mains power
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ac_power: Option<bool>,
+}
+
+/// Machine state the conditions are checked against
+#[derive(Debug, Clone)]
+pub struct Facts {
+    pub driver_version: String,
+    pub architecture: GpuArchitecture,
+    pub temperature: Option<u32>,
+    /// `None` when no mains adapter is reported, as on most desktops
+    pub on_ac: Option<bool>,
+}
+
+impl Facts {
+    pub fn gather(device: &Device) -> Self {
+        Self {
+            driver_version: nvml::system_get_driver_version().unwrap_or_default(),
+            architecture: validation::detect_architecture(device).unwrap_or(GpuArchitecture::Unknown),
+            temperature: nvml::device_get_temperature(device).ok(),
+            on_ac: on_ac_power(),
+        }
+    }
+}
+
+/// Whether any mains adapter is online, or `None` if there is none
+fn on_ac_power() -> Option<bool> {
+    let mut found = false;
+    for entry in fs::read_dir(POWER_SUPPLY_DIR).ok()?.flatten() {
+        let read = |file: &str| fs::read_to_string(entry.path().join(file)).unwrap_or_default();
+        if read("type").trim() != "Mains" {
+            continue;
+        }
+        if read("online").trim() == "1" {
+            return Some(true);
+        }
+        found = true;
+    }
+    found.then_some(false)
+}
+
+/// Compare dotted version numbers numerically, so "575.9" < "575.51"
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |v: &str| -> Vec<u32> { v.split('.').map(|p| p.trim().parse().unwrap_or(0)).collect() };
+    parts(a).cmp(&parts(b))
+}
+
+impl Conditions {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Conditions not met by `facts`, as readable reasons
+    pub fn unmet(&self, facts: &Facts) -> Vec<String> {
+        let mut unmet = Vec::new();
+        if let Some(min) = &self.min_driver {
+            if compare_versions(&facts.driver_version, min) == Ordering::Less {
+                unmet.push(format!("driver {} is older than {min}", facts.driver_version));
+            }
+        }
+        if let Some(required) = &self.architecture {
+            if !facts.architecture.to_string().eq_ignore_ascii_case(required) {
+                unmet.push(format!("architecture is {}, not {required}", facts.architecture));
+            }
+        }
+        if let Some(max) = self.max_temp {
+            match facts.temperature {
+                Some(temp) if temp > max => unmet.push(format!("gpu is at {temp}°C, above {max}°C")),
+                Some(_) => {}
+                None => unmet.push("gpu temperature unavailable".to_string()),
+            }
+        }
+        if self.ac_power == Some(true) && facts.on_ac == Some(false) {
+            unmet.push("running on battery".to_string());
+        }
+        unmet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unmet_conditions() {
+        let conditions = Conditions {
+            min_driver: Some("575.51".to_string()),
+            architecture: Some("Blackwell".to_string()),
+            max_temp: Some(50),
+            ac_power: Some(true),
+        };
+        let mut facts = Facts {
+            driver_version: "575.57.08".to_string(),
+            architecture: GpuArchitecture::Blackwell,
+            temperature: Some(41),
+            on_ac: None,
+        };
+        assert!(conditions.unmet(&facts).is_empty());
+
+        facts.driver_version = "575.9".to_string();
+        facts.temperature = Some(63);
+        facts.on_ac = Some(false);
+        assert_eq!(
+            conditions.unmet(&facts),
+            vec![
+                "driver 575.9 is older than 575.51".to_string(),
+                "gpu is at 63°C, above 50°C".to_string(),
+                "running on battery".to_string(),
+            ]
+        );
+    }
+}
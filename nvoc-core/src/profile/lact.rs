@@ -0,0 +1,165 @@
+//! Import of LACT (Linux GPU Control Application) configuration
+//!
+//! LACT keeps per-GPU settings in `/etc/lact/config.yaml`, keyed by PCI
+//! IDs and bus address:
+//!
+//! ```yaml
+//! gpus:
+//!   10DE:2B85-1458:4160-0000:01:00.0:
+//!     fan_control_enabled: true
+//!     fan_control_settings:
+//!       mode: curve
+//!       curve: { 40: 0.3, 60: 0.5, 80: 1.0 }
+//!     power_cap: 450.0
+//!     min_core_clock: 210
+//!     max_core_clock: 2820
+//!     gpu_clock_offsets: { 0: 150 }
+//!     mem_clock_offsets: { 0: 1000 }
+//! ```
+//!
+//! Both tools drive the same NVML offset calls, so offsets carry over
+//! unchanged. LACT stores the power cap in watts, which is converted to a
+//! percentage of the GPU's default limit.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::profile::Settings;
+use crate::units::Watts;
+
+#[derive(Debug, Default, Deserialize)]
+struct LactConfig {
+    #[serde(default)]
+    gpus: BTreeMap<String, LactGpu>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LactGpu {
+    #[serde(default)]
+    fan_control_enabled: bool,
+    fan_control_settings: Option<LactFanSettings>,
+    /// Power limit in watts
+    power_cap: Option<f64>,
+    min_core_clock: Option<u32>,
+    max_core_clock: Option<u32>,
+    /// Offsets in MHz keyed by performance state
+    #[serde(default)]
+    gpu_clock_offsets: BTreeMap<u32, i32>,
+    #[serde(default)]
+    mem_clock_offsets: BTreeMap<u32, i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct LactFanSettings {
+    mode: Option<String>,
+    /// Fan speed as a fraction keyed by temperature
+    #[serde(default)]
+    curve: BTreeMap<u32, f64>,
+}
+
+impl LactGpu {
+    /// Settings for this GPU. `default_watts` is needed to convert the
+    /// power cap and the cap is dropped without it.
+    pub fn settings(&self, default_watts: Option<Watts>) -> Settings {
+        Settings {
+            clocks: self.min_core_clock.zip(self.max_core_clock),
+            mem_clocks: None,
+            graphics_offset: pstate_offset(&self.gpu_clock_offsets),
+            memory_offset: pstate_offset(&self.mem_clock_offsets),
+            power_limit: self
+                .power_cap
+                .zip(default_watts.filter(|w| w.0 > 0))
+                .map(|(cap, default)| (cap / default.0 as f64 * 100.0).round() as u32),
+            fan_speed: None,
+            fan_curve: self.fan_curve(),
+        }
+    }
+
+    /// Fan curve as `(temp, percent)` points, when LACT runs one
+    fn fan_curve(&self) -> Option<Vec<(u32, u32)>> {
+        let fan = self.fan_control_settings.as_ref().filter(|_| self.fan_control_enabled)?;
+        if fan.mode.as_deref() != Some("curve") || fan.curve.is_empty() {
+            return None;
+        }
+        Some(
+            fan.curve
+                .iter()
+                .map(|(&temp, &speed)| (temp, (speed * 100.0).round().clamp(0.0, 100.0) as u32))
+                .collect(),
+        )
+    }
+}
+
+/// nvoc applies offsets to the highest performance state, P0
+fn pstate_offset(offsets: &BTreeMap<u32, i32>) -> Option<i32> {
+    offsets.get(&0).or_else(|| offsets.values().next()).copied()
+}
+
+/// PCI bus address from a LACT GPU key, e.g. `0000:01:00.0` from
+/// `10DE:2B85-1458:4160-0000:01:00.0`
+fn pci_bus_id(key: &str) -> Option<&str> {
+    key.splitn(3, '-').nth(2).filter(|id| !id.is_empty())
+}
+
+/// Parse a LACT config into `(pci bus id, settings)` pairs
+pub fn parse(text: &str) -> Result<Vec<(String, LactGpu)>, String> {
+    let config: LactConfig = serde_yaml::from_str(text).map_err(|e| e.to_string())?;
+    config
+        .gpus
+        .into_iter()
+        .map(|(key, gpu)| match pci_bus_id(&key) {
+            Some(id) => Ok((id.to_string(), gpu)),
+            None => Err(format!("unrecognized GPU key '{key}'")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "
+version: 5
+daemon:
+  log_level: info
+gpus:
+  10DE:2B85-1458:4160-0000:01:00.0:
+    fan_control_enabled: true
+    fan_control_settings:
+      mode: curve
+      static_speed: 0.5
+      curve:
+        40: 0.3
+        80: 1.0
+    power_cap: 540.0
+    min_core_clock: 210
+    max_core_clock: 2820
+    gpu_clock_offsets:
+      0: 150
+    mem_clock_offsets:
+      0: 1000
+";
+
+    #[test]
+    fn test_parse_lact_config() {
+        let gpus = parse(CONFIG).unwrap();
+        assert_eq!(gpus.len(), 1);
+        let (bus_id, gpu) = &gpus[0];
+        assert_eq!(bus_id, "0000:01:00.0");
+        assert_eq!(
+            gpu.settings(Some(Watts(575))),
+            Settings {
+                clocks: Some((210, 2820)),
+                mem_clocks: None,
+                graphics_offset: Some(150),
+                memory_offset: Some(1000),
+                power_limit: Some(94),
+                fan_speed: None,
+                fan_curve: Some(vec![(40, 30), (80, 100)]),
+            }
+        );
+        assert_eq!(gpu.settings(None).power_limit, None);
+        assert_eq!(gpu.fan_curve(), Some(vec![(40, 30), (80, 100)]));
+    }
+}
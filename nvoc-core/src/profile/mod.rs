@@ -0,0 +1,438 @@
+//! Named overclock profiles stored as TOML
+//!
+//! Top-level settings apply to every GPU. `[gpu."<uuid>"]` sections
+//! override them for a specific card, so mixed multi-GPU rigs get the right
+//! settings regardless of enumeration order.
+//!
+//! ```toml
+//! # /etc/nvoc/profiles/daily.toml
+//! clocks = [200, 2820]
+//! mem_clocks = [405, 14001]
+//! power_limit = 105
+//!
+//! [gpu."GPU-5a7e1c52-3f0e-4b6c-9d8a-0c1e2f3a4b5c"]
+//! graphics_offset = 856
+//! memory_offset = 2000
+//! fan_curve = [[40, 30], [60, 50], [80, 100]]
+//! ```
+//!
+//! Fans are part of the bundle: `fan_speed` fixes the duty cycle when the
+//! profile is applied, while a `fan_curve` is followed by the daemon for as
+//! long as the profile stays applied.
+//!
+//! An optional `[requires]` section restricts where the profile may be
+//! applied; see [`conditions`].
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::params::OverclockParams;
+use crate::constants::daemon::MAX_FAN_SPEED;
+use crate::constants::paths;
+use crate::daemon::curve::FanCurve;
+use crate::gpu::domain::get_power_info;
+use crate::gpu::virtualization::{self, Knob};
+use crate::gpu::{self, fan, overclock, state};
+use crate::nvml::{self, Device};
+use crate::{output, AppError, ErrorKind};
+use conditions::{Conditions, Facts};
+
+pub mod afterburner;
+pub mod bundle;
+pub mod conditions;
+pub mod lact;
+pub mod stability;
+
+/// Settings for one GPU
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// Locked graphics clock range `[min, max]` in MHz
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clocks: Option<(u32, u32)>,
+    /// Locked memory clock range `[min, max]` in MHz
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mem_clocks: Option<(u32, u32)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub graphics_offset: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_offset: Option<i32>,
+    /// Power limit as a percentage of the default limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub power_limit: Option<u32>,
+    /// Fixed fan duty cycle in percent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fan_speed: Option<u32>,
+    /// `[temp, speed]` points followed by the daemon
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fan_curve: Option<Vec<(u32, u32)>>,
+}
+
+impl Settings {
+    pub fn from_params(params: &OverclockParams) -> Self {
+        Self {
+            clocks: params.clocks,
+            mem_clocks: params.mem_clocks,
+            graphics_offset: params.graphics_offset,
+            memory_offset: params.memory_offset,
+            power_limit: params.power_limit,
+            fan_speed: None,
+            fan_curve: None,
+        }
+    }
+
+    pub fn to_params(&self, dry_run: bool) -> OverclockParams {
+        OverclockParams {
+            clocks: self.clocks,
+            mem_clocks: self.mem_clocks,
+            graphics_offset: self.graphics_offset,
+            memory_offset: self.memory_offset,
+            graphics_pstate_offsets: Vec::new(),
+            memory_pstate_offsets: Vec::new(),
+            power_limit: self.power_limit,
+            dry_run,
+            verify: true,
+            revert_after: None,
+        }
+    }
+
+    /// Build settings from what is currently applied to a device. Locked
+    /// clocks cannot be read back and are not captured.
+    pub fn capture(device: &Device) -> Self {
+        let current = state::capture(device);
+        Self {
+            clocks: None,
+            mem_clocks: None,
+            graphics_offset: current.graphics_offset.map(|o| o.0),
+            memory_offset: current.memory_offset.map(|o| o.0),
+            power_limit: get_power_info(device).ok().map(|p| p.current_percentage()),
+            fan_speed: None,
+            fan_curve: None,
+        }
+    }
+
+    /// The fan curve, validated
+    pub fn curve(&self) -> Result<Option<FanCurve>, AppError> {
+        match &self.fan_curve {
+            Some(points) => FanCurve::new(points.clone()).map(Some).map_err(|e| AppError::msg("profile", e)),
+            None => Ok(None),
+        }
+    }
+
+    /// Fill unset values from `base`
+    fn or(self, base: &Settings) -> Self {
+        Self {
+            clocks: self.clocks.or(base.clocks),
+            mem_clocks: self.mem_clocks.or(base.mem_clocks),
+            graphics_offset: self.graphics_offset.or(base.graphics_offset),
+            memory_offset: self.memory_offset.or(base.memory_offset),
+            power_limit: self.power_limit.or(base.power_limit),
+            fan_speed: self.fan_speed.or(base.fan_speed),
+            fan_curve: self.fan_curve.or_else(|| base.fan_curve.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clocks: Option<(u32, u32)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mem_clocks: Option<(u32, u32)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub graphics_offset: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_offset: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub power_limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fan_speed: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fan_curve: Option<Vec<(u32, u32)>>,
+    /// Per-GPU overrides keyed by NVML UUID
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub gpu: BTreeMap<String, Settings>,
+    /// Checked before applying to any GPU
+    #[serde(default, skip_serializing_if = "Conditions::is_empty")]
+    pub requires: Conditions,
+}
+
+impl Profile {
+    pub fn from_settings(settings: Settings) -> Self {
+        Self {
+            clocks: settings.clocks,
+            mem_clocks: settings.mem_clocks,
+            graphics_offset: settings.graphics_offset,
+            memory_offset: settings.memory_offset,
+            power_limit: settings.power_limit,
+            fan_speed: settings.fan_speed,
+            fan_curve: settings.fan_curve,
+            gpu: BTreeMap::new(),
+            requires: Conditions::default(),
+        }
+    }
+
+    /// Settings applying to every GPU
+    pub fn defaults(&self) -> Settings {
+        Settings {
+            clocks: self.clocks,
+            mem_clocks: self.mem_clocks,
+            graphics_offset: self.graphics_offset,
+            memory_offset: self.memory_offset,
+            power_limit: self.power_limit,
+            fan_speed: self.fan_speed,
+            fan_curve: self.fan_curve.clone(),
+        }
+    }
+
+    /// Effective settings for the GPU with `uuid`: its section layered over
+    /// the top-level defaults
+    pub fn settings_for(&self, uuid: &str) -> Settings {
+        match self.gpu.get(uuid) {
+            Some(section) => section.clone().or(&self.defaults()),
+            None => self.defaults(),
+        }
+    }
+}
+
+/// Profile directory from the config's `paths.profiles`, if set
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Keep profiles in `dir` instead of the default directory
+pub fn set_dir(dir: PathBuf) {
+    let _ = DIR.set(dir);
+}
+
+fn dir() -> PathBuf {
+    DIR.get().cloned().unwrap_or_else(|| paths::system(paths::PROFILES_DIR))
+}
+
+fn profile_path(name: &str) -> Result<PathBuf, AppError> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(AppError::msg("profile", format!("invalid profile name '{name}'")).with_kind(ErrorKind::InvalidArgument));
+    }
+    Ok(dir().join(format!("{name}.toml")))
+}
+
+pub fn load(name: &str) -> Result<Profile, AppError> {
+    let path = profile_path(name)?;
+    let text = fs::read_to_string(&path)
+        .map_err(|e| AppError::msg("profile", format!("{}: {e}", path.display())))?;
+    toml::from_str(&text)
+        .map_err(|e| AppError::msg("profile", format!("{}: {}", path.display(), e.message())))
+}
+
+/// Load a profile, or an empty one if it does not exist yet
+pub fn load_or_default(name: &str) -> Result<Profile, AppError> {
+    match profile_path(name)?.exists() {
+        true => load(name),
+        false => Ok(Profile::default()),
+    }
+}
+
+pub fn save(name: &str, profile: &Profile) -> Result<PathBuf, AppError> {
+    let path = profile_path(name)?;
+    let text = toml::to_string(profile).map_err(|e| AppError::msg("profile", e.to_string()))?;
+    fs::create_dir_all(dir())
+        .and_then(|()| fs::write(&path, text))
+        .map_err(|e| AppError::msg("profile", format!("{}: {e}", path.display())))?;
+    Ok(path)
+}
+
+/// Names of all saved profiles, sorted
+pub fn list() -> Result<Vec<String>, AppError> {
+    let entries = match fs::read_dir(dir()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::msg("profile", format!("{}: {e}", dir().display()))),
+    };
+
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            match path.extension()?.to_str()? {
+                "toml" => path.file_stem()?.to_str().map(str::to_string),
+                _ => None,
+            }
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Apply a profile's settings for a device and record it as applied. The
+/// profile's conditions are checked first and nothing is changed if any
+/// is unmet.
+pub fn apply(device: &Device, device_index: u32, name: &str, dry_run: bool) -> Result<(), AppError> {
+    let uuid = nvml::device_get_uuid(device).map_err(|e| AppError::new("profile", e))?;
+    let profile = load(name)?;
+    let unmet = profile.requires.unmet(&Facts::gather(device));
+    if !unmet.is_empty() {
+        return Err(AppError::msg("profile", format!("'{name}' not applied: {}", unmet.join(", "))));
+    }
+    let settings = profile.settings_for(&uuid);
+    if settings == Settings::default() {
+        return Err(AppError::msg("profile", format!("'{name}' has no settings for {uuid}")));
+    }
+    output::status!("profile: {name}");
+    apply_settings(device, device_index, &settings, dry_run)?;
+    if !dry_run {
+        record_applied(device_index, Some(name))?;
+    }
+    Ok(())
+}
+
+/// Apply clocks, offsets, power and fans together. If the fans cannot be
+/// set, the other settings are rolled back so the bundle is all or nothing.
+pub fn apply_settings(device: &Device, device_index: u32, settings: &Settings, dry_run: bool) -> Result<(), AppError> {
+    if settings.fan_speed.is_some() && settings.fan_curve.is_some() {
+        let err = AppError::msg("profile", "fan_speed and fan_curve cannot both be set".to_string());
+        return Err(err.with_kind(ErrorKind::InvalidArgument));
+    }
+    if let Some(speed) = settings.fan_speed.filter(|&s| s > MAX_FAN_SPEED) {
+        let err = AppError::msg("profile", format!("fan speed {speed}% exceeds {MAX_FAN_SPEED}%"));
+        return Err(err.with_kind(ErrorKind::InvalidArgument));
+    }
+    let curve = settings.curve()?;
+    if settings.fan_speed.is_some() || curve.is_some() {
+        virtualization::check_knobs(device, &[Knob::Fans])?;
+    }
+    if let Some(speed) = settings.fan_speed {
+        fan::check_speed(device, speed)?;
+    }
+
+    let before = state::capture(device);
+    let params = settings.to_params(dry_run);
+    if !params.is_empty() {
+        overclock::apply(device, device_index, &params)?;
+    }
+
+    if let Some(speed) = settings.fan_speed {
+        if dry_run {
+            println!("fan: {speed}% (dry run)");
+        } else if let Err(e) = fan::set_speed(device, speed) {
+            if !params.is_empty() {
+                eprintln!("warning[profile]: fans not set, rolling back");
+                state::restore(device, &before).map_err(|e| AppError::new("profile", e))?;
+            }
+            return Err(AppError::new("fan", e).context(format!("set fan speed ({speed}%)")).on_device(device.index()));
+        } else {
+            output::status!("fan: {speed}%");
+        }
+    }
+    if let Some(points) = &settings.fan_curve {
+        output::status!("fan: curve with {} points, followed by the daemon", points.len());
+    }
+    Ok(())
+}
+
+fn applied_path(device_index: u32) -> PathBuf {
+    paths::system(paths::RUNTIME_DIR).join(format!("profile-{device_index}"))
+}
+
+/// Record which profile is applied to a device, or clear the record when
+/// its settings were changed some other way. The record lives in the
+/// runtime directory so it disappears on reboot along with the settings.
+pub fn record_applied(device_index: u32, name: Option<&str>) -> Result<(), AppError> {
+    let path = applied_path(device_index);
+    let result = match name {
+        Some(name) => fs::create_dir_all(paths::system(paths::RUNTIME_DIR)).and_then(|()| fs::write(&path, name)),
+        None => match fs::remove_file(&path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        },
+    };
+    result.map_err(|e| AppError::msg("profile", format!("{}: {e}", path.display())))
+}
+
+/// Name of the profile last applied to a device this boot
+pub fn applied(device_index: u32) -> Option<String> {
+    fs::read_to_string(applied_path(device_index))
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Convert a LACT config into a profile with a section for each of its
+/// GPUs present in this system
+pub fn import_lact(path: &Path) -> Result<Profile, AppError> {
+    let read_error = |e: String| AppError::msg("profile", format!("{}: {e}", path.display()));
+    let text = fs::read_to_string(path).map_err(|e| read_error(e.to_string()))?;
+    let gpus = lact::parse(&text).map_err(read_error)?;
+
+    let mut profile = Profile::default();
+    for (bus_id, gpu) in gpus {
+        let device = &match gpu::get_device_by_pci_bus_id(&bus_id) {
+            Ok(device) => device,
+            Err(e) => {
+                eprintln!("warning[profile]: skipping {bus_id}: {e}");
+                continue;
+            }
+        };
+        let uuid = nvml::device_get_uuid(device).map_err(|e| AppError::new("profile", e))?;
+        let default_watts = get_power_info(device).ok().map(|p| p.default_watts);
+        output::status!("profile: {bus_id} -> {uuid}");
+
+        profile.gpu.insert(uuid, gpu.settings(default_watts));
+    }
+
+    if profile.gpu.is_empty() {
+        return Err(read_error("no matching GPUs found".to_string()));
+    }
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_roundtrip() {
+        let mut profile = Profile {
+            clocks: Some((200, 2820)),
+            mem_clocks: Some((405, 14001)),
+            graphics_offset: Some(856),
+            memory_offset: None,
+            power_limit: Some(105),
+            fan_speed: None,
+            fan_curve: Some(vec![(40, 30), (80, 100)]),
+            gpu: BTreeMap::new(),
+            requires: Conditions { max_temp: Some(50), ..Default::default() },
+        };
+        profile.gpu.insert(
+            "GPU-1234".to_string(),
+            Settings { memory_offset: Some(1500), ..Default::default() },
+        );
+        let text = toml::to_string(&profile).unwrap();
+        assert_eq!(toml::from_str::<Profile>(&text).unwrap(), profile);
+    }
+
+    #[test]
+    fn test_gpu_section_overrides_defaults() {
+        let profile: Profile = toml::from_str(
+            "power_limit = 90\ngraphics_offset = 100\n\n[gpu.\"GPU-b\"]\ngraphics_offset = 250\n",
+        )
+        .unwrap();
+        let b = profile.settings_for("GPU-b");
+        assert_eq!((b.graphics_offset, b.power_limit), (Some(250), Some(90)));
+        let a = profile.settings_for("GPU-a");
+        assert_eq!((a.graphics_offset, a.power_limit), (Some(100), Some(90)));
+    }
+
+    #[test]
+    fn test_profile_name_validation() {
+        assert!(profile_path("daily-driver_2").is_ok());
+        assert!(profile_path("../etc/passwd").is_err());
+        assert!(profile_path(".hidden").is_err());
+        assert!(profile_path("").is_err());
+    }
+}
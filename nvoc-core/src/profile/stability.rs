@@ -0,0 +1,303 @@
+//! Stability scoring from soak runs
+//!
+//! `profile soak` applies a profile and watches the GPU while the user runs
+//! a load. Clock variance, slowdown events and failed readings are folded
+//! into a 0-100 score, which is kept per profile so `profile list` shows
+//! which profiles have been validated and how thoroughly.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{hardware, paths};
+use crate::gpu::ecc;
+use crate::nvml::{
+    self, Device, NvmlClockType, NVML_CLOCKS_EVENT_REASON_HW_POWER_BRAKE_SLOWDOWN,
+    NVML_CLOCKS_EVENT_REASON_HW_SLOWDOWN, NVML_CLOCKS_EVENT_REASON_HW_THERMAL_SLOWDOWN,
+    NVML_CLOCKS_EVENT_REASON_SW_THERMAL_SLOWDOWN,
+};
+use crate::xid::XidGuard;
+use crate::{output, signal, AppError};
+
+/// Time between samples during a soak
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Slowdowns that point at an unstable or overheating card. Sitting at the
+/// power limit is expected under load and does not count.
+pub const SLOWDOWN_MASK: u64 = NVML_CLOCKS_EVENT_REASON_HW_SLOWDOWN
+    | NVML_CLOCKS_EVENT_REASON_SW_THERMAL_SLOWDOWN
+    | NVML_CLOCKS_EVENT_REASON_HW_THERMAL_SLOWDOWN
+    | NVML_CLOCKS_EVENT_REASON_HW_POWER_BRAKE_SLOWDOWN;
+
+/// Outcome of one soak run
+#[derive(Debug, Default)]
+pub struct Soak {
+    clocks: Vec<u32>,
+    /// Transitions into a slowdown state
+    slowdowns: u32,
+    /// Samples where the driver failed to answer
+    errors: u32,
+    in_slowdown: bool,
+    /// XID error that ended the soak, after which the overclock was reverted
+    pub xid: Option<u64>,
+    peak_temp_c: Option<u32>,
+    peak_power_mw: Option<u32>,
+    /// Sum and count of power readings, for the average draw
+    power_total_mw: u64,
+    power_samples: u32,
+    /// PCIe packets retransmitted during the run, when the GPU counts them
+    pub pcie_replays: Option<u32>,
+    /// Memory errors ECC corrected during the run, on GPUs with ECC
+    pub ecc_errors: Option<u64>,
+    pub duration: Duration,
+}
+
+impl Soak {
+    fn record(&mut self, clock: Option<u32>, reasons: Option<u64>) {
+        match clock {
+            Some(mhz) => self.clocks.push(mhz),
+            None => self.errors += 1,
+        }
+        let slowed = reasons.is_some_and(|r| r & SLOWDOWN_MASK != 0);
+        if slowed && !self.in_slowdown {
+            self.slowdowns += 1;
+        }
+        self.in_slowdown = slowed;
+    }
+
+    fn observe(&mut self, temp_c: Option<u32>, power_mw: Option<u32>) {
+        self.peak_temp_c = self.peak_temp_c.max(temp_c);
+        self.peak_power_mw = self.peak_power_mw.max(power_mw);
+        if let Some(power) = power_mw {
+            self.power_total_mw += u64::from(power);
+            self.power_samples += 1;
+        }
+    }
+
+    /// Average graphics clock in MHz, if any reading succeeded
+    pub fn mean_clock_mhz(&self) -> Option<f64> {
+        (!self.clocks.is_empty()).then(|| self.clocks.iter().map(|&c| c as f64).sum::<f64>() / self.clocks.len() as f64)
+    }
+
+    pub fn peak_temp_c(&self) -> Option<u32> {
+        self.peak_temp_c
+    }
+
+    /// Average power draw in watts, if any reading succeeded
+    pub fn mean_power_w(&self) -> Option<f64> {
+        (self.power_samples > 0)
+            .then(|| self.power_total_mw as f64 / self.power_samples as f64 / hardware::MILLIWATTS_TO_WATTS as f64)
+    }
+
+    /// Retransmitted PCIe packets between two readings of the counter
+    fn count_replays(&mut self, before: Option<u32>, after: Option<u32>) {
+        self.pcie_replays = before.zip(after).map(|(before, after)| after.saturating_sub(before));
+    }
+
+    /// Corrected ECC errors between two readings of the counter
+    fn count_ecc_errors(&mut self, before: Option<u64>, after: Option<u64>) {
+        self.ecc_errors = before.zip(after).map(|(before, after)| after.saturating_sub(before));
+    }
+
+    /// Highest temperature and power draw seen, when they could be read
+    pub fn peaks(&self) -> String {
+        let mut peaks = Vec::new();
+        if let Some(temp) = self.peak_temp_c {
+            peaks.push(format!("{temp}°C"));
+        }
+        if let Some(power) = self.peak_power_mw {
+            peaks.push(format!("{}W", power / hardware::MILLIWATTS_TO_WATTS));
+        }
+        match peaks.is_empty() {
+            true => "no readings".to_string(),
+            false => format!("peak {}", peaks.join(", ")),
+        }
+    }
+
+    /// Graphics clock standard deviation as a percentage of the mean
+    fn clock_variation(&self) -> f64 {
+        if self.clocks.len() < 2 {
+            return 0.0;
+        }
+        let n = self.clocks.len() as f64;
+        let mean = self.clocks.iter().map(|&c| c as f64).sum::<f64>() / n;
+        if mean == 0.0 {
+            return 0.0;
+        }
+        let variance = self.clocks.iter().map(|&c| (c as f64 - mean).powi(2)).sum::<f64>() / n;
+        variance.sqrt() / mean * 100.0
+    }
+
+    /// Zero after an XID error, otherwise from 100 down, losing up to 40
+    /// points for clock variation, 30 for slowdown events and 30 for driver
+    /// errors
+    pub fn score(&self) -> u32 {
+        if self.xid.is_some() {
+            return 0;
+        }
+        let variation = (self.clock_variation() * 4.0).min(40.0);
+        let slowdowns = (self.slowdowns as f64 * 5.0).min(30.0);
+        let errors = (self.errors as f64 * 15.0).min(30.0);
+        (100.0 - variation - slowdowns - errors).round() as u32
+    }
+
+    pub fn summary(&self) -> String {
+        if let Some(xid) = self.xid {
+            return format!("score 0 (xid {xid}, overclock reverted)");
+        }
+        let mut extra = String::new();
+        if let Some(replays) = self.pcie_replays.filter(|&r| r > 0) {
+            extra += &format!(", {replays} pcie replays");
+        }
+        if let Some(errors) = self.ecc_errors.filter(|&e| e > 0) {
+            extra += &format!(", {errors} ecc errors");
+        }
+        format!(
+            "score {} (clock variation {:.1}%, {} slowdowns, {} errors{extra})",
+            self.score(),
+            output::Decimal(self.clock_variation()),
+            self.slowdowns,
+            self.errors
+        )
+    }
+}
+
+/// Sample a device until `duration` passes, a stop is requested or an XID
+/// error reverts the overclock
+pub fn soak(device: &Device, device_index: u32, duration: Duration) -> Result<Soak, AppError> {
+    soak_while(device, device_index, duration, || true)
+}
+
+/// Like [`soak`], also ending as soon as `running` returns false
+pub fn soak_while(
+    device: &Device,
+    device_index: u32,
+    duration: Duration,
+    mut running: impl FnMut() -> bool,
+) -> Result<Soak, AppError> {
+    let started = Instant::now();
+    let mut soak = Soak::default();
+    let guard = XidGuard::new(device, device_index);
+    let replays = nvml::device_get_pcie_replay_counter(device).ok();
+    let ecc_errors = ecc::corrected(device).ok();
+    signal::install();
+    while started.elapsed() < duration && running() {
+        soak.xid = guard.check()?;
+        if soak.xid.is_some() {
+            break;
+        }
+        soak.record(
+            nvml::device_get_clock_info(device, NvmlClockType::Graphics).ok(),
+            nvml::device_get_current_clocks_event_reasons(device).ok(),
+        );
+        soak.observe(nvml::device_get_temperature(device).ok(), nvml::device_get_power_usage(device).ok());
+        if !signal::sleep(SAMPLE_INTERVAL) {
+            break;
+        }
+    }
+    soak.count_replays(replays, nvml::device_get_pcie_replay_counter(device).ok());
+    soak.count_ecc_errors(ecc_errors, ecc::corrected(device).ok());
+    soak.duration = started.elapsed();
+    Ok(soak)
+}
+
+/// Validation history of one profile
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// Score of the most recent run
+    pub score: u32,
+    pub runs: u32,
+    /// Total soak time across all runs
+    pub seconds: u64,
+}
+
+impl std::fmt::Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let runs = if self.runs == 1 { "run" } else { "runs" };
+        write!(f, "score {}, {} {runs}, {}m soaked", self.score, self.runs, self.seconds / 60)
+    }
+}
+
+/// Stability records keyed by profile name
+pub fn load() -> BTreeMap<String, Record> {
+    fs::read_to_string(paths::system(paths::STABILITY_FILE))
+        .ok()
+        .and_then(|text| toml::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Add a soak run to a profile's record
+pub fn record(name: &str, soak: &Soak) -> Result<Record, AppError> {
+    let path = &paths::system(paths::STABILITY_FILE);
+    let err = |e: String| AppError::msg("stability", format!("{}: {e}", path.display()));
+
+    let mut records = load();
+    let entry = records.entry(name.to_string()).or_default();
+    entry.score = soak.score();
+    entry.runs += 1;
+    entry.seconds += soak.duration.as_secs();
+    let updated = entry.clone();
+
+    let text = toml::to_string(&records).map_err(|e| err(e.to_string()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| err(e.to_string()))?;
+    }
+    fs::write(path, text).map_err(|e| err(e.to_string()))?;
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steady_run_scores_full() {
+        let mut soak = Soak::default();
+        for _ in 0..10 {
+            soak.record(Some(2800), Some(0));
+        }
+        assert_eq!(soak.score(), 100);
+        soak.xid = Some(79);
+        assert_eq!(soak.score(), 0);
+    }
+
+    #[test]
+    fn test_slowdowns_counted_once_per_episode() {
+        let mut soak = Soak::default();
+        let thermal = NVML_CLOCKS_EVENT_REASON_HW_THERMAL_SLOWDOWN;
+        for reasons in [0, thermal, thermal, 0, NVML_CLOCKS_EVENT_REASON_HW_SLOWDOWN] {
+            soak.record(Some(2800), Some(reasons));
+        }
+        soak.record(None, None);
+        assert_eq!((soak.slowdowns, soak.errors), (2, 1));
+        assert_eq!(soak.score(), 100 - 10 - 15);
+    }
+
+    #[test]
+    fn test_peaks() {
+        let mut soak = Soak::default();
+        assert_eq!(soak.peaks(), "no readings");
+        soak.observe(Some(71), None);
+        soak.observe(Some(68), Some(574_900));
+        assert_eq!(soak.peaks(), "peak 71°C, 574W");
+        soak.observe(None, Some(425_100));
+        assert_eq!(soak.mean_power_w(), Some(500.0));
+        assert_eq!(soak.mean_clock_mhz(), None);
+    }
+
+    #[test]
+    fn test_pcie_replays() {
+        let mut soak = Soak::default();
+        soak.record(Some(2800), Some(0));
+        soak.count_replays(Some(40), Some(40));
+        assert_eq!(soak.summary(), "score 100 (clock variation 0.0%, 0 slowdowns, 0 errors)");
+        soak.count_replays(Some(40), Some(52));
+        assert_eq!(soak.summary(), "score 100 (clock variation 0.0%, 0 slowdowns, 0 errors, 12 pcie replays)");
+        soak.count_replays(None, Some(52));
+        assert_eq!(soak.pcie_replays, None);
+        soak.count_ecc_errors(Some(0), Some(3));
+        assert_eq!(soak.summary(), "score 100 (clock variation 0.0%, 0 slowdowns, 0 errors, 3 ecc errors)");
+    }
+}
@@ -0,0 +1,67 @@
+//! Per-device rate limiting of modifying operations
+//!
+//! The time of the last modification of each device is stamped in a file
+//! under the runtime directory, so the limit holds across separate CLI
+//! invocations as well as within the daemon. Callers that arrive too soon
+//! are delayed rather than rejected, which throttles runaway scripts while
+//! keeping back-to-back manual commands working.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::constants::paths;
+use crate::AppError;
+
+/// Exclusive claim on a device's modification slot. Concurrent callers
+/// block until it is dropped.
+pub struct ModifyPermit {
+    _file: File,
+}
+
+fn stamp_path(device_index: u32) -> PathBuf {
+    paths::system(paths::RUNTIME_DIR).join(format!("modify-{device_index}"))
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Wait until `min_interval` has passed since the last modification of the
+/// device, then record a new modification
+pub fn acquire(device_index: u32, min_interval: Duration) -> Result<ModifyPermit, AppError> {
+    let path = stamp_path(device_index);
+    let err = |e: std::io::Error| AppError::msg("rate limit", format!("{}: {e}", path.display()));
+
+    fs::create_dir_all(paths::system(paths::RUNTIME_DIR)).map_err(err)?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(err)?;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        return Err(err(std::io::Error::last_os_error()));
+    }
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).map_err(err)?;
+    if let Ok(last) = contents.trim().parse::<u64>() {
+        let elapsed = Duration::from_millis(now_millis().saturating_sub(last));
+        if elapsed < min_interval {
+            std::thread::sleep(min_interval - elapsed);
+        }
+    }
+
+    file.set_len(0).map_err(err)?;
+    file.seek(SeekFrom::Start(0)).map_err(err)?;
+    write!(file, "{}", now_millis()).map_err(err)?;
+
+    Ok(ModifyPermit { _file: file })
+}
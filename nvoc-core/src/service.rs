@@ -0,0 +1,199 @@
+//! systemd units for applying a profile at boot
+//!
+//! `install-service` writes a oneshot service running `profile apply`, a
+//! udev rule that starts it again whenever the nvidia kernel module is
+//! (re)loaded, and optionally a timer that re-applies it periodically. A
+//! second unit clears the active profile marker on clean shutdown, so
+//! `profile apply --boot` can tell when the machine went down uncleanly.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::constants::paths;
+use crate::{output, AppError};
+
+const SERVICE_FILE: &str = "/etc/systemd/system/nvoc-apply.service";
+const TIMER_FILE: &str = "/etc/systemd/system/nvoc-apply.timer";
+const SHUTDOWN_FILE: &str = "/etc/systemd/system/nvoc-clean-shutdown.service";
+const UDEV_RULE_FILE: &str = "/etc/udev/rules.d/99-nvoc-apply.rules";
+
+/// GPUs the installed service applies the profile to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Device(u32),
+    All,
+}
+
+fn render_service(exe: &Path, profile: &str, target: Target) -> String {
+    let target = match target {
+        Target::Device(index) => format!("-d {index}"),
+        Target::All => "--all".to_string(),
+    };
+    format!(
+        "[Unit]
+Description=Apply nvoc profile '{profile}'
+After=nvidia-persistenced.service
+
+[Service]
+Type=oneshot
+ExecStart={} profile apply {profile} {target} --boot
+# The driver may still be initializing when started by the udev rule
+Restart=on-failure
+RestartSec=5
+
+[Install]
+WantedBy=multi-user.target
+",
+        exe.display()
+    )
+}
+
+/// Stays active while the system runs; stopping it at shutdown removes the
+/// markers, which therefore only survive a crash or power loss
+fn render_shutdown_service() -> String {
+    format!(
+        "[Unit]
+Description=Record clean shutdown for nvoc
+
+[Service]
+Type=oneshot
+RemainAfterExit=yes
+ExecStart=/bin/true
+ExecStop=/bin/rm -rf {}
+
+[Install]
+WantedBy=multi-user.target
+",
+        paths::ACTIVE_PROFILE_DIR
+    )
+}
+
+fn render_timer(interval: Duration) -> String {
+    format!(
+        "[Unit]
+Description=Re-apply nvoc profile periodically
+
+[Timer]
+OnBootSec={0}s
+OnUnitActiveSec={0}s
+
+[Install]
+WantedBy=timers.target
+",
+        interval.as_secs()
+    )
+}
+
+fn render_udev_rule() -> String {
+    "# Re-apply the nvoc profile when the nvidia driver is loaded or reloaded
+ACTION==\"add\", SUBSYSTEM==\"module\", KERNEL==\"nvidia\", TAG+=\"systemd\", ENV{SYSTEMD_WANTS}+=\"nvoc-apply.service\"
+"
+    .to_string()
+}
+
+fn write(path: &str, contents: &str, dry_run: bool) -> Result<(), AppError> {
+    if dry_run {
+        println!("# {path}\n{contents}");
+        return Ok(());
+    }
+    fs::write(path, contents).map_err(|e| AppError::msg("service", format!("{path}: {e}")))?;
+    output::status!("service: wrote {path}");
+    Ok(())
+}
+
+fn remove(path: &str, dry_run: bool) -> Result<(), AppError> {
+    if !Path::new(path).exists() {
+        return Ok(());
+    }
+    if dry_run {
+        println!("service: would remove {path}");
+        return Ok(());
+    }
+    fs::remove_file(path).map_err(|e| AppError::msg("service", format!("{path}: {e}")))?;
+    output::status!("service: removed {path}");
+    Ok(())
+}
+
+fn run(program: &str, args: &[&str], dry_run: bool) -> Result<(), AppError> {
+    let command = format!("{program} {}", args.join(" "));
+    if dry_run {
+        println!("service: would run {command}");
+        return Ok(());
+    }
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| AppError::msg("service", format!("{command}: {e}")))?;
+    if !status.success() {
+        return Err(AppError::msg("service", format!("{command}: {status}")));
+    }
+    Ok(())
+}
+
+/// Enable and start `nvidia-persistenced`, returning false when the
+/// driver installation does not ship its unit
+pub fn enable_persistenced() -> Result<bool, AppError> {
+    let unit = "nvidia-persistenced.service";
+    let installed = Command::new("systemctl").args(["cat", unit]).output().is_ok_and(|o| o.status.success());
+    if !installed {
+        return Ok(false);
+    }
+    run("systemctl", &["enable", "--now", unit], false)?;
+    output::status!("service: enabled {unit}");
+    Ok(true)
+}
+
+pub fn install(profile: &str, target: Target, timer: Option<Duration>, dry_run: bool) -> Result<(), AppError> {
+    // Fail now rather than at boot
+    crate::profile::load(profile)?;
+    let exe = std::env::current_exe().map_err(|e| AppError::msg("service", e.to_string()))?;
+
+    write(SERVICE_FILE, &render_service(&exe, profile, target), dry_run)?;
+    write(SHUTDOWN_FILE, &render_shutdown_service(), dry_run)?;
+    write(UDEV_RULE_FILE, &render_udev_rule(), dry_run)?;
+    let mut units = vec!["nvoc-apply.service", "nvoc-clean-shutdown.service"];
+    match timer {
+        Some(interval) => {
+            write(TIMER_FILE, &render_timer(interval), dry_run)?;
+            units.push("nvoc-apply.timer");
+        }
+        None => remove(TIMER_FILE, dry_run)?,
+    }
+
+    run("systemctl", &["daemon-reload"], dry_run)?;
+    run("udevadm", &["control", "--reload"], dry_run)?;
+    run("systemctl", &[&["enable"], units.as_slice()].concat(), dry_run)?;
+    // Started now so that this boot's shutdown is recorded as clean
+    run("systemctl", &["start", "nvoc-clean-shutdown.service"], dry_run)?;
+    Ok(())
+}
+
+pub fn uninstall(dry_run: bool) -> Result<(), AppError> {
+    for unit in ["nvoc-apply.timer", "nvoc-apply.service", "nvoc-clean-shutdown.service"] {
+        let path = format!("/etc/systemd/system/{unit}");
+        if Path::new(&path).exists() {
+            run("systemctl", &["disable", unit], dry_run)?;
+        }
+    }
+    for path in [TIMER_FILE, SERVICE_FILE, SHUTDOWN_FILE, UDEV_RULE_FILE] {
+        remove(path, dry_run)?;
+    }
+    run("systemctl", &["daemon-reload"], dry_run)?;
+    run("udevadm", &["control", "--reload"], dry_run)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_service() {
+        let unit = render_service(Path::new("/usr/bin/nvoc"), "daily", Target::All);
+        assert!(unit.contains("ExecStart=/usr/bin/nvoc profile apply daily --all --boot\n"));
+        let unit = render_service(Path::new("/usr/bin/nvoc"), "daily", Target::Device(1));
+        assert!(unit.contains("ExecStart=/usr/bin/nvoc profile apply daily -d 1 --boot\n"));
+    }
+}
@@ -0,0 +1,145 @@
+//! First-run guided setup
+//!
+//! `nvoc setup` takes a fresh install to a daily-driver overclock: it shows
+//! the detected GPU, proposes small offsets checked against the ranges the
+//! driver reports, enables persistence mode, tries the proposal under a
+//! short soak and, only if it holds, saves it as a profile and installs the
+//! boot service for it. An unstable proposal is reverted and nothing is
+//! installed.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::time::Duration;
+
+use crate::constants::setup::{GRAPHICS_OFFSET_MHZ, MEMORY_OFFSET_MHZ};
+use crate::constants::stress::MIN_STABLE_SCORE;
+use crate::gpu::overclock::{self, OffsetRange, Trial};
+use crate::nvml::{self, Device};
+use crate::profile::bundle::Identity;
+use crate::profile::{self, stability, Profile, Settings};
+use crate::ratelimit::ModifyPermit;
+use crate::{output, service, signal, AppError};
+
+/// `offset`, brought inside `range` when the driver reports one
+fn conservative(offset: i32, range: Option<OffsetRange>) -> i32 {
+    match range {
+        Some(range) => offset.clamp(range.min.0.min(0), range.max.0.max(0)),
+        None => offset,
+    }
+}
+
+fn confirm(question: &str) -> Result<bool, AppError> {
+    if !io::stdin().is_terminal() {
+        return Err(AppError::msg("setup", "stdin is not a terminal, pass --yes to accept the proposal".to_string()));
+    }
+    print!("{question} [y/N] ");
+    io::stdout().flush().map_err(|e| AppError::msg("setup", e.to_string()))?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).map_err(|e| AppError::msg("setup", e.to_string()))?;
+    Ok(matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Keep the driver loaded between clients, so settings are not dropped
+/// when the last one exits
+fn enable_persistence(device: &Device, device_index: u32) -> Result<(), AppError> {
+    if service::enable_persistenced()? {
+        return Ok(());
+    }
+    if nvml::device_get_persistence_mode(device).unwrap_or(false) {
+        output::status!("setup: persistence mode already enabled");
+        return Ok(());
+    }
+    nvml::device_set_persistence_mode(device, true).map_err(|e| AppError::new("persistence", e))?;
+    eprintln!(
+        "warning[setup]: nvidia-persistenced is not installed, persistence mode enabled on gpu {device_index} until the driver is unloaded"
+    );
+    Ok(())
+}
+
+/// Walk through setting up a first profile `name` on `device`, soaking it
+/// for `soak`. `yes` accepts the proposal without asking; `permit` is held
+/// while applying.
+pub fn run(
+    device: &Device,
+    device_index: u32,
+    name: &str,
+    soak: Duration,
+    yes: bool,
+    permit: ModifyPermit,
+) -> Result<(), AppError> {
+    if profile::list()?.iter().any(|n| n == name) {
+        return Err(AppError::msg("setup", format!("profile '{name}' already exists, choose another with --name")));
+    }
+    let identity = Identity::of(device)?;
+    println!(
+        "setup: gpu {device_index} is an {} ({}), driver {}",
+        identity.name, identity.architecture, identity.driver
+    );
+
+    let settings = Settings {
+        graphics_offset: Some(conservative(GRAPHICS_OFFSET_MHZ, overclock::graphics_offset_range(device).ok())),
+        memory_offset: Some(conservative(MEMORY_OFFSET_MHZ, overclock::memory_offset_range(device).ok())),
+        ..Default::default()
+    };
+    let params = settings.to_params(false);
+    println!(
+        "setup: proposed profile '{name}': gpu offset {:+}MHz, mem offset {:+}MHz, stock power limit",
+        settings.graphics_offset.unwrap_or_default(),
+        settings.memory_offset.unwrap_or_default()
+    );
+    if !yes && !confirm("Try it now?")? {
+        println!("setup: cancelled, nothing changed");
+        return Ok(());
+    }
+
+    enable_persistence(device, device_index)?;
+    let trial = Trial::start(device, device_index, &params)?;
+    drop(permit);
+    println!("setup: soaking for {}s, start a game or stress test now", soak.as_secs());
+    let result = match stability::soak(device, device_index, soak) {
+        Ok(result) => result,
+        Err(e) => {
+            trial.revert()?;
+            return Err(e);
+        }
+    };
+    println!("setup: {}, {}", result.summary(), result.peaks());
+    if signal::stop_requested() {
+        trial.revert()?;
+        return Err(AppError::msg("setup", "interrupted, settings reverted and nothing installed".to_string()));
+    }
+    if result.xid.is_some() || result.score() < MIN_STABLE_SCORE || result.ecc_errors.is_some_and(|e| e > 0) {
+        trial.revert()?;
+        return Err(AppError::msg(
+            "setup",
+            "the proposal was not stable on this gpu, settings reverted and nothing installed".to_string(),
+        ));
+    }
+    trial.keep()?;
+
+    let uuid = nvml::device_get_uuid(device).map_err(|e| AppError::new("profile", e))?;
+    let mut saved = Profile::default();
+    saved.gpu.insert(uuid, settings);
+    let path = profile::save(name, &saved)?;
+    output::status!("profile: saved {}", path.display());
+    let record = stability::record(name, &result)?;
+    println!("profile: {name} {record}");
+    profile::record_applied(device_index, Some(name))?;
+    service::install(name, service::Target::Device(device_index), None, false)?;
+    println!("setup: done, '{name}' is applied at every boot; raise its offsets with `nvoc stress` when you want more");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::MegaHertz;
+
+    #[test]
+    fn test_conservative_offsets_stay_in_range() {
+        assert_eq!(conservative(500, None), 500);
+        let range = |min, max| Some(OffsetRange { min: MegaHertz(min), max: MegaHertz(max) });
+        assert_eq!(conservative(500, range(-1000, 3000)), 500);
+        assert_eq!(conservative(500, range(-200, 200)), 200);
+        assert_eq!(conservative(100, range(0, 0)), 0);
+    }
+}
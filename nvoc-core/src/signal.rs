@@ -0,0 +1,78 @@
+//! Termination signal handling for long-running modes
+//!
+//! SIGINT and SIGTERM only set a flag; loops poll it and unwind normally so
+//! drop guards get a chance to restore the GPU before exit. A second signal
+//! while that is under way terminates at once, for a driver call that
+//! hangs; the journal reverts whatever was left on the next run. SIGHUP
+//! likewise sets a flag asking the daemon to reload its configuration.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+static STOP: AtomicBool = AtomicBool::new(false);
+static RELOAD: AtomicBool = AtomicBool::new(false);
+
+/// Granularity at which interruptible sleeps check for a stop request
+const POLL_STEP: Duration = Duration::from_millis(100);
+
+extern "C" fn handle_stop(signal: libc::c_int) {
+    if STOP.swap(true, Ordering::SeqCst) {
+        // signal() and raise() are async-signal-safe
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+}
+
+/// Install SIGINT and SIGTERM handlers. Calling it again is harmless.
+pub fn install() {
+    let handler = handle_stop as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+extern "C" fn handle_reload(_signal: libc::c_int) {
+    RELOAD.store(true, Ordering::SeqCst);
+}
+
+/// Install a SIGHUP handler requesting a configuration reload
+pub fn install_reload() {
+    let handler = handle_reload as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGHUP, handler);
+    }
+}
+
+/// Whether a reload was requested since the last call, clearing the request
+pub fn take_reload() -> bool {
+    RELOAD.swap(false, Ordering::SeqCst)
+}
+
+/// Ask every loop to stop as if a termination signal had been received
+pub fn request_stop() {
+    STOP.store(true, Ordering::SeqCst);
+}
+
+/// Whether a termination signal has been received
+pub fn stop_requested() -> bool {
+    STOP.load(Ordering::SeqCst)
+}
+
+/// Sleep for `duration`, waking early on a stop request. Returns `false`
+/// if the sleep was interrupted.
+pub fn sleep(duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+    loop {
+        if stop_requested() {
+            return false;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        std::thread::sleep(POLL_STEP.min(deadline - now));
+    }
+}
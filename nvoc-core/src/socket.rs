@@ -0,0 +1,124 @@
+//! Unix socket helpers shared by the exporter and the daemon
+
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+
+use crate::AppError;
+
+/// Bind a Unix socket with the given permissions, replacing a stale socket
+/// left by a previous run
+pub fn bind_unix(path: &Path, mode: u32, domain: &'static str) -> Result<UnixListener, AppError> {
+    let err = |e: std::io::Error| AppError::msg(domain, format!("{}: {e}", path.display()));
+
+    // Only replace a stale socket, never an unrelated file
+    if let Ok(meta) = fs::symlink_metadata(path) {
+        if !meta.file_type().is_socket() {
+            return Err(AppError::msg(domain, format!("{}: exists and is not a socket", path.display())));
+        }
+        fs::remove_file(path).map_err(err)?;
+    }
+
+    let listener = UnixListener::bind(path).map_err(err)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).map_err(err)?;
+    Ok(listener)
+}
+
+/// Credentials of the process on the other end of a Unix socket, as the
+/// kernel recorded them when it connected
+#[derive(Debug, Clone, Default)]
+pub struct PeerCred {
+    pub pid: i32,
+    pub uid: u32,
+    pub gid: u32,
+    /// Supplementary groups; empty on kernels that cannot report them
+    pub groups: Vec<u32>,
+}
+
+pub fn peer_cred(stream: &UnixStream) -> std::io::Result<PeerCred> {
+    let mut cred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // Before Linux 4.13 only the primary group is known
+    let groups = peer_groups(stream).unwrap_or_default();
+    Ok(PeerCred { pid: cred.pid, uid: cred.uid, gid: cred.gid, groups })
+}
+
+/// Supplementary groups of the peer from `SO_PEERGROUPS`. Read from the
+/// socket itself rather than `/proc/<pid>`, whose pid may since have
+/// exited and been reused.
+fn peer_groups(stream: &UnixStream) -> std::io::Result<Vec<u32>> {
+    let gid_size = std::mem::size_of::<libc::gid_t>();
+    let mut groups: Vec<libc::gid_t> = vec![0; 32];
+    loop {
+        let mut len = (groups.len() * gid_size) as libc::socklen_t;
+        let result = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERGROUPS,
+                groups.as_mut_ptr() as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        let count = len as usize / gid_size;
+        if result == 0 {
+            groups.truncate(count);
+            return Ok(groups);
+        }
+        // The kernel reports the size needed when the buffer is too small
+        let e = std::io::Error::last_os_error();
+        match e.raw_os_error() == Some(libc::ERANGE) && count > groups.len() {
+            true => groups.resize(count, 0),
+            false => return Err(e),
+        }
+    }
+}
+
+impl PeerCred {
+    /// Whether the peer has `gid` as its primary or a supplementary group
+    pub fn in_group(&self, gid: u32) -> bool {
+        self.gid == gid || self.groups.contains(&gid)
+    }
+}
+
+/// Look up a group ID by name
+pub fn group_id(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    let group = unsafe { libc::getgrnam(name.as_ptr()) };
+    match group.is_null() {
+        true => None,
+        false => Some(unsafe { (*group).gr_gid }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_cred_of_own_socket() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let cred = peer_cred(&server).unwrap();
+        assert_eq!(cred.uid, unsafe { libc::getuid() });
+        assert!(cred.in_group(unsafe { libc::getgid() }));
+        let mut own = vec![0; 256];
+        let count = unsafe { libc::getgroups(own.len() as libc::c_int, own.as_mut_ptr()) };
+        own.truncate(count.max(0) as usize);
+        assert!(own.iter().all(|&gid| cred.in_group(gid)));
+        drop(client);
+    }
+}
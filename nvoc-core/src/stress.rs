@@ -0,0 +1,209 @@
+//! Built-in stability test
+//!
+//! `nvoc stress` applies the requested overclock, starts a workload
+//! command and watches the GPU with the same sampling as `profile soak`.
+//! A configuration counts as stable when the GPU reported no XID error,
+//! the workload did not fail and the soak scored high enough. Stable
+//! settings are kept; anything else reverts to the settings from before
+//! the test.
+
+use std::io::Read;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::params::OverclockParams;
+use crate::constants::stress::{KILL_GRACE, MIN_STABLE_SCORE};
+use crate::gpu::overclock::Trial;
+use crate::nvml::Device;
+use crate::profile::stability::{self, Soak};
+use crate::ratelimit::ModifyPermit;
+use crate::{output, signal, AppError};
+
+/// How the workload ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+    /// Still running when the test ended, and stopped by nvoc
+    Stopped,
+    Exited(i32),
+    /// Killed by a signal it did not get from nvoc
+    Signaled(i32),
+}
+
+impl Workload {
+    fn from_status(status: ExitStatus) -> Self {
+        match (status.code(), status.signal()) {
+            (Some(code), _) => Workload::Exited(code),
+            (None, Some(signal)) => Workload::Signaled(signal),
+            (None, None) => Workload::Exited(-1),
+        }
+    }
+}
+
+/// Why a run does not count as stable, if it does not. Memory errors that
+/// ECC corrected during the run count against it, as the first sign of a
+/// memory offset the chips cannot hold.
+pub fn judge(score: u32, xid: Option<u64>, ecc_errors: Option<u64>, workload: Workload) -> Option<String> {
+    if let Some(xid) = xid {
+        return Some(format!("xid {xid}"));
+    }
+    match workload {
+        Workload::Exited(code) if code != 0 => return Some(format!("workload exited with status {code}")),
+        Workload::Signaled(signal) => return Some(format!("workload killed by signal {signal}")),
+        _ => {}
+    }
+    if let Some(errors) = ecc_errors.filter(|&e| e > 0) {
+        return Some(format!("{errors} corrected ecc errors"));
+    }
+    (score < MIN_STABLE_SCORE).then(|| format!("score {score} is below {MIN_STABLE_SCORE}"))
+}
+
+/// Start `command` through the shell in its own process group, so Ctrl-C
+/// reaches nvoc alone and the whole workload can be stopped at the end
+fn spawn(command: &str, stdout: Stdio) -> Result<Child, AppError> {
+    Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdout(stdout)
+        .process_group(0)
+        .spawn()
+        .map_err(|e| AppError::msg("stress", format!("{command}: {e}")))
+}
+
+/// Stop the workload's process group, killing it if it does not exit in time
+fn stop(child: &mut Child) -> Result<ExitStatus, AppError> {
+    let group = -(child.id() as libc::pid_t);
+    unsafe { libc::kill(group, libc::SIGTERM) };
+    let deadline = Instant::now() + KILL_GRACE;
+    loop {
+        if let Some(status) = child.try_wait().map_err(|e| AppError::msg("stress", e.to_string()))? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            unsafe { libc::kill(group, libc::SIGKILL) };
+            return child.wait().map_err(|e| AppError::msg("stress", e.to_string()));
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Run `command` and watch the GPU until it exits or `duration` passes
+pub fn probe(
+    device: &Device,
+    device_index: u32,
+    command: &str,
+    duration: Duration,
+) -> Result<(Soak, Workload), AppError> {
+    let mut child = spawn(command, Stdio::inherit())?;
+    watch(device, device_index, &mut child, duration)
+}
+
+/// Like [`probe`], also returning what the workload printed to stdout
+pub fn probe_captured(
+    device: &Device,
+    device_index: u32,
+    command: &str,
+    duration: Duration,
+) -> Result<(Soak, Workload, String), AppError> {
+    let mut child = spawn(command, Stdio::piped())?;
+    // Read on a thread, so a chatty workload never blocks on a full pipe
+    let reader = child.stdout.take().map(|mut stdout| {
+        std::thread::spawn(move || {
+            let mut text = String::new();
+            stdout.read_to_string(&mut text).map(|_| text)
+        })
+    });
+    let (soak, workload) = watch(device, device_index, &mut child, duration)?;
+    let text = match reader.map(|reader| reader.join()) {
+        Some(Ok(Ok(text))) => text,
+        _ => String::new(),
+    };
+    Ok((soak, workload, text))
+}
+
+/// Sample the GPU while `child` runs, stopping it after `duration`
+fn watch(
+    device: &Device,
+    device_index: u32,
+    child: &mut Child,
+    duration: Duration,
+) -> Result<(Soak, Workload), AppError> {
+    let mut exited = None;
+    let soak = stability::soak_while(device, device_index, duration, || {
+        if exited.is_none() {
+            exited = child.try_wait().ok().flatten();
+        }
+        exited.is_none()
+    });
+    let workload = match exited {
+        Some(status) => Workload::from_status(status),
+        None => {
+            if let Err(e) = stop(child) {
+                eprintln!("warning[stress]: stopping the workload: {e}");
+            }
+            Workload::Stopped
+        }
+    };
+    Ok((soak?, workload))
+}
+
+/// Run `command` against `params` for up to `duration` and keep the
+/// settings only if the GPU stays stable. `permit` is held while applying.
+pub fn run(
+    device: &Device,
+    device_index: u32,
+    params: &OverclockParams,
+    command: &str,
+    duration: Duration,
+    permit: ModifyPermit,
+) -> Result<(), AppError> {
+    signal::install();
+    let trial = Trial::start(device, device_index, params)?;
+    drop(permit);
+    output::status!("stress: gpu {device_index} for {}s under `{command}`", duration.as_secs());
+    let (soak, workload) = match probe(device, device_index, command, duration) {
+        Ok(result) => result,
+        Err(e) => {
+            trial.revert()?;
+            return Err(e);
+        }
+    };
+
+    println!("stress: {}, {}", soak.summary(), soak.peaks());
+    if let Some(replays) = soak.pcie_replays.filter(|&r| r > 0) {
+        eprintln!("warning[stress]: {replays} pcie replays during the test, a sign of an unstable memory offset or a bad riser");
+    }
+    if workload == Workload::Exited(0) {
+        println!("stress: workload finished after {}s", soak.duration.as_secs());
+    }
+    if signal::stop_requested() {
+        trial.revert()?;
+        return Err(AppError::msg("stress", "interrupted, settings reverted".to_string()));
+    }
+    match judge(soak.score(), soak.xid, soak.ecc_errors, workload) {
+        None => {
+            println!("stress: stable");
+            trial.keep()
+        }
+        Some(reason) => {
+            trial.revert()?;
+            Err(AppError::msg("stress", format!("unstable ({reason}), settings reverted")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_judge() {
+        assert_eq!(judge(97, None, None, Workload::Stopped), None);
+        assert_eq!(judge(97, None, Some(0), Workload::Exited(0)), None);
+        assert_eq!(judge(0, Some(13), None, Workload::Stopped), Some("xid 13".to_string()));
+        assert_eq!(judge(97, None, None, Workload::Exited(2)), Some("workload exited with status 2".to_string()));
+        assert_eq!(judge(97, None, None, Workload::Signaled(11)), Some("workload killed by signal 11".to_string()));
+        assert_eq!(judge(97, None, Some(4), Workload::Stopped), Some("4 corrected ecc errors".to_string()));
+        assert_eq!(judge(60, None, None, Workload::Stopped), Some(format!("score 60 is below {MIN_STABLE_SCORE}")));
+    }
+}
@@ -0,0 +1,159 @@
+//! Power limit sweep
+//!
+//! `nvoc sweep` runs a benchmark at a series of power limits and reports
+//! the clocks, temperature and performance per watt reached at each, so a
+//! sensible cap can be picked from where the gains flatten out. Every
+//! limit is reverted after its run.
+
+use std::fs;
+use std::path::Path;
+
+use crate::bench::{Bench, Measurement};
+use crate::params::{OverclockParams, SweepParams};
+use crate::gpu::domain;
+use crate::nvml::Device;
+use crate::ratelimit::ModifyPermit;
+use crate::units::Watts;
+use crate::{output, signal, AppError};
+
+const HEADER: &str = "power_percent,power_limit_watts,graphics_clock_mhz,peak_temperature_c,performance,power_watts,\
+performance_per_watt,result";
+
+/// What one power limit achieved
+#[derive(Debug, Default, PartialEq)]
+struct Step {
+    power_percent: u32,
+    limit_watts: Option<Watts>,
+    clock_mhz: Option<f64>,
+    peak_temp_c: Option<u32>,
+    performance: Option<f64>,
+    watts: Option<f64>,
+    per_watt: Option<f64>,
+    /// Why the run was unstable, if it was
+    failure: Option<String>,
+}
+
+impl Step {
+    fn new(power_percent: u32, limit_watts: Option<Watts>, measurement: &Measurement) -> Self {
+        Self {
+            power_percent,
+            limit_watts,
+            clock_mhz: measurement.soak.mean_clock_mhz(),
+            peak_temp_c: measurement.soak.peak_temp_c(),
+            performance: measurement.performance(),
+            watts: measurement.soak.mean_power_w(),
+            per_watt: measurement.per_watt(),
+            failure: measurement.failure(),
+        }
+    }
+
+    fn result(&self) -> &str {
+        self.failure.as_deref().unwrap_or("stable")
+    }
+
+    fn csv_row(&self) -> String {
+        let cell = |value: Option<String>| value.unwrap_or_default();
+        [
+            self.power_percent.to_string(),
+            cell(self.limit_watts.map(|w| w.0.to_string())),
+            cell(self.clock_mhz.map(|c| format!("{c:.0}"))),
+            cell(self.peak_temp_c.map(|t| t.to_string())),
+            cell(self.performance.map(|p| format!("{p:.2}"))),
+            cell(self.watts.map(|w| format!("{w:.1}"))),
+            cell(self.per_watt.map(|p| format!("{p:.4}"))),
+            self.result().to_string(),
+        ]
+        .join(",")
+    }
+
+    fn table_row(&self) -> String {
+        let cell = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        format!(
+            "{:>5}  {:>6}  {:>8}  {:>5}  {:>10}  {:>6}  {:>8}  {}",
+            format!("{}%", self.power_percent),
+            cell(self.limit_watts.map(|w| w.to_string())),
+            cell(self.clock_mhz.map(|c| format!("{c:.0}MHz"))),
+            cell(self.peak_temp_c.map(|t| format!("{t}°C"))),
+            cell(self.performance.map(|p| format!("{:.1}", output::Decimal(p)))),
+            cell(self.watts.map(|w| format!("{w:.0}W"))),
+            cell(self.per_watt.map(|p| format!("{:.3}", output::Decimal(p)))),
+            self.result()
+        )
+    }
+}
+
+fn print_table(steps: &[Step]) {
+    println!(
+        "{:>5}  {:>6}  {:>8}  {:>5}  {:>10}  {:>6}  {:>8}  result",
+        "power", "limit", "clock", "temp", "perf", "draw", "perf/W"
+    );
+    for step in steps {
+        println!("{}", step.table_row());
+    }
+}
+
+fn write_csv(path: &Path, steps: &[Step]) -> Result<(), AppError> {
+    let rows: Vec<String> = std::iter::once(HEADER.to_string()).chain(steps.iter().map(Step::csv_row)).collect();
+    fs::write(path, rows.join("\n") + "\n").map_err(|e| AppError::msg("sweep", format!("{}: {e}", path.display())))
+}
+
+/// Run the benchmark at each power limit of `params` and report the
+/// results. `permit` is held while the first limit is applied.
+pub fn run(device: &Device, device_index: u32, params: &SweepParams, permit: ModifyPermit) -> Result<(), AppError> {
+    let SweepParams { ref power, ref command, step, ref csv } = *params;
+    let power_info = domain::get_power_info(device).ok();
+    signal::install();
+    output::status!("sweep: {} power limits, up to {}s each", power.len(), step.as_secs());
+    let mut permit = Some(permit);
+    let mut bench = Bench::new(command, step);
+    let mut steps = Vec::new();
+    for &percent in power {
+        if signal::stop_requested() {
+            break;
+        }
+        let params = OverclockParams { power_limit: Some(percent), ..Default::default() };
+        let measurement = bench.measure_with(device, device_index, &params, permit.take())?;
+        // A run cut short by Ctrl-C is not comparable
+        if signal::stop_requested() {
+            break;
+        }
+        let limit_watts = power_info.as_ref().map(|info| {
+            let percent = domain::power_floor_percent().map_or(percent, |floor| percent.max(floor));
+            info.effective_watts_from_percentage(percent)
+        });
+        steps.push(Step::new(percent, limit_watts, &measurement));
+    }
+
+    if signal::stop_requested() {
+        eprintln!("warning[sweep]: interrupted, showing the power limits measured so far");
+    }
+    print_table(&steps);
+    if let Some(path) = csv {
+        write_csv(path, &steps)?;
+        output::status!("sweep: wrote {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_rows() {
+        let step = Step {
+            power_percent: 80,
+            limit_watts: Some(Watts(460)),
+            clock_mhz: Some(2581.4),
+            peak_temp_c: Some(71),
+            performance: Some(139.12),
+            watts: Some(452.26),
+            per_watt: Some(0.30762),
+            failure: None,
+        };
+        assert_eq!(step.csv_row(), "80,460,2581,71,139.12,452.3,0.3076,stable");
+        let failed = Step { power_percent: 60, failure: Some("xid 13".to_string()), ..Default::default() };
+        assert_eq!(failed.csv_row(), "60,,,,,,,xid 13");
+        assert_eq!(HEADER.split(',').count(), step.csv_row().split(',').count());
+    }
+}
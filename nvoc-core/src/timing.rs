@@ -0,0 +1,103 @@
+//! Optional timing of NVML library load, calls and shutdown
+//!
+//! With `--timing`, the time spent loading libnvidia-ml and in each NVML
+//! function is recorded and summarized on stderr when nvoc exits. Slow
+//! applies at boot are usually down to `nvmlInit_v2` waiting for the driver,
+//! which this makes visible without strace.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Per-label totals in order of first use
+static STATS: Mutex<Vec<Stat>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Stat {
+    label: &'static str,
+    calls: u32,
+    total: Duration,
+    max: Duration,
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+fn add(stats: &mut Vec<Stat>, label: &'static str, elapsed: Duration) {
+    match stats.iter_mut().find(|s| s.label == label) {
+        Some(stat) => {
+            stat.calls += 1;
+            stat.total += elapsed;
+            stat.max = stat.max.max(elapsed);
+        }
+        None => stats.push(Stat { label, calls: 1, total: elapsed, max: elapsed }),
+    }
+}
+
+/// Run `f`, recording how long it took under `label` when timing is enabled
+pub fn measure<T>(label: &'static str, f: impl FnOnce() -> T) -> T {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return f();
+    }
+    let started = Instant::now();
+    let result = f();
+    let elapsed = started.elapsed();
+    if let Ok(mut stats) = STATS.lock() {
+        add(&mut stats, label, elapsed);
+    }
+    result
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Print the recorded timings, if enabled
+pub fn report() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let Ok(stats) = STATS.lock() else {
+        return;
+    };
+    let mut total = Duration::ZERO;
+    for stat in stats.iter() {
+        total += stat.total;
+        match stat.calls {
+            1 => eprintln!("timing: {} {:.3}ms", stat.label, ms(stat.total)),
+            calls => eprintln!(
+                "timing: {} {:.3}ms ({calls} calls, max {:.3}ms)",
+                stat.label,
+                ms(stat.total),
+                ms(stat.max)
+            ),
+        }
+    }
+    eprintln!("timing: total {:.3}ms", ms(total));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_accumulate_per_label() {
+        let mut stats = Vec::new();
+        add(&mut stats, "nvmlInit_v2", Duration::from_millis(900));
+        add(&mut stats, "nvmlDeviceGetName", Duration::from_millis(2));
+        add(&mut stats, "nvmlDeviceGetName", Duration::from_millis(5));
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            stats[1],
+            Stat {
+                label: "nvmlDeviceGetName",
+                calls: 2,
+                total: Duration::from_millis(7),
+                max: Duration::from_millis(5),
+            }
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Optional log of every NVML call
+//!
+//! With `--trace-nvml`, each NVML function nvoc calls is written as one line
+//! with its arguments, return code and duration, to stderr or to a file
+//! given as `--trace-nvml=FILE`. When a driver rejects an overclock, the
+//! trace shows exactly which call failed and what it was passed, which is
+//! what a driver bug report needs.
+
+use std::cell::Cell;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::nvml::types::NvmlReturn;
+use crate::nvml::{NvmlError, NVML_SUCCESS};
+use crate::timing;
+
+/// Where trace lines go, set once when tracing is enabled
+static SINK: OnceLock<Mutex<Box<dyn Write + Send>>> = OnceLock::new();
+
+thread_local! {
+    /// Function and return code of the last NVML call on this thread
+    static LAST_CALL: Cell<Option<(&'static str, NvmlReturn)>> = const { Cell::new(None) };
+}
+
+/// Write trace lines to `path`, or to stderr for `-`
+pub fn enable(path: &Path) -> io::Result<()> {
+    let sink: Box<dyn Write + Send> = match path.as_os_str() == "-" {
+        true => Box::new(io::stderr()),
+        false => Box::new(File::create(path)?),
+    };
+    let _ = SINK.set(Mutex::new(sink));
+    Ok(())
+}
+
+fn line(function: &str, args: &[&dyn Debug], code: NvmlReturn, elapsed: Duration) -> String {
+    let args: Vec<String> = args.iter().map(|arg| format!("{arg:?}")).collect();
+    let outcome = match code {
+        NVML_SUCCESS => "success".to_string(),
+        code => NvmlError::from_nvml_return(code).to_string(),
+    };
+    format!(
+        "trace: {function}({}) = {code} ({outcome}) in {:.3}ms",
+        args.join(", "),
+        elapsed.as_secs_f64() * 1000.0
+    )
+}
+
+/// Call the NVML function `function` through `f`, timing it and logging
+/// the call with `args` when tracing is enabled
+pub fn call(function: &'static str, args: &[&dyn Debug], f: impl FnOnce() -> NvmlReturn) -> NvmlReturn {
+    let Some(sink) = SINK.get() else {
+        let code = timing::measure(function, f);
+        LAST_CALL.set(Some((function, code)));
+        return code;
+    };
+    let started = Instant::now();
+    let code = timing::measure(function, f);
+    LAST_CALL.set(Some((function, code)));
+    let line = line(function, args, code, started.elapsed());
+    if let Ok(mut sink) = sink.lock() {
+        let _ = writeln!(sink, "{line}");
+    }
+    code
+}
+
+/// The NVML function that returned `error`, if it is what the last call on
+/// this thread returned. Errors nvoc raises itself, such as a missing
+/// symbol, are not attributed to a call.
+pub fn failed_function(error: &NvmlError) -> Option<&'static str> {
+    let (function, code) = LAST_CALL.get()?;
+    (code != NVML_SUCCESS && NvmlError::from_nvml_return(code) == *error).then_some(function)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        assert_eq!(
+            line("nvmlDeviceSetGpcClkVfOffset", &[&1u32, &200i32], 3, Duration::from_micros(1250)),
+            "trace: nvmlDeviceSetGpcClkVfOffset(1, 200) = 3 (not supported by this gpu) in 1.250ms"
+        );
+        assert_eq!(line("nvmlInit_v2", &[], 0, Duration::ZERO), "trace: nvmlInit_v2() = 0 (success) in 0.000ms");
+    }
+
+    #[test]
+    fn test_failed_function() {
+        call("nvmlDeviceSetGpcClkVfOffset", &[], || 3);
+        assert_eq!(failed_function(&NvmlError::NotSupported), Some("nvmlDeviceSetGpcClkVfOffset"));
+        assert_eq!(failed_function(&NvmlError::NoPermission), None);
+        call("nvmlDeviceGetCount_v2", &[], || NVML_SUCCESS);
+        assert_eq!(failed_function(&NvmlError::NotSupported), None);
+    }
+}
@@ -0,0 +1,94 @@
+//! Units of the values nvoc reads and writes
+//!
+//! NVML works in milliwatts while nvoc configures and prints power in
+//! watts, so the two are distinct types and converting between them is
+//! explicit. Each unit prints with its suffix the way nvoc's output shows
+//! it: `460W`, `575000mW`, `83°C`, and `150MHz` or, with `{:+}`, `+150MHz`.
+//! Values serialize as plain numbers.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::hardware::MILLIWATTS_TO_WATTS;
+
+/// Power in watts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Watts(pub u32);
+
+/// Power in milliwatts, as NVML reports and takes it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Milliwatts(pub u32);
+
+/// Clock speed or clock offset in MHz
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MegaHertz(pub i32);
+
+/// Temperature in degrees Celsius
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Celsius(pub u32);
+
+/// Rounds down to whole watts
+impl From<Milliwatts> for Watts {
+    fn from(power: Milliwatts) -> Self {
+        Watts(power.0 / MILLIWATTS_TO_WATTS)
+    }
+}
+
+impl From<Watts> for Milliwatts {
+    fn from(power: Watts) -> Self {
+        Milliwatts(power.0.saturating_mul(MILLIWATTS_TO_WATTS))
+    }
+}
+
+impl fmt::Display for Watts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}W", self.0)
+    }
+}
+
+impl fmt::Display for Milliwatts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}mW", self.0)
+    }
+}
+
+impl fmt::Display for MegaHertz {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.sign_plus() {
+            true => write!(f, "{:+}MHz", self.0),
+            false => write!(f, "{}MHz", self.0),
+        }
+    }
+}
+
+impl fmt::Display for Celsius {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}°C", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_conversion() {
+        assert_eq!(Watts::from(Milliwatts(575_999)), Watts(575));
+        assert_eq!(Milliwatts::from(Watts(460)), Milliwatts(460_000));
+        assert_eq!(Milliwatts::from(Watts(u32::MAX)), Milliwatts(u32::MAX));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Watts(460).to_string(), "460W");
+        assert_eq!(format!("{:+}", MegaHertz(150)), "+150MHz");
+        assert_eq!(format!("{:+}", MegaHertz(-200)), "-200MHz");
+        assert_eq!(MegaHertz(2820).to_string(), "2820MHz");
+        assert_eq!(Celsius(83).to_string(), "83°C");
+    }
+}
@@ -0,0 +1,87 @@
+//! Plain-text status lines at a fixed interval
+//!
+//! `nvoc watch` prints one timestamped line per GPU each interval, which
+//! reads fine in a terminal and appends cleanly to a log file.
+
+use std::time::{Duration, SystemTime};
+
+use crate::gpu::telemetry::{self, Sample};
+use crate::gpu::throttle;
+use crate::{output, signal, AppError};
+
+fn value<T: std::fmt::Display>(value: Option<T>, unit: &str) -> String {
+    match value {
+        Some(value) => format!("{value}{unit}"),
+        None => "n/a".to_string(),
+    }
+}
+
+/// Offsets are shown only when set, keeping the line short on stock cards
+fn clock(clock: Option<u32>, offset: Option<i32>) -> String {
+    match offset {
+        Some(offset) if offset != 0 => format!("{} ({offset:+})", value(clock, "MHz")),
+        _ => value(clock, "MHz"),
+    }
+}
+
+fn format_line(sample: &Sample) -> String {
+    let line = format!(
+        "gpu {}: {}, gpu {}, mem {}, {}/{}, fan {}, util {}",
+        sample.index,
+        value(sample.temperature_c, "°C"),
+        clock(sample.graphics_clock_mhz, sample.graphics_offset_mhz),
+        clock(sample.memory_clock_mhz, sample.memory_offset_mhz),
+        value(sample.power_watts, "W"),
+        value(sample.power_limit_watts, "W"),
+        value(sample.fan_percent, "%"),
+        value(sample.utilization_percent, "%"),
+    );
+    // Reasons are only listed while clocks are held back
+    match sample.throttle_reasons {
+        Some(mask) if mask != 0 => format!("{line}, throttle {}", throttle::describe(mask)),
+        _ => line,
+    }
+}
+
+pub fn run(indices: &[u32], interval: Duration) -> Result<(), AppError> {
+    signal::install();
+    loop {
+        let timestamp = output::local_timestamp(SystemTime::now());
+        for &index in indices {
+            match telemetry::sample(index) {
+                Some(sample) => println!("{timestamp} {}", format_line(&sample)),
+                None => println!("{timestamp} gpu {index}: unavailable"),
+            }
+        }
+        if !signal::sleep(interval) {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_line() {
+        let sample = Sample {
+            index: 1,
+            temperature_c: Some(64),
+            graphics_clock_mhz: Some(2820),
+            graphics_offset_mhz: Some(150),
+            memory_clock_mhz: Some(14001),
+            memory_offset_mhz: Some(0),
+            power_watts: Some(312),
+            power_limit_watts: Some(600),
+            fan_percent: Some(45),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_line(&sample),
+            "gpu 1: 64°C, gpu 2820MHz (+150), mem 14001MHz, 312W/600W, fan 45%, util n/a"
+        );
+        let throttled = Sample { throttle_reasons: Some(0x4), ..sample };
+        assert!(format_line(&throttled).ends_with(", util n/a, throttle power cap"));
+    }
+}
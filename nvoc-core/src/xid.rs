@@ -0,0 +1,93 @@
+//! Reverting an overclock after an XID error
+//!
+//! The driver reports faults such as a graphics engine exception (XID 13)
+//! or a GPU that fell off the bus (XID 79) as XID errors, and an unstable
+//! overclock is a common cause. While the daemon runs or a profile soaks,
+//! every watched GPU is subscribed to XID events; when one arrives the
+//! clock offsets go back to zero, locked clocks are released and the
+//! failure is written to the journal's failure log. Power limits and fans
+//! are left alone, as they do not make a card unstable.
+
+use crate::constants::clocks;
+use crate::gpu::events::EventSet;
+use crate::gpu::{overclock, state};
+use crate::nvml::{Device, NvmlClockType, NVML_EVENT_TYPE_XID_CRITICAL_ERROR};
+use crate::units::MegaHertz;
+use crate::{journal, profile, AppError};
+
+pub struct XidGuard {
+    device: Device,
+    device_index: u32,
+    /// `None` when the GPU cannot report XID errors
+    events: Option<EventSet>,
+}
+
+impl XidGuard {
+    /// Subscribe to XID errors of the GPU. One that cannot report them is
+    /// left unguarded with a warning.
+    pub fn new(device: &Device, device_index: u32) -> Self {
+        let events = EventSet::new().and_then(|mut set| {
+            set.register(device, NVML_EVENT_TYPE_XID_CRITICAL_ERROR).map(|registered| (set, registered))
+        });
+        let events = match events {
+            Ok((set, registered)) if registered != 0 => Some(set),
+            Ok(_) => {
+                eprintln!("warning[xid]: gpu {device_index} does not report xid errors, an unstable overclock will not be reverted");
+                None
+            }
+            Err(e) => {
+                eprintln!("warning[xid]: gpu {device_index}: {}, an unstable overclock will not be reverted", e.user_message());
+                None
+            }
+        };
+        Self { device: device.clone(), device_index, events }
+    }
+
+    /// Take the XID errors reported since the last check without blocking,
+    /// reverting the overclock if there were any. Returns the first XID.
+    pub fn check(&self) -> Result<Option<u64>, AppError> {
+        let first = self.drain()?;
+        if let Some(xid) = first {
+            self.revert(xid)?;
+        }
+        Ok(first)
+    }
+
+    /// Take the XID errors reported since the last check without acting on
+    /// them, returning the first
+    pub fn drain(&self) -> Result<Option<u64>, AppError> {
+        let Some(events) = &self.events else {
+            return Ok(None);
+        };
+        let mut first = None;
+        while let Some((_, event)) = events.wait(0).map_err(|e| AppError::new("xid", e))? {
+            if event.eventType == NVML_EVENT_TYPE_XID_CRITICAL_ERROR {
+                first.get_or_insert(event.eventData);
+            }
+        }
+        Ok(first)
+    }
+
+    fn revert(&self, xid: u64) -> Result<(), AppError> {
+        let device = &self.device;
+        eprintln!(
+            "warning[xid]: gpu {} reported xid {xid}, reverting clock offsets and locked clocks",
+            self.device_index
+        );
+        let mut result = overclock::set_graphics_offset(device, clocks::DEFAULT_GRAPHICS_OFFSET)
+            .and(overclock::set_memory_offset(device, clocks::DEFAULT_MEMORY_OFFSET))
+            .and(state::unlock_clocks(device))
+            .map_err(|e| AppError::new("xid", e));
+        for clock_type in [NvmlClockType::Graphics, NvmlClockType::Memory] {
+            for (pstate, _) in overclock::pstate_offsets(device, clock_type) {
+                result = result.and(overclock::set_pstate_offset(device, clock_type, pstate, MegaHertz(0)));
+            }
+        }
+
+        // Forget the profile so `--enforce` does not apply it again
+        let applied = profile::applied(self.device_index);
+        profile::record_applied(self.device_index, None)?;
+        journal::record_xid(self.device_index, xid, applied.as_deref())?;
+        result
+    }
+}
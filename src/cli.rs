@@ -1,7 +1,20 @@
 //! Command-line interface parsing and configuration
 
-use crate::constants::app;
-use clap::{Arg, Command};
+use nvoc_core::config::{parse_duration, DefaultsSection};
+use nvoc_core::constants::{app, autotune, csv_log, exporter, history, monitor, paths, watch};
+use nvoc_core::daemon::defer::Manager;
+use nvoc_core::history::{metric_column, Retention};
+use nvoc_core::nvml::NvmlPerfState;
+pub use nvoc_core::params::{
+    AutotuneParams, AutotuneTarget, EfficiencyParams, ExporterParams, OverclockParams, SweepParams,
+};
+use nvoc_core::profile::afterburner;
+use clap::parser::ValueSource;
+use clap::{Arg, ArgGroup, ArgMatches, Command};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
 
 fn device_arg() -> Arg {
     Arg::new("device")
@@ -13,6 +26,21 @@ fn device_arg() -> Arg {
         .value_parser(clap::value_parser!(u32))
 }
 
+fn all_arg() -> Arg {
+    Arg::new("all")
+        .long("all")
+        .help("All GPUs")
+        .conflicts_with("device")
+        .action(clap::ArgAction::SetTrue)
+}
+
+fn json_arg() -> Arg {
+    Arg::new("json")
+        .long("json")
+        .help("JSON output")
+        .action(clap::ArgAction::SetTrue)
+}
+
 fn dry_run_arg() -> Arg {
     Arg::new("dry-run")
         .long("dry-run")
@@ -20,32 +48,445 @@ fn dry_run_arg() -> Arg {
         .action(clap::ArgAction::SetTrue)
 }
 
+fn overclock_args() -> [Arg; 5] {
+    [
+        Arg::new("clocks")
+            .short('c')
+            .long("clocks")
+            .value_name("MIN,MAX")
+            .help("GPU clocks MHz")
+            .value_parser(parse_clocks),
+        Arg::new("mem-clocks")
+            .long("mem-clocks")
+            .value_name("MIN,MAX")
+            .help("Mem clocks MHz")
+            .value_parser(parse_clocks),
+        Arg::new("offset")
+            .short('o')
+            .long("offset")
+            .value_name("GRAPHICS_OFFSET[@PSTATE]")
+            .help("GPU offset MHz, P0 unless a pstate is given; repeatable")
+            .allow_hyphen_values(true)
+            .action(clap::ArgAction::Append)
+            .value_parser(parse_offset),
+        Arg::new("memory-offset")
+            .short('m')
+            .long("memory-offset")
+            .value_name("MEMORY_OFFSET[@PSTATE]")
+            .help("Mem offset MHz, P0 unless a pstate is given; repeatable")
+            .allow_hyphen_values(true)
+            .action(clap::ArgAction::Append)
+            .value_parser(parse_offset),
+        Arg::new("power")
+            .short('p')
+            .long("power")
+            .value_name("PERCENT")
+            .help("Power limit %")
+            .value_parser(clap::value_parser!(u32)),
+    ]
+}
+
+/// Split repeated `OFFSET[@PSTATE]` values into the P0 offset and those of
+/// other pstates. A pstate given twice keeps its last offset.
+fn pstate_offsets(matches: &ArgMatches, id: &str) -> (Option<i32>, Vec<(NvmlPerfState, i32)>) {
+    let offsets: BTreeMap<NvmlPerfState, i32> =
+        matches.get_many::<(NvmlPerfState, i32)>(id).into_iter().flatten().copied().collect();
+    let p0 = offsets.get(&NvmlPerfState::P0).copied();
+    (p0, offsets.into_iter().filter(|(pstate, _)| *pstate != NvmlPerfState::P0).collect())
+}
+
+/// Read overclock options; `None` when no option was given
+fn overclock_params(matches: &ArgMatches, dry_run: bool, verify: bool) -> Option<OverclockParams> {
+    let (graphics_offset, graphics_pstate_offsets) = pstate_offsets(matches, "offset");
+    let (memory_offset, memory_pstate_offsets) = pstate_offsets(matches, "memory-offset");
+    let params = OverclockParams {
+        clocks: matches.get_one::<(u32, u32)>("clocks").copied(),
+        mem_clocks: matches.get_one::<(u32, u32)>("mem-clocks").copied(),
+        graphics_offset,
+        memory_offset,
+        graphics_pstate_offsets,
+        memory_pstate_offsets,
+        power_limit: matches.get_one::<u32>("power").copied(),
+        dry_run,
+        verify,
+        revert_after: None,
+    };
+    (!params.is_empty()).then_some(params)
+}
+
+/// Foreign tool whose settings can be converted to and from profiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileFormat {
+    Lact,
+    Afterburner,
+    /// A bundle written by `profile bundle` on another machine
+    Bundle,
+}
+
 #[derive(Debug)]
-pub struct OverclockParams {
-    pub clocks: Option<(u32, u32)>,
-    pub graphics_offset: Option<i32>,
-    pub memory_offset: Option<i32>,
-    pub power_limit: Option<u32>,
-    pub dry_run: bool,
+pub enum ProfileOperation {
+    /// Save the given settings, or the device's current settings when `None`
+    Save { name: String, params: Option<OverclockParams> },
+    /// `boot` steps down to the configured fallback after an unclean shutdown
+    Apply { name: String, dry_run: bool, boot: bool },
+    List,
+    /// Without a name, LACT imports are saved as `lact` and bundles under
+    /// the name they were shared with
+    Import { name: Option<String>, format: ProfileFormat, path: PathBuf },
+    /// Print a profile with its GPU, driver and stability record for sharing
+    Bundle { name: String },
+    /// Print a profile's settings for the selected device in another tool's format
+    Export { name: String, format: ProfileFormat, slot: u32 },
+    /// Apply a profile and score its stability while the user runs a load
+    Soak { name: String, duration: Duration },
+}
+
+#[derive(Debug)]
+pub enum FanOperation {
+    /// Show each fan's duty cycle and speed
+    Status,
+    /// Hand every fan back to the driver's temperature control
+    Auto { dry_run: bool },
+    /// Hold every fan at a fixed duty cycle
+    Manual { percent: u32, dry_run: bool },
+}
+
+/// Where `nvoc log` writes its samples
+#[derive(Debug)]
+pub enum LogSink {
+    Csv(PathBuf),
+    Sqlite { path: PathBuf, retention: Retention },
 }
 
 #[derive(Debug)]
 pub enum Operation {
-    Info,
+    Info { json: bool },
+    /// Read raw NVML fields by `NVML_FI_*` ID
+    Field { ids: Vec<u32> },
+    /// List the processes running on the GPU
+    Ps,
+    /// Report remapped rows and retired pages
+    Health,
+    /// Report which nvoc features the driver and GPU support
+    Capabilities,
+    /// Show the offset, power limit and fan ranges the driver accepts
+    Limits,
+    Fan(FanOperation),
+    /// Stream NVML events as JSON lines until interrupted
+    Events,
+    /// Live dashboard redrawn every `interval`
+    Monitor { interval: Duration },
+    /// One status line per GPU every `interval`
+    Watch { interval: Duration },
+    /// Write a row per GPU to `sink` every `interval`
+    Log { sink: LogSink, interval: Duration },
+    /// Summarize logged telemetry between `since` and `until` ago
+    Stats { db: PathBuf, since: Option<Duration>, until: Option<Duration> },
+    /// Show how one metric trended over the `last` period, in `buckets` rows.
+    /// `db` defaults to the daemon's history database.
+    History { db: Option<PathBuf>, metric: &'static str, last: Duration, buckets: usize },
     Reset { dry_run: bool },
     Overclock(OverclockParams),
+    /// Set applications clocks to `(memory, graphics)` MHz, or reset them when `None`
+    AppClocks { clocks: Option<(u32, u32)>, dry_run: bool },
+    /// Set the thermal target to `target` °C, or show the thresholds when `None`
+    Thermal { target: Option<u32>, dry_run: bool },
+    /// `enforce` re-applies the active profile when its settings drift;
+    /// enforcement is suspended while any manager in `defer_to` runs
+    Daemon { config_path: PathBuf, enforce: bool, defer_to: Vec<Manager> },
+    Exporter(ExporterParams),
+    Profile(ProfileOperation),
+    /// Print Kubernetes node labels for every GPU
+    K8sLabels { json: bool },
+    /// Diagnose the NVML installation; `symbols` lists every function's availability
+    Doctor { symbols: bool },
+    /// Search for the highest stable graphics offset
+    Autotune(AutotuneParams),
+    /// Propose, soak and install a first profile `name`; `yes` skips the prompt
+    Setup { name: String, soak: Duration, yes: bool },
+    /// Apply `params`, run `command` for up to `duration` and keep the
+    /// settings only if the GPU stays stable
+    Stress { params: OverclockParams, command: String, duration: Duration },
+    /// Benchmark a series of power limits and report performance per watt
+    Sweep(SweepParams),
+    /// Install systemd units applying `profile` to the selected GPUs at boot
+    InstallService { profile: String, timer: Option<Duration>, dry_run: bool },
+    UninstallService { dry_run: bool },
 }
 
 impl Operation {
     pub fn modifies_gpu(&self) -> bool {
-        matches!(self, Operation::Reset { .. } | Operation::Overclock(_))
+        matches!(
+            self,
+            Operation::Reset { .. }
+                | Operation::Overclock(_)
+                | Operation::AppClocks { .. }
+                | Operation::Thermal { target: Some(_), .. }
+                | Operation::Fan(FanOperation::Auto { .. } | FanOperation::Manual { .. })
+                | Operation::Daemon { .. }
+                | Operation::Profile(ProfileOperation::Apply { .. })
+                | Operation::Profile(ProfileOperation::Soak { .. })
+                | Operation::Stress { .. }
+                | Operation::Setup { .. }
+                | Operation::Autotune(_)
+                | Operation::Sweep(_)
+        )
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        match self {
+            Operation::Reset { dry_run } => *dry_run,
+            Operation::Overclock(params) => params.dry_run,
+            Operation::AppClocks { dry_run, .. } => *dry_run,
+            Operation::Thermal { dry_run, .. } => *dry_run,
+            Operation::Fan(FanOperation::Auto { dry_run } | FanOperation::Manual { dry_run, .. }) => *dry_run,
+            Operation::Profile(ProfileOperation::Apply { dry_run, .. }) => *dry_run,
+            _ => false,
+        }
     }
 }
 
 #[derive(Debug)]
 pub struct Config {
     pub device: u32,
+    /// Target every detected GPU instead of `device`
+    pub all_devices: bool,
     pub operation: Operation,
+    /// Report time spent loading and calling NVML on exit
+    pub timing: bool,
+    /// Leave out confirmations of applied changes
+    pub quiet: bool,
+    /// Raise power and offsets even above `limits.max_apply_temperature`
+    pub ignore_thermal_guard: bool,
+    /// NVML library to load, overriding `paths.library`
+    pub nvml_path: Option<PathBuf>,
+    /// Log every NVML call to this file, or to stderr for `-`
+    pub trace_nvml: Option<PathBuf>,
+}
+
+/// Options given before or after any subcommand
+struct Globals {
+    timing: bool,
+    quiet: bool,
+    ignore_thermal_guard: bool,
+    nvml_path: Option<PathBuf>,
+    trace_nvml: Option<PathBuf>,
+}
+
+impl Globals {
+    fn from_matches(matches: &ArgMatches) -> Self {
+        Self {
+            timing: matches.get_flag("timing"),
+            quiet: matches.get_flag("quiet"),
+            ignore_thermal_guard: matches.get_flag("ignore-thermal-guard"),
+            nvml_path: matches.get_one::<PathBuf>("nvml-path").cloned(),
+            trace_nvml: matches.get_one::<PathBuf>("trace-nvml").cloned(),
+        }
+    }
+
+    /// Run `operation` on the GPUs picked by the innermost subcommand's `-d`
+    /// and `--all`. Commands without `-d` use GPU 0, and the exporter and
+    /// `k8s-labels` always cover every GPU.
+    fn config(self, matches: &ArgMatches, operation: Operation) -> Config {
+        let leaf = leaf_matches(matches);
+        let all_devices = leaf.try_get_one::<bool>("all").ok().flatten().copied().unwrap_or(false)
+            || matches!(operation, Operation::Exporter(_) | Operation::K8sLabels { .. });
+        Config {
+            device: leaf.try_get_one::<u32>("device").ok().flatten().copied().unwrap_or(0),
+            all_devices,
+            operation,
+            timing: self.timing,
+            quiet: self.quiet,
+            ignore_thermal_guard: self.ignore_thermal_guard,
+            nvml_path: self.nvml_path,
+            trace_nvml: self.trace_nvml,
+        }
+    }
+}
+
+fn profile_command() -> Command {
+    let name_arg = || {
+        Arg::new("name")
+            .value_name("NAME")
+            .help("Profile name")
+            .required(true)
+    };
+
+    Command::new("profile")
+        .about("Manage saved profiles")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("save")
+                .about("Save settings, or the current GPU state, as a profile")
+                .arg(name_arg())
+                .args(overclock_args())
+                .arg(device_arg())
+                .arg(all_arg()),
+        )
+        .subcommand(
+            Command::new("apply")
+                .about("Apply a saved profile")
+                .arg(name_arg())
+                .arg(device_arg())
+                .arg(all_arg())
+                .arg(dry_run_arg())
+                .arg(
+                    Arg::new("boot")
+                        .long("boot")
+                        .help("Apply the [boot] fallback profile instead if the last shutdown was unclean")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(Command::new("list").about("List saved profiles"))
+        .subcommand(
+            Command::new("import")
+                .about("Convert another tool's configuration into a profile")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Configuration format")
+                        .required(true)
+                        .value_parser(["lact", "bundle"]),
+                )
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .help("Configuration file, e.g. /etc/lact/config.yaml or a profile bundle")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .value_name("NAME")
+                        .help("Profile name to save as [default: lact, or the bundle's name]"),
+                )
+                .arg(device_arg()),
+        )
+        .subcommand(
+            Command::new("bundle")
+                .about("Print a profile with its GPU, driver and stability score for sharing")
+                .arg(name_arg())
+                .arg(device_arg()),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Print a profile in another tool's format")
+                .arg(name_arg())
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Output format")
+                        .required(true)
+                        .value_parser(["afterburner"]),
+                )
+                .arg(
+                    Arg::new("slot")
+                        .long("slot")
+                        .value_name("N")
+                        .help("Afterburner profile slot")
+                        .default_value("1")
+                        .value_parser(clap::value_parser!(u32).range(1..=afterburner::MAX_SLOT as i64)),
+                )
+                .arg(device_arg()),
+        )
+        .subcommand(
+            Command::new("soak")
+                .about("Apply a profile and score its stability under load")
+                .arg(name_arg())
+                .arg(
+                    Arg::new("duration")
+                        .long("duration")
+                        .value_name("DURATION")
+                        .help("How long to watch the GPU")
+                        .default_value("10m")
+                        .value_parser(parse_duration),
+                )
+                .arg(device_arg()),
+        )
+}
+
+fn fan_command() -> Command {
+    Command::new("fan")
+        .about("Show fan speeds or switch between automatic and manual control")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("status")
+                .about("Show each fan's duty cycle, measured speed and control policy")
+                .arg(device_arg())
+                .arg(all_arg()),
+        )
+        .subcommand(
+            Command::new("auto")
+                .about("Return fans to the driver's temperature control")
+                .arg(device_arg())
+                .arg(all_arg())
+                .arg(dry_run_arg()),
+        )
+        .subcommand(
+            Command::new("manual")
+                .about("Hold fans at a fixed duty cycle")
+                .arg(
+                    Arg::new("percent")
+                        .value_name("PERCENT")
+                        .help("Fan duty cycle")
+                        .required(true)
+                        .value_parser(clap::value_parser!(u32).range(0..=100)),
+                )
+                .arg(device_arg())
+                .arg(all_arg())
+                .arg(dry_run_arg()),
+        )
+}
+
+fn parse_fan(matches: &ArgMatches) -> FanOperation {
+    let (name, sub_matches) = matches.subcommand().expect("subcommand required");
+    match name {
+        "auto" => FanOperation::Auto { dry_run: sub_matches.get_flag("dry-run") },
+        "manual" => FanOperation::Manual {
+            percent: *sub_matches.get_one::<u32>("percent").unwrap(),
+            dry_run: sub_matches.get_flag("dry-run"),
+        },
+        _ => FanOperation::Status,
+    }
+}
+
+fn parse_profile(matches: &ArgMatches) -> ProfileOperation {
+    let (name, sub_matches) = matches.subcommand().expect("subcommand required");
+    let profile_name = || sub_matches.get_one::<String>("name").unwrap().clone();
+
+    match name {
+        "save" => ProfileOperation::Save {
+            name: profile_name(),
+            params: overclock_params(sub_matches, false, true),
+        },
+        "apply" => ProfileOperation::Apply {
+            name: profile_name(),
+            dry_run: sub_matches.get_flag("dry-run"),
+            boot: sub_matches.get_flag("boot"),
+        },
+        "import" => ProfileOperation::Import {
+            name: sub_matches.get_one::<String>("name").cloned(),
+            format: match sub_matches.get_one::<String>("format").unwrap().as_str() {
+                "bundle" => ProfileFormat::Bundle,
+                _ => ProfileFormat::Lact,
+            },
+            path: sub_matches.get_one::<PathBuf>("path").unwrap().clone(),
+        },
+        "export" => ProfileOperation::Export {
+            name: profile_name(),
+            format: ProfileFormat::Afterburner,
+            slot: *sub_matches.get_one::<u32>("slot").unwrap(),
+        },
+        "bundle" => ProfileOperation::Bundle { name: profile_name() },
+        "soak" => ProfileOperation::Soak {
+            name: profile_name(),
+            duration: *sub_matches.get_one::<Duration>("duration").unwrap(),
+        },
+        _ => ProfileOperation::List,
+    }
 }
 
 fn parse_clocks(s: &str) -> std::result::Result<(u32, u32), &'static str> {
@@ -68,8 +509,88 @@ fn parse_clocks(s: &str) -> std::result::Result<(u32, u32), &'static str> {
     Ok((min, max))
 }
 
+/// Parse an offset in MHz with an optional pstate, e.g. `150` or `50@P2`
+fn parse_offset(s: &str) -> std::result::Result<(NvmlPerfState, i32), String> {
+    let (offset, pstate) = match s.split_once('@') {
+        Some((offset, pstate)) => (offset, pstate.parse()?),
+        None => (s, NvmlPerfState::P0),
+    };
+    let offset = offset.parse().map_err(|_| format!("invalid offset '{offset}'"))?;
+    Ok((pstate, offset))
+}
+
+/// Parse `MEM,GFX` applications clocks, or `reset`
+fn parse_app_clocks(s: &str) -> std::result::Result<Option<(u32, u32)>, &'static str> {
+    if s == "reset" {
+        return Ok(None);
+    }
+    let (memory, graphics) = s.split_once(',').ok_or("Applications clocks must be 'mem,gfx' or 'reset'")?;
+    let memory = memory.parse().map_err(|_| "Invalid memory clock value")?;
+    let graphics = graphics.parse().map_err(|_| "Invalid graphics clock value")?;
+    Ok(Some((memory, graphics)))
+}
+
+fn parse_mode(s: &str) -> std::result::Result<u32, &'static str> {
+    u32::from_str_radix(s, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or("Mode must be octal, e.g. 660")
+}
+
+/// Parse power limit percentages as `FROM..TO[:STEP]`, e.g. `60..100:5`,
+/// or a single percentage. The step defaults to 5.
+fn parse_power_steps(s: &str) -> std::result::Result<Vec<u32>, String> {
+    let invalid = || format!("invalid power range '{s}', use FROM..TO[:STEP] such as 60..100:5");
+    let Some((from, rest)) = s.split_once("..") else {
+        return s.parse().map(|percent| vec![percent]).map_err(|_| invalid());
+    };
+    let (to, step) = rest.split_once(':').unwrap_or((rest, "5"));
+    let (from, to, step): (u32, u32, u32) = match (from.parse(), to.parse(), step.parse()) {
+        (Ok(from), Ok(to), Ok(step)) if from <= to && step > 0 => (from, to, step),
+        _ => return Err(invalid()),
+    };
+    Ok((from..=to).step_by(step as usize).collect())
+}
+
+/// Read `nvoc autotune` options, rejecting those of the other target
+fn autotune_params(matches: &ArgMatches) -> Result<AutotuneParams, clap::Error> {
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let efficiency = matches.get_one::<String>("target").unwrap() == "efficiency";
+    let (other, other_args) = match efficiency {
+        true => ("offset", ["max", "resolution"].as_slice()),
+        false => ("efficiency", ["power", "clock-caps", "offset", "min-perf"].as_slice()),
+    };
+    if let Some(arg) = other_args.iter().find(|id| given(id)) {
+        return Err(Command::new(app::NAME).error(
+            clap::error::ErrorKind::ArgumentConflict,
+            format!("--{arg} only applies to --target {other}"),
+        ));
+    }
+
+    let default_name = if efficiency { "efficiency" } else { "autotune" };
+    Ok(AutotuneParams {
+        name: matches.get_one::<String>("name").map_or(default_name, String::as_str).to_string(),
+        command: matches.get_one::<String>("cmd").unwrap().clone(),
+        step: *matches.get_one::<Duration>("step").unwrap(),
+        target: match efficiency {
+            true => AutotuneTarget::Efficiency(EfficiencyParams {
+                power: matches.get_one::<Vec<u32>>("power").unwrap().clone(),
+                clock_caps: matches.get_many::<u32>("clock-caps").into_iter().flatten().copied().collect(),
+                offset: *matches.get_one::<i32>("offset").unwrap(),
+                min_performance: *matches.get_one::<u32>("min-perf").unwrap(),
+            }),
+            false => AutotuneTarget::Offset {
+                max: matches.get_one::<i32>("max").copied(),
+                resolution: *matches.get_one::<i32>("resolution").unwrap(),
+            },
+        },
+    })
+}
+
 impl Config {
-    pub fn from_args() -> Result<Self, clap::Error> {
+    /// Parse the command line, taking defaults it leaves open from the
+    /// config file
+    pub fn from_args(defaults: &DefaultsSection) -> Result<Self, clap::Error> {
         let matches = Command::new(app::NAME)
             .version(app::VERSION)
             .author(app::AUTHOR)
@@ -79,88 +600,708 @@ impl Config {
                 Command::new("reset")
                     .about("Reset GPU to defaults")
                     .arg(device_arg())
+                    .arg(all_arg())
+                    .arg(dry_run_arg()),
+            )
+            .subcommand(
+                Command::new("appclocks")
+                    .about("Set applications clocks, the clocks the GPU boosts to under load")
+                    .arg(
+                        Arg::new("clocks")
+                            .value_name("MEM,GFX|reset")
+                            .help("Memory and graphics clock in MHz, or reset to restore the defaults")
+                            .required(true)
+                            .value_parser(parse_app_clocks),
+                    )
+                    .arg(device_arg())
+                    .arg(all_arg())
+                    .arg(dry_run_arg()),
+            )
+            .subcommand(
+                Command::new("thermal")
+                    .about("Show temperature thresholds or set the thermal target")
+                    .arg(
+                        Arg::new("target")
+                            .long("target")
+                            .value_name("CELSIUS")
+                            .help("Temperature the GPU manages its boost clocks to stay under")
+                            .value_parser(clap::value_parser!(u32)),
+                    )
+                    .arg(device_arg())
+                    .arg(all_arg())
                     .arg(dry_run_arg()),
             )
             .subcommand(
                 Command::new("info")
                     .about("Show GPU information")
+                    .arg(device_arg())
+                    .arg(all_arg())
+                    .arg(json_arg()),
+            )
+            .subcommand(
+                Command::new("ps")
+                    .about("List processes running on the GPU")
+                    .arg(device_arg())
+                    .arg(all_arg()),
+            )
+            .subcommand(
+                Command::new("health")
+                    .about("Check the GPU memory for remapped rows and retired pages")
+                    .arg(device_arg())
+                    .arg(all_arg()),
+            )
+            .subcommand(
+                Command::new("capabilities")
+                    .about("Show which nvoc features the driver and GPU support")
+                    .arg(device_arg())
+                    .arg(all_arg()),
+            )
+            .subcommand(
+                Command::new("limits")
+                    .about("Show the offset, power limit and fan ranges the driver accepts")
+                    .arg(device_arg())
+                    .arg(all_arg()),
+            )
+            .subcommand(fan_command())
+            .subcommand(
+                Command::new("field")
+                    .about("Read NVML field values by ID or name")
+                    .arg(
+                        Arg::new("ids")
+                            .value_name("ID")
+                            .help("Field ID (NVML_FI_*) or name: memory_temp, energy")
+                            .required(true)
+                            .num_args(1..)
+                            .value_parser(nvoc_core::gpu::field::parse),
+                    )
+                    .arg(device_arg())
+                    .arg(all_arg()),
+            )
+            .subcommand(
+                Command::new("events")
+                    .about("Stream XID errors, clock and power state changes as JSON lines")
+                    .arg(device_arg())
+                    .arg(all_arg()),
+            )
+            .subcommand(
+                Command::new("monitor")
+                    .about("Live GPU dashboard")
+                    .arg(device_arg())
+                    .arg(all_arg())
+                    .arg(
+                        Arg::new("interval")
+                            .long("interval")
+                            .value_name("DURATION")
+                            .help("Refresh interval")
+                            .default_value(monitor::DEFAULT_INTERVAL)
+                            .value_parser(parse_duration),
+                    ),
+            )
+            .subcommand(
+                Command::new("watch")
+                    .about("Print a status line per GPU at an interval")
+                    .arg(device_arg())
+                    .arg(all_arg())
+                    .arg(
+                        Arg::new("interval")
+                            .long("interval")
+                            .value_name("DURATION")
+                            .help("Time between lines")
+                            .default_value(watch::DEFAULT_INTERVAL)
+                            .value_parser(parse_duration),
+                    ),
+            )
+            .subcommand(
+                Command::new("log")
+                    .about("Log telemetry to a CSV file or SQLite database")
+                    .arg(device_arg())
+                    .arg(all_arg())
+                    .arg(
+                        Arg::new("out")
+                            .long("out")
+                            .value_name("PATH")
+                            .help("CSV file to write")
+                            .value_parser(clap::value_parser!(PathBuf)),
+                    )
+                    .arg(
+                        Arg::new("db")
+                            .long("db")
+                            .value_name("PATH")
+                            .help("SQLite database to append to")
+                            .value_parser(clap::value_parser!(PathBuf)),
+                    )
+                    .group(ArgGroup::new("sink").args(["out", "db"]).required(true))
+                    .arg(
+                        Arg::new("retention")
+                            .long("retention")
+                            .value_name("DURATION")
+                            .help("Delete database samples older than this, e.g. 7d")
+                            .conflicts_with("out")
+                            .value_parser(parse_duration),
+                    )
+                    .arg(
+                        Arg::new("max-rows")
+                            .long("max-rows")
+                            .value_name("N")
+                            .help("Delete the oldest database samples beyond N rows")
+                            .conflicts_with("out")
+                            .value_parser(clap::value_parser!(u64).range(1..)),
+                    )
+                    .arg(
+                        Arg::new("interval")
+                            .long("interval")
+                            .value_name("DURATION")
+                            .help("Time between samples")
+                            .default_value(csv_log::DEFAULT_INTERVAL)
+                            .value_parser(parse_duration),
+                    ),
+            )
+            .subcommand(
+                Command::new("stats")
+                    .about("Summarize telemetry logged with log --db")
+                    .arg(device_arg())
+                    .arg(all_arg())
+                    .arg(
+                        Arg::new("db")
+                            .long("db")
+                            .value_name("PATH")
+                            .help("SQLite database written by log --db")
+                            .required(true)
+                            .value_parser(clap::value_parser!(PathBuf)),
+                    )
+                    .arg(
+                        Arg::new("since")
+                            .long("since")
+                            .value_name("DURATION")
+                            .help("Only samples newer than this long ago, e.g. 2h")
+                            .value_parser(parse_duration),
+                    )
+                    .arg(
+                        Arg::new("until")
+                            .long("until")
+                            .value_name("DURATION")
+                            .help("Only samples older than this long ago")
+                            .value_parser(parse_duration),
+                    ),
+            )
+            .subcommand(
+                Command::new("history")
+                    .about("Show the trend of a metric recorded by the daemon")
+                    .arg(device_arg())
+                    .arg(
+                        Arg::new("metric")
+                            .long("metric")
+                            .value_name("METRIC")
+                            .help("temp, clock, mem-clock, offset, mem-offset, power, power-limit, fan or util")
+                            .required(true)
+                            .value_parser(metric_column),
+                    )
+                    .arg(
+                        Arg::new("last")
+                            .long("last")
+                            .value_name("DURATION")
+                            .help("Period to show, ending now")
+                            .default_value("24h")
+                            .value_parser(parse_duration),
+                    )
+                    .arg(
+                        Arg::new("buckets")
+                            .long("buckets")
+                            .value_name("N")
+                            .help("Number of rows the period is split into")
+                            .default_value(history::DEFAULT_BUCKETS)
+                            .value_parser(clap::value_parser!(u32).range(1..=1000)),
+                    )
+                    .arg(
+                        Arg::new("db")
+                            .long("db")
+                            .value_name("PATH")
+                            .help("History database, defaults to [history] path in the config")
+                            .value_parser(clap::value_parser!(PathBuf)),
+                    ),
+            )
+            .subcommand(
+                Command::new("daemon")
+                    .about("Run fan curve / power budget daemon")
+                    .arg(device_arg())
+                    .arg(all_arg())
+                    .arg(
+                        Arg::new("config")
+                            .long("config")
+                            .value_name("PATH")
+                            .help("Config file")
+                            .default_value(paths::CONFIG_FILE)
+                            .value_parser(clap::value_parser!(PathBuf)),
+                    )
+                    .arg(
+                        Arg::new("enforce")
+                            .long("enforce")
+                            .help("Re-apply the active profile when its settings are changed externally")
+                            .action(clap::ArgAction::SetTrue),
+                    )
+                    .arg(
+                        Arg::new("defer-to")
+                            .long("defer-to")
+                            .value_name("PIDFILE|DBUS-NAME")
+                            .help("Suspend enforcement while this external manager runs (repeatable)")
+                            .action(clap::ArgAction::Append)
+                            .value_parser(Manager::parse),
+                    ),
+            )
+            .subcommand(
+                Command::new("exporter")
+                    .about("Serve Prometheus metrics")
+                    .arg(
+                        Arg::new("listen")
+                            .long("listen")
+                            .value_name("ADDR")
+                            .help("TCP address [default: 127.0.0.1:9835 without --unix]")
+                            .value_parser(clap::value_parser!(SocketAddr)),
+                    )
+                    .arg(
+                        Arg::new("unix")
+                            .long("unix")
+                            .value_name("PATH")
+                            .help("Unix socket path")
+                            .value_parser(clap::value_parser!(PathBuf)),
+                    )
+                    .arg(
+                        Arg::new("socket-mode")
+                            .long("socket-mode")
+                            .value_name("MODE")
+                            .help("Unix socket permissions")
+                            .default_value(exporter::DEFAULT_SOCKET_MODE)
+                            .value_parser(parse_mode),
+                    )
+                    .arg(
+                        Arg::new("cache-ttl")
+                            .long("cache-ttl")
+                            .alias("interval")
+                            .value_name("DURATION")
+                            .help("How long readings are reused before a scrape samples again")
+                            .default_value(exporter::DEFAULT_CACHE_TTL)
+                            .value_parser(parse_duration),
+                    )
+                    .arg(
+                        Arg::new("emit-dashboard")
+                            .long("emit-dashboard")
+                            .help("Print Grafana dashboard JSON and exit")
+                            .action(clap::ArgAction::SetTrue),
+                    ),
+            )
+            .subcommand(profile_command())
+            .subcommand(
+                Command::new("install-service")
+                    .about("Install a systemd service applying a profile at boot and on driver reload")
+                    .arg(
+                        Arg::new("profile")
+                            .long("profile")
+                            .value_name("NAME")
+                            .help("Profile to apply")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::new("timer")
+                            .long("timer")
+                            .value_name("INTERVAL")
+                            .help("Also re-apply periodically")
+                            .value_parser(parse_duration),
+                    )
+                    .arg(device_arg())
+                    .arg(all_arg())
+                    .arg(dry_run_arg().help("Print the units instead of installing them")),
+            )
+            .subcommand(
+                Command::new("uninstall-service")
+                    .about("Remove the systemd units written by install-service")
+                    .arg(dry_run_arg().help("Show what would be removed")),
+            )
+            .subcommand(
+                Command::new("k8s-labels")
+                    .about("Print Kubernetes node labels for node-feature-discovery")
+                    .arg(json_arg().help("Print a kubectl node patch instead")),
+            )
+            .subcommand(
+                Command::new("setup")
+                    .about("Guided first setup: propose, soak and install a daily profile")
+                    .arg(
+                        Arg::new("name")
+                            .long("name")
+                            .value_name("NAME")
+                            .help("Name of the profile to create")
+                            .default_value("daily"),
+                    )
+                    .arg(
+                        Arg::new("soak")
+                            .long("soak")
+                            .value_name("DURATION")
+                            .help("How long to soak the proposal")
+                            .default_value("2m")
+                            .value_parser(parse_duration),
+                    )
+                    .arg(
+                        Arg::new("yes")
+                            .long("yes")
+                            .short('y')
+                            .help("Accept the proposal without asking")
+                            .action(clap::ArgAction::SetTrue),
+                    )
+                    .arg(device_arg()),
+            )
+            .subcommand(
+                Command::new("autotune")
+                    .about("Find the highest stable graphics offset, or the most efficient settings, and save them to a profile")
+                    .arg(
+                        Arg::new("cmd")
+                            .long("cmd")
+                            .value_name("COMMAND")
+                            .help("Workload or benchmark to run at each step, through sh -c")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::new("target")
+                            .long("target")
+                            .value_name("TARGET")
+                            .help("What to tune for")
+                            .value_parser(["offset", "efficiency"])
+                            .default_value("offset"),
+                    )
+                    .arg(
+                        Arg::new("name")
+                            .long("name")
+                            .value_name("NAME")
+                            .help("Profile to save the result into [default: autotune, or efficiency with --target efficiency]"),
+                    )
+                    .arg(
+                        Arg::new("step")
+                            .long("step")
+                            .value_name("DURATION")
+                            .help("How long to run each step at most")
+                            .default_value("60s")
+                            .value_parser(parse_duration),
+                    )
+                    .arg(
+                        Arg::new("max")
+                            .long("max")
+                            .value_name("MHZ")
+                            .help("Highest offset to try (offset) [default: top of the driver's range]")
+                            .allow_negative_numbers(true)
+                            .value_parser(clap::value_parser!(i32)),
+                    )
+                    .arg(
+                        Arg::new("resolution")
+                            .long("resolution")
+                            .value_name("MHZ")
+                            .help("Stop when the search is narrowed to this step (offset)")
+                            .default_value("15")
+                            .value_parser(clap::value_parser!(i32).range(1..)),
+                    )
+                    .arg(
+                        Arg::new("power")
+                            .long("power")
+                            .value_name("FROM..TO[:STEP]")
+                            .help("Power limits to try, in % (efficiency)")
+                            .default_value(autotune::POWER_STEPS)
+                            .value_parser(parse_power_steps),
+                    )
+                    .arg(
+                        Arg::new("clock-caps")
+                            .long("clock-caps")
+                            .value_name("MHZ,...")
+                            .help("Graphics clock caps to try (efficiency) [default: 80% and 90% of the supported maximum]")
+                            .value_delimiter(',')
+                            .value_parser(clap::value_parser!(u32)),
+                    )
+                    .arg(
+                        Arg::new("offset")
+                            .long("offset")
+                            .value_name("MHZ")
+                            .help("Offset to try with capped clocks, 0 to skip (efficiency)")
+                            .default_value("100")
+                            .allow_negative_numbers(true)
+                            .value_parser(clap::value_parser!(i32)),
+                    )
+                    .arg(
+                        Arg::new("min-perf")
+                            .long("min-perf")
+                            .value_name("PERCENT")
+                            .help("Least performance to keep, in % of stock (efficiency)")
+                            .default_value("90")
+                            .value_parser(clap::value_parser!(u32).range(1..=100)),
+                    )
+                    .arg(device_arg()),
+            )
+            .subcommand(
+                Command::new("sweep")
+                    .about("Benchmark a series of power limits and report performance per watt")
+                    .arg(
+                        Arg::new("power")
+                            .long("power")
+                            .value_name("FROM..TO[:STEP]")
+                            .help("Power limits to try, in %")
+                            .required(true)
+                            .value_parser(parse_power_steps),
+                    )
+                    .arg(
+                        Arg::new("cmd")
+                            .long("cmd")
+                            .value_name("COMMAND")
+                            .help("Benchmark to run at each power limit, through sh -c")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::new("step")
+                            .long("step")
+                            .value_name("DURATION")
+                            .help("How long to run the benchmark at each limit at most")
+                            .default_value("60s")
+                            .value_parser(parse_duration),
+                    )
+                    .arg(
+                        Arg::new("csv")
+                            .long("csv")
+                            .value_name("PATH")
+                            .help("Also write the results as CSV")
+                            .value_parser(clap::value_parser!(PathBuf)),
+                    )
                     .arg(device_arg()),
             )
+            .subcommand(
+                Command::new("stress")
+                    .about("Test an overclock under a workload and revert it if unstable")
+                    .args(overclock_args())
+                    .arg(
+                        Arg::new("cmd")
+                            .long("cmd")
+                            .value_name("COMMAND")
+                            .help("Workload to run, through sh -c")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::new("duration")
+                            .long("duration")
+                            .value_name("DURATION")
+                            .help("Longest the test runs")
+                            .default_value("10m")
+                            .value_parser(parse_duration),
+                    )
+                    .arg(device_arg()),
+            )
+            .subcommand(
+                Command::new("doctor")
+                    .about("Diagnose the NVML library and driver")
+                    .arg(
+                        Arg::new("symbols")
+                            .long("symbols")
+                            .help("Check every NVML function nvoc uses")
+                            .action(clap::ArgAction::SetTrue),
+                    ),
+            )
+            .args(overclock_args())
+            .arg(device_arg())
+            .arg(all_arg())
+            .arg(dry_run_arg())
             .arg(
-                Arg::new("clocks")
-                    .short('c')
-                    .long("clocks")
-                    .value_name("MIN,MAX")
-                    .help("GPU clocks MHz")
-                    .value_parser(parse_clocks),
+                Arg::new("no-verify")
+                    .long("no-verify")
+                    .help("Do not read settings back to check the driver applied them")
+                    .action(clap::ArgAction::SetTrue),
             )
             .arg(
-                Arg::new("offset")
-                    .short('o')
-                    .long("offset")
-                    .value_name("GRAPHICS_OFFSET")
-                    .help("GPU offset MHz")
-                    .allow_hyphen_values(true)
-                    .value_parser(clap::value_parser!(i32)),
+                Arg::new("revert-after")
+                    .long("revert-after")
+                    .value_name("DURATION")
+                    .help("Try the settings: revert them after DURATION or on Ctrl-C")
+                    .conflicts_with("all")
+                    .value_parser(parse_duration),
             )
             .arg(
-                Arg::new("memory-offset")
-                    .short('m')
-                    .long("memory-offset")
-                    .value_name("MEMORY_OFFSET")
-                    .help("Mem offset MHz")
-                    .allow_hyphen_values(true)
-                    .value_parser(clap::value_parser!(i32)),
+                Arg::new("timing")
+                    .long("timing")
+                    .help("Report time spent in NVML on exit")
+                    .global(true)
+                    .action(clap::ArgAction::SetTrue),
             )
             .arg(
-                Arg::new("power")
-                    .short('p')
-                    .long("power")
-                    .value_name("PERCENT")
-                    .help("Power limit %")
-                    .value_parser(clap::value_parser!(u32)),
+                Arg::new("quiet")
+                    .short('q')
+                    .long("quiet")
+                    .help("Only print errors, warnings and requested data")
+                    .global(true)
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("ignore-thermal-guard")
+                    .long("ignore-thermal-guard")
+                    .help("Raise power or offsets even when the GPU is above the configured temperature")
+                    .global(true)
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("nvml-path")
+                    .long("nvml-path")
+                    .value_name("PATH")
+                    .help("Load libnvidia-ml from PATH instead of searching for it")
+                    .global(true)
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("trace-nvml")
+                    .long("trace-nvml")
+                    .value_name("FILE")
+                    .help("Log every NVML call with its arguments, result and duration to stderr or FILE")
+                    .global(true)
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .default_missing_value("-")
+                    .value_parser(clap::value_parser!(PathBuf)),
             )
-            .arg(device_arg())
-            .arg(dry_run_arg())
             .get_matches();
 
-        match matches.subcommand() {
-            Some(("reset", sub_matches)) => Ok(Config {
-                device: *sub_matches.get_one::<u32>("device").unwrap(),
-                operation: Operation::Reset {
-                    dry_run: sub_matches.get_flag("dry-run"),
+        let operation = match matches.subcommand() {
+            Some(("reset", sub_matches)) => Operation::Reset {
+                dry_run: sub_matches.get_flag("dry-run"),
+            },
+            Some(("appclocks", sub_matches)) => Operation::AppClocks {
+                clocks: *sub_matches.get_one::<Option<(u32, u32)>>("clocks").unwrap(),
+                dry_run: sub_matches.get_flag("dry-run"),
+            },
+            Some(("thermal", sub_matches)) => Operation::Thermal {
+                target: sub_matches.get_one::<u32>("target").copied(),
+                dry_run: sub_matches.get_flag("dry-run"),
+            },
+            Some(("info", sub_matches)) => Operation::Info {
+                json: sub_matches.get_flag("json"),
+            },
+            Some(("field", sub_matches)) => Operation::Field {
+                ids: sub_matches.get_many::<u32>("ids").unwrap().copied().collect(),
+            },
+            Some(("ps", _)) => Operation::Ps,
+            Some(("health", _)) => Operation::Health,
+            Some(("capabilities", _)) => Operation::Capabilities,
+            Some(("limits", _)) => Operation::Limits,
+            Some(("fan", sub_matches)) => Operation::Fan(parse_fan(sub_matches)),
+            Some(("events", _)) => Operation::Events,
+            Some(("monitor", sub_matches)) => Operation::Monitor {
+                interval: *sub_matches.get_one::<Duration>("interval").unwrap(),
+            },
+            Some(("watch", sub_matches)) => Operation::Watch {
+                interval: *sub_matches.get_one::<Duration>("interval").unwrap(),
+            },
+            Some(("log", sub_matches)) => Operation::Log {
+                sink: match sub_matches.get_one::<PathBuf>("db") {
+                    Some(path) => LogSink::Sqlite {
+                        path: path.clone(),
+                        retention: Retention {
+                            max_age: sub_matches.get_one::<Duration>("retention").copied(),
+                            max_rows: sub_matches.get_one::<u64>("max-rows").copied(),
+                        },
+                    },
+                    None => LogSink::Csv(sub_matches.get_one::<PathBuf>("out").unwrap().clone()),
                 },
+                interval: *sub_matches.get_one::<Duration>("interval").unwrap(),
+            },
+            Some(("stats", sub_matches)) => Operation::Stats {
+                db: sub_matches.get_one::<PathBuf>("db").unwrap().clone(),
+                since: sub_matches.get_one::<Duration>("since").copied(),
+                until: sub_matches.get_one::<Duration>("until").copied(),
+            },
+            Some(("history", sub_matches)) => Operation::History {
+                db: sub_matches.get_one::<PathBuf>("db").cloned(),
+                metric: sub_matches.get_one::<&'static str>("metric").unwrap(),
+                last: *sub_matches.get_one::<Duration>("last").unwrap(),
+                buckets: *sub_matches.get_one::<u32>("buckets").unwrap() as usize,
+            },
+            Some(("daemon", sub_matches)) => Operation::Daemon {
+                config_path: sub_matches.get_one::<PathBuf>("config").unwrap().clone(),
+                enforce: sub_matches.get_flag("enforce"),
+                defer_to: sub_matches.get_many::<Manager>("defer-to").into_iter().flatten().cloned().collect(),
+            },
+            Some(("exporter", sub_matches)) => {
+                let unix = sub_matches.get_one::<PathBuf>("unix").cloned();
+                let listen = match sub_matches.get_one::<SocketAddr>("listen") {
+                    Some(addr) => Some(*addr),
+                    None if unix.is_none() => Some(exporter::DEFAULT_LISTEN.parse().unwrap()),
+                    None => None,
+                };
+                Operation::Exporter(ExporterParams {
+                    listen,
+                    unix,
+                    socket_mode: *sub_matches.get_one::<u32>("socket-mode").unwrap(),
+                    cache_ttl: *sub_matches.get_one::<Duration>("cache-ttl").unwrap(),
+                    emit_dashboard: sub_matches.get_flag("emit-dashboard"),
+                })
+            }
+            Some(("profile", sub_matches)) => Operation::Profile(parse_profile(sub_matches)),
+            Some(("install-service", sub_matches)) => Operation::InstallService {
+                profile: sub_matches.get_one::<String>("profile").unwrap().clone(),
+                timer: sub_matches.get_one::<Duration>("timer").copied(),
+                dry_run: sub_matches.get_flag("dry-run"),
+            },
+            Some(("uninstall-service", sub_matches)) => Operation::UninstallService {
+                dry_run: sub_matches.get_flag("dry-run"),
+            },
+            Some(("k8s-labels", sub_matches)) => Operation::K8sLabels {
+                json: sub_matches.get_flag("json"),
+            },
+            Some(("setup", sub_matches)) => Operation::Setup {
+                name: sub_matches.get_one::<String>("name").unwrap().clone(),
+                soak: *sub_matches.get_one::<Duration>("soak").unwrap(),
+                yes: sub_matches.get_flag("yes"),
+            },
+            Some(("autotune", sub_matches)) => Operation::Autotune(autotune_params(sub_matches)?),
+            Some(("sweep", sub_matches)) => Operation::Sweep(SweepParams {
+                power: sub_matches.get_one::<Vec<u32>>("power").unwrap().clone(),
+                command: sub_matches.get_one::<String>("cmd").unwrap().clone(),
+                step: *sub_matches.get_one::<Duration>("step").unwrap(),
+                csv: sub_matches.get_one::<PathBuf>("csv").cloned(),
             }),
-            Some(("info", sub_matches)) => Ok(Config {
-                device: *sub_matches.get_one::<u32>("device").unwrap(),
-                operation: Operation::Info,
-            }),
+            Some(("stress", sub_matches)) => Operation::Stress {
+                params: overclock_params(sub_matches, false, true).unwrap_or_default(),
+                command: sub_matches.get_one::<String>("cmd").unwrap().clone(),
+                duration: *sub_matches.get_one::<Duration>("duration").unwrap(),
+            },
+            Some(("doctor", sub_matches)) => Operation::Doctor {
+                symbols: sub_matches.get_flag("symbols"),
+            },
             _ => {
-                let clocks = matches.get_one::<(u32, u32)>("clocks").copied();
-                let graphics_offset = matches.get_one::<i32>("offset").copied();
-                let memory_offset = matches.get_one::<i32>("memory-offset").copied();
-                let power_limit = matches.get_one::<u32>("power").copied();
-
-                if clocks.is_none()
-                    && graphics_offset.is_none()
-                    && memory_offset.is_none()
-                    && power_limit.is_none()
-                {
+                let Some(mut params) = overclock_params(&matches, matches.get_flag("dry-run"), !matches.get_flag("no-verify")) else {
                     return Err(Command::new(app::NAME)
-                        .error(clap::error::ErrorKind::MissingRequiredArgument, "No operation specified. Use a subcommand (info, reset) or provide overclock options (-c, -o, -m, -p)."));
-                }
-
-                Ok(Config {
-                    device: *matches.get_one::<u32>("device").unwrap(),
-                    operation: Operation::Overclock(OverclockParams {
-                        clocks,
-                        graphics_offset,
-                        memory_offset,
-                        power_limit,
-                        dry_run: matches.get_flag("dry-run"),
-                    }),
-                })
+                        .error(clap::error::ErrorKind::MissingRequiredArgument, "No operation specified. Use a subcommand (info, ps, health, capabilities, limits, fan, field, monitor, watch, log, stats, history, reset, appclocks, thermal, daemon, exporter, profile, setup, stress, autotune, sweep, install-service, k8s-labels, doctor) or provide overclock options (-c, -o, -m, -p)."));
+                };
+                params.revert_after = matches.get_one::<Duration>("revert-after").copied();
+
+                Operation::Overclock(params)
             }
+        };
+        let mut config = Globals::from_matches(&matches).config(&matches, operation);
+        apply_defaults(&mut config, &matches, defaults);
+        Ok(config)
+    }
+}
+
+/// Matches of the innermost subcommand, where `-d` and `--interval` live
+fn leaf_matches(matches: &ArgMatches) -> &ArgMatches {
+    match matches.subcommand() {
+        Some((_, sub_matches)) => leaf_matches(sub_matches),
+        None => matches,
+    }
+}
+
+/// Replace built-in defaults the command line did not override with the
+/// config file's `[defaults]`
+fn apply_defaults(config: &mut Config, matches: &ArgMatches, defaults: &DefaultsSection) {
+    let leaf = leaf_matches(matches);
+    let defaulted =
+        |id: &str| leaf.try_contains_id(id).is_ok() && leaf.value_source(id) == Some(ValueSource::DefaultValue);
+    if let Some(device) = defaults.device.filter(|_| defaulted("device")) {
+        config.device = device;
+    }
+    if let Some(default) = defaults.interval.filter(|_| defaulted("interval")) {
+        if let Operation::Monitor { interval } | Operation::Watch { interval } | Operation::Log { interval, .. } =
+            &mut config.operation
+        {
+            *interval = default;
         }
     }
 }
@@ -2,84 +2,484 @@
 //!
 //! Command-line utility for GPU overclocking using NVML.
 //! Designed for RTX 5000 series GPUs with nvidia-open drivers.
+//! Argument parsing lives here; the GPU logic is in the `nvoc-core` crate.
 
 use std::process;
 
 mod cli;
-mod constants;
-mod gpu;
-mod nvml;
-
-use cli::Operation;
-use nvml::NvmlError;
-
-pub struct AppError {
-    domain: &'static str,
-    source: Option<NvmlError>,
-    message: Option<String>,
-    printed: bool,
+
+use cli::{ExporterParams, FanOperation, LogSink, Operation, OverclockParams, ProfileFormat, ProfileOperation};
+use config::ConfigFile;
+use constants::paths;
+use nvoc_core::{
+    autotune, config, constants, csv_log, daemon, doctor, exporter, gpu, history, journal, k8s, monitor, nvml,
+    output, profile, ratelimit, service, setup, signal, stress, sweep, timing, trace, watch, AppError, ErrorKind,
+};
+
+fn open_device(device_index: u32, settings: &ConfigFile) -> Result<nvml::Device, AppError> {
+    let device = gpu::get_device(device_index).map_err(|e| AppError::new("device", e).on_device(device_index))?;
+    gpu::validation::validate_architecture(&device, &settings.architecture.unknown)
+        .map_err(|e| AppError::new("gpu", e))?;
+    Ok(device)
 }
 
-impl AppError {
-    pub fn new(domain: &'static str, source: NvmlError) -> Self {
-        Self { domain, source: Some(source), message: None, printed: false }
-    }
+fn show_info_json(config: &cli::Config, settings: &ConfigFile) -> Result<(), AppError> {
+    let indices = match config.all_devices {
+        true => 0..gpu::device_count().map_err(|e| AppError::new("device", e))?,
+        false => config.device..config.device + 1,
+    };
 
-    pub fn msg(domain: &'static str, message: String) -> Self {
-        Self { domain, source: None, message: Some(message), printed: false }
+    let mut gpus = Vec::new();
+    for device_index in indices {
+        let device = &open_device(device_index, settings)?;
+        gpus.push(gpu::info::collect(device, device_index).map_err(|e| AppError::new("info", e))?);
     }
+    let report = gpu::info::InfoReport {
+        driver_version: gpu::driver_version().map_err(|e| AppError::new("driver", e))?,
+        gpus,
+    };
+    print_info_json(&report)
+}
 
-    pub fn printed(domain: &'static str) -> Self {
-        Self { domain, source: None, message: None, printed: true }
-    }
+fn print_info_json(report: &gpu::info::InfoReport) -> Result<(), AppError> {
+    let text = serde_json::to_string_pretty(report).map_err(|e| AppError::msg("info", e.to_string()))?;
+    println!("{text}");
+    Ok(())
 }
 
-impl std::fmt::Display for AppError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match (&self.source, &self.message) {
-            (Some(source), _) => write!(f, "error[{}]: {}", self.domain, source.user_message()),
-            (_, Some(msg)) => write!(f, "error[{}]: {}", self.domain, msg),
-            _ => write!(f, "error[{}]", self.domain),
+/// Show info from the daemon serving the selected GPU, if its control
+/// socket answers. Returns false when the caller should query NVML itself.
+fn show_info_from_daemon(config: &cli::Config, json: bool) -> Result<bool, AppError> {
+    let template = config::load_or_default(&paths::system(paths::CONFIG_FILE))
+        .ok()
+        .and_then(|settings| settings.daemon.socket);
+    let Some(status) = template.and_then(|t| daemon::control::query_status(&t, config.device)) else {
+        return Ok(false);
+    };
+
+    match json {
+        true => print_info_json(&gpu::info::InfoReport {
+            driver_version: status.driver_version,
+            gpus: vec![status.gpu],
+        })?,
+        false => {
+            println!("driver: {}", status.driver_version);
+            gpu::info::print(&status.gpu);
         }
     }
+    Ok(true)
 }
 
-fn run() -> Result<(), AppError> {
-    let config = cli::Config::from_args().unwrap_or_else(|e| e.exit());
+fn run_on_device(config: &cli::Config, settings: &ConfigFile, device_index: u32) -> Result<(), AppError> {
+    let device = &open_device(device_index, settings)?;
 
     if config.operation.modifies_gpu() {
-        gpu::validation::check_system_for_modification()
-            .map_err(|e| AppError::new("nvoc", e))?;
+        gpu::mig::check(device, device_index)?;
+        // A dry run writes nothing, so it leaves a leftover entry for the next real change
+        if !config.operation.is_dry_run() {
+            journal::reconcile(device, device_index)?;
+        }
     }
 
-    let _cleanup = gpu::init_with_cleanup()?;
-    let device = gpu::get_device(config.device).map_err(|e| AppError::new("device", e))?;
-    gpu::validation::validate_blackwell_architecture(device)
-        .map_err(|e| AppError::new("gpu", e))?;
+    // Daemon modifications are rate limited inside its control loop, and a
+    // soak only holds the limit while applying
+    let rate_limited = config.operation.modifies_gpu()
+        && !config.operation.is_dry_run()
+        && !matches!(
+            config.operation,
+            Operation::Daemon { .. } | Operation::Profile(ProfileOperation::Soak { .. })
+                | Operation::Stress { .. }
+                | Operation::Setup { .. }
+                | Operation::Autotune(_)
+                | Operation::Sweep(_)
+        );
+    let _permit = match rate_limited {
+        true => Some(ratelimit::acquire(device_index, settings.limits.min_modify_interval)?),
+        false => None,
+    };
 
     match config.operation {
-        Operation::Info => {
-            let version = gpu::driver_version().map_err(|e| AppError::new("driver", e))?;
-            println!("driver: {version}");
-            gpu::info::show_gpu_info(device, config.device)
+        Operation::Info { .. } => {
+            gpu::info::show_gpu_info(device, device_index)
                 .map_err(|e| AppError::new("info", e))?;
         }
+        Operation::Field { ref ids } => {
+            if config.all_devices {
+                println!("gpu {device_index}:");
+            }
+            gpu::field::show(device, ids)?;
+        }
+        Operation::Ps => {
+            if config.all_devices {
+                println!("gpu {device_index}:");
+            }
+            gpu::process::show(device)?;
+        }
+        Operation::Health => {
+            if config.all_devices {
+                println!("gpu {device_index}:");
+            }
+            gpu::health::show(device)?;
+        }
+        Operation::Capabilities => {
+            if config.all_devices {
+                println!("gpu {device_index}:");
+            }
+            gpu::capabilities::show(device)?;
+        }
+        Operation::Limits => {
+            if config.all_devices {
+                println!("gpu {device_index}:");
+            }
+            gpu::limits::show(device);
+        }
+        Operation::Fan(FanOperation::Status) => {
+            if config.all_devices {
+                println!("gpu {device_index}:");
+            }
+            gpu::fan::status(device)?;
+        }
+        Operation::Fan(FanOperation::Auto { dry_run }) => {
+            gpu::fan::apply_auto(device, dry_run)?;
+        }
+        Operation::Fan(FanOperation::Manual { percent, dry_run }) => {
+            gpu::fan::apply_manual(device, percent, dry_run)?;
+        }
         Operation::Reset { dry_run } => {
             gpu::reset::reset_gpu_settings(device, dry_run)?;
+            if !dry_run {
+                profile::record_applied(device_index, None)?;
+                journal::clear_active(device_index)?;
+            }
+        }
+        Operation::Overclock(ref params @ OverclockParams { revert_after: Some(duration), dry_run: false, .. }) => {
+            gpu::overclock::trial(device, device_index, params, duration)?;
         }
         Operation::Overclock(ref params) => {
-            gpu::overclock::apply(device, params)?;
+            gpu::overclock::apply(device, device_index, params)?;
+            if !params.dry_run {
+                profile::record_applied(device_index, None)?;
+                journal::clear_active(device_index)?;
+            }
+        }
+        Operation::Autotune(ref params) => {
+            let permit = ratelimit::acquire(device_index, settings.limits.min_modify_interval)?;
+            autotune::run(device, device_index, params, permit)?;
+        }
+        Operation::Sweep(ref params) => {
+            let permit = ratelimit::acquire(device_index, settings.limits.min_modify_interval)?;
+            sweep::run(device, device_index, params, permit)?;
+        }
+        Operation::Setup { ref name, soak, yes } => {
+            let permit = ratelimit::acquire(device_index, settings.limits.min_modify_interval)?;
+            setup::run(device, device_index, name, soak, yes, permit)?;
+            journal::mark_active(device_index, name, false)?;
+        }
+        Operation::Stress { ref params, ref command, duration } => {
+            let permit = ratelimit::acquire(device_index, settings.limits.min_modify_interval)?;
+            stress::run(device, device_index, params, command, duration, permit)?;
+            if !params.is_empty() {
+                profile::record_applied(device_index, None)?;
+                journal::clear_active(device_index)?;
+            }
+        }
+        Operation::AppClocks { clocks: Some(clocks), dry_run } => {
+            gpu::appclocks::apply(device, clocks, dry_run)?;
+        }
+        Operation::AppClocks { clocks: None, dry_run } => {
+            gpu::appclocks::reset(device, dry_run)?;
+        }
+        Operation::Thermal { target: Some(target), dry_run } => {
+            gpu::thermal::set_target(device, target, dry_run)?;
+        }
+        Operation::Thermal { target: None, .. } => {
+            if config.all_devices {
+                println!("gpu {device_index}:");
+            }
+            gpu::thermal::show(device);
+        }
+        Operation::Profile(ProfileOperation::Save { ref name, params: None }) => {
+            // Captured state is device specific, so it goes in the GPU's own section
+            let uuid = nvml::device_get_uuid(device).map_err(|e| AppError::new("profile", e))?;
+            let mut saved = profile::load_or_default(name)?;
+            saved.gpu.insert(uuid.clone(), profile::Settings::capture(device));
+            let path = profile::save(name, &saved)?;
+            output::status!("profile: saved gpu {device_index} ({uuid}) to {}", path.display());
+        }
+        Operation::Profile(ProfileOperation::Apply { ref name, dry_run, boot }) => {
+            // At boot, step down to the fallback profile after an unclean shutdown
+            let (name, fallback) = match boot {
+                true => journal::boot_profile(device_index, name, settings.boot.fallback.as_deref()),
+                false => (name.clone(), false),
+            };
+            profile::apply(device, device_index, &name, dry_run)?;
+            if !dry_run {
+                journal::mark_active(device_index, &name, fallback)?;
+            }
         }
+        Operation::Profile(ProfileOperation::Soak { ref name, duration }) => {
+            {
+                let _permit = ratelimit::acquire(device_index, settings.limits.min_modify_interval)?;
+                profile::apply(device, device_index, name, false)?;
+            }
+            output::status!("soak: watching gpu {device_index} for {}s, start your load now", duration.as_secs());
+            let soak = profile::stability::soak(device, device_index, duration)?;
+            println!("soak: {}", soak.summary());
+            let record = profile::stability::record(name, &soak)?;
+            output::status!("profile: {name} {record}");
+        }
+        Operation::Profile(ProfileOperation::Export { ref name, format: ProfileFormat::Afterburner, slot }) => {
+            let uuid = nvml::device_get_uuid(device).map_err(|e| AppError::new("profile", e))?;
+            let (text, skipped) = profile::afterburner::render(&profile::load(name)?.settings_for(&uuid), slot);
+            for setting in skipped {
+                eprintln!("warning[profile]: {setting} cannot be exported to afterburner, skipped");
+            }
+            print!("{text}");
+        }
+        Operation::Profile(ProfileOperation::Bundle { ref name }) => {
+            print!("{}", profile::bundle::render(&profile::bundle::create(device, name)?)?);
+        }
+        Operation::Profile(ProfileOperation::Import { ref name, format: ProfileFormat::Bundle, ref path }) => {
+            let (shared_as, imported) = profile::bundle::import(device, path)?;
+            let path = profile::save(name.as_deref().unwrap_or(&shared_as), &imported)?;
+            output::status!("profile: saved {}", path.display());
+        }
+        Operation::Exporter(_)
+        | Operation::Daemon { .. }
+        | Operation::Profile(_)
+        | Operation::Events
+        | Operation::Monitor { .. }
+        | Operation::Watch { .. }
+        | Operation::Log { .. }
+        | Operation::Stats { .. }
+        | Operation::History { .. }
+        | Operation::K8sLabels { .. }
+        | Operation::Doctor { .. }
+        | Operation::InstallService { .. }
+        | Operation::UninstallService { .. } => unreachable!("handled before device selection"),
     };
 
     Ok(())
 }
 
+fn run() -> Result<(), AppError> {
+    // Read before the command line, which takes its defaults from it
+    let file = config::load_or_default(&paths::system(paths::CONFIG_FILE));
+    let defaults = config::DefaultsSection::default();
+    let config =
+        cli::Config::from_args(file.as_ref().map_or(&defaults, |settings| &settings.defaults)).unwrap_or_else(|e| e.exit());
+    let configured = file.as_ref().ok().map(|settings| &settings.paths);
+    if let Some(dir) = configured.and_then(|paths| paths.profiles.clone()) {
+        profile::set_dir(dir);
+    }
+    if let Some(library) = config.nvml_path.clone().or_else(|| configured.and_then(|paths| paths.library.clone())) {
+        nvml::set_library_path(library);
+    }
+    if config.timing {
+        timing::enable();
+    }
+    if let Some(path) = &config.trace_nvml {
+        trace::enable(path).map_err(|e| AppError::msg("trace", format!("cannot write {}: {e}", path.display())))?;
+    }
+    if config.quiet {
+        output::set_quiet();
+    }
+
+    // Operations that never touch NVML
+    match config.operation {
+        Operation::Exporter(ExporterParams { emit_dashboard: true, .. }) => {
+            return exporter::emit_dashboard();
+        }
+        Operation::Profile(ProfileOperation::List) => {
+            let records = profile::stability::load();
+            for name in profile::list()? {
+                match records.get(&name) {
+                    Some(record) => println!("{name}: {record}"),
+                    None => println!("{name}: not validated"),
+                }
+            }
+            return Ok(());
+        }
+        Operation::Profile(ProfileOperation::Save { ref name, params: Some(ref params) }) => {
+            if !params.graphics_pstate_offsets.is_empty() || !params.memory_pstate_offsets.is_empty() {
+                return Err(AppError::msg("profile", "per-pstate offsets are not stored in profiles".to_string()));
+            }
+            let settings = profile::Settings::from_params(params);
+            let path = profile::save(name, &profile::Profile::from_settings(settings))?;
+            output::status!("profile: saved {}", path.display());
+            return Ok(());
+        }
+        Operation::InstallService { ref profile, timer, dry_run } => {
+            if !dry_run {
+                gpu::validation::check_system_for_modification().map_err(|e| AppError::new("service", e))?;
+            }
+            let target = match config.all_devices {
+                true => service::Target::All,
+                false => service::Target::Device(config.device),
+            };
+            return service::install(profile, target, timer, dry_run);
+        }
+        // Reports init failures itself rather than stopping at them
+        Operation::Doctor { symbols } => return doctor::run(symbols),
+        // Reads the database only, no driver needed
+        Operation::Stats { ref db, since, until } => {
+            let device = [config.device];
+            let indices = (!config.all_devices).then_some(&device[..]);
+            return history::stats(db, indices, since, until);
+        }
+        Operation::History { ref db, metric, last, buckets } => {
+            let db = db.clone().unwrap_or_else(|| {
+                file.as_ref()
+                    .ok()
+                    .and_then(|settings| settings.history.as_ref())
+                    .map_or_else(|| constants::history::DEFAULT_PATH.into(), |h| h.path.clone())
+            });
+            return history::trend(&db, config.device, metric, last, buckets);
+        }
+        Operation::UninstallService { dry_run } => {
+            if !dry_run {
+                gpu::validation::check_system_for_modification().map_err(|e| AppError::new("service", e))?;
+            }
+            return service::uninstall(dry_run);
+        }
+        // A running daemon answers without loading NVML in this process
+        Operation::Info { json } if !config.all_devices && show_info_from_daemon(&config, json)? => {
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    if config.operation.modifies_gpu() && !paths::simulated() {
+        gpu::validation::check_system_for_modification()
+            .map_err(|e| AppError::new("nvoc", e))?;
+    }
+
+    // Read-only operations tolerate a broken config file
+    let settings = match config.operation.modifies_gpu() {
+        true => file?,
+        false => file.unwrap_or_default(),
+    };
+    gpu::domain::set_power_floor(settings.limits.min_power_limit);
+    gpu::domain::set_power_raise_on_battery(settings.limits.allow_power_raise_on_battery);
+    // The daemon reacts to temperature through its own rules
+    let guarded = !config.ignore_thermal_guard && !matches!(config.operation, Operation::Daemon { .. });
+    gpu::domain::set_thermal_guard(settings.limits.max_apply_temperature.filter(|_| guarded));
+
+    let _cleanup = gpu::init_with_cleanup()?;
+
+    // Ctrl-C during a change lets it finish or roll back instead of leaving
+    // it half applied; long-running modes revert from the same flag
+    if config.operation.modifies_gpu() && !config.operation.is_dry_run() {
+        signal::install();
+    }
+
+    if let Operation::Exporter(ref params) = config.operation {
+        return exporter::run(params);
+    }
+
+    if let Operation::Monitor { interval } | Operation::Watch { interval } | Operation::Log { interval, .. } =
+        config.operation
+    {
+        let indices: Vec<u32> = match config.all_devices {
+            true => (0..gpu::device_count().map_err(|e| AppError::new("device", e))?).collect(),
+            false => vec![config.device],
+        };
+        return match config.operation {
+            Operation::Monitor { .. } => monitor::run(indices, interval),
+            Operation::Log { sink: LogSink::Csv(ref out), .. } => csv_log::run(&indices, out, interval),
+            Operation::Log { sink: LogSink::Sqlite { ref path, retention }, .. } => {
+                history::log(&indices, path, retention, interval)
+            }
+            _ => watch::run(&indices, interval),
+        };
+    }
+
+    if let Operation::Events = config.operation {
+        let indices: Vec<u32> = match config.all_devices {
+            true => (0..gpu::device_count().map_err(|e| AppError::new("device", e))?).collect(),
+            false => vec![config.device],
+        };
+        return gpu::events::stream(&indices);
+    }
+
+    if let Operation::Daemon { ref config_path, enforce, ref defer_to } = config.operation {
+        let indices: Vec<u32> = match config.all_devices {
+            true => (0..gpu::device_count().map_err(|e| AppError::new("device", e))?).collect(),
+            false => vec![config.device],
+        };
+        for &device_index in &indices {
+            let device = open_device(device_index, &settings)?;
+            journal::reconcile(&device, device_index)?;
+        }
+        return daemon::run(&indices, config_path, enforce, defer_to);
+    }
+
+    if let Operation::K8sLabels { json } = config.operation {
+        return k8s::run(json);
+    }
+
+    if let Operation::Profile(ProfileOperation::Import { ref name, format: ProfileFormat::Lact, ref path }) = config.operation {
+        let path = profile::save(name.as_deref().unwrap_or("lact"), &profile::import_lact(path)?)?;
+        output::status!("profile: saved {}", path.display());
+        return Ok(());
+    }
+
+    if let Operation::Info { json: true } = config.operation {
+        return show_info_json(&config, &settings);
+    }
+
+    if let Operation::Info { .. } = config.operation {
+        let version = gpu::driver_version().map_err(|e| AppError::new("driver", e))?;
+        println!("driver: {version}");
+    }
+
+    run_on_devices(&config, &settings)
+}
+
+fn run_on_devices(config: &cli::Config, settings: &ConfigFile) -> Result<(), AppError> {
+    if !config.all_devices {
+        return run_on_device(config, settings, config.device);
+    }
+
+    // Per-device failures are reported and the remaining GPUs still processed
+    let count = gpu::device_count().map_err(|e| AppError::new("device", e))?;
+    let mut failures = Vec::new();
+    let mut interrupted = false;
+    for device_index in 0..count {
+        if signal::stop_requested() {
+            eprintln!("warning[nvoc]: interrupted, gpu {device_index} and later left unchanged");
+            interrupted = true;
+            break;
+        }
+        if config.operation.modifies_gpu() {
+            output::status!("gpu {device_index}:");
+        }
+        if let Err(e) = run_on_device(config, settings, device_index) {
+            failures.push(e.kind());
+            if !e.is_printed() {
+                eprintln!("{}", e.on_device(device_index));
+            }
+        }
+    }
+
+    // Every GPU failing the same way exits as that failure would
+    let uniform = !interrupted && failures.len() == count as usize && failures.windows(2).all(|w| w[0] == w[1]);
+    let kind = match failures.first() {
+        None if !interrupted => return Ok(()),
+        Some(&kind) if uniform => kind,
+        _ if config.operation.modifies_gpu() => ErrorKind::PartialApply,
+        first => first.copied().unwrap_or(ErrorKind::Other),
+    };
+    Err(AppError::printed("nvoc").with_kind(kind))
+}
+
 fn main() {
-    if let Err(e) = run() {
-        if !e.printed {
+    let result = run();
+    timing::report();
+    if let Err(e) = result {
+        if !e.is_printed() {
             eprintln!("{e}");
         }
-        process::exit(1);
+        process::exit(e.kind().exit_code());
     }
 }
@@ -0,0 +1,113 @@
+//! End-to-end runs of the nvoc binary against the simulated GPUs of
+//! `NVOC_MOCK`. Runs that change settings keep them in `NVOC_MOCK_STATE`
+//! inside a directory of their own, which also holds the simulated
+//! machine's journal, profiles and runtime files.
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn nvoc(args: &[&str], env: &[(&str, &str)]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_nvoc"))
+        .args(args)
+        .env("NVOC_MOCK", "1")
+        .env_remove("NVOC_MOCK_STATE")
+        .env_remove("NVOC_MOCK_FAIL")
+        .envs(env.iter().copied())
+        .output()
+        .unwrap()
+}
+
+/// Empty directory for a simulated machine, and the state file to pass as
+/// `NVOC_MOCK_STATE`
+fn machine(name: &str) -> (PathBuf, String) {
+    let dir = std::env::temp_dir().join(format!("nvoc-mock-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let state = dir.join("state.json").to_string_lossy().into_owned();
+    (dir, state)
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_info_lists_every_gpu() {
+    let output = nvoc(&["info", "--all"], &[("NVOC_MOCK_GPUS", "2")]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("gpu 0: NVIDIA GeForce RTX 5090"));
+    assert!(stdout.contains("gpu 1: NVIDIA GeForce RTX 5090"));
+    assert!(stderr(&output).contains("warning[mock]"));
+}
+
+#[test]
+fn test_dry_run_reports_changes() {
+    let output = nvoc(&["-o", "150", "-p", "90", "--dry-run"], &[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("gpu offset: +150MHz (dry run"));
+}
+
+#[test]
+fn test_offset_outside_driver_range_is_refused() {
+    let output = nvoc(&["-o", "5000", "--dry-run"], &[]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("outside the range the driver accepts"));
+}
+
+#[test]
+fn test_injected_failure_is_reported() {
+    let output = nvoc(&["info"], &[("NVOC_MOCK_FAIL", "nvmlDeviceGetHandleByIndex_v2=15")]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("error[device]: gpu 0: nvmlDeviceGetHandleByIndex_v2: "), "{}", stderr(&output));
+}
+
+#[test]
+fn test_exit_status_reflects_failure() {
+    assert_eq!(nvoc(&["-o", "5000", "--dry-run"], &[]).status.code(), Some(2));
+    let lost = nvoc(&["info"], &[("NVOC_MOCK_FAIL", "nvmlDeviceGetHandleByIndex_v2=15")]);
+    assert_eq!(lost.status.code(), Some(7));
+}
+
+#[test]
+fn test_apply_then_reset() {
+    let (dir, state) = machine("reset");
+    let env = [("NVOC_MOCK_STATE", state.as_str())];
+    let applied = nvoc(&["-o", "150", "-p", "90"], &env);
+    assert!(applied.status.success(), "{}", stderr(&applied));
+    assert!(stdout(&applied).contains("gpu offset: +150MHz"));
+    let info = stdout(&nvoc(&["info"], &env));
+    assert!(info.contains("gpu offset: 150MHz"), "{info}");
+    assert!(info.contains("power limit: 517W"), "{info}");
+
+    let reset = nvoc(&["reset"], &env);
+    assert!(reset.status.success(), "{}", stderr(&reset));
+    let info = stdout(&nvoc(&["info"], &env));
+    assert!(info.contains("gpu offset: 0MHz"), "{info}");
+    assert!(info.contains("power limit: 575W"), "{info}");
+    // Every change committed its journal entry
+    let journal = std::fs::read_dir(dir.join("var/lib/nvoc/journal.d")).unwrap();
+    assert!(journal.flatten().all(|entry| entry.path().extension().is_some_and(|ext| ext == "lock")));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_profile_save_then_apply() {
+    let (dir, state) = machine("profile");
+    let env = [("NVOC_MOCK_STATE", state.as_str())];
+    let saved = nvoc(&["profile", "save", "daily", "-o", "120", "-m", "500"], &env);
+    assert!(saved.status.success(), "{}", stderr(&saved));
+    assert!(dir.join("etc/nvoc/profiles/daily.toml").exists());
+
+    let applied = nvoc(&["profile", "apply", "daily"], &env);
+    assert!(applied.status.success(), "{}", stderr(&applied));
+    let info = stdout(&nvoc(&["info"], &env));
+    assert!(info.contains("gpu offset: 120MHz"), "{info}");
+    assert!(info.contains("mem offset: 500MHz"), "{info}");
+    assert_eq!(stdout(&nvoc(&["profile", "list"], &env)), "daily: not validated\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}